@@ -0,0 +1,164 @@
+//! Benchmarks of the individual HPKE components.
+//!
+//! `bench.rs` measures the full API per ciphersuite and mode. The benchmarks
+//! here vary only one algorithm at a time to isolate key generation,
+//! encapsulation, the key schedule, and the AEAD at several message sizes.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use hpke_rs::prelude::*;
+use hpke_rs_crypto::{
+    types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm},
+    HpkeCrypto, RngCore,
+};
+use hpke_rs_rust_crypto::*;
+use rand::rngs::OsRng;
+
+const AEAD_IDS: [AeadAlgorithm; 3] = [
+    AeadAlgorithm::Aes128Gcm,
+    AeadAlgorithm::Aes256Gcm,
+    AeadAlgorithm::ChaCha20Poly1305,
+];
+const KDF_IDS: [KdfAlgorithm; 3] = [
+    KdfAlgorithm::HkdfSha256,
+    KdfAlgorithm::HkdfSha384,
+    KdfAlgorithm::HkdfSha512,
+];
+const KEM_IDS: [KemAlgorithm; 5] = [
+    KemAlgorithm::DhKemP256,
+    KemAlgorithm::DhKemP384,
+    KemAlgorithm::DhKemP521,
+    KemAlgorithm::DhKem25519,
+    KemAlgorithm::DhKem448,
+];
+
+const PAYLOAD_SIZES: [usize; 5] = [0, 64, 1024, 16 * 1024, 64 * 1024];
+const AEAD_AAD: usize = 48;
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+fn kem<Crypto: HpkeCrypto + 'static>(c: &mut Criterion) {
+    for kem_id in KEM_IDS {
+        if Crypto::supports_kem(kem_id).is_err() {
+            continue;
+        }
+        let hpke = Hpke::<Crypto>::new(
+            HpkeMode::Base,
+            kem_id,
+            KdfAlgorithm::HkdfSha256,
+            AeadAlgorithm::Aes128Gcm,
+        );
+        let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+        let (enc, _) = hpke.setup_sender(&pk_r, b"info", None, None, None).unwrap();
+        let ikm = random_bytes(32);
+
+        let mut group = c.benchmark_group(format!("{} {:?}", Crypto::name(), kem_id));
+        group.bench_function("Generate Key Pair", |b| {
+            b.iter(|| hpke.generate_key_pair().unwrap())
+        });
+        group.bench_function("Generate 100 Key Pairs", |b| {
+            b.iter(|| hpke.generate_key_pairs(100).unwrap())
+        });
+        group.bench_function("Derive Key Pair", |b| {
+            b.iter(|| hpke.derive_key_pair(&ikm).unwrap())
+        });
+        group.bench_function("Encap", |b| {
+            b.iter(|| hpke.setup_sender(&pk_r, b"info", None, None, None).unwrap())
+        });
+        group.bench_function("Decap", |b| {
+            b.iter(|| {
+                hpke.setup_receiver(&enc, &sk_r, b"info", None, None, None)
+                    .unwrap()
+            })
+        });
+        group.finish();
+    }
+}
+
+fn key_schedule<Crypto: HpkeCrypto + 'static>(c: &mut Criterion) {
+    for kdf_id in KDF_IDS {
+        if Crypto::supports_kdf(kdf_id).is_err() {
+            continue;
+        }
+        let mut group = c.benchmark_group(format!("{} {:?}", Crypto::name(), kdf_id));
+        for mode in [HpkeMode::Base, HpkeMode::Psk] {
+            let hpke = Hpke::<Crypto>::new(
+                mode,
+                KemAlgorithm::DhKem25519,
+                kdf_id,
+                AeadAlgorithm::Aes128Gcm,
+            );
+            let shared_secret = random_bytes(Crypto::kdf_digest_length(kdf_id));
+            let (psk, psk_id): (&[u8], &[u8]) = match mode {
+                HpkeMode::Psk => (&[0x42; 32], b"psk id"),
+                _ => (&[], &[]),
+            };
+            group.bench_function(BenchmarkId::new("Key Schedule", mode), |b| {
+                b.iter(|| {
+                    hpke.key_schedule(&shared_secret, b"info", psk, psk_id)
+                        .unwrap()
+                })
+            });
+        }
+        group.finish();
+    }
+}
+
+fn aead<Crypto: HpkeCrypto + 'static>(c: &mut Criterion) {
+    for aead_id in AEAD_IDS {
+        if Crypto::supports_aead(aead_id).is_err() {
+            continue;
+        }
+        let hpke = Hpke::<Crypto>::new(
+            HpkeMode::Base,
+            KemAlgorithm::DhKem25519,
+            KdfAlgorithm::HkdfSha256,
+            aead_id,
+        );
+        let shared_secret = random_bytes(32);
+        let aad = random_bytes(AEAD_AAD);
+
+        let mut group = c.benchmark_group(format!("{} {:?}", Crypto::name(), aead_id));
+        for size in PAYLOAD_SIZES {
+            let ptxt = random_bytes(size);
+            group.throughput(Throughput::Bytes(size as u64));
+            group.bench_with_input(BenchmarkId::new("Seal", size), &ptxt, |b, ptxt| {
+                b.iter_batched(
+                    || {
+                        hpke.key_schedule(&shared_secret, b"info", &[], &[])
+                            .unwrap()
+                    },
+                    |mut context| context.seal(&aad, ptxt).unwrap(),
+                    BatchSize::SmallInput,
+                )
+            });
+            let ctxt = hpke
+                .key_schedule(&shared_secret, b"info", &[], &[])
+                .unwrap()
+                .seal(&aad, &ptxt)
+                .unwrap();
+            group.bench_with_input(BenchmarkId::new("Open", size), &ctxt, |b, ctxt| {
+                b.iter_batched(
+                    || {
+                        hpke.key_schedule(&shared_secret, b"info", &[], &[])
+                            .unwrap()
+                    },
+                    |mut context| context.open(&aad, ctxt).unwrap(),
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(
+    benches,
+    kem::<HpkeRustCrypto>,
+    key_schedule::<HpkeRustCrypto>,
+    aead::<HpkeRustCrypto>,
+);
+criterion_main!(benches);
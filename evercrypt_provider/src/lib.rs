@@ -14,6 +14,23 @@ use rand::{CryptoRng, RngCore, SeedableRng};
 #[derive(Debug)]
 pub struct HpkeEvercrypt {}
 
+/// An AEAD key for the Evercrypt Provider.
+///
+/// Evercrypt sets up the cipher state on every call, so this only holds the
+/// key.
+pub struct HpkeEvercryptAead {
+    alg: AeadAlgorithm,
+    key: Vec<u8>,
+}
+
+/// A prepared KEM public key of the Evercrypt provider.
+///
+/// Evercrypt decodes the public key on every call, so this only holds the
+/// encoded key.
+pub struct HpkeEvercryptKemPublicKey {
+    pk: Vec<u8>,
+}
+
 /// The PRNG for the Evercrypt Provider.
 pub struct HpkeEvercryptPrng {
     #[cfg(feature = "deterministic-prng")]
@@ -31,6 +48,8 @@ impl HpkeCrypto for HpkeEvercrypt {
             KdfAlgorithm::HkdfSha256 => hkdf_extract(HmacMode::Sha256, salt, ikm),
             KdfAlgorithm::HkdfSha384 => hkdf_extract(HmacMode::Sha384, salt, ikm),
             KdfAlgorithm::HkdfSha512 => hkdf_extract(HmacMode::Sha512, salt, ikm),
+            // Not supported, see `supports_kdf`.
+            KdfAlgorithm::HkdfSha3_256 | KdfAlgorithm::Kmac256 => Vec::new(),
         }
     }
 
@@ -44,6 +63,9 @@ impl HpkeCrypto for HpkeEvercrypt {
             KdfAlgorithm::HkdfSha256 => hkdf_expand(HmacMode::Sha256, prk, info, output_size),
             KdfAlgorithm::HkdfSha384 => hkdf_expand(HmacMode::Sha384, prk, info, output_size),
             KdfAlgorithm::HkdfSha512 => hkdf_expand(HmacMode::Sha512, prk, info, output_size),
+            KdfAlgorithm::HkdfSha3_256 | KdfAlgorithm::Kmac256 => {
+                return Err(Error::UnknownKdfAlgorithm)
+            }
         })
     }
 
@@ -92,6 +114,21 @@ impl HpkeCrypto for HpkeEvercrypt {
         }
     }
 
+    type KemPublicKey = HpkeEvercryptKemPublicKey;
+
+    fn kem_prepare_pk(alg: KemAlgorithm, pk: &[u8]) -> Result<Self::KemPublicKey, Error> {
+        kem_key_type_to_mode(alg)?;
+        Ok(HpkeEvercryptKemPublicKey { pk: pk.to_vec() })
+    }
+
+    fn kem_derive_prepared(
+        alg: KemAlgorithm,
+        pk: &Self::KemPublicKey,
+        sk: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        Self::kem_derive(alg, &pk.pk, sk)
+    }
+
     fn aead_seal(
         alg: AeadAlgorithm,
         key: &[u8],
@@ -137,6 +174,37 @@ impl HpkeCrypto for HpkeEvercrypt {
             .map_err(|e| Error::CryptoLibraryError(format!("AEAD decryption error: {:?}", e)))
     }
 
+    type AeadCipher = HpkeEvercryptAead;
+
+    fn aead_init(alg: AeadAlgorithm, key: &[u8]) -> Result<Self::AeadCipher, Error> {
+        aead_type_to_mode(alg)?;
+        if key.len() != Self::aead_key_length(alg) {
+            return Err(Error::AeadInvalidKey);
+        }
+        Ok(HpkeEvercryptAead {
+            alg,
+            key: key.to_vec(),
+        })
+    }
+
+    fn aead_cipher_seal(
+        cipher: &Self::AeadCipher,
+        nonce: &[u8],
+        aad: &[u8],
+        msg: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        Self::aead_seal(cipher.alg, &cipher.key, nonce, aad, msg)
+    }
+
+    fn aead_cipher_open(
+        cipher: &Self::AeadCipher,
+        nonce: &[u8],
+        aad: &[u8],
+        msg: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        Self::aead_open(cipher.alg, &cipher.key, nonce, aad, msg)
+    }
+
     type HpkePrng = HpkeEvercryptPrng;
 
     fn prng() -> Self::HpkePrng {
@@ -156,8 +224,13 @@ impl HpkeCrypto for HpkeEvercrypt {
     }
 
     /// Returns an error if the KDF algorithm is not supported by this crypto provider.
-    fn supports_kdf(_: KdfAlgorithm) -> Result<(), Error> {
-        Ok(())
+    fn supports_kdf(alg: KdfAlgorithm) -> Result<(), Error> {
+        match alg {
+            KdfAlgorithm::HkdfSha256 | KdfAlgorithm::HkdfSha384 | KdfAlgorithm::HkdfSha512 => {
+                Ok(())
+            }
+            _ => Err(Error::UnknownKdfAlgorithm),
+        }
     }
 
     /// Returns an error if the KEM algorithm is not supported by this crypto provider.
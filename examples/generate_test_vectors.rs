@@ -0,0 +1,187 @@
+//! Generate HPKE test vectors in the JSON format of the RFC 9180 test vectors.
+//!
+//! ```text
+//! cargo run --example generate_test_vectors > test-vectors.json
+//! ```
+//!
+//! Vectors are generated with fresh input key material for every mode and
+//! ciphersuite supported by the Rust Crypto provider.
+
+use hpke_rs::{
+    prelude::*,
+    test_util::{bytes_to_hex, hex_to_bytes},
+};
+use hpke_rs_crypto::{
+    types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm},
+    HpkeCrypto, RngCore,
+};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+use rand::rngs::OsRng;
+use serde_json::{json, Value};
+
+const MODES: [HpkeMode; 4] = [
+    HpkeMode::Base,
+    HpkeMode::Psk,
+    HpkeMode::Auth,
+    HpkeMode::AuthPsk,
+];
+const KEM_IDS: [KemAlgorithm; 5] = [
+    KemAlgorithm::DhKemP256,
+    KemAlgorithm::DhKemP384,
+    KemAlgorithm::DhKemP521,
+    KemAlgorithm::DhKem25519,
+    KemAlgorithm::DhKem448,
+];
+const KDF_IDS: [KdfAlgorithm; 3] = [
+    KdfAlgorithm::HkdfSha256,
+    KdfAlgorithm::HkdfSha384,
+    KdfAlgorithm::HkdfSha512,
+];
+const AEAD_IDS: [AeadAlgorithm; 4] = [
+    AeadAlgorithm::Aes128Gcm,
+    AeadAlgorithm::Aes256Gcm,
+    AeadAlgorithm::ChaCha20Poly1305,
+    AeadAlgorithm::HpkeExport,
+];
+
+// The inputs used in the RFC 9180 test vectors.
+const INFO: &str = "4f6465206f6e2061204772656369616e2055726e";
+const PSK: &str = "0247fd33b913760fa1fa51e1892d9f307fbe65eb171e8132c2af18555a738b82";
+const PSK_ID: &str = "456e6e796e20447572696e206172616e204d6f726961";
+const PLAINTEXT: &str = "4265617574792069732074727574682c20747275746820626561757479";
+const ENCRYPTIONS: u32 = 10;
+const EXPORTER_CONTEXTS: [&str; 3] = ["", "00", "54657374436f6e74657874"];
+const EXPORT_LENGTH: usize = 32;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes_to_hex(bytes).to_lowercase()
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+fn test_vector<Crypto: HpkeCrypto + 'static>(
+    mode: HpkeMode,
+    kem_id: KemAlgorithm,
+    kdf_id: KdfAlgorithm,
+    aead_id: AeadAlgorithm,
+) -> Result<Value, HpkeError> {
+    let hpke = Hpke::<Crypto>::new(mode, kem_id, kdf_id, aead_id);
+    let psk_mode = matches!(mode, HpkeMode::Psk | HpkeMode::AuthPsk);
+    let auth_mode = matches!(mode, HpkeMode::Auth | HpkeMode::AuthPsk);
+
+    let info = hex_to_bytes(INFO);
+    let (psk, psk_id) = if psk_mode {
+        (hex_to_bytes(PSK), hex_to_bytes(PSK_ID))
+    } else {
+        (vec![], vec![])
+    };
+
+    let ikm_r = random_bytes(kem_id.private_key_len());
+    let ikm_e = random_bytes(kem_id.private_key_len());
+    let (sk_r, pk_r) = hpke.derive_key_pair(&ikm_r)?.into_keys();
+    let (sk_e, pk_e) = hpke.derive_key_pair(&ikm_e)?.into_keys();
+    let ikm_s = random_bytes(kem_id.private_key_len());
+    let sender = if auth_mode {
+        Some(hpke.derive_key_pair(&ikm_s)?)
+    } else {
+        None
+    };
+    let sk_s = sender.as_ref().map(|kp| kp.private_key());
+
+    let (shared_secret, enc) = hpke.encaps_with_ikm(&pk_r, sk_s, &ikm_e)?;
+    let (key_schedule_context, secret) =
+        hpke.key_schedule_intermediates(&shared_secret, &info, &psk, &psk_id);
+    let mut context = hpke.key_schedule(&shared_secret, &info, &psk, &psk_id)?;
+
+    let mut vector = json!({
+        "mode": mode as u8,
+        "kem_id": kem_id as u16,
+        "kdf_id": kdf_id as u16,
+        "aead_id": aead_id as u16,
+        "info": INFO,
+        "ikmR": hex(&ikm_r),
+        "ikmE": hex(&ikm_e),
+        "skRm": hex(sk_r.as_slice()),
+        "skEm": hex(sk_e.as_slice()),
+        "pkRm": hex(pk_r.as_slice()),
+        "pkEm": hex(pk_e.as_slice()),
+        "enc": hex(&enc),
+        "shared_secret": hex(&shared_secret),
+        "key_schedule_context": hex(&key_schedule_context),
+        "secret": hex(&secret),
+        "key": hex(context.key()),
+        "base_nonce": hex(context.nonce()),
+        "exporter_secret": hex(context.exporter_secret()),
+    });
+    if psk_mode {
+        vector["psk"] = PSK.into();
+        vector["psk_id"] = PSK_ID.into();
+    }
+    if let Some(sender) = &sender {
+        vector["ikmS"] = hex(&ikm_s).into();
+        vector["skSm"] = hex(sender.private_key().as_slice()).into();
+        vector["pkSm"] = hex(sender.public_key().as_slice()).into();
+    }
+
+    let mut encryptions = vec![];
+    if aead_id != AeadAlgorithm::HpkeExport {
+        let pt = hex_to_bytes(PLAINTEXT);
+        for i in 0..ENCRYPTIONS {
+            let aad = format!("Count-{}", i);
+            let mut nonce = context.nonce().to_vec();
+            let seq = context.sequence_number().to_be_bytes();
+            for (n, s) in nonce.iter_mut().rev().zip(seq.iter().rev()) {
+                *n ^= s;
+            }
+            let ct = context.seal(aad.as_bytes(), &pt)?;
+            encryptions.push(json!({
+                "aad": hex(aad.as_bytes()),
+                "ct": hex(&ct),
+                "nonce": hex(&nonce),
+                "pt": PLAINTEXT,
+            }));
+        }
+    }
+    vector["encryptions"] = encryptions.into();
+
+    let mut exports = vec![];
+    for exporter_context in EXPORTER_CONTEXTS {
+        let exported_value = context.export(&hex_to_bytes(exporter_context), EXPORT_LENGTH)?;
+        exports.push(json!({
+            "exporter_context": exporter_context,
+            "L": EXPORT_LENGTH,
+            "exported_value": hex(&exported_value),
+        }));
+    }
+    vector["exports"] = exports.into();
+
+    Ok(vector)
+}
+
+fn main() {
+    let mut vectors = vec![];
+    for mode in MODES {
+        for kem_id in KEM_IDS {
+            for kdf_id in KDF_IDS {
+                for aead_id in AEAD_IDS {
+                    if HpkeRustCrypto::supports_kem(kem_id).is_err()
+                        || HpkeRustCrypto::supports_kdf(kdf_id).is_err()
+                        || (aead_id != AeadAlgorithm::HpkeExport
+                            && HpkeRustCrypto::supports_aead(aead_id).is_err())
+                    {
+                        continue;
+                    }
+                    vectors.push(
+                        test_vector::<HpkeRustCrypto>(mode, kem_id, kdf_id, aead_id)
+                            .expect("Error generating test vector"),
+                    );
+                }
+            }
+        }
+    }
+    println!("{}", serde_json::to_string_pretty(&vectors).unwrap());
+}
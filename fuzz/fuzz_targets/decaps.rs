@@ -0,0 +1,58 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use hpke_rs::prelude::*;
+use hpke_rs_fuzz::{hpke, key_pair, split, ModeInputs};
+
+// Encapsulate to and decapsulate with arbitrary keys and `enc` values.
+fuzz_target!(|data: &[u8]| {
+    let Some((hpke, mode, data)) = hpke(data) else {
+        return;
+    };
+    let key_pair = key_pair(&hpke);
+    let inputs = ModeInputs::new(&hpke, mode);
+    let (enc, key) = split(data);
+    let info = b"HPKE fuzz info";
+
+    // Arbitrary `enc` with a valid private key.
+    let _ = hpke.setup_receiver(
+        enc,
+        key_pair.private_key(),
+        info,
+        inputs.psk(),
+        inputs.psk_id(),
+        inputs.pk_s(),
+    );
+
+    // Arbitrary private key with a valid `enc`.
+    let (valid_enc, _) = hpke
+        .setup_sender(
+            key_pair.public_key(),
+            info,
+            inputs.psk(),
+            inputs.psk_id(),
+            inputs.sk_s(),
+        )
+        .unwrap();
+    let sk_r = HpkePrivateKey::new(key.to_vec());
+    let _ = hpke.setup_receiver(
+        &valid_enc,
+        &sk_r,
+        info,
+        inputs.psk(),
+        inputs.psk_id(),
+        inputs.pk_s(),
+    );
+
+    // Arbitrary public keys.
+    let pk = HpkePublicKey::new(key.to_vec());
+    let _ = hpke.setup_sender(&pk, info, inputs.psk(), inputs.psk_id(), inputs.sk_s());
+    let _ = hpke.setup_receiver(
+        &valid_enc,
+        key_pair.private_key(),
+        info,
+        inputs.psk(),
+        inputs.psk_id(),
+        Some(&pk),
+    );
+});
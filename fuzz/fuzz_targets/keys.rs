@@ -0,0 +1,24 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use hpke_rs::{prelude::*, KeyId};
+use hpke_rs_fuzz::hpke;
+
+// Derive key pairs from arbitrary input key material and parse arbitrary key
+// identifiers and public keys.
+fuzz_target!(|data: &[u8]| {
+    let Some((hpke, _mode, data)) = hpke(data) else {
+        return;
+    };
+
+    if let Ok(key_pair) = hpke.derive_key_pair(data) {
+        let again = hpke.derive_key_pair(data).unwrap();
+        assert_eq!(key_pair.public_key(), again.public_key());
+        assert_eq!(key_pair.private_key(), again.private_key());
+    }
+
+    if let Ok(key_id) = KeyId::try_from(data) {
+        assert_eq!(key_id.as_slice(), data);
+    }
+    let _ = hpke.key_id(&HpkePublicKey::new(data.to_vec()));
+});
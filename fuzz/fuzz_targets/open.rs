@@ -0,0 +1,57 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use hpke_rs_fuzz::{hpke, key_pair, split, ModeInputs};
+
+// Open arbitrary `enc` and ciphertexts with a valid key pair in all modes and
+// ciphersuites.
+fuzz_target!(|data: &[u8]| {
+    let Some((hpke, mode, data)) = hpke(data) else {
+        return;
+    };
+    let key_pair = key_pair(&hpke);
+    let inputs = ModeInputs::new(&hpke, mode);
+    let (enc, ct) = split(data);
+    let info = b"HPKE fuzz info";
+    let aad = b"HPKE fuzz aad";
+
+    let _ = hpke.open(
+        enc,
+        key_pair.private_key(),
+        info,
+        aad,
+        ct,
+        inputs.psk(),
+        inputs.psk_id(),
+        inputs.pk_s(),
+    );
+
+    // Open garbage with a valid context.
+    let (enc, mut context) = hpke
+        .setup_sender(
+            key_pair.public_key(),
+            info,
+            inputs.psk(),
+            inputs.psk_id(),
+            inputs.sk_s(),
+        )
+        .unwrap();
+    let mut receiver = hpke
+        .setup_receiver(
+            &enc,
+            key_pair.private_key(),
+            info,
+            inputs.psk(),
+            inputs.psk_id(),
+            inputs.pk_s(),
+        )
+        .unwrap();
+    let pt = ct;
+    if let Ok(ct) = context.seal(aad, pt) {
+        // Truncated ciphertexts must never open.
+        for len in 0..ct.len() {
+            assert!(receiver.open(aad, &ct[..len]).is_err());
+        }
+        assert_eq!(receiver.open(aad, &ct).unwrap(), pt);
+    }
+});
@@ -0,0 +1,82 @@
+//! Helpers shared by the fuzz targets.
+
+use hpke_rs::prelude::*;
+use hpke_rs_crypto::types::*;
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+const KEMS: [KemAlgorithm; 2] = [KemAlgorithm::DhKemP256, KemAlgorithm::DhKem25519];
+const KDFS: [KdfAlgorithm; 3] = [
+    KdfAlgorithm::HkdfSha256,
+    KdfAlgorithm::HkdfSha384,
+    KdfAlgorithm::HkdfSha512,
+];
+const AEADS: [AeadAlgorithm; 4] = [
+    AeadAlgorithm::Aes128Gcm,
+    AeadAlgorithm::Aes256Gcm,
+    AeadAlgorithm::ChaCha20Poly1305,
+    AeadAlgorithm::HpkeExport,
+];
+
+/// Pick a mode and ciphersuite from the first byte of `data`.
+///
+/// Returns the HPKE instance, its mode, and the remaining data, or `None` if
+/// `data` is empty.
+pub fn hpke(data: &[u8]) -> Option<(Hpke<HpkeRustCrypto>, HpkeMode, &[u8])> {
+    let (&b, data) = data.split_first()?;
+    let mode = HpkeMode::try_from(b >> 6).unwrap();
+    let kem = KEMS[usize::from(b & 1)];
+    let kdf = KDFS[usize::from(b >> 1 & 3) % KDFS.len()];
+    let aead = AEADS[usize::from(b >> 3 & 7) % AEADS.len()];
+    Some((Hpke::new(mode, kem, kdf, aead), mode, data))
+}
+
+/// Split `data` at the two-byte length prefix.
+pub fn split(data: &[u8]) -> (&[u8], &[u8]) {
+    if data.len() < 2 {
+        return (data, &[]);
+    }
+    let len = usize::from(u16::from_be_bytes([data[0], data[1]]));
+    let data = &data[2..];
+    data.split_at(len.min(data.len()))
+}
+
+/// A fixed key pair for the KEM of `hpke`.
+pub fn key_pair(hpke: &Hpke<HpkeRustCrypto>) -> HpkeKeyPair {
+    hpke.derive_key_pair(b"hpke-rs fuzzing key pair ikm")
+        .unwrap()
+}
+
+/// Pre-shared key and sender key material for the mode of `hpke`.
+pub struct ModeInputs {
+    pub psk: Option<Vec<u8>>,
+    pub psk_id: Option<Vec<u8>>,
+    pub sender: Option<HpkeKeyPair>,
+}
+
+impl ModeInputs {
+    pub fn new(hpke: &Hpke<HpkeRustCrypto>, mode: HpkeMode) -> Self {
+        let psk = matches!(mode, HpkeMode::Psk | HpkeMode::AuthPsk);
+        let auth = matches!(mode, HpkeMode::Auth | HpkeMode::AuthPsk);
+        Self {
+            psk: psk.then(|| vec![0x42; 32]),
+            psk_id: psk.then(|| b"psk id".to_vec()),
+            sender: auth.then(|| hpke.derive_key_pair(b"hpke-rs fuzzing sender ikm").unwrap()),
+        }
+    }
+
+    pub fn psk(&self) -> Option<&[u8]> {
+        self.psk.as_deref()
+    }
+
+    pub fn psk_id(&self) -> Option<&[u8]> {
+        self.psk_id.as_deref()
+    }
+
+    pub fn pk_s(&self) -> Option<&HpkePublicKey> {
+        self.sender.as_ref().map(|kp| kp.public_key())
+    }
+
+    pub fn sk_s(&self) -> Option<&HpkePrivateKey> {
+        self.sender.as_ref().map(|kp| kp.private_key())
+    }
+}
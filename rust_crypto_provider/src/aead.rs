@@ -1,59 +1,180 @@
+#[cfg(any(feature = "aes-gcm", feature = "chacha20poly1305"))]
+use aead::{Aead, AeadInPlace, KeyInit, Payload};
+#[cfg(feature = "aes-gcm")]
 use aes_gcm::{Aes128Gcm as RC_Aes128Gcm, Aes256Gcm as RC_Aes256Gcm};
-use chacha20poly1305::{
-    aead::{Aead, KeyInit, Payload},
-    ChaCha20Poly1305 as RC_ChaCha20Poly1305,
-};
-use hpke_rs_crypto::{error::Error, types::AeadAlgorithm, HpkeCrypto};
+#[cfg(feature = "chacha20poly1305")]
+use chacha20poly1305::ChaCha20Poly1305 as RC_ChaCha20Poly1305;
+#[cfg(any(feature = "aes-gcm", feature = "chacha20poly1305"))]
+use hpke_rs_crypto::HpkeCrypto;
+use hpke_rs_crypto::{error::Error, types::AeadAlgorithm};
 
+#[cfg(any(feature = "aes-gcm", feature = "chacha20poly1305"))]
 use super::HpkeRustCrypto;
 
-macro_rules! implement_aead {
-    ($name_seal: ident, $name_open: ident, $name:ident, $algorithm:ident) => {
-        pub(crate) fn $name_seal(
-            key: &[u8],
-            nonce: &[u8],
-            aad: &[u8],
-            msg: &[u8],
-        ) -> Result<Vec<u8>, Error> {
-            if nonce.len() != 12 {
-                return Err(Error::AeadInvalidNonce);
-            }
+/// An initialized AEAD cipher of the Rust Crypto provider.
+pub enum HpkeRustCryptoAead {
+    /// AES-GCM 128
+    #[cfg(feature = "aes-gcm")]
+    Aes128Gcm(Box<RC_Aes128Gcm>),
+    /// AES-GCM 256
+    #[cfg(feature = "aes-gcm")]
+    Aes256Gcm(Box<RC_Aes256Gcm>),
+    /// ChaCha20 Poly1305
+    #[cfg(feature = "chacha20poly1305")]
+    ChaCha20Poly1305(Box<RC_ChaCha20Poly1305>),
+}
+
+impl std::fmt::Debug for HpkeRustCryptoAead {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("HpkeRustCryptoAead")
+            .field(&self.algorithm())
+            .finish()
+    }
+}
 
-            let cipher = $algorithm::new(key.into());
-            cipher
-                .encrypt(nonce.into(), Payload { msg, aad })
-                .map_err(|e| Error::CryptoLibraryError(format!("AEAD error: {:?}", e)))
-        }
-        pub(crate) fn $name_open(
-            alg: AeadAlgorithm,
-            key: &[u8],
-            nonce: &[u8],
-            aad: &[u8],
-            msg: &[u8],
-        ) -> Result<Vec<u8>, Error> {
-            let nonce_length = HpkeRustCrypto::aead_nonce_length(alg);
-            if nonce.len() != nonce_length {
-                return Err(Error::AeadInvalidNonce);
+#[cfg(any(feature = "aes-gcm", feature = "chacha20poly1305"))]
+impl HpkeRustCryptoAead {
+    pub(crate) fn new(alg: AeadAlgorithm, key: &[u8]) -> Result<Self, Error> {
+        if key.len() != HpkeRustCrypto::aead_key_length(alg) {
+            return Err(Error::AeadInvalidKey);
+        }
+        match alg {
+            #[cfg(feature = "aes-gcm")]
+            AeadAlgorithm::Aes128Gcm => {
+                Ok(Self::Aes128Gcm(Box::new(RC_Aes128Gcm::new(key.into()))))
             }
-            let tag_length = HpkeRustCrypto::aead_tag_length(alg);
-            if msg.len() <= tag_length {
-                return Err(Error::AeadInvalidCiphertext);
+            #[cfg(feature = "aes-gcm")]
+            AeadAlgorithm::Aes256Gcm => {
+                Ok(Self::Aes256Gcm(Box::new(RC_Aes256Gcm::new(key.into()))))
             }
+            #[cfg(feature = "chacha20poly1305")]
+            AeadAlgorithm::ChaCha20Poly1305 => Ok(Self::ChaCha20Poly1305(Box::new(
+                RC_ChaCha20Poly1305::new(key.into()),
+            ))),
+            _ => Err(Error::UnknownAeadAlgorithm),
+        }
+    }
 
-            let cipher = $algorithm::new(key.into());
+    fn algorithm(&self) -> AeadAlgorithm {
+        match *self {
+            #[cfg(feature = "aes-gcm")]
+            Self::Aes128Gcm(_) => AeadAlgorithm::Aes128Gcm,
+            #[cfg(feature = "aes-gcm")]
+            Self::Aes256Gcm(_) => AeadAlgorithm::Aes256Gcm,
+            #[cfg(feature = "chacha20poly1305")]
+            Self::ChaCha20Poly1305(_) => AeadAlgorithm::ChaCha20Poly1305,
+        }
+    }
+
+    pub(crate) fn seal(&self, nonce: &[u8], aad: &[u8], msg: &[u8]) -> Result<Vec<u8>, Error> {
+        if nonce.len() != HpkeRustCrypto::aead_nonce_length(self.algorithm()) {
+            return Err(Error::AeadInvalidNonce);
+        }
+        let payload = Payload { msg, aad };
+        let result: Result<Vec<u8>, aead::Error> = match *self {
+            #[cfg(feature = "aes-gcm")]
+            Self::Aes128Gcm(ref cipher) => cipher.encrypt(nonce.into(), payload),
+            #[cfg(feature = "aes-gcm")]
+            Self::Aes256Gcm(ref cipher) => cipher.encrypt(nonce.into(), payload),
+            #[cfg(feature = "chacha20poly1305")]
+            Self::ChaCha20Poly1305(ref cipher) => cipher.encrypt(nonce.into(), payload),
+        };
+        result.map_err(|e| Error::CryptoLibraryError(format!("AEAD error: {:?}", e)))
+    }
+
+    pub(crate) fn open(&self, nonce: &[u8], aad: &[u8], msg: &[u8]) -> Result<Vec<u8>, Error> {
+        let alg = self.algorithm();
+        if nonce.len() != HpkeRustCrypto::aead_nonce_length(alg) {
+            return Err(Error::AeadInvalidNonce);
+        }
+        if msg.len() < HpkeRustCrypto::aead_tag_length(alg) {
+            return Err(Error::AeadInvalidCiphertext);
+        }
+        let payload = Payload { msg, aad };
+        let result: Result<Vec<u8>, aead::Error> = match *self {
+            #[cfg(feature = "aes-gcm")]
+            Self::Aes128Gcm(ref cipher) => cipher.decrypt(nonce.into(), payload),
+            #[cfg(feature = "aes-gcm")]
+            Self::Aes256Gcm(ref cipher) => cipher.decrypt(nonce.into(), payload),
+            #[cfg(feature = "chacha20poly1305")]
+            Self::ChaCha20Poly1305(ref cipher) => cipher.decrypt(nonce.into(), payload),
+        };
+        result.map_err(|_| Error::AeadOpenError)
+    }
 
-            cipher
-                .decrypt(nonce.into(), Payload { msg, aad })
-                .map_err(|_| Error::AeadOpenError)
+    pub(crate) fn seal_in_place(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        if nonce.len() != HpkeRustCrypto::aead_nonce_length(self.algorithm()) {
+            return Err(Error::AeadInvalidNonce);
         }
-    };
+        let result: Result<(), aead::Error> = match *self {
+            #[cfg(feature = "aes-gcm")]
+            Self::Aes128Gcm(ref cipher) => cipher.encrypt_in_place(nonce.into(), aad, buffer),
+            #[cfg(feature = "aes-gcm")]
+            Self::Aes256Gcm(ref cipher) => cipher.encrypt_in_place(nonce.into(), aad, buffer),
+            #[cfg(feature = "chacha20poly1305")]
+            Self::ChaCha20Poly1305(ref cipher) => {
+                cipher.encrypt_in_place(nonce.into(), aad, buffer)
+            }
+        };
+        result.map_err(|e| Error::CryptoLibraryError(format!("AEAD error: {:?}", e)))
+    }
+
+    pub(crate) fn open_in_place(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let alg = self.algorithm();
+        if nonce.len() != HpkeRustCrypto::aead_nonce_length(alg) {
+            return Err(Error::AeadInvalidNonce);
+        }
+        if buffer.len() < HpkeRustCrypto::aead_tag_length(alg) {
+            return Err(Error::AeadInvalidCiphertext);
+        }
+        let result: Result<(), aead::Error> = match *self {
+            #[cfg(feature = "aes-gcm")]
+            Self::Aes128Gcm(ref cipher) => cipher.decrypt_in_place(nonce.into(), aad, buffer),
+            #[cfg(feature = "aes-gcm")]
+            Self::Aes256Gcm(ref cipher) => cipher.decrypt_in_place(nonce.into(), aad, buffer),
+            #[cfg(feature = "chacha20poly1305")]
+            Self::ChaCha20Poly1305(ref cipher) => {
+                cipher.decrypt_in_place(nonce.into(), aad, buffer)
+            }
+        };
+        result.map_err(|_| Error::AeadOpenError)
+    }
 }
 
-implement_aead!(aes128_seal, aes128_open, AesGcm128, RC_Aes128Gcm);
-implement_aead!(aes256_seal, aes256_open, AesGcm256, RC_Aes256Gcm);
-implement_aead!(
-    chacha_seal,
-    chacha_open,
-    ChaCha20Poly1305,
-    RC_ChaCha20Poly1305
-);
+/// Without any AEAD there are no ciphers to initialize.
+#[cfg(not(any(feature = "aes-gcm", feature = "chacha20poly1305")))]
+impl HpkeRustCryptoAead {
+    pub(crate) fn new(_: AeadAlgorithm, _: &[u8]) -> Result<Self, Error> {
+        Err(Error::UnknownAeadAlgorithm)
+    }
+
+    fn algorithm(&self) -> AeadAlgorithm {
+        match *self {}
+    }
+
+    pub(crate) fn seal(&self, _: &[u8], _: &[u8], _: &[u8]) -> Result<Vec<u8>, Error> {
+        match *self {}
+    }
+
+    pub(crate) fn open(&self, _: &[u8], _: &[u8], _: &[u8]) -> Result<Vec<u8>, Error> {
+        match *self {}
+    }
+
+    pub(crate) fn seal_in_place(&self, _: &[u8], _: &[u8], _: &mut Vec<u8>) -> Result<(), Error> {
+        match *self {}
+    }
+
+    pub(crate) fn open_in_place(&self, _: &[u8], _: &[u8], _: &mut Vec<u8>) -> Result<(), Error> {
+        match *self {}
+    }
+}
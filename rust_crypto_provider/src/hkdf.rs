@@ -1,20 +1,41 @@
+//! # HKDF
+//!
+//! The raw HKDF ([RFC 5869]) extract and expand functions for every hash that
+//! is enabled.
+//! They don't add any HPKE labels, such that they can be combined with
+//! secrets exported from an HPKE context in an application's own key
+//! schedule.
+//!
+//! [RFC 5869]: https://www.rfc-editor.org/rfc/rfc5869
+
 use hkdf::Hkdf;
 use hpke_rs_crypto::error::Error;
-use sha2::{Sha256, Sha384, Sha512};
+#[cfg(feature = "hkdf-sha256")]
+use sha2::Sha256;
+#[cfg(feature = "hkdf-sha384")]
+use sha2::Sha384;
+#[cfg(feature = "hkdf-sha512")]
+use sha2::Sha512;
+#[cfg(feature = "hkdf-sha3-256")]
+use sha3::Sha3_256;
 
 macro_rules! implement_hkdfs {
-    ($name_extract:ident, $name_expand:ident, $hmac_mode:ident, $name:ident) => {
-        pub(crate) fn $name_extract(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+    ($name_extract:ident, $name_expand:ident, $hmac_mode:ident, $name:literal) => {
+        #[doc = concat!("`Extract(salt, ikm)` with ", $name, ".")]
+        ///
+        /// Returns the pseudorandom key of the length of the hash output.
+        pub fn $name_extract(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
             Hkdf::<$hmac_mode>::extract(Some(salt), ikm)
                 .0
                 .as_slice()
                 .into()
         }
-        pub(crate) fn $name_expand(
-            prk: &[u8],
-            info: &[u8],
-            output_size: usize,
-        ) -> Result<Vec<u8>, Error> {
+        #[doc = concat!("`Expand(prk, info, output_size)` with ", $name, ".")]
+        ///
+        /// Returns [`Error::HpkeInvalidOutputLength`] if `prk` is shorter
+        /// than the hash output or `output_size` is larger than 255 times the
+        /// hash output.
+        pub fn $name_expand(prk: &[u8], info: &[u8], output_size: usize) -> Result<Vec<u8>, Error> {
             let hkdf =
                 Hkdf::<$hmac_mode>::from_prk(prk).map_err(|_| Error::HpkeInvalidOutputLength)?;
             let mut okm = vec![0u8; output_size];
@@ -25,6 +46,11 @@ macro_rules! implement_hkdfs {
     };
 }
 
-implement_hkdfs!(sha256_extract, sha256_expand, Sha256, HkdfSha256);
-implement_hkdfs!(sha384_extract, sha384_expand, Sha384, HkdfSha384);
-implement_hkdfs!(sha512_extract, sha512_expand, Sha512, HkdfSha512);
+#[cfg(feature = "hkdf-sha256")]
+implement_hkdfs!(sha256_extract, sha256_expand, Sha256, "HKDF-SHA256");
+#[cfg(feature = "hkdf-sha384")]
+implement_hkdfs!(sha384_extract, sha384_expand, Sha384, "HKDF-SHA384");
+#[cfg(feature = "hkdf-sha512")]
+implement_hkdfs!(sha512_extract, sha512_expand, Sha512, "HKDF-SHA512");
+#[cfg(feature = "hkdf-sha3-256")]
+implement_hkdfs!(sha3_256_extract, sha3_256_expand, Sha3_256, "HKDF-SHA3-256");
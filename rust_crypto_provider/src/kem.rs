@@ -0,0 +1,111 @@
+use hpke_rs_crypto::{error::Error, types::KemAlgorithm};
+#[cfg(feature = "p256")]
+use p256::{elliptic_curve::ecdh::diffie_hellman, PublicKey, SecretKey};
+#[cfg(feature = "p384")]
+use p384::{
+    elliptic_curve::ecdh::diffie_hellman as p384_diffie_hellman, PublicKey as P384PublicKey,
+    SecretKey as P384SecretKey,
+};
+#[cfg(feature = "x25519")]
+use x25519_dalek_ng::{PublicKey as X25519PublicKey, StaticSecret as X25519StaticSecret};
+
+/// A decoded and validated KEM public key of the Rust Crypto provider.
+#[derive(Clone)]
+pub enum HpkeRustCryptoKemPublicKey {
+    /// X25519
+    #[cfg(feature = "x25519")]
+    X25519(X25519PublicKey),
+    /// P-256 in affine representation
+    #[cfg(feature = "p256")]
+    P256(PublicKey),
+    /// P-384 in affine representation
+    #[cfg(feature = "p384")]
+    P384(P384PublicKey),
+}
+
+impl std::fmt::Debug for HpkeRustCryptoKemPublicKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("HpkeRustCryptoKemPublicKey")
+            .field(&self.algorithm())
+            .finish()
+    }
+}
+
+#[cfg(any(feature = "x25519", feature = "p256", feature = "p384"))]
+impl HpkeRustCryptoKemPublicKey {
+    pub(crate) fn new(alg: KemAlgorithm, pk: &[u8]) -> Result<Self, Error> {
+        match alg {
+            #[cfg(feature = "x25519")]
+            KemAlgorithm::DhKem25519 => {
+                let pk_array: [u8; 32] = pk.try_into().map_err(|_| Error::KemInvalidPublicKey)?;
+                Ok(Self::X25519(X25519PublicKey::from(pk_array)))
+            }
+            #[cfg(feature = "p256")]
+            KemAlgorithm::DhKemP256 => PublicKey::from_sec1_bytes(pk)
+                .map(Self::P256)
+                .map_err(|_| Error::KemInvalidPublicKey),
+            #[cfg(feature = "p384")]
+            KemAlgorithm::DhKemP384 => P384PublicKey::from_sec1_bytes(pk)
+                .map(Self::P384)
+                .map_err(|_| Error::KemInvalidPublicKey),
+            _ => Err(Error::UnknownKemAlgorithm),
+        }
+    }
+
+    fn algorithm(&self) -> KemAlgorithm {
+        match *self {
+            #[cfg(feature = "x25519")]
+            Self::X25519(_) => KemAlgorithm::DhKem25519,
+            #[cfg(feature = "p256")]
+            Self::P256(_) => KemAlgorithm::DhKemP256,
+            #[cfg(feature = "p384")]
+            Self::P384(_) => KemAlgorithm::DhKemP384,
+        }
+    }
+
+    pub(crate) fn derive(&self, alg: KemAlgorithm, sk: &[u8]) -> Result<Vec<u8>, Error> {
+        if alg != self.algorithm() {
+            return Err(Error::KemInvalidPublicKey);
+        }
+        match *self {
+            #[cfg(feature = "x25519")]
+            Self::X25519(ref pk) => {
+                let sk_array: [u8; 32] = sk.try_into().map_err(|_| Error::KemInvalidSecretKey)?;
+                let sk = X25519StaticSecret::from(sk_array);
+                Ok(sk.diffie_hellman(pk).as_bytes().to_vec())
+            }
+            #[cfg(feature = "p256")]
+            Self::P256(ref pk) => {
+                let sk = SecretKey::from_slice(sk).map_err(|_| Error::KemInvalidSecretKey)?;
+                Ok(diffie_hellman(sk.to_nonzero_scalar(), pk.as_affine())
+                    .raw_secret_bytes()
+                    .as_slice()
+                    .into())
+            }
+            #[cfg(feature = "p384")]
+            Self::P384(ref pk) => {
+                let sk = P384SecretKey::from_slice(sk).map_err(|_| Error::KemInvalidSecretKey)?;
+                Ok(p384_diffie_hellman(sk.to_nonzero_scalar(), pk.as_affine())
+                    .raw_secret_bytes()
+                    .as_slice()
+                    .into())
+            }
+        }
+    }
+}
+
+/// Without any KEM there are no public keys to decode.
+#[cfg(not(any(feature = "x25519", feature = "p256", feature = "p384")))]
+impl HpkeRustCryptoKemPublicKey {
+    pub(crate) fn new(_: KemAlgorithm, _: &[u8]) -> Result<Self, Error> {
+        Err(Error::UnknownKemAlgorithm)
+    }
+
+    fn algorithm(&self) -> KemAlgorithm {
+        match *self {}
+    }
+
+    pub(crate) fn derive(&self, _: KemAlgorithm, _: &[u8]) -> Result<Vec<u8>, Error> {
+        match *self {}
+    }
+}
@@ -0,0 +1,84 @@
+//! # KMAC
+//!
+//! KMAC256 ([NIST SP 800-185]) on top of cSHAKE256, and the extract and
+//! expand functions of the private-use KMAC256 KDF.
+//!
+//! ```text
+//! KMAC256(K, X, L, S) = cSHAKE256(bytepad(encode_string(K), 136) || X || right_encode(L), L, "KMAC", S)
+//! Extract(salt, ikm) = KMAC256(salt, ikm, 512, "HPKE-Extract")
+//! Expand(prk, info, L) = KMAC256(prk, info, 8 * L, "HPKE-Expand")
+//! ```
+//!
+//! [NIST SP 800-185]: https://doi.org/10.6028/NIST.SP.800-185
+
+use hpke_rs_crypto::error::Error;
+use sha3::{
+    digest::{core_api::CoreWrapper, ExtendableOutput, Update, XofReader},
+    CShake256Core,
+};
+
+/// The rate of cSHAKE256 in bytes.
+const RATE: usize = 136;
+
+/// The output length of [`kmac256_extract`] in bytes.
+const PRK_LEN: usize = 64;
+
+/// `left_encode(x)`
+fn left_encode(x: u64, out: &mut Vec<u8>) {
+    let bytes = x.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&b| b == 0).count().min(7);
+    out.push((8 - skip) as u8);
+    out.extend_from_slice(&bytes[skip..]);
+}
+
+/// `right_encode(x)`
+fn right_encode(x: u64, out: &mut Vec<u8>) {
+    let bytes = x.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&b| b == 0).count().min(7);
+    out.extend_from_slice(&bytes[skip..]);
+    out.push((8 - skip) as u8);
+}
+
+/// `KMAC256(key, data, 8 * output_size, customization)`
+pub fn kmac256(key: &[u8], data: &[u8], output_size: usize, customization: &[u8]) -> Vec<u8> {
+    let mut cshake = CoreWrapper::from_core(CShake256Core::new_with_function_name(
+        b"KMAC",
+        customization,
+    ));
+
+    // bytepad(encode_string(K), 136)
+    let mut padded_key = Vec::with_capacity(RATE + key.len());
+    left_encode(RATE as u64, &mut padded_key);
+    left_encode(key.len() as u64 * 8, &mut padded_key);
+    padded_key.extend_from_slice(key);
+    padded_key.resize(padded_key.len().next_multiple_of(RATE), 0);
+    cshake.update(&padded_key);
+    padded_key.fill(0);
+
+    cshake.update(data);
+    let mut length = Vec::with_capacity(9);
+    right_encode(output_size as u64 * 8, &mut length);
+    cshake.update(&length);
+
+    let mut out = vec![0u8; output_size];
+    cshake.finalize_xof().read(&mut out);
+    out
+}
+
+/// `Extract(salt, ikm)` with KMAC256.
+///
+/// Returns a 64 byte pseudorandom key.
+pub fn kmac256_extract(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+    kmac256(salt, ikm, PRK_LEN, b"HPKE-Extract")
+}
+
+/// `Expand(prk, info, output_size)` with KMAC256.
+///
+/// Returns [`Error::HpkeInvalidOutputLength`] if `prk` is shorter than 64
+/// bytes or `output_size` is larger than 255 times 64 bytes, like HKDF.
+pub fn kmac256_expand(prk: &[u8], info: &[u8], output_size: usize) -> Result<Vec<u8>, Error> {
+    if prk.len() < PRK_LEN || output_size > 255 * PRK_LEN {
+        return Err(Error::HpkeInvalidOutputLength);
+    }
+    Ok(kmac256(prk, info, output_size, b"HPKE-Expand"))
+}
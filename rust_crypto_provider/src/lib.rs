@@ -7,17 +7,38 @@ use hpke_rs_crypto::{
     types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm},
     CryptoRng, HpkeCrypto, HpkeTestRng, RngCore,
 };
-use p256::{
-    elliptic_curve::{ecdh::diffie_hellman, sec1::ToEncodedPoint},
-    PublicKey, SecretKey,
-};
+#[cfg(feature = "p256")]
+use p256::{elliptic_curve::sec1::ToEncodedPoint, SecretKey};
+#[cfg(all(feature = "p384", not(feature = "p256")))]
+use p384::elliptic_curve::sec1::ToEncodedPoint;
+#[cfg(feature = "p384")]
+use p384::SecretKey as P384SecretKey;
 use rand::SeedableRng;
+#[cfg(feature = "x25519")]
 use x25519_dalek_ng::{PublicKey as X25519PublicKey, StaticSecret as X25519StaticSecret};
 
 mod aead;
-mod hkdf;
-use crate::aead::*;
+#[cfg(any(
+    feature = "hkdf-sha256",
+    feature = "hkdf-sha384",
+    feature = "hkdf-sha512",
+    feature = "hkdf-sha3-256"
+))]
+pub mod hkdf;
+mod kem;
+#[cfg(feature = "kmac256")]
+pub mod kmac;
+pub use crate::aead::HpkeRustCryptoAead;
+#[cfg(any(
+    feature = "hkdf-sha256",
+    feature = "hkdf-sha384",
+    feature = "hkdf-sha512",
+    feature = "hkdf-sha3-256"
+))]
 use crate::hkdf::*;
+pub use crate::kem::HpkeRustCryptoKemPublicKey;
+#[cfg(feature = "kmac256")]
+use crate::kmac::{kmac256_expand, kmac256_extract};
 
 /// The Rust Crypto HPKE Provider
 #[derive(Debug)]
@@ -35,14 +56,45 @@ impl HpkeCrypto for HpkeRustCrypto {
         "RustCrypto".into()
     }
 
+    /// Returns an empty PRK for KDFs that are not enabled.
+    /// Callers must check [`supports_kdf`](Self::supports_kdf) first.
+    #[cfg_attr(
+        not(any(
+            feature = "hkdf-sha256",
+            feature = "hkdf-sha384",
+            feature = "hkdf-sha512",
+            feature = "hkdf-sha3-256",
+            feature = "kmac256"
+        )),
+        allow(unused_variables)
+    )]
     fn kdf_extract(alg: KdfAlgorithm, salt: &[u8], ikm: &[u8]) -> Vec<u8> {
         match alg {
+            #[cfg(feature = "hkdf-sha256")]
             KdfAlgorithm::HkdfSha256 => sha256_extract(salt, ikm),
+            #[cfg(feature = "hkdf-sha384")]
             KdfAlgorithm::HkdfSha384 => sha384_extract(salt, ikm),
+            #[cfg(feature = "hkdf-sha512")]
             KdfAlgorithm::HkdfSha512 => sha512_extract(salt, ikm),
+            #[cfg(feature = "hkdf-sha3-256")]
+            KdfAlgorithm::HkdfSha3_256 => sha3_256_extract(salt, ikm),
+            #[cfg(feature = "kmac256")]
+            KdfAlgorithm::Kmac256 => kmac256_extract(salt, ikm),
+            #[allow(unreachable_patterns)]
+            _ => Vec::new(),
         }
     }
 
+    #[cfg_attr(
+        not(any(
+            feature = "hkdf-sha256",
+            feature = "hkdf-sha384",
+            feature = "hkdf-sha512",
+            feature = "hkdf-sha3-256",
+            feature = "kmac256"
+        )),
+        allow(unused_variables)
+    )]
     fn kdf_expand(
         alg: KdfAlgorithm,
         prk: &[u8],
@@ -50,45 +102,32 @@ impl HpkeCrypto for HpkeRustCrypto {
         output_size: usize,
     ) -> Result<Vec<u8>, Error> {
         match alg {
+            #[cfg(feature = "hkdf-sha256")]
             KdfAlgorithm::HkdfSha256 => sha256_expand(prk, info, output_size),
+            #[cfg(feature = "hkdf-sha384")]
             KdfAlgorithm::HkdfSha384 => sha384_expand(prk, info, output_size),
+            #[cfg(feature = "hkdf-sha512")]
             KdfAlgorithm::HkdfSha512 => sha512_expand(prk, info, output_size),
+            #[cfg(feature = "hkdf-sha3-256")]
+            KdfAlgorithm::HkdfSha3_256 => sha3_256_expand(prk, info, output_size),
+            #[cfg(feature = "kmac256")]
+            KdfAlgorithm::Kmac256 => kmac256_expand(prk, info, output_size),
+            #[allow(unreachable_patterns)]
+            _ => Err(Error::UnknownKdfAlgorithm),
         }
     }
 
     fn kem_derive(alg: KemAlgorithm, pk: &[u8], sk: &[u8]) -> Result<Vec<u8>, Error> {
-        match alg {
-            KemAlgorithm::DhKem25519 => {
-                if sk.len() != 32 {
-                    return Err(Error::KemInvalidSecretKey);
-                }
-                if pk.len() != 32 {
-                    return Err(Error::KemInvalidPublicKey);
-                }
-                assert!(pk.len() == 32);
-                assert!(sk.len() == 32);
-                let sk_array: [u8; 32] = sk.try_into().map_err(|_| Error::KemInvalidSecretKey)?;
-                let pk_array: [u8; 32] = pk.try_into().map_err(|_| Error::KemInvalidPublicKey)?;
-                let sk = X25519StaticSecret::from(sk_array);
-                Ok(sk
-                    .diffie_hellman(&X25519PublicKey::from(pk_array))
-                    .as_bytes()
-                    .to_vec())
-            }
-            KemAlgorithm::DhKemP256 => {
-                let sk = SecretKey::from_slice(sk).map_err(|_| Error::KemInvalidSecretKey)?;
-                let pk = PublicKey::from_sec1_bytes(pk).map_err(|_| Error::KemInvalidPublicKey)?;
-                Ok(diffie_hellman(sk.to_nonzero_scalar(), pk.as_affine())
-                    .raw_secret_bytes()
-                    .as_slice()
-                    .into())
-            }
-            _ => Err(Error::UnknownKemAlgorithm),
-        }
+        HpkeRustCryptoKemPublicKey::new(alg, pk)?.derive(alg, sk)
     }
 
+    #[cfg_attr(
+        not(any(feature = "x25519", feature = "p256", feature = "p384")),
+        allow(unused_variables)
+    )]
     fn kem_derive_base(alg: KemAlgorithm, sk: &[u8]) -> Result<Vec<u8>, Error> {
         match alg {
+            #[cfg(feature = "x25519")]
             KemAlgorithm::DhKem25519 => {
                 if sk.len() != 32 {
                     return Err(Error::KemInvalidSecretKey);
@@ -98,34 +137,75 @@ impl HpkeCrypto for HpkeRustCrypto {
                 let sk = X25519StaticSecret::from(sk_array);
                 Ok(X25519PublicKey::from(&sk).as_bytes().to_vec())
             }
+            #[cfg(feature = "p256")]
             KemAlgorithm::DhKemP256 => {
                 let sk = SecretKey::from_slice(sk).map_err(|_| Error::KemInvalidSecretKey)?;
                 Ok(sk.public_key().to_encoded_point(false).as_bytes().into())
             }
+            #[cfg(feature = "p384")]
+            KemAlgorithm::DhKemP384 => {
+                let sk = P384SecretKey::from_slice(sk).map_err(|_| Error::KemInvalidSecretKey)?;
+                Ok(sk.public_key().to_encoded_point(false).as_bytes().into())
+            }
             _ => Err(Error::UnknownKemAlgorithm),
         }
     }
 
+    #[cfg_attr(
+        not(any(feature = "x25519", feature = "p256", feature = "p384")),
+        allow(unused_variables)
+    )]
     fn kem_key_gen(alg: KemAlgorithm, prng: &mut Self::HpkePrng) -> Result<Vec<u8>, Error> {
-        let mut rng = prng.rng.write().unwrap();
         match alg {
-            KemAlgorithm::DhKem25519 => Ok(X25519StaticSecret::new(&mut *rng).to_bytes().to_vec()),
-            KemAlgorithm::DhKemP256 => {
-                Ok(SecretKey::random(&mut *rng).to_bytes().as_slice().into())
+            #[cfg(feature = "x25519")]
+            KemAlgorithm::DhKem25519 => {
+                Ok(X25519StaticSecret::new(&mut *prng.rng.write().unwrap())
+                    .to_bytes()
+                    .to_vec())
             }
+            #[cfg(feature = "p256")]
+            KemAlgorithm::DhKemP256 => Ok(SecretKey::random(&mut *prng.rng.write().unwrap())
+                .to_bytes()
+                .as_slice()
+                .into()),
+            #[cfg(feature = "p384")]
+            KemAlgorithm::DhKemP384 => Ok(P384SecretKey::random(&mut *prng.rng.write().unwrap())
+                .to_bytes()
+                .as_slice()
+                .into()),
             _ => Err(Error::UnknownKemAlgorithm),
         }
     }
 
+    #[cfg_attr(not(any(feature = "p256", feature = "p384")), allow(unused_variables))]
     fn kem_validate_sk(alg: KemAlgorithm, sk: &[u8]) -> Result<Vec<u8>, Error> {
         match alg {
+            #[cfg(feature = "p256")]
             KemAlgorithm::DhKemP256 => SecretKey::from_slice(sk)
                 .map_err(|_| Error::KemInvalidSecretKey)
                 .map(|_| sk.into()),
+            #[cfg(feature = "p384")]
+            KemAlgorithm::DhKemP384 => P384SecretKey::from_slice(sk)
+                .map_err(|_| Error::KemInvalidSecretKey)
+                .map(|_| sk.into()),
             _ => Err(Error::UnknownKemAlgorithm),
         }
     }
 
+    type KemPublicKey = HpkeRustCryptoKemPublicKey;
+
+    fn kem_prepare_pk(alg: KemAlgorithm, pk: &[u8]) -> Result<Self::KemPublicKey, Error> {
+        HpkeRustCryptoKemPublicKey::new(alg, pk)
+    }
+
+    fn kem_derive_prepared(
+        alg: KemAlgorithm,
+        pk: &Self::KemPublicKey,
+        sk: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        pk.derive(alg, sk)
+    }
+
     fn aead_seal(
         alg: AeadAlgorithm,
         key: &[u8],
@@ -133,12 +213,7 @@ impl HpkeCrypto for HpkeRustCrypto {
         aad: &[u8],
         msg: &[u8],
     ) -> Result<Vec<u8>, Error> {
-        match alg {
-            AeadAlgorithm::Aes128Gcm => aes128_seal(key, nonce, aad, msg),
-            AeadAlgorithm::Aes256Gcm => aes256_seal(key, nonce, aad, msg),
-            AeadAlgorithm::ChaCha20Poly1305 => chacha_seal(key, nonce, aad, msg),
-            AeadAlgorithm::HpkeExport => Err(Error::UnknownAeadAlgorithm),
-        }
+        HpkeRustCryptoAead::new(alg, key)?.seal(nonce, aad, msg)
     }
 
     fn aead_open(
@@ -148,12 +223,49 @@ impl HpkeCrypto for HpkeRustCrypto {
         aad: &[u8],
         msg: &[u8],
     ) -> Result<Vec<u8>, Error> {
-        match alg {
-            AeadAlgorithm::Aes128Gcm => aes128_open(alg, key, nonce, aad, msg),
-            AeadAlgorithm::Aes256Gcm => aes256_open(alg, key, nonce, aad, msg),
-            AeadAlgorithm::ChaCha20Poly1305 => chacha_open(alg, key, nonce, aad, msg),
-            AeadAlgorithm::HpkeExport => Err(Error::UnknownAeadAlgorithm),
-        }
+        HpkeRustCryptoAead::new(alg, key)?.open(nonce, aad, msg)
+    }
+
+    type AeadCipher = HpkeRustCryptoAead;
+
+    fn aead_init(alg: AeadAlgorithm, key: &[u8]) -> Result<Self::AeadCipher, Error> {
+        HpkeRustCryptoAead::new(alg, key)
+    }
+
+    fn aead_cipher_seal(
+        cipher: &Self::AeadCipher,
+        nonce: &[u8],
+        aad: &[u8],
+        msg: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        cipher.seal(nonce, aad, msg)
+    }
+
+    fn aead_cipher_open(
+        cipher: &Self::AeadCipher,
+        nonce: &[u8],
+        aad: &[u8],
+        msg: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        cipher.open(nonce, aad, msg)
+    }
+
+    fn aead_cipher_seal_in_place(
+        cipher: &Self::AeadCipher,
+        nonce: &[u8],
+        aad: &[u8],
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        cipher.seal_in_place(nonce, aad, buffer)
+    }
+
+    fn aead_cipher_open_in_place(
+        cipher: &Self::AeadCipher,
+        nonce: &[u8],
+        aad: &[u8],
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        cipher.open_in_place(nonce, aad, buffer)
     }
 
     type HpkePrng = HpkeRustCryptoPrng;
@@ -175,14 +287,32 @@ impl HpkeCrypto for HpkeRustCrypto {
     }
 
     /// Returns an error if the KDF algorithm is not supported by this crypto provider.
-    fn supports_kdf(_: KdfAlgorithm) -> Result<(), Error> {
-        Ok(())
+    fn supports_kdf(alg: KdfAlgorithm) -> Result<(), Error> {
+        match alg {
+            #[cfg(feature = "hkdf-sha256")]
+            KdfAlgorithm::HkdfSha256 => Ok(()),
+            #[cfg(feature = "hkdf-sha384")]
+            KdfAlgorithm::HkdfSha384 => Ok(()),
+            #[cfg(feature = "hkdf-sha512")]
+            KdfAlgorithm::HkdfSha512 => Ok(()),
+            #[cfg(feature = "hkdf-sha3-256")]
+            KdfAlgorithm::HkdfSha3_256 => Ok(()),
+            #[cfg(feature = "kmac256")]
+            KdfAlgorithm::Kmac256 => Ok(()),
+            #[allow(unreachable_patterns)]
+            _ => Err(Error::UnknownKdfAlgorithm),
+        }
     }
 
     /// Returns an error if the KEM algorithm is not supported by this crypto provider.
     fn supports_kem(alg: KemAlgorithm) -> Result<(), Error> {
         match alg {
-            KemAlgorithm::DhKem25519 | KemAlgorithm::DhKemP256 => Ok(()),
+            #[cfg(feature = "x25519")]
+            KemAlgorithm::DhKem25519 => Ok(()),
+            #[cfg(feature = "p256")]
+            KemAlgorithm::DhKemP256 => Ok(()),
+            #[cfg(feature = "p384")]
+            KemAlgorithm::DhKemP384 => Ok(()),
             _ => Err(Error::UnknownKemAlgorithm),
         }
     }
@@ -190,10 +320,13 @@ impl HpkeCrypto for HpkeRustCrypto {
     /// Returns an error if the AEAD algorithm is not supported by this crypto provider.
     fn supports_aead(alg: AeadAlgorithm) -> Result<(), Error> {
         match alg {
-            AeadAlgorithm::Aes128Gcm
-            | AeadAlgorithm::Aes256Gcm
-            | AeadAlgorithm::ChaCha20Poly1305
-            | AeadAlgorithm::HpkeExport => Ok(()),
+            #[cfg(feature = "aes-gcm")]
+            AeadAlgorithm::Aes128Gcm | AeadAlgorithm::Aes256Gcm => Ok(()),
+            #[cfg(feature = "chacha20poly1305")]
+            AeadAlgorithm::ChaCha20Poly1305 => Ok(()),
+            AeadAlgorithm::HpkeExport => Ok(()),
+            #[allow(unreachable_patterns)]
+            _ => Err(Error::UnknownAeadAlgorithm),
         }
     }
 }
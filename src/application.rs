@@ -0,0 +1,79 @@
+//! # Application Domain Separation
+//!
+//! Key pairs are often shared by several features of one product, e.g. a
+//! device key that receives backups and chat messages.
+//! If both features use the same `info`, or one feature's `info` can be
+//! chosen by an attacker, a message of one feature can be replayed to the
+//! other and is decrypted there.
+//!
+//! [`Hpke::for_application`] binds a configuration to an application label
+//! such as `"com.example.backup/v2"`.
+//! The label is mixed into the `info` of every setup of the configuration,
+//! so a message sealed for one application fails to open in any other
+//! application, or without a label.
+//!
+//! ```
+//! use hpke_rs::prelude::*;
+//! use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+//! use hpke_rs_rust_crypto::HpkeRustCrypto;
+//!
+//! let hpke = Hpke::<HpkeRustCrypto>::new(
+//!     HpkeMode::Base,
+//!     KemAlgorithm::DhKem25519,
+//!     KdfAlgorithm::HkdfSha256,
+//!     AeadAlgorithm::ChaCha20Poly1305,
+//! );
+//! let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+//! let backup = hpke.clone().for_application("com.example.backup/v2").unwrap();
+//! let chat = hpke.for_application("com.example.chat/v1").unwrap();
+//!
+//! let (enc, ct) = backup
+//!     .seal(&pk_r, b"", b"", b"backup", None, None, None)
+//!     .unwrap();
+//! assert!(chat.open(&enc, &sk_r, b"", b"", &ct, None, None, None).is_err());
+//! let pt = backup
+//!     .open(&enc, &sk_r, b"", b"", &ct, None, None, None)
+//!     .unwrap();
+//! assert_eq!(pt, b"backup");
+//! ```
+
+use hpke_rs_crypto::HpkeCrypto;
+
+use crate::{util, Hpke, HpkeError};
+
+impl<Crypto: HpkeCrypto> Hpke<Crypto> {
+    /// Bind this configuration to the application `label`, see the
+    /// [module documentation](crate::application).
+    ///
+    /// The `info` of every setup is prefixed with
+    ///
+    /// ```text
+    /// concat("HPKE-app", I2OSP(len(label), 2), label)
+    /// ```
+    ///
+    /// ahead of a [suite binding](Self::with_suite_binding).
+    /// This also applies to [`info_hash`](Self::info_hash) and key schedule
+    /// templates, but not to info hashes that are computed otherwise.
+    /// Returns [`HpkeError::InvalidInput`] if the `label` is empty or longer
+    /// than `u16::MAX` bytes.
+    pub fn for_application(mut self, label: &str) -> Result<Self, HpkeError> {
+        if label.is_empty() {
+            return Err(HpkeError::InvalidInput);
+        }
+        u16::try_from(label.len()).map_err(|_| HpkeError::InvalidInput)?;
+        self.application = Some(label.into());
+        Ok(self)
+    }
+
+    /// Get the application label of this configuration if it has one.
+    pub fn application(&self) -> Option<&str> {
+        self.application.as_deref()
+    }
+
+    /// The prefix of the `info` for the application label.
+    pub(crate) fn application_prefix(&self) -> Option<Vec<u8>> {
+        let label = self.application.as_deref()?;
+        let len = (label.len() as u16).to_be_bytes();
+        Some(util::concat(&[b"HPKE-app", &len, label.as_bytes()]))
+    }
+}
@@ -0,0 +1,123 @@
+//! # ASCII Armor
+//!
+//! ASCII armor wraps binary data such as public keys and ciphertexts in
+//! text that survives being pasted into tickets, emails, and configuration
+//! files.
+//! The format follows OpenPGP ([RFC 4880, Section 6.2]): a header line, the
+//! base64 encoded data in lines of 64 characters, the base64 encoded CRC-24
+//! of the data prefixed with `=`, and a footer line.
+//!
+//! ```text
+//! -----BEGIN HPKE PUBLIC KEY-----
+//! 3kQWMrcwZJmBNaDJNK7nVbSP2ECzw0Jt5ZAgGdhLzUk=
+//! =W/1f
+//! -----END HPKE PUBLIC KEY-----
+//! ```
+//!
+//! When reading armored data, text around the armor and whitespace at the
+//! start and end of lines are ignored.
+//!
+//! [RFC 4880, Section 6.2]: https://www.rfc-editor.org/rfc/rfc4880#section-6.2
+
+use crate::{
+    util::{base64_decode, base64_encode, BASE64},
+    HpkeError, HpkePublicKey,
+};
+
+/// The label of an armored public key.
+pub const PUBLIC_KEY: &str = "HPKE PUBLIC KEY";
+
+/// The label of an armored ciphertext.
+pub const CIPHERTEXT: &str = "HPKE CIPHERTEXT";
+
+/// The label of an armored envelope.
+pub const ENVELOPE: &str = "HPKE ENVELOPE";
+
+const LINE_LEN: usize = 64;
+
+/// Armor the `data` with the `label`, e.g. [`CIPHERTEXT`].
+pub fn armor(label: &str, data: &[u8]) -> String {
+    let body = base64_encode(BASE64, data, true);
+    let mut out = format!("-----BEGIN {label}-----\n");
+    // The base64 alphabet is ASCII such that the lines can be split anywhere.
+    for start in (0..body.len()).step_by(LINE_LEN) {
+        out.push_str(&body[start..body.len().min(start + LINE_LEN)]);
+        out.push('\n');
+    }
+    out.push('=');
+    out.push_str(&base64_encode(
+        BASE64,
+        &crc24(data).to_be_bytes()[1..],
+        true,
+    ));
+    out.push('\n');
+    out.push_str(&format!("-----END {label}-----\n"));
+    out
+}
+
+/// Read the data armored with the `label` from the `text`.
+///
+/// Returns [`HpkeError::InvalidInput`] if there is no armor with the
+/// `label`, the encoding is invalid, or the checksum doesn't match.
+pub fn dearmor(label: &str, text: &str) -> Result<Vec<u8>, HpkeError> {
+    let begin = format!("-----BEGIN {label}-----");
+    let end = format!("-----END {label}-----");
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| *line != begin)
+        .skip(1);
+
+    let mut body = String::new();
+    let mut checksum = None;
+    loop {
+        match lines.next() {
+            None => return Err(HpkeError::InvalidInput),
+            Some(line) if line == end => break,
+            Some(_) if checksum.is_some() => return Err(HpkeError::InvalidInput),
+            Some(line) => match line.strip_prefix('=') {
+                Some(crc) => checksum = Some(base64_decode(BASE64, crc, true)?),
+                None => body.push_str(line),
+            },
+        }
+    }
+
+    let data = base64_decode(BASE64, &body, true)?;
+    match checksum {
+        Some(crc) if crc == crc24(&data).to_be_bytes()[1..] => Ok(data),
+        _ => Err(HpkeError::InvalidInput),
+    }
+}
+
+impl HpkePublicKey {
+    /// Armor this public key with the [`PUBLIC_KEY`] label.
+    pub fn to_armor(&self) -> String {
+        armor(PUBLIC_KEY, self.as_slice())
+    }
+
+    /// Read a public key armored with the [`PUBLIC_KEY`] label.
+    ///
+    /// See [`dearmor`].
+    pub fn from_armor(text: &str) -> Result<Self, HpkeError> {
+        dearmor(PUBLIC_KEY, text).map(Self::new)
+    }
+}
+
+/// The CRC-24 of OpenPGP ([RFC 4880, Section 6.1]).
+///
+/// [RFC 4880, Section 6.1]: https://www.rfc-editor.org/rfc/rfc4880#section-6.1
+fn crc24(data: &[u8]) -> u32 {
+    const INIT: u32 = 0xb7_04ce;
+    const POLY: u32 = 0x186_4cfb;
+    let mut crc = INIT;
+    for &b in data {
+        crc ^= u32::from(b) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x100_0000 != 0 {
+                crc ^= POLY;
+            }
+        }
+    }
+    crc & 0xff_ffff
+}
@@ -0,0 +1,431 @@
+//! # Asynchronous Streaming Encryption
+//!
+//! [`AsyncEncryptingWriter`] and [`AsyncDecryptingReader`] are the
+//! asynchronous counterparts of the [`stream`](crate::stream) writer and
+//! reader and produce and consume the same records.
+//!
+//! With the `futures-io` feature they implement the `AsyncWrite` and
+//! `AsyncRead` traits of [`futures_io`], and with the `tokio` feature the
+//! ones of [`tokio::io`].
+//! The underlying writer or reader has to be [`Unpin`] (use [`Box::pin`]
+//! otherwise).
+//!
+//! Closing the writer (`close` or `shutdown`) seals the last chunk, so it
+//! has to be closed after the last write.
+
+use std::{
+    io,
+    pin::Pin,
+    task::{self, ready, Poll},
+};
+
+use hpke_rs_crypto::HpkeCrypto;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::{
+    stream::{io_error, truncated, Framing, HEADER_LEN},
+    Context, HpkeError,
+};
+
+/// An asynchronous writer that encrypts everything written to it with a
+/// sender [`Context`], see the [module documentation](self).
+pub struct AsyncEncryptingWriter<Crypto: 'static + HpkeCrypto, W> {
+    inner: W,
+    context: Box<Context<Crypto>>,
+    framing: Framing,
+    /// The plain text of the current chunk.
+    chunk: Zeroizing<Vec<u8>>,
+    /// Sealed records that are not written to `inner` yet.
+    pending: Vec<u8>,
+    finished: bool,
+}
+
+impl<Crypto: HpkeCrypto, W: Unpin> AsyncEncryptingWriter<Crypto, W> {
+    /// Encrypt everything written to this writer with the `context` and the
+    /// associated data `aad`, and write the records to `inner`.
+    pub fn new(context: Context<Crypto>, aad: &[u8], inner: W) -> Self {
+        Self {
+            inner,
+            context: Box::new(context),
+            framing: Framing::new(aad),
+            chunk: Zeroizing::new(Vec::new()),
+            pending: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Split the plain text into chunks of `chunk_size` bytes.
+    ///
+    /// Returns [`HpkeError::InvalidInput`] if `chunk_size` is `0` or larger
+    /// than [`MAX_CHUNK_SIZE`](crate::stream::MAX_CHUNK_SIZE).
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Result<Self, HpkeError> {
+        self.framing.set_chunk_size(chunk_size)?;
+        Ok(self)
+    }
+
+    /// The length of the stream for `pt_len` bytes of plain text, see
+    /// [`EncryptingWriter::ct_len`](crate::stream::EncryptingWriter::ct_len).
+    pub fn ct_len(&self, pt_len: usize) -> usize {
+        self.framing.ct_len(&self.context, pt_len)
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Unwrap the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn seal_chunk(&mut self, last: bool) -> io::Result<()> {
+        self.framing
+            .seal(&mut self.context, &self.chunk, last, &mut self.pending)
+            .map_err(io_error)?;
+        self.chunk.zeroize();
+        Ok(())
+    }
+
+    fn poll_pending(
+        &mut self,
+        cx: &mut task::Context<'_>,
+        write: &mut impl FnMut(Pin<&mut W>, &mut task::Context<'_>, &[u8]) -> Poll<io::Result<usize>>,
+    ) -> Poll<io::Result<()>> {
+        while !self.pending.is_empty() {
+            match ready!(write(Pin::new(&mut self.inner), cx, &self.pending))? {
+                0 => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                n => {
+                    self.pending.drain(..n);
+                }
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_write_with(
+        &mut self,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+        mut write: impl FnMut(Pin<&mut W>, &mut task::Context<'_>, &[u8]) -> Poll<io::Result<usize>>,
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_pending(cx, &mut write))?;
+        if self.finished {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "HPKE stream is closed",
+            )));
+        }
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        // A full chunk is only sealed once more data arrives because the
+        // last chunk has to be marked as such.
+        if self.chunk.len() == self.framing.chunk_size() {
+            self.seal_chunk(false)?;
+            ready!(self.poll_pending(cx, &mut write))?;
+        }
+        let n = buf.len().min(self.framing.chunk_size() - self.chunk.len());
+        self.chunk.extend_from_slice(&buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush_with(
+        &mut self,
+        cx: &mut task::Context<'_>,
+        mut write: impl FnMut(Pin<&mut W>, &mut task::Context<'_>, &[u8]) -> Poll<io::Result<usize>>,
+        flush: impl FnOnce(Pin<&mut W>, &mut task::Context<'_>) -> Poll<io::Result<()>>,
+    ) -> Poll<io::Result<()>> {
+        ready!(self.poll_pending(cx, &mut write))?;
+        if !self.chunk.is_empty() {
+            self.seal_chunk(false)?;
+            ready!(self.poll_pending(cx, &mut write))?;
+        }
+        flush(Pin::new(&mut self.inner), cx)
+    }
+
+    fn poll_close_with(
+        &mut self,
+        cx: &mut task::Context<'_>,
+        mut write: impl FnMut(Pin<&mut W>, &mut task::Context<'_>, &[u8]) -> Poll<io::Result<usize>>,
+        close: impl FnOnce(Pin<&mut W>, &mut task::Context<'_>) -> Poll<io::Result<()>>,
+    ) -> Poll<io::Result<()>> {
+        ready!(self.poll_pending(cx, &mut write))?;
+        if !self.finished {
+            self.seal_chunk(true)?;
+            self.finished = true;
+            ready!(self.poll_pending(cx, &mut write))?;
+        }
+        close(Pin::new(&mut self.inner), cx)
+    }
+}
+
+/// The state of an [`AsyncDecryptingReader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReadState {
+    /// Reading the header of the next record.
+    Header {
+        filled: usize,
+    },
+    /// Reading the ciphertext of a record.
+    Record {
+        filled: usize,
+        last: bool,
+    },
+    /// Returning the plain text of a record.
+    Plain {
+        position: usize,
+        last: bool,
+    },
+    Finished,
+    Failed,
+}
+
+/// An asynchronous reader that decrypts a stream written by an
+/// [`AsyncEncryptingWriter`] or an
+/// [`EncryptingWriter`](crate::stream::EncryptingWriter) with a receiver
+/// [`Context`], see the [module documentation](self).
+///
+/// Errors are reported like by the
+/// [`DecryptingReader`](crate::stream::DecryptingReader).
+pub struct AsyncDecryptingReader<Crypto: 'static + HpkeCrypto, R> {
+    inner: R,
+    context: Box<Context<Crypto>>,
+    framing: Framing,
+    header: [u8; HEADER_LEN],
+    /// The ciphertext or plain text of the current record.
+    chunk: Zeroizing<Vec<u8>>,
+    state: ReadState,
+}
+
+impl<Crypto: HpkeCrypto, R: Unpin> AsyncDecryptingReader<Crypto, R> {
+    /// Decrypt the stream read from `inner` with the `context` and the
+    /// associated data `aad`.
+    pub fn new(context: Context<Crypto>, aad: &[u8], inner: R) -> Self {
+        Self {
+            inner,
+            context: Box::new(context),
+            framing: Framing::new(aad),
+            header: [0; HEADER_LEN],
+            chunk: Zeroizing::new(Vec::new()),
+            state: ReadState::Header { filled: 0 },
+        }
+    }
+
+    /// Accept chunks of up to `chunk_size` bytes.
+    ///
+    /// This has to be at least the chunk size of the writer.
+    /// Returns [`HpkeError::InvalidInput`] if `chunk_size` is `0` or larger
+    /// than [`MAX_CHUNK_SIZE`](crate::stream::MAX_CHUNK_SIZE).
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Result<Self, HpkeError> {
+        self.framing.set_chunk_size(chunk_size)?;
+        Ok(self)
+    }
+
+    /// Whether the last record has been decrypted.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.state,
+            ReadState::Finished | ReadState::Plain { last: true, .. }
+        )
+    }
+
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Unwrap the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Read records until there's plain text to return or the stream is
+    /// finished.
+    fn poll_fill(
+        &mut self,
+        cx: &mut task::Context<'_>,
+        mut read: impl FnMut(Pin<&mut R>, &mut task::Context<'_>, &mut [u8]) -> Poll<io::Result<usize>>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            match self.state {
+                ReadState::Failed => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "HPKE stream failed",
+                    )))
+                }
+                ReadState::Finished => return Poll::Ready(Ok(())),
+                ReadState::Plain { position, last } => {
+                    if position < self.chunk.len() {
+                        return Poll::Ready(Ok(()));
+                    }
+                    self.state = if last {
+                        ReadState::Finished
+                    } else {
+                        ReadState::Header { filled: 0 }
+                    };
+                }
+                ReadState::Header { filled } if filled == HEADER_LEN => {
+                    let (len, last) = self
+                        .framing
+                        .header(&self.context, self.header)
+                        .map_err(io_error)?;
+                    self.chunk.zeroize();
+                    self.chunk.resize(len, 0);
+                    self.state = ReadState::Record { filled: 0, last };
+                }
+                ReadState::Header { filled } => {
+                    let n = ready!(read(
+                        Pin::new(&mut self.inner),
+                        cx,
+                        &mut self.header[filled..]
+                    ))?;
+                    if n == 0 {
+                        return Poll::Ready(Err(truncated()));
+                    }
+                    self.state = ReadState::Header { filled: filled + n };
+                }
+                ReadState::Record { filled, last } if filled == self.chunk.len() => {
+                    self.framing
+                        .open(&mut self.context, &mut self.chunk, last)
+                        .map_err(io_error)?;
+                    self.state = ReadState::Plain { position: 0, last };
+                }
+                ReadState::Record { filled, last } => {
+                    let n = ready!(read(
+                        Pin::new(&mut self.inner),
+                        cx,
+                        &mut self.chunk[filled..]
+                    ))?;
+                    if n == 0 {
+                        return Poll::Ready(Err(truncated()));
+                    }
+                    self.state = ReadState::Record {
+                        filled: filled + n,
+                        last,
+                    };
+                }
+            }
+        }
+    }
+
+    /// Decrypt into `buf` with the `read` function of the underlying reader.
+    fn poll_read_with(
+        &mut self,
+        cx: &mut task::Context<'_>,
+        buf: &mut [u8],
+        read: impl FnMut(Pin<&mut R>, &mut task::Context<'_>, &mut [u8]) -> Poll<io::Result<usize>>,
+    ) -> Poll<io::Result<usize>> {
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        if let Err(e) = ready!(self.poll_fill(cx, read)) {
+            self.state = ReadState::Failed;
+            self.chunk.zeroize();
+            return Poll::Ready(Err(e));
+        }
+        let ReadState::Plain { position, last } = self.state else {
+            return Poll::Ready(Ok(0));
+        };
+        let n = buf.len().min(self.chunk.len() - position);
+        buf[..n].copy_from_slice(&self.chunk[position..position + n]);
+        self.state = ReadState::Plain {
+            position: position + n,
+            last,
+        };
+        Poll::Ready(Ok(n))
+    }
+}
+
+#[cfg(feature = "futures-io")]
+mod futures_impls {
+    use futures_io::{AsyncRead, AsyncWrite};
+
+    use super::*;
+
+    impl<Crypto: HpkeCrypto, W: AsyncWrite + Unpin> AsyncWrite for AsyncEncryptingWriter<Crypto, W> {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut task::Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.get_mut().poll_write_with(cx, buf, W::poll_write)
+        }
+
+        /// Seal the buffered plain text as a (shorter) chunk, write all
+        /// records, and flush the underlying writer.
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+            self.get_mut()
+                .poll_flush_with(cx, W::poll_write, W::poll_flush)
+        }
+
+        /// Seal the last chunk, write all records, and close the underlying
+        /// writer.
+        fn poll_close(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+            self.get_mut()
+                .poll_close_with(cx, W::poll_write, W::poll_close)
+        }
+    }
+
+    impl<Crypto: HpkeCrypto, R: AsyncRead + Unpin> AsyncRead for AsyncDecryptingReader<Crypto, R> {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut task::Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            self.get_mut().poll_read_with(cx, buf, R::poll_read)
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+mod tokio_impls {
+    use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+    use super::*;
+
+    impl<Crypto: HpkeCrypto, W: AsyncWrite + Unpin> AsyncWrite for AsyncEncryptingWriter<Crypto, W> {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut task::Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.get_mut().poll_write_with(cx, buf, W::poll_write)
+        }
+
+        /// Seal the buffered plain text as a (shorter) chunk, write all
+        /// records, and flush the underlying writer.
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+            self.get_mut()
+                .poll_flush_with(cx, W::poll_write, W::poll_flush)
+        }
+
+        /// Seal the last chunk, write all records, and shut down the
+        /// underlying writer.
+        fn poll_shutdown(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+            self.get_mut()
+                .poll_close_with(cx, W::poll_write, W::poll_shutdown)
+        }
+    }
+
+    impl<Crypto: HpkeCrypto, R: AsyncRead + Unpin> AsyncRead for AsyncDecryptingReader<Crypto, R> {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut task::Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            let n = ready!(self.get_mut().poll_read_with(
+                cx,
+                buf.initialize_unfilled(),
+                |inner, cx, out| {
+                    let mut out = ReadBuf::new(out);
+                    ready!(inner.poll_read(cx, &mut out))?;
+                    Poll::Ready(Ok(out.filled().len()))
+                }
+            ))?;
+            buf.advance(n);
+            Poll::Ready(Ok(()))
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! # Audit Hooks
+//!
+//! An [`AuditObserver`] registered on a configuration with
+//! [`Hpke::with_audit_observer`] is notified of every setup, seal, open,
+//! and export, whether it succeeded or not.
+//! This allows deployments that have to keep an audit trail of their
+//! cryptographic operations to record them without wrapping every call.
+//!
+//! Contexts set up by the configuration (and [`SyncSenderContext`]s made
+//! from them) report to the same observer.
+//! An [`AuditEvent`] only carries non-secret metadata: the operation, the
+//! [`HpkeConfig`], the [`KeyId`] of the recipient where it is known, the
+//! sequence number, and the error.
+//! Neither keys, nor plain texts, nor any other inputs are passed to the
+//! observer.
+//!
+//! The observer is called synchronously on the thread that performs the
+//! operation and should not block.
+//!
+//! [`SyncSenderContext`]: crate::SyncSenderContext
+
+use std::sync::Arc;
+
+use hpke_rs_crypto::HpkeCrypto;
+
+use crate::{Hpke, HpkeConfig, HpkeError, KeyId};
+
+/// The audited operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditOperation {
+    /// [`Hpke::setup_sender`], including single-shot sealing.
+    SetupSender,
+    /// [`Hpke::setup_receiver`], including single-shot opening.
+    SetupReceiver,
+    /// Sealing a message with a context.
+    Seal,
+    /// Opening a message with a context.
+    Open,
+    /// Exporting a secret from a context.
+    Export,
+}
+
+/// An audited operation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEvent {
+    /// The operation.
+    pub operation: AuditOperation,
+    /// The mode, the algorithms, and the version of the operation.
+    pub config: HpkeConfig,
+    /// The key id of the recipient's public key.
+    ///
+    /// This is only known to senders. Receivers are set up with the private
+    /// key only and report `None`.
+    pub key_id: Option<KeyId>,
+    /// The sequence number of a sealed or opened message.
+    ///
+    /// This is `None` for the other operations and if no sequence number
+    /// was reserved.
+    pub sequence_number: Option<u32>,
+    /// The error if the operation failed.
+    pub error: Option<HpkeError>,
+}
+
+/// An observer of [`AuditEvent`]s, see the [module documentation](self).
+///
+/// This is implemented for closures that take an [`AuditEvent`].
+pub trait AuditObserver: Send + Sync {
+    /// Record the `event`.
+    fn observe(&self, event: &AuditEvent);
+}
+
+impl<F: Fn(&AuditEvent) + Send + Sync> AuditObserver for F {
+    fn observe(&self, event: &AuditEvent) {
+        self(event)
+    }
+}
+
+/// The observer of a configuration and its contexts.
+#[derive(Clone)]
+pub(crate) struct Auditor {
+    observer: Arc<dyn AuditObserver>,
+    key_id: Option<KeyId>,
+}
+
+impl Auditor {
+    /// The same observer for a context with the recipient `key_id`.
+    pub(crate) fn with_key_id(&self, key_id: Option<KeyId>) -> Self {
+        Self {
+            observer: self.observer.clone(),
+            key_id,
+        }
+    }
+
+    /// Report the `result` of `operation`.
+    pub(crate) fn report<T>(
+        &self,
+        operation: AuditOperation,
+        config: HpkeConfig,
+        sequence_number: Option<u32>,
+        result: &Result<T, HpkeError>,
+    ) {
+        self.observer.observe(&AuditEvent {
+            operation,
+            config,
+            key_id: self.key_id,
+            sequence_number,
+            error: result.as_ref().err().cloned(),
+        });
+    }
+}
+
+impl std::fmt::Debug for Auditor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Auditor")
+            .field("key_id", &self.key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Report the `result` of `operation` to `auditor` if there is one.
+#[inline]
+pub(crate) fn report<T>(
+    auditor: &Option<Auditor>,
+    operation: AuditOperation,
+    config: HpkeConfig,
+    sequence_number: Option<u32>,
+    result: &Result<T, HpkeError>,
+) {
+    if let Some(auditor) = auditor {
+        auditor.report(operation, config, sequence_number, result);
+    }
+}
+
+impl<Crypto: HpkeCrypto> Hpke<Crypto> {
+    /// Report every operation of this configuration and its contexts to
+    /// `observer`.
+    ///
+    /// Clones of this configuration share the observer.
+    pub fn with_audit_observer(mut self, observer: impl AuditObserver + 'static) -> Self {
+        self.auditor = Some(Auditor {
+            observer: Arc::new(observer),
+            key_id: None,
+        });
+        self
+    }
+}
@@ -0,0 +1,347 @@
+//! # Encrypted Backups
+//!
+//! A self-contained, versioned encoding for data at rest that is encrypted
+//! to an HPKE public key or, with the `passphrase` feature, to a passphrase.
+//!
+//! The payload is split into chunks of [`CHUNK_SIZE`] bytes that are sealed
+//! with one [`Context`](crate::Context) such that large backups don't have
+//! to be sealed as a single message.
+//! The header is the `info` of the key schedule, and every chunk is sealed
+//! with a one byte additional data that marks the last chunk.
+//! Reordered, dropped, or truncated chunks therefore fail to open.
+//!
+//! ```text
+//! struct {
+//!     opaque magic[8] = "HPKE-BAK";
+//!     uint8 version = 1;
+//!     uint16 kem_id;
+//!     uint16 kdf_id;
+//!     uint16 aead_id;
+//!     uint8 protection;      // 1: public key, 2: passphrase
+//!     opaque params<0..2^16-1>;
+//!     uint32 chunk_size;
+//!     opaque enc<0..2^16-1>;
+//!     Chunk chunks[];
+//! } Backup;
+//!
+//! struct {
+//!     uint32 length;
+//!     opaque ciphertext[length];
+//! } Chunk;
+//! ```
+//!
+//! The `params` are the 8 byte [`KeyId`] of the recipient key for a public
+//! key, and the KDF with its parameters followed by a 16 byte salt for a
+//! passphrase (see [`protected_key`](crate::protected_key)).
+//! A passphrase backup is sealed to a key pair derived from the passphrase.
+//!
+//! Backups are parsed strictly: anything but the exact encoding, e.g.
+//! trailing bytes or a short chunk that is not the last one, is rejected.
+//! Backups with a newer format version or protection are rejected with
+//! [`HpkeError::UnknownMode`] such that older readers fail clearly.
+//!
+//! ```
+//! use hpke_rs::{backup, prelude::*};
+//! use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+//! use hpke_rs_rust_crypto::HpkeRustCrypto;
+//!
+//! let hpke = Hpke::<HpkeRustCrypto>::new(
+//!     HpkeMode::Base,
+//!     KemAlgorithm::DhKem25519,
+//!     KdfAlgorithm::HkdfSha256,
+//!     AeadAlgorithm::ChaCha20Poly1305,
+//! );
+//! let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+//!
+//! let sealed = backup::seal(&hpke, &pk_r, b"data").unwrap();
+//! assert_eq!(backup::open(&hpke, &sk_r, &sealed).unwrap(), b"data");
+//! ```
+
+use hpke_rs_crypto::HpkeCrypto;
+#[cfg(feature = "passphrase")]
+use zeroize::Zeroize;
+
+#[cfg(feature = "passphrase")]
+use crate::protected_key::{PassphraseKdf, SALT_LEN};
+use crate::{
+    ciphersuite::Ciphersuite,
+    util::{take, take_u16, take_u32},
+    Hpke, HpkeError, HpkePrivateKey, HpkePublicKey, KeyId, Mode,
+};
+
+const MAGIC: &[u8] = b"HPKE-BAK";
+const VERSION: u8 = 1;
+
+const PROTECTION_PUBLIC_KEY: u8 = 1;
+const PROTECTION_PASSPHRASE: u8 = 2;
+
+const MORE_CHUNKS: &[u8] = &[0];
+const LAST_CHUNK: &[u8] = &[1];
+
+/// The size of the plain text chunks of a backup.
+pub const CHUNK_SIZE: u32 = 1 << 16;
+
+/// The header of an encrypted backup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupHeader {
+    /// The format version.
+    pub version: u8,
+    /// The ciphersuite the backup is sealed with.
+    pub suite: Ciphersuite,
+    /// The key id of the recipient key, or `None` for a passphrase backup.
+    pub key_id: Option<KeyId>,
+    /// The size of the plain text chunks.
+    pub chunk_size: u32,
+}
+
+/// A parsed backup.
+struct Parsed<'a> {
+    header: BackupHeader,
+    protection: u8,
+    #[cfg_attr(not(feature = "passphrase"), allow(dead_code))]
+    params: &'a [u8],
+    /// The encoded header, used as `info`.
+    info: &'a [u8],
+    enc: &'a [u8],
+    chunks: Vec<&'a [u8]>,
+}
+
+impl BackupHeader {
+    /// Read the header of the encrypted `backup`, e.g. to pick the
+    /// configuration and private key to open it with.
+    ///
+    /// Returns [`HpkeError::UnknownMode`] if the format version, protection,
+    /// or an algorithm is unknown, and [`HpkeError::InvalidInput`] if the
+    /// encoding is malformed.
+    pub fn parse(backup: &[u8]) -> Result<Self, HpkeError> {
+        parse(backup).map(|parsed| parsed.header)
+    }
+}
+
+/// Seal `data` to the public key `pk_r` with the `hpke` configuration.
+///
+/// Returns [`HpkeError::InvalidConfig`] if `hpke` is not in the base mode.
+pub fn seal<Crypto: HpkeCrypto>(
+    hpke: &Hpke<Crypto>,
+    pk_r: &HpkePublicKey,
+    data: &[u8],
+) -> Result<Vec<u8>, HpkeError> {
+    let key_id = hpke.key_id(pk_r)?;
+    seal_to(hpke, pk_r, PROTECTION_PUBLIC_KEY, key_id.as_slice(), data)
+}
+
+/// Open a backup sealed with [`seal`] with the private key `sk_r`.
+///
+/// Returns [`HpkeError::InvalidConfig`] if the ciphersuite of the backup is
+/// not the one of `hpke` or `hpke` is not in the base mode, and
+/// [`HpkeError::OpenError`] if the key is wrong or the backup has been
+/// tampered with.
+pub fn open<Crypto: HpkeCrypto>(
+    hpke: &Hpke<Crypto>,
+    sk_r: &HpkePrivateKey,
+    backup: &[u8],
+) -> Result<Vec<u8>, HpkeError> {
+    let parsed = parse(backup)?;
+    if parsed.protection != PROTECTION_PUBLIC_KEY {
+        return Err(HpkeError::InvalidConfig);
+    }
+    open_with(hpke, sk_r, &parsed)
+}
+
+/// Seal `data` to the `passphrase` with the `hpke` configuration.
+///
+/// The key pair is derived from the passphrase with the `kdf` and a random
+/// salt.
+/// Returns [`HpkeError::InvalidConfig`] if `hpke` is not in the base mode.
+#[cfg(feature = "passphrase")]
+pub fn seal_with_passphrase<Crypto: HpkeCrypto>(
+    hpke: &Hpke<Crypto>,
+    passphrase: &[u8],
+    kdf: &PassphraseKdf,
+    data: &[u8],
+) -> Result<Vec<u8>, HpkeError> {
+    let salt = hpke.random(SALT_LEN)?;
+    let mut params = Vec::new();
+    kdf.encode(&mut params);
+    params.extend_from_slice(&salt);
+
+    let key_pair = passphrase_key_pair(hpke, passphrase, kdf, &salt)?;
+    seal_to(
+        hpke,
+        key_pair.public_key(),
+        PROTECTION_PASSPHRASE,
+        &params,
+        data,
+    )
+}
+
+/// Open a backup sealed with [`seal_with_passphrase`] with the `passphrase`.
+///
+/// Returns [`HpkeError::InvalidConfig`] if the ciphersuite of the backup is
+/// not the one of `hpke` or `hpke` is not in the base mode, and
+/// [`HpkeError::OpenError`] if the passphrase is wrong or the backup has been
+/// tampered with.
+#[cfg(feature = "passphrase")]
+pub fn open_with_passphrase<Crypto: HpkeCrypto>(
+    hpke: &Hpke<Crypto>,
+    passphrase: &[u8],
+    backup: &[u8],
+) -> Result<Vec<u8>, HpkeError> {
+    let parsed = parse(backup)?;
+    if parsed.protection != PROTECTION_PASSPHRASE {
+        return Err(HpkeError::InvalidConfig);
+    }
+    check_config(hpke, &parsed.header)?;
+    let mut params = parsed.params;
+    let kdf = PassphraseKdf::decode(&mut params)?;
+    let salt = take(&mut params, SALT_LEN)?;
+    if !params.is_empty() {
+        return Err(HpkeError::InvalidInput);
+    }
+    let key_pair = passphrase_key_pair(hpke, passphrase, &kdf, salt)?;
+    open_with(hpke, key_pair.private_key(), &parsed)
+}
+
+#[cfg(feature = "passphrase")]
+fn passphrase_key_pair<Crypto: HpkeCrypto>(
+    hpke: &Hpke<Crypto>,
+    passphrase: &[u8],
+    kdf: &PassphraseKdf,
+    salt: &[u8],
+) -> Result<crate::HpkeKeyPair, HpkeError> {
+    let mut ikm = vec![0u8; hpke.kem_id.private_key_len()];
+    kdf.derive(passphrase, salt, &mut ikm)?;
+    let key_pair = hpke.derive_key_pair(&ikm);
+    ikm.zeroize();
+    key_pair
+}
+
+fn seal_to<Crypto: HpkeCrypto>(
+    hpke: &Hpke<Crypto>,
+    pk_r: &HpkePublicKey,
+    protection: u8,
+    params: &[u8],
+    data: &[u8],
+) -> Result<Vec<u8>, HpkeError> {
+    if hpke.mode != Mode::Base {
+        return Err(HpkeError::InvalidConfig);
+    }
+    let (kem_id, kdf_id, aead_id) = hpke.ciphersuite().ids();
+    let params_len = u16::try_from(params.len()).map_err(|_| HpkeError::InvalidInput)?;
+    let mut out = MAGIC.to_vec();
+    out.push(VERSION);
+    out.extend_from_slice(&kem_id.to_be_bytes());
+    out.extend_from_slice(&kdf_id.to_be_bytes());
+    out.extend_from_slice(&aead_id.to_be_bytes());
+    out.push(protection);
+    out.extend_from_slice(&params_len.to_be_bytes());
+    out.extend_from_slice(params);
+    out.extend_from_slice(&CHUNK_SIZE.to_be_bytes());
+
+    let (enc, mut context) = hpke.setup_sender(pk_r, &out, None, None, None)?;
+    let enc_len = u16::try_from(enc.len()).map_err(|_| HpkeError::InvalidInput)?;
+    out.extend_from_slice(&enc_len.to_be_bytes());
+    out.extend_from_slice(&enc);
+
+    // An empty backup still has one (empty) last chunk.
+    let chunks: Vec<&[u8]> = if data.is_empty() {
+        vec![&[]]
+    } else {
+        data.chunks(CHUNK_SIZE as usize).collect()
+    };
+    for (i, chunk) in chunks.iter().enumerate() {
+        let aad = if i + 1 == chunks.len() {
+            LAST_CHUNK
+        } else {
+            MORE_CHUNKS
+        };
+        let ct = context.seal(aad, chunk)?;
+        let ct_len = u32::try_from(ct.len()).map_err(|_| HpkeError::InvalidInput)?;
+        out.extend_from_slice(&ct_len.to_be_bytes());
+        out.extend_from_slice(&ct);
+    }
+    Ok(out)
+}
+
+fn open_with<Crypto: HpkeCrypto>(
+    hpke: &Hpke<Crypto>,
+    sk_r: &HpkePrivateKey,
+    parsed: &Parsed,
+) -> Result<Vec<u8>, HpkeError> {
+    check_config(hpke, &parsed.header)?;
+    let mut context = hpke.setup_receiver(parsed.enc, sk_r, parsed.info, None, None, None)?;
+    let chunk_size = parsed.header.chunk_size as usize;
+    let mut out = Vec::new();
+    for (i, ct) in parsed.chunks.iter().enumerate() {
+        let last = i + 1 == parsed.chunks.len();
+        let aad = if last { LAST_CHUNK } else { MORE_CHUNKS };
+        let chunk = context.open(aad, ct)?;
+        if chunk.len() > chunk_size || (!last && chunk.len() != chunk_size) {
+            return Err(HpkeError::InvalidInput);
+        }
+        out.extend_from_slice(&chunk);
+    }
+    Ok(out)
+}
+
+fn check_config<Crypto: HpkeCrypto>(
+    hpke: &Hpke<Crypto>,
+    header: &BackupHeader,
+) -> Result<(), HpkeError> {
+    if hpke.mode != Mode::Base || hpke.ciphersuite() != header.suite {
+        return Err(HpkeError::InvalidConfig);
+    }
+    Ok(())
+}
+
+fn parse(backup: &[u8]) -> Result<Parsed<'_>, HpkeError> {
+    let mut reader = backup;
+    if take(&mut reader, MAGIC.len())? != MAGIC {
+        return Err(HpkeError::InvalidInput);
+    }
+    let version = take(&mut reader, 1)?[0];
+    if version != VERSION {
+        return Err(HpkeError::UnknownMode);
+    }
+    let suite = Ciphersuite::from_ids(
+        take_u16(&mut reader)?,
+        take_u16(&mut reader)?,
+        take_u16(&mut reader)?,
+    )?;
+    let protection = take(&mut reader, 1)?[0];
+    let params_len = take_u16(&mut reader)?;
+    let params = take(&mut reader, params_len.into())?;
+    let key_id = match protection {
+        PROTECTION_PUBLIC_KEY => Some(KeyId::try_from(params)?),
+        PROTECTION_PASSPHRASE => None,
+        _ => return Err(HpkeError::UnknownMode),
+    };
+    let chunk_size = take_u32(&mut reader)?;
+    if chunk_size == 0 {
+        return Err(HpkeError::InvalidInput);
+    }
+    let info = &backup[..backup.len() - reader.len()];
+    let enc_len = take_u16(&mut reader)?;
+    let enc = take(&mut reader, enc_len.into())?;
+    let mut chunks = Vec::new();
+    while !reader.is_empty() {
+        let ct_len = take_u32(&mut reader)?;
+        chunks.push(take(&mut reader, ct_len as usize)?);
+    }
+    if chunks.is_empty() {
+        return Err(HpkeError::InvalidInput);
+    }
+    Ok(Parsed {
+        header: BackupHeader {
+            version,
+            suite,
+            key_id,
+            chunk_size,
+        },
+        protection,
+        params,
+        info,
+        enc,
+        chunks,
+    })
+}
@@ -0,0 +1,194 @@
+//! # Bech32 Public Keys
+//!
+//! Human-friendly public key strings in the style of [age] recipients, for
+//! sharing keys over chat or typing them by hand:
+//!
+//! ```text
+//! hpke1qqsq...
+//! ```
+//!
+//! A public key string is the [Bech32m] encoding (BIP 350) with the
+//! human-readable part [`PUBLIC_KEY_HRP`] of
+//!
+//! ```text
+//! concat(I2OSP(kem_id, 2), pkRm)
+//! ```
+//!
+//! The checksum detects typos, i.e. up to 4 substituted characters in
+//! strings of up to 89 characters, and almost all other errors.
+//! Like age, the 90 character limit of Bech32 is lifted for the larger keys
+//! of some KEMs.
+//! Strings are encoded in lower case and decoded in either case, but not in
+//! mixed case.
+//!
+//! [`encode`] and [`decode`] implement Bech32m for other human-readable
+//! parts.
+//! [`KeyStore::get_by_public_key_string`](crate::keystore::KeyStore::get_by_public_key_string)
+//! looks up the key pair of a public key string.
+//!
+//! [age]: https://age-encryption.org/v1
+//! [Bech32m]: https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki
+
+use hpke_rs_crypto::types::KemAlgorithm;
+
+use crate::{util::take_u16, HpkeError, HpkePublicKey};
+
+/// The human-readable part of public key strings.
+pub const PUBLIC_KEY_HRP: &str = "hpke";
+
+/// The Bech32 alphabet.
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// The constant the Bech32m checksum is XORed with.
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+
+/// The length of the checksum in characters.
+const CHECKSUM_LEN: usize = 6;
+
+/// Encode `data` with the human-readable part `hrp` in Bech32m.
+///
+/// Returns [`HpkeError::InvalidInput`] if `hrp` is empty or contains
+/// characters outside of the printable ASCII range or upper case letters.
+pub fn encode(hrp: &str, data: &[u8]) -> Result<String, HpkeError> {
+    if hrp.is_empty()
+        || !hrp
+            .bytes()
+            .all(|c| (33..=126).contains(&c) && !c.is_ascii_uppercase())
+    {
+        return Err(HpkeError::InvalidInput);
+    }
+    let mut values = convert_bits(data, 8, 5, true)?;
+    let checksum = polymod(
+        hrp_expand(hrp)
+            .into_iter()
+            .chain(values.iter().copied())
+            .chain([0; CHECKSUM_LEN]),
+    ) ^ BECH32M_CONST;
+    values.extend((0..CHECKSUM_LEN).map(|i| ((checksum >> (5 * (5 - i))) & 31) as u8));
+
+    let mut out = String::with_capacity(hrp.len() + 1 + values.len());
+    out.push_str(hrp);
+    out.push('1');
+    out.extend(values.into_iter().map(|v| CHARSET[v as usize] as char));
+    Ok(out)
+}
+
+/// Decode the Bech32m string `s` into its (lower case) human-readable part
+/// and data.
+///
+/// Returns [`HpkeError::InvalidInput`] if the string is malformed, in mixed
+/// case, or the checksum doesn't match.
+pub fn decode(s: &str) -> Result<(String, Vec<u8>), HpkeError> {
+    if !s.bytes().all(|c| (33..=126).contains(&c))
+        || (s.bytes().any(|c| c.is_ascii_lowercase()) && s.bytes().any(|c| c.is_ascii_uppercase()))
+    {
+        return Err(HpkeError::InvalidInput);
+    }
+    let s = s.to_ascii_lowercase();
+    let separator = s.rfind('1').ok_or(HpkeError::InvalidInput)?;
+    let (hrp, data) = (&s[..separator], &s[separator + 1..]);
+    if hrp.is_empty() || data.len() < CHECKSUM_LEN {
+        return Err(HpkeError::InvalidInput);
+    }
+    let values = data
+        .bytes()
+        .map(|c| {
+            CHARSET
+                .iter()
+                .position(|&d| d == c)
+                .map(|v| v as u8)
+                .ok_or(HpkeError::InvalidInput)
+        })
+        .collect::<Result<Vec<u8>, _>>()?;
+    if polymod(hrp_expand(hrp).into_iter().chain(values.iter().copied())) != BECH32M_CONST {
+        return Err(HpkeError::InvalidInput);
+    }
+    let data = convert_bits(&values[..values.len() - CHECKSUM_LEN], 5, 8, false)?;
+    Ok((hrp.to_owned(), data))
+}
+
+impl HpkePublicKey {
+    /// Encode this public key for the KEM `kem_id` as Bech32m string, see the
+    /// [module documentation](crate::bech32).
+    pub fn to_bech32(&self, kem_id: KemAlgorithm) -> String {
+        let mut data = (kem_id as u16).to_be_bytes().to_vec();
+        data.extend_from_slice(self.as_slice());
+        encode(PUBLIC_KEY_HRP, &data).expect("The public key HRP is valid")
+    }
+
+    /// Decode a public key string created with
+    /// [`to_bech32`](Self::to_bech32) into the KEM and the public key.
+    ///
+    /// Returns [`HpkeError::InvalidInput`] if the string is malformed, is not
+    /// a public key string, or the key doesn't have the length of the KEM,
+    /// and [`HpkeError::UnknownMode`] if the KEM is unknown.
+    pub fn from_bech32(s: &str) -> Result<(KemAlgorithm, Self), HpkeError> {
+        let (hrp, data) = decode(s)?;
+        if hrp != PUBLIC_KEY_HRP {
+            return Err(HpkeError::InvalidInput);
+        }
+        let mut reader = data.as_slice();
+        let kem_id = KemAlgorithm::try_from(take_u16(&mut reader)?)?;
+        if reader.len() != kem_id.public_key_len() {
+            return Err(HpkeError::InvalidInput);
+        }
+        Ok((kem_id, HpkePublicKey::new(reader.to_vec())))
+    }
+}
+
+/// The BCH checksum of the 5 bit `values`.
+fn polymod(values: impl IntoIterator<Item = u8>) -> u32 {
+    const GENERATOR: [u32; 5] = [
+        0x3b6a_57b2,
+        0x2650_8e6d,
+        0x1ea1_19fa,
+        0x3d42_33dd,
+        0x2a14_62b3,
+    ];
+    let mut checksum = 1u32;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x1ff_ffff) << 5) ^ u32::from(value);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+/// The human-readable part as input to the checksum.
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut out: Vec<u8> = hrp.bytes().map(|c| c >> 5).collect();
+    out.push(0);
+    out.extend(hrp.bytes().map(|c| c & 31));
+    out
+}
+
+/// Regroup the `from` bit values of `data` into `to` bit values.
+///
+/// Without `pad`, the leftover bits must be fewer than `from` and zero.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, HpkeError> {
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let max = (1u32 << to) - 1;
+    let max_acc = (1u32 << (from + to - 1)) - 1;
+    let mut out = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for &value in data {
+        acc = ((acc << from) | u32::from(value)) & max_acc;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            out.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max != 0 {
+        return Err(HpkeError::InvalidInput);
+    }
+    Ok(out)
+}
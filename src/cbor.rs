@@ -0,0 +1,145 @@
+//! # CBOR Envelopes
+//!
+//! With the `cbor` feature, an [`Envelope`] can be encoded in canonical CBOR
+//! ([RFC 8949, Section 4.2.1]) for ecosystems that use CBOR rather than
+//! TLS-style structs.
+//!
+//! ```text
+//! HpkeEnvelope = [
+//!   kem: uint,
+//!   kdf: uint,
+//!   aead: uint,
+//!   enc: bstr,
+//!   ct: bstr,
+//!   ? kid: bstr .size 8,
+//! ]
+//! ```
+//!
+//! The algorithms are the HPKE registry identifiers.
+//! Decoding is strict: only the deterministic encoding of an envelope is
+//! accepted, i.e. definite lengths, the shortest form of all integers and
+//! lengths, and no trailing bytes.
+//!
+//! [RFC 8949, Section 4.2.1]: https://www.rfc-editor.org/rfc/rfc8949#section-4.2.1
+
+use crate::{ciphersuite::Ciphersuite, envelope::Envelope, util::take, HpkeError, KeyId};
+
+const MAJOR_UINT: u8 = 0;
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_ARRAY: u8 = 4;
+
+impl Envelope {
+    /// Encode this envelope in canonical CBOR.
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let (kem, kdf, aead) = self.suite.ids();
+        let mut out = Vec::with_capacity(32 + self.enc.len() + self.ciphertext.len());
+        write_head(&mut out, MAJOR_ARRAY, 5 + u64::from(self.key_id.is_some()));
+        for id in [kem, kdf, aead] {
+            write_head(&mut out, MAJOR_UINT, id.into());
+        }
+        write_bytes(&mut out, &self.enc);
+        write_bytes(&mut out, &self.ciphertext);
+        if let Some(key_id) = &self.key_id {
+            write_bytes(&mut out, key_id.as_slice());
+        }
+        out
+    }
+
+    /// Decode an envelope from canonical CBOR.
+    ///
+    /// Returns [`HpkeError::UnknownMode`] if an algorithm is not registered
+    /// and [`HpkeError::InvalidInput`] if the encoding is malformed or not
+    /// canonical.
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, HpkeError> {
+        let mut reader = bytes;
+        let len = read_head(&mut reader, MAJOR_ARRAY)?;
+        if len != 5 && len != 6 {
+            return Err(HpkeError::InvalidInput);
+        }
+        let mut ids = [0u16; 3];
+        for id in ids.iter_mut() {
+            *id = read_head(&mut reader, MAJOR_UINT)?
+                .try_into()
+                .map_err(|_| HpkeError::InvalidInput)?;
+        }
+        let enc = read_bytes(&mut reader)?.to_vec();
+        let ciphertext = read_bytes(&mut reader)?.to_vec();
+        let key_id = if len == 6 {
+            Some(KeyId::try_from(read_bytes(&mut reader)?)?)
+        } else {
+            None
+        };
+        if !reader.is_empty() {
+            return Err(HpkeError::InvalidInput);
+        }
+        Ok(Self {
+            suite: Ciphersuite::from_ids(ids[0], ids[1], ids[2])?,
+            key_id,
+            enc,
+            ciphertext,
+        })
+    }
+}
+
+/// Write the head of a data item in its shortest form.
+fn write_head(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    match value {
+        0..=23 => out.push(major | value as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, value as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_head(out, MAJOR_BYTES, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Read the head of a data item of the `major` type and return its argument.
+///
+/// Indefinite lengths and arguments that are not in their shortest form are
+/// rejected.
+fn read_head(reader: &mut &[u8], major: u8) -> Result<u64, HpkeError> {
+    let initial = take(reader, 1)?[0];
+    if initial >> 5 != major {
+        return Err(HpkeError::InvalidInput);
+    }
+    let (value, min) = match initial & 0x1f {
+        info @ 0..=23 => return Ok(info.into()),
+        24 => (take(reader, 1)?[0].into(), 24),
+        25 => (u16::from_be_bytes(take_array(reader)?).into(), 0x100),
+        26 => (u32::from_be_bytes(take_array(reader)?).into(), 0x1_0000),
+        27 => (u64::from_be_bytes(take_array(reader)?), 0x1_0000_0000),
+        _ => return Err(HpkeError::InvalidInput),
+    };
+    if value < min {
+        return Err(HpkeError::InvalidInput);
+    }
+    Ok(value)
+}
+
+fn read_bytes<'a>(reader: &mut &'a [u8]) -> Result<&'a [u8], HpkeError> {
+    let len = read_head(reader, MAJOR_BYTES)?;
+    take(
+        reader,
+        usize::try_from(len).map_err(|_| HpkeError::InvalidInput)?,
+    )
+}
+
+fn take_array<const N: usize>(reader: &mut &[u8]) -> Result<[u8; N], HpkeError> {
+    take(reader, N)?
+        .try_into()
+        .map_err(|_| HpkeError::InvalidInput)
+}
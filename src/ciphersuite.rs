@@ -0,0 +1,342 @@
+//! # Ciphersuites
+//!
+//! A [`Ciphersuite`] is the combination of a KEM, a KDF, and an AEAD.
+//! The IANA HPKE registries ([`KEM_REGISTRY`], [`KDF_REGISTRY`], and
+//! [`AEAD_REGISTRY`]) hold the names, identifiers, and security levels of the
+//! algorithms.
+//!
+//! ```
+//! use hpke_rs::ciphersuite::Ciphersuite;
+//! use hpke_rs_crypto::types::KemAlgorithm;
+//!
+//! let suite = Ciphersuite::from_ids(0x0020, 0x0001, 0x0001).unwrap();
+//! assert_eq!(suite.kem, KemAlgorithm::DhKem25519);
+//! assert_eq!(
+//!     suite.to_string(),
+//!     "DHKEM(X25519, HKDF-SHA256), HKDF-SHA256, AES-128-GCM"
+//! );
+//! assert_eq!(suite.security_level(), 128);
+//! ```
+//!
+//! ## Downgrade Protection
+//!
+//! HPKE binds the mode and the ciphersuite of a configuration into its key
+//! schedule, so a peer using a different suite fails to decrypt.
+//! It doesn't bind how the suite was chosen: an attacker that removes the
+//! strong suites from an offer makes both sides agree on a weak one.
+//! With [`Hpke::with_suite_binding`], both sides mix the suites that were
+//! offered into the `info` of every setup, such that a tampered offer breaks
+//! decryption.
+
+use std::borrow::Cow;
+
+use hpke_rs_crypto::{
+    types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm},
+    HpkeCrypto,
+};
+
+use crate::{Hpke, HpkeError};
+
+/// An entry of an IANA HPKE registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegistryEntry<A: 'static> {
+    /// The algorithm.
+    pub algorithm: A,
+    /// The identifier of the algorithm.
+    pub id: u16,
+    /// The name of the algorithm in the registry.
+    pub name: &'static str,
+    /// The (classical) security level of the algorithm in bits.
+    pub security_level: u16,
+}
+
+/// The HPKE KEM identifiers registry.
+pub const KEM_REGISTRY: &[RegistryEntry<KemAlgorithm>] = &[
+    RegistryEntry {
+        algorithm: KemAlgorithm::DhKemP256,
+        id: 0x0010,
+        name: "DHKEM(P-256, HKDF-SHA256)",
+        security_level: 128,
+    },
+    RegistryEntry {
+        algorithm: KemAlgorithm::DhKemP384,
+        id: 0x0011,
+        name: "DHKEM(P-384, HKDF-SHA384)",
+        security_level: 192,
+    },
+    RegistryEntry {
+        algorithm: KemAlgorithm::DhKemP521,
+        id: 0x0012,
+        name: "DHKEM(P-521, HKDF-SHA512)",
+        security_level: 256,
+    },
+    RegistryEntry {
+        algorithm: KemAlgorithm::DhKem25519,
+        id: 0x0020,
+        name: "DHKEM(X25519, HKDF-SHA256)",
+        security_level: 128,
+    },
+    RegistryEntry {
+        algorithm: KemAlgorithm::DhKem448,
+        id: 0x0021,
+        name: "DHKEM(X448, HKDF-SHA512)",
+        security_level: 192,
+    },
+];
+
+/// The HPKE KDF identifiers registry.
+pub const KDF_REGISTRY: &[RegistryEntry<KdfAlgorithm>] = &[
+    RegistryEntry {
+        algorithm: KdfAlgorithm::HkdfSha256,
+        id: 0x0001,
+        name: "HKDF-SHA256",
+        security_level: 128,
+    },
+    RegistryEntry {
+        algorithm: KdfAlgorithm::HkdfSha384,
+        id: 0x0002,
+        name: "HKDF-SHA384",
+        security_level: 192,
+    },
+    RegistryEntry {
+        algorithm: KdfAlgorithm::HkdfSha512,
+        id: 0x0003,
+        name: "HKDF-SHA512",
+        security_level: 256,
+    },
+    // Private use, not registered with IANA.
+    RegistryEntry {
+        algorithm: KdfAlgorithm::HkdfSha3_256,
+        id: 0xFF01,
+        name: "HKDF-SHA3-256",
+        security_level: 128,
+    },
+    // Private use, not registered with IANA.
+    RegistryEntry {
+        algorithm: KdfAlgorithm::Kmac256,
+        id: 0xFF02,
+        name: "KMAC256",
+        security_level: 256,
+    },
+];
+
+/// The HPKE AEAD identifiers registry.
+///
+/// The export-only AEAD doesn't encrypt and meets every security level.
+pub const AEAD_REGISTRY: &[RegistryEntry<AeadAlgorithm>] = &[
+    RegistryEntry {
+        algorithm: AeadAlgorithm::Aes128Gcm,
+        id: 0x0001,
+        name: "AES-128-GCM",
+        security_level: 128,
+    },
+    RegistryEntry {
+        algorithm: AeadAlgorithm::Aes256Gcm,
+        id: 0x0002,
+        name: "AES-256-GCM",
+        security_level: 256,
+    },
+    RegistryEntry {
+        algorithm: AeadAlgorithm::ChaCha20Poly1305,
+        id: 0x0003,
+        name: "ChaCha20Poly1305",
+        security_level: 256,
+    },
+    RegistryEntry {
+        algorithm: AeadAlgorithm::HpkeExport,
+        id: 0xFFFF,
+        name: "Export-only",
+        security_level: u16::MAX,
+    },
+];
+
+fn entry<A: PartialEq + Copy>(
+    registry: &'static [RegistryEntry<A>],
+    algorithm: A,
+) -> &'static RegistryEntry<A> {
+    registry
+        .iter()
+        .find(|entry| entry.algorithm == algorithm)
+        .expect("Every algorithm is in the registry")
+}
+
+fn lookup<A>(
+    registry: &'static [RegistryEntry<A>],
+    f: impl Fn(&RegistryEntry<A>) -> bool,
+) -> Result<&'static RegistryEntry<A>, HpkeError> {
+    registry
+        .iter()
+        .find(|entry| f(entry))
+        .ok_or(HpkeError::UnknownMode)
+}
+
+/// An HPKE ciphersuite.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serialization",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct Ciphersuite {
+    /// The KEM.
+    pub kem: KemAlgorithm,
+    /// The KDF.
+    pub kdf: KdfAlgorithm,
+    /// The AEAD.
+    pub aead: AeadAlgorithm,
+}
+
+impl Ciphersuite {
+    /// Create a new ciphersuite.
+    pub const fn new(kem: KemAlgorithm, kdf: KdfAlgorithm, aead: AeadAlgorithm) -> Self {
+        Self { kem, kdf, aead }
+    }
+
+    /// Look up the ciphersuite with the registered identifiers.
+    ///
+    /// Returns [`HpkeError::UnknownMode`] if an identifier is not registered.
+    pub fn from_ids(kem: u16, kdf: u16, aead: u16) -> Result<Self, HpkeError> {
+        Ok(Self {
+            kem: lookup(KEM_REGISTRY, |e| e.id == kem)?.algorithm,
+            kdf: lookup(KDF_REGISTRY, |e| e.id == kdf)?.algorithm,
+            aead: lookup(AEAD_REGISTRY, |e| e.id == aead)?.algorithm,
+        })
+    }
+
+    /// Look up the ciphersuite with the registered names, e.g.
+    /// `("DHKEM(X25519, HKDF-SHA256)", "HKDF-SHA256", "AES-128-GCM")`.
+    ///
+    /// Returns [`HpkeError::UnknownMode`] if a name is not registered.
+    pub fn from_names(kem: &str, kdf: &str, aead: &str) -> Result<Self, HpkeError> {
+        Ok(Self {
+            kem: lookup(KEM_REGISTRY, |e| e.name == kem)?.algorithm,
+            kdf: lookup(KDF_REGISTRY, |e| e.name == kdf)?.algorithm,
+            aead: lookup(AEAD_REGISTRY, |e| e.name == aead)?.algorithm,
+        })
+    }
+
+    /// The registry entry of the KEM.
+    pub fn kem_entry(&self) -> &'static RegistryEntry<KemAlgorithm> {
+        entry(KEM_REGISTRY, self.kem)
+    }
+
+    /// The registry entry of the KDF.
+    pub fn kdf_entry(&self) -> &'static RegistryEntry<KdfAlgorithm> {
+        entry(KDF_REGISTRY, self.kdf)
+    }
+
+    /// The registry entry of the AEAD.
+    pub fn aead_entry(&self) -> &'static RegistryEntry<AeadAlgorithm> {
+        entry(AEAD_REGISTRY, self.aead)
+    }
+
+    /// The identifiers `(kem_id, kdf_id, aead_id)`.
+    pub fn ids(&self) -> (u16, u16, u16) {
+        (
+            self.kem_entry().id,
+            self.kdf_entry().id,
+            self.aead_entry().id,
+        )
+    }
+
+    /// The security level of the ciphersuite in bits, i.e. the lowest security
+    /// level of its algorithms.
+    pub fn security_level(&self) -> u16 {
+        self.kem_entry()
+            .security_level
+            .min(self.kdf_entry().security_level)
+            .min(self.aead_entry().security_level)
+    }
+
+    /// The suite id used in the labels of the key schedule.
+    ///
+    /// ```text
+    /// suite_id = concat(
+    ///   "HPKE",
+    ///   I2OSP(kem_id, 2),
+    ///   I2OSP(kdf_id, 2),
+    ///   I2OSP(aead_id, 2)
+    /// )
+    /// ```
+    pub fn suite_id(&self) -> [u8; 10] {
+        let (kem, kdf, aead) = self.ids();
+        let mut suite_id = [0u8; 10];
+        suite_id[..4].copy_from_slice(b"HPKE");
+        suite_id[4..6].copy_from_slice(&kem.to_be_bytes());
+        suite_id[6..8].copy_from_slice(&kdf.to_be_bytes());
+        suite_id[8..].copy_from_slice(&aead.to_be_bytes());
+        suite_id
+    }
+}
+
+impl<Crypto: HpkeCrypto> Hpke<Crypto> {
+    /// Bind the negotiated mode and ciphersuite and the `offered` suites
+    /// into the `info` of every setup, see
+    /// [Downgrade Protection](self#downgrade-protection).
+    ///
+    /// Sender and receiver have to pass the same suites in the same order,
+    /// e.g. the list that one side offered and the other selected from.
+    /// The `info` is prefixed with
+    ///
+    /// ```text
+    /// binding = concat(
+    ///   "HPKE-suites",
+    ///   I2OSP(mode, 1), I2OSP(kem_id, 2), I2OSP(kdf_id, 2), I2OSP(aead_id, 2),
+    ///   I2OSP(len(offered), 2),
+    ///   I2OSP(kem_id, 2), I2OSP(kdf_id, 2), I2OSP(aead_id, 2), ...
+    /// )
+    /// ```
+    ///
+    /// This also applies to [`info_hash`](Self::info_hash) and key schedule
+    /// templates, but not to info hashes that are computed otherwise.
+    /// Returns [`HpkeError::InvalidConfig`] if the suite of this
+    /// configuration is not one of the `offered` suites, and
+    /// [`HpkeError::InvalidInput`] if more than `u16::MAX` suites are offered.
+    pub fn with_suite_binding(mut self, offered: &[Ciphersuite]) -> Result<Self, HpkeError> {
+        if !offered.contains(&self.ciphersuite()) {
+            return Err(HpkeError::InvalidConfig);
+        }
+        let count = u16::try_from(offered.len()).map_err(|_| HpkeError::InvalidInput)?;
+        let (kem, kdf, aead) = self.ciphersuite().ids();
+        let mut binding = b"HPKE-suites".to_vec();
+        binding.push(self.mode as u8);
+        for id in [kem, kdf, aead, count] {
+            binding.extend_from_slice(&id.to_be_bytes());
+        }
+        for suite in offered {
+            let (kem, kdf, aead) = suite.ids();
+            for id in [kem, kdf, aead] {
+                binding.extend_from_slice(&id.to_be_bytes());
+            }
+        }
+        self.suite_binding = Some(binding.into());
+        Ok(self)
+    }
+
+    /// The `info` prefixed with the application label and the suite binding
+    /// if there are any.
+    pub(crate) fn bound_info<'a>(&self, info: &'a [u8]) -> Cow<'a, [u8]> {
+        match (self.application_prefix(), &self.suite_binding) {
+            (None, None) => Cow::Borrowed(info),
+            (application, binding) => Cow::Owned(
+                [
+                    application.as_deref().unwrap_or_default(),
+                    binding.as_deref().unwrap_or_default(),
+                    info,
+                ]
+                .concat(),
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for Ciphersuite {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}, {}, {}",
+            self.kem_entry().name,
+            self.kdf_entry().name,
+            self.aead_entry().name
+        )
+    }
+}
@@ -0,0 +1,540 @@
+//! # CMS KEMRecipientInfo
+//!
+//! With the `cms` feature, a content-encryption key (CEK) of a CMS
+//! `EnvelopedData` or `AuthEnvelopedData` can be transported to a recipient
+//! with the KEMs of this crate in a `KEMRecipientInfo` ([RFC 9629]).
+//!
+//! ```text
+//! KEMRecipientInfo ::= SEQUENCE {
+//!   version CMSVersion,  -- always set to 0
+//!   rid RecipientIdentifier,
+//!   kem KEMAlgorithmIdentifier,
+//!   kemct OCTET STRING,
+//!   kdf KeyDerivationAlgorithmIdentifier,
+//!   kekLength INTEGER (1..65535),
+//!   ukm [0] EXPLICIT UserKeyingMaterial OPTIONAL,
+//!   wrap KeyEncryptionAlgorithmIdentifier,
+//!   encryptedKey EncryptedKey }
+//! ```
+//!
+//! The key-encryption key is derived from the KEM shared secret with HKDF
+//! ([RFC 8619]) and the DER encoding of
+//!
+//! ```text
+//! CMSORIforKEMOtherInfo ::= SEQUENCE {
+//!   wrap KeyEncryptionAlgorithmIdentifier,
+//!   kekLength INTEGER (1..65535),
+//!   ukm [0] EXPLICIT UserKeyingMaterial OPTIONAL }
+//! ```
+//!
+//! as `info` and wraps the CEK with AES Key Wrap ([RFC 3394]).
+//!
+//! RFC 9629 leaves the KEM algorithm identifiers to other specifications, so
+//! the identifier of the KEM is an input.
+//!
+//! [RFC 9629]: https://www.rfc-editor.org/rfc/rfc9629
+//! [RFC 8619]: https://www.rfc-editor.org/rfc/rfc8619
+//! [RFC 3394]: https://www.rfc-editor.org/rfc/rfc3394
+
+use hpke_rs_crypto::{types::KdfAlgorithm, HpkeCrypto};
+use zeroize::Zeroize;
+
+use crate::{kem::Kem, util::take, HpkeError, HpkePrivateKey, HpkePublicKey};
+
+/// `id-ori-kem`
+const ID_ORI_KEM: &[u64] = &[1, 2, 840, 113549, 1, 9, 16, 13, 3];
+/// `id-alg-hkdf-with-sha256`, `-sha384`, and `-sha512`
+const ID_ALG_HKDF_WITH_SHA256: &[u64] = &[1, 2, 840, 113549, 1, 9, 16, 3, 28];
+const ID_ALG_HKDF_WITH_SHA384: &[u64] = &[1, 2, 840, 113549, 1, 9, 16, 3, 29];
+const ID_ALG_HKDF_WITH_SHA512: &[u64] = &[1, 2, 840, 113549, 1, 9, 16, 3, 30];
+/// `id-aes128-wrap`, `id-aes192-wrap`, and `id-aes256-wrap`
+const ID_AES128_WRAP: &[u64] = &[2, 16, 840, 1, 101, 3, 4, 1, 5];
+const ID_AES192_WRAP: &[u64] = &[2, 16, 840, 1, 101, 3, 4, 1, 25];
+const ID_AES256_WRAP: &[u64] = &[2, 16, 840, 1, 101, 3, 4, 1, 45];
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_CONTEXT_0_PRIMITIVE: u8 = 0x80;
+const TAG_CONTEXT_0: u8 = 0xa0;
+const TAG_CONTEXT_4: u8 = 0xa4;
+
+/// An X.509 `AlgorithmIdentifier`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlgorithmIdentifier {
+    /// The content octets of the DER encoded object identifier.
+    pub oid: Vec<u8>,
+    /// The DER encoded parameters, if present.
+    pub parameters: Option<Vec<u8>>,
+}
+
+impl AlgorithmIdentifier {
+    /// An algorithm identifier with the object identifier `arcs` and without
+    /// parameters.
+    ///
+    /// Returns [`HpkeError::InvalidInput`] if `arcs` is not a valid object
+    /// identifier.
+    pub fn from_arcs(arcs: &[u64]) -> Result<Self, HpkeError> {
+        Ok(Self {
+            oid: encode_oid(arcs)?,
+            parameters: None,
+        })
+    }
+
+    /// The HKDF algorithm identifier for `kdf`.
+    ///
+    /// Returns [`HpkeError::UnknownMode`] for HKDF-SHA3-256 and KMAC256,
+    /// which have no identifier.
+    pub fn hkdf(kdf: KdfAlgorithm) -> Result<Self, HpkeError> {
+        let arcs = match kdf {
+            KdfAlgorithm::HkdfSha256 => ID_ALG_HKDF_WITH_SHA256,
+            KdfAlgorithm::HkdfSha384 => ID_ALG_HKDF_WITH_SHA384,
+            KdfAlgorithm::HkdfSha512 => ID_ALG_HKDF_WITH_SHA512,
+            KdfAlgorithm::HkdfSha3_256 | KdfAlgorithm::Kmac256 => {
+                return Err(HpkeError::UnknownMode)
+            }
+        };
+        Ok(Self::from_arcs(arcs).expect("The HKDF identifiers are valid"))
+    }
+
+    fn to_kdf(&self) -> Result<KdfAlgorithm, HpkeError> {
+        [
+            KdfAlgorithm::HkdfSha256,
+            KdfAlgorithm::HkdfSha384,
+            KdfAlgorithm::HkdfSha512,
+        ]
+        .into_iter()
+        .find(|&kdf| Self::hkdf(kdf).as_ref() == Ok(self))
+        .ok_or(HpkeError::UnknownMode)
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        let mut content = Vec::new();
+        write_tlv(&mut content, TAG_OID, &self.oid);
+        if let Some(parameters) = &self.parameters {
+            content.extend_from_slice(parameters);
+        }
+        write_tlv(out, TAG_SEQUENCE, &content);
+    }
+
+    fn decode(reader: &mut &[u8]) -> Result<Self, HpkeError> {
+        let mut content = read_tlv(reader, TAG_SEQUENCE)?;
+        let oid = read_tlv(&mut content, TAG_OID)?.to_vec();
+        let parameters = if content.is_empty() {
+            None
+        } else {
+            let parameters = content;
+            read_any(&mut content)?;
+            if !content.is_empty() {
+                return Err(HpkeError::InvalidInput);
+            }
+            Some(parameters.to_vec())
+        };
+        Ok(Self { oid, parameters })
+    }
+}
+
+/// The AES Key Wrap algorithms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyWrap {
+    /// `id-aes128-wrap`
+    Aes128,
+    /// `id-aes192-wrap`
+    Aes192,
+    /// `id-aes256-wrap`
+    Aes256,
+}
+
+impl KeyWrap {
+    /// The length of the key-encryption key in bytes.
+    pub const fn kek_len(&self) -> usize {
+        match self {
+            KeyWrap::Aes128 => 16,
+            KeyWrap::Aes192 => 24,
+            KeyWrap::Aes256 => 32,
+        }
+    }
+
+    /// The algorithm identifier of the key wrap.
+    pub fn algorithm_identifier(&self) -> AlgorithmIdentifier {
+        let arcs = match self {
+            KeyWrap::Aes128 => ID_AES128_WRAP,
+            KeyWrap::Aes192 => ID_AES192_WRAP,
+            KeyWrap::Aes256 => ID_AES256_WRAP,
+        };
+        AlgorithmIdentifier::from_arcs(arcs).expect("The key wrap identifiers are valid")
+    }
+
+    fn from_algorithm_identifier(id: &AlgorithmIdentifier) -> Result<Self, HpkeError> {
+        [KeyWrap::Aes128, KeyWrap::Aes192, KeyWrap::Aes256]
+            .into_iter()
+            .find(|wrap| wrap.algorithm_identifier() == *id)
+            .ok_or(HpkeError::UnknownMode)
+    }
+
+    fn wrap(&self, kek: &[u8], cek: &[u8]) -> Result<Vec<u8>, HpkeError> {
+        let wrapped = match self {
+            KeyWrap::Aes128 => aes_kw::KekAes128::try_from(kek).and_then(|k| k.wrap_vec(cek)),
+            KeyWrap::Aes192 => aes_kw::KekAes192::try_from(kek).and_then(|k| k.wrap_vec(cek)),
+            KeyWrap::Aes256 => aes_kw::KekAes256::try_from(kek).and_then(|k| k.wrap_vec(cek)),
+        };
+        wrapped.map_err(|_| HpkeError::InvalidInput)
+    }
+
+    fn unwrap(&self, kek: &[u8], wrapped: &[u8]) -> Result<Vec<u8>, HpkeError> {
+        let cek = match self {
+            KeyWrap::Aes128 => aes_kw::KekAes128::try_from(kek).and_then(|k| k.unwrap_vec(wrapped)),
+            KeyWrap::Aes192 => aes_kw::KekAes192::try_from(kek).and_then(|k| k.unwrap_vec(wrapped)),
+            KeyWrap::Aes256 => aes_kw::KekAes256::try_from(kek).and_then(|k| k.unwrap_vec(wrapped)),
+        };
+        cek.map_err(|_| HpkeError::OpenError)
+    }
+}
+
+/// The CMS `RecipientIdentifier`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecipientIdentifier {
+    /// The DER encoded `IssuerAndSerialNumber` of the recipient's
+    /// certificate.
+    IssuerAndSerialNumber(Vec<u8>),
+    /// The `SubjectKeyIdentifier` of the recipient's certificate.
+    SubjectKeyIdentifier(Vec<u8>),
+}
+
+/// The algorithms of a [`KemRecipientInfo`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KemRecipientParams {
+    /// The algorithm identifier of the KEM.
+    pub kem: AlgorithmIdentifier,
+    /// The KDF to derive the key-encryption key.
+    pub kdf: KdfAlgorithm,
+    /// The key wrap algorithm.
+    pub wrap: KeyWrap,
+    /// The optional user keying material.
+    pub ukm: Option<Vec<u8>>,
+}
+
+/// A CMS `KEMRecipientInfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KemRecipientInfo {
+    /// The recipient identifier.
+    pub rid: RecipientIdentifier,
+    /// The KEM algorithm identifier.
+    pub kem: AlgorithmIdentifier,
+    /// The KEM ciphertext, i.e. the encapsulated secret.
+    pub kemct: Vec<u8>,
+    /// The KDF algorithm identifier.
+    pub kdf: AlgorithmIdentifier,
+    /// The length of the key-encryption key in bytes.
+    pub kek_length: u16,
+    /// The optional user keying material.
+    pub ukm: Option<Vec<u8>>,
+    /// The key wrap algorithm identifier.
+    pub wrap: AlgorithmIdentifier,
+    /// The wrapped content-encryption key.
+    pub encrypted_key: Vec<u8>,
+}
+
+impl KemRecipientInfo {
+    /// Encapsulate the content-encryption key `cek` to the public key `pk_r`
+    /// of the recipient `rid`.
+    pub fn encapsulate<Crypto: HpkeCrypto>(
+        kem: &Kem<Crypto>,
+        pk_r: &HpkePublicKey,
+        rid: RecipientIdentifier,
+        params: &KemRecipientParams,
+        cek: &[u8],
+    ) -> Result<Self, HpkeError> {
+        let kdf = AlgorithmIdentifier::hkdf(params.kdf)?;
+        let (ss, kemct) = kem.encaps(pk_r)?;
+        let wrap = params.wrap.algorithm_identifier();
+        let kek_length = params.wrap.kek_len() as u16;
+        let mut kek = derive_kek::<Crypto>(
+            params.kdf,
+            ss.as_slice(),
+            &wrap,
+            kek_length,
+            params.ukm.as_deref(),
+        )?;
+        let encrypted_key = params.wrap.wrap(&kek, cek);
+        kek.zeroize();
+        Ok(Self {
+            rid,
+            kem: params.kem.clone(),
+            kemct,
+            kdf,
+            kek_length,
+            ukm: params.ukm.clone(),
+            wrap,
+            encrypted_key: encrypted_key?,
+        })
+    }
+
+    /// Decapsulate the content-encryption key with the private key `sk_r`.
+    ///
+    /// Returns [`HpkeError::UnknownMode`] for unsupported KDF or key wrap
+    /// algorithms and [`HpkeError::OpenError`] if the key can't be unwrapped.
+    pub fn decapsulate<Crypto: HpkeCrypto>(
+        &self,
+        kem: &Kem<Crypto>,
+        sk_r: &HpkePrivateKey,
+    ) -> Result<Vec<u8>, HpkeError> {
+        let kdf = self.kdf.to_kdf()?;
+        let wrap = KeyWrap::from_algorithm_identifier(&self.wrap)?;
+        if usize::from(self.kek_length) != wrap.kek_len() {
+            return Err(HpkeError::InvalidInput);
+        }
+        let ss = kem.decaps(&self.kemct, sk_r)?;
+        let mut kek = derive_kek::<Crypto>(
+            kdf,
+            ss.as_slice(),
+            &self.wrap,
+            self.kek_length,
+            self.ukm.as_deref(),
+        )?;
+        let cek = wrap.unwrap(&kek, &self.encrypted_key);
+        kek.zeroize();
+        cek
+    }
+
+    /// DER encode the `KEMRecipientInfo`.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut content = Vec::new();
+        write_tlv(&mut content, TAG_INTEGER, &[0]);
+        match &self.rid {
+            RecipientIdentifier::IssuerAndSerialNumber(der) => content.extend_from_slice(der),
+            RecipientIdentifier::SubjectKeyIdentifier(ski) => {
+                write_tlv(&mut content, TAG_CONTEXT_0_PRIMITIVE, ski)
+            }
+        }
+        self.kem.encode(&mut content);
+        write_tlv(&mut content, TAG_OCTET_STRING, &self.kemct);
+        self.kdf.encode(&mut content);
+        write_tlv(&mut content, TAG_INTEGER, &encode_u16(self.kek_length));
+        if let Some(ukm) = &self.ukm {
+            write_tlv(&mut content, TAG_CONTEXT_0, &octet_string(ukm));
+        }
+        self.wrap.encode(&mut content);
+        write_tlv(&mut content, TAG_OCTET_STRING, &self.encrypted_key);
+        let mut out = Vec::new();
+        write_tlv(&mut out, TAG_SEQUENCE, &content);
+        out
+    }
+
+    /// Decode a DER encoded `KEMRecipientInfo`.
+    ///
+    /// Returns [`HpkeError::InvalidInput`] if the encoding is malformed.
+    pub fn decode(bytes: &[u8]) -> Result<Self, HpkeError> {
+        let mut reader = bytes;
+        let mut content = read_tlv(&mut reader, TAG_SEQUENCE)?;
+        if !reader.is_empty() || read_tlv(&mut content, TAG_INTEGER)? != [0] {
+            return Err(HpkeError::InvalidInput);
+        }
+        let rid = match content.first() {
+            Some(&TAG_SEQUENCE) => {
+                let start = content;
+                read_any(&mut content)?;
+                RecipientIdentifier::IssuerAndSerialNumber(
+                    start[..start.len() - content.len()].to_vec(),
+                )
+            }
+            _ => RecipientIdentifier::SubjectKeyIdentifier(
+                read_tlv(&mut content, TAG_CONTEXT_0_PRIMITIVE)?.to_vec(),
+            ),
+        };
+        let kem = AlgorithmIdentifier::decode(&mut content)?;
+        let kemct = read_tlv(&mut content, TAG_OCTET_STRING)?.to_vec();
+        let kdf = AlgorithmIdentifier::decode(&mut content)?;
+        let kek_length = decode_u16(read_tlv(&mut content, TAG_INTEGER)?)?;
+        let ukm = if content.first() == Some(&TAG_CONTEXT_0) {
+            let mut ukm = read_tlv(&mut content, TAG_CONTEXT_0)?;
+            let octets = read_tlv(&mut ukm, TAG_OCTET_STRING)?.to_vec();
+            if !ukm.is_empty() {
+                return Err(HpkeError::InvalidInput);
+            }
+            Some(octets)
+        } else {
+            None
+        };
+        let wrap = AlgorithmIdentifier::decode(&mut content)?;
+        let encrypted_key = read_tlv(&mut content, TAG_OCTET_STRING)?.to_vec();
+        if !content.is_empty() || kek_length == 0 {
+            return Err(HpkeError::InvalidInput);
+        }
+        Ok(Self {
+            rid,
+            kem,
+            kemct,
+            kdf,
+            kek_length,
+            ukm,
+            wrap,
+            encrypted_key,
+        })
+    }
+
+    /// DER encode the `KEMRecipientInfo` as `RecipientInfo`, i.e. as
+    /// `[4] OtherRecipientInfo` with the type `id-ori-kem`.
+    pub fn encode_recipient_info(&self) -> Vec<u8> {
+        let mut content = Vec::new();
+        write_tlv(
+            &mut content,
+            TAG_OID,
+            &encode_oid(ID_ORI_KEM).expect("id-ori-kem is valid"),
+        );
+        content.extend_from_slice(&self.encode());
+        let mut out = Vec::new();
+        write_tlv(&mut out, TAG_CONTEXT_4, &content);
+        out
+    }
+
+    /// Decode a DER encoded `RecipientInfo` that holds a `KEMRecipientInfo`.
+    ///
+    /// Returns [`HpkeError::UnknownMode`] for other recipient infos.
+    pub fn decode_recipient_info(bytes: &[u8]) -> Result<Self, HpkeError> {
+        let mut reader = bytes;
+        if reader.first() != Some(&TAG_CONTEXT_4) {
+            return Err(HpkeError::UnknownMode);
+        }
+        let mut content = read_tlv(&mut reader, TAG_CONTEXT_4)?;
+        if !reader.is_empty() {
+            return Err(HpkeError::InvalidInput);
+        }
+        if read_tlv(&mut content, TAG_OID)? != encode_oid(ID_ORI_KEM)? {
+            return Err(HpkeError::UnknownMode);
+        }
+        Self::decode(content)
+    }
+}
+
+/// `KEK = HKDF(salt = "", IKM = ss, info = DER(CMSORIforKEMOtherInfo), L = kekLength)`
+fn derive_kek<Crypto: HpkeCrypto>(
+    kdf: KdfAlgorithm,
+    ss: &[u8],
+    wrap: &AlgorithmIdentifier,
+    kek_length: u16,
+    ukm: Option<&[u8]>,
+) -> Result<Vec<u8>, HpkeError> {
+    let mut other_info = Vec::new();
+    wrap.encode(&mut other_info);
+    write_tlv(&mut other_info, TAG_INTEGER, &encode_u16(kek_length));
+    if let Some(ukm) = ukm {
+        write_tlv(&mut other_info, TAG_CONTEXT_0, &octet_string(ukm));
+    }
+    let mut info = Vec::new();
+    write_tlv(&mut info, TAG_SEQUENCE, &other_info);
+
+    let mut prk = Crypto::kdf_extract(kdf, &[], ss);
+    let kek = Crypto::kdf_expand(kdf, &prk, &info, kek_length.into());
+    prk.zeroize();
+    Ok(kek?)
+}
+
+// A minimal DER codec.
+
+fn write_tlv(out: &mut Vec<u8>, tag: u8, content: &[u8]) {
+    out.push(tag);
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let len_bytes = len.to_be_bytes();
+        let skip = len_bytes.iter().take_while(|&&b| b == 0).count();
+        out.push(0x80 | (len_bytes.len() - skip) as u8);
+        out.extend_from_slice(&len_bytes[skip..]);
+    }
+    out.extend_from_slice(content);
+}
+
+fn octet_string(value: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_tlv(&mut out, TAG_OCTET_STRING, value);
+    out
+}
+
+/// Read the tag and content of the next element.
+fn read_any<'a>(reader: &mut &'a [u8]) -> Result<(u8, &'a [u8]), HpkeError> {
+    let tag = take(reader, 1)?[0];
+    if tag & 0x1f == 0x1f {
+        // Multi-byte tags are not used here.
+        return Err(HpkeError::InvalidInput);
+    }
+    let first = take(reader, 1)?[0];
+    let len = if first < 0x80 {
+        usize::from(first)
+    } else {
+        let n = usize::from(first & 0x7f);
+        if n == 0 || n > size_of::<usize>() {
+            return Err(HpkeError::InvalidInput);
+        }
+        let len_bytes = take(reader, n)?;
+        let len = len_bytes
+            .iter()
+            .fold(0usize, |len, &b| (len << 8) | usize::from(b));
+        // DER requires the minimal length encoding.
+        if len_bytes[0] == 0 || len < 0x80 {
+            return Err(HpkeError::InvalidInput);
+        }
+        len
+    };
+    Ok((tag, take(reader, len)?))
+}
+
+/// Read the content of the next element, which must have the tag `tag`.
+fn read_tlv<'a>(reader: &mut &'a [u8], tag: u8) -> Result<&'a [u8], HpkeError> {
+    match read_any(reader)? {
+        (t, content) if t == tag => Ok(content),
+        _ => Err(HpkeError::InvalidInput),
+    }
+}
+
+fn encode_u16(value: u16) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let mut out = if bytes[0] == 0 {
+        vec![bytes[1]]
+    } else {
+        bytes.to_vec()
+    };
+    // A leading zero keeps the integer positive.
+    if out[0] & 0x80 != 0 {
+        out.insert(0, 0);
+    }
+    out
+}
+
+fn decode_u16(content: &[u8]) -> Result<u16, HpkeError> {
+    if content.is_empty() || content.len() > 3 || content[0] & 0x80 != 0 {
+        return Err(HpkeError::InvalidInput);
+    }
+    let content = match content {
+        [0, rest @ ..] if !rest.is_empty() => rest,
+        _ => content,
+    };
+    if content.len() > 2 {
+        return Err(HpkeError::InvalidInput);
+    }
+    Ok(content.iter().fold(0u16, |v, &b| (v << 8) | u16::from(b)))
+}
+
+fn encode_oid(arcs: &[u64]) -> Result<Vec<u8>, HpkeError> {
+    let (first, second, rest) = match arcs {
+        [first @ 0..=2, second, rest @ ..] => (*first, *second, rest),
+        _ => return Err(HpkeError::InvalidInput),
+    };
+    if first < 2 && second >= 40 {
+        return Err(HpkeError::InvalidInput);
+    }
+    let mut out = Vec::new();
+    let head = first * 40 + second;
+    for arc in std::iter::once(head).chain(rest.iter().copied()) {
+        let mut groups = vec![(arc & 0x7f) as u8];
+        let mut arc = arc >> 7;
+        while arc > 0 {
+            groups.push((arc & 0x7f) as u8 | 0x80);
+            arc >>= 7;
+        }
+        out.extend(groups.iter().rev());
+    }
+    Ok(out)
+}
@@ -0,0 +1,132 @@
+//! # Framed HPKE Records
+//!
+//! With the `tokio-util` feature, [`HpkeCodec`] is a [`Decoder`] and
+//! [`Encoder`] that turns a byte stream, such as a TCP connection wrapped in
+//! a [`Framed`](tokio_util::codec::Framed), into a channel of messages that
+//! are sealed with a [`Context`].
+//!
+//! Every message is sealed on its own with the next sequence number of the
+//! sender context and written as a length-prefixed record:
+//!
+//! ```text
+//! record = I2OSP(len(ct), 4) || ct
+//! ct = Seal(aad, message)
+//! ```
+//!
+//! A context only protects one direction.
+//! A codec that sends and receives needs a sender context for the outgoing
+//! and a receiver context for the incoming messages, e.g. set up from one
+//! encapsulation in each direction.
+
+use bytes::{Bytes, BytesMut};
+use hpke_rs_crypto::HpkeCrypto;
+use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec};
+
+use crate::{stream::io_error, Context, HpkeError, Plaintext};
+
+/// The default maximum length of a sealed message (16 MiB).
+pub const DEFAULT_MAX_RECORD_LENGTH: usize = 16 * 1024 * 1024;
+
+/// A codec for messages sealed with a [`Context`], see the
+/// [module documentation](self).
+///
+/// Encoding fails with [`HpkeError::InvalidConfig`] without a sender context
+/// and decoding without a receiver context.
+/// Errors are reported as [`std::io::Error`]s; HPKE errors have the kind
+/// [`std::io::ErrorKind::InvalidData`] and wrap the [`HpkeError`].
+pub struct HpkeCodec<Crypto: 'static + HpkeCrypto> {
+    sender: Option<Context<Crypto>>,
+    receiver: Option<Context<Crypto>>,
+    aad: Vec<u8>,
+    framing: LengthDelimitedCodec,
+}
+
+impl<Crypto: HpkeCrypto> HpkeCodec<Crypto> {
+    /// A codec that seals outgoing messages with the `sender` context and
+    /// opens incoming messages with the `receiver` context.
+    pub fn new(sender: Option<Context<Crypto>>, receiver: Option<Context<Crypto>>) -> Self {
+        Self {
+            sender,
+            receiver,
+            aad: Vec::new(),
+            framing: LengthDelimitedCodec::builder()
+                .length_field_length(4)
+                .max_frame_length(DEFAULT_MAX_RECORD_LENGTH)
+                .new_codec(),
+        }
+    }
+
+    /// A codec that only sends messages sealed with the `context`.
+    pub fn sender(context: Context<Crypto>) -> Self {
+        Self::new(Some(context), None)
+    }
+
+    /// A codec that only receives messages opened with the `context`.
+    pub fn receiver(context: Context<Crypto>) -> Self {
+        Self::new(None, Some(context))
+    }
+
+    /// Seal and open every message with the associated data `aad`.
+    pub fn with_aad(mut self, aad: &[u8]) -> Self {
+        self.aad = aad.to_vec();
+        self
+    }
+
+    /// Reject records with a ciphertext longer than `max_record_length`
+    /// bytes.
+    ///
+    /// Both sides should use the same maximum.
+    /// Encoding a message that is too long fails with
+    /// [`HpkeError::InvalidInput`].
+    pub fn with_max_record_length(mut self, max_record_length: usize) -> Self {
+        self.framing.set_max_frame_length(max_record_length);
+        self
+    }
+
+    /// The sender context.
+    pub fn sender_context(&self) -> Option<&Context<Crypto>> {
+        self.sender.as_ref()
+    }
+
+    /// The receiver context.
+    pub fn receiver_context(&self) -> Option<&Context<Crypto>> {
+        self.receiver.as_ref()
+    }
+}
+
+impl<Crypto: HpkeCrypto> Decoder for HpkeCodec<Crypto> {
+    type Item = Plaintext;
+    type Error = std::io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let receiver = self
+            .receiver
+            .as_mut()
+            .ok_or_else(|| io_error(HpkeError::InvalidConfig))?;
+        let Some(record) = self.framing.decode(src)? else {
+            return Ok(None);
+        };
+        receiver
+            .open(&self.aad, &record)
+            .map(Some)
+            .map_err(io_error)
+    }
+}
+
+impl<Crypto: HpkeCrypto, T: AsRef<[u8]>> Encoder<T> for HpkeCodec<Crypto> {
+    type Error = std::io::Error;
+
+    fn encode(&mut self, message: T, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let sender = self
+            .sender
+            .as_mut()
+            .ok_or_else(|| io_error(HpkeError::InvalidConfig))?;
+        // Check the length before the sequence number is used up.
+        let len = message.as_ref().len() + sender.config.aead_id.tag_length();
+        if len > self.framing.max_frame_length() {
+            return Err(io_error(HpkeError::InvalidInput));
+        }
+        let record = sender.seal(&self.aad, message.as_ref()).map_err(io_error)?;
+        self.framing.encode(Bytes::from(record), dst)
+    }
+}
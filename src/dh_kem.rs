@@ -6,14 +6,22 @@ use crate::util::*;
 use crate::{
     kdf::{labeled_expand, labeled_extract},
     kem::*,
+    HpkeVersion,
 };
 
 fn extract_and_expand<Crypto: HpkeCrypto>(
     alg: KemAlgorithm,
+    version: HpkeVersion,
     pk: PublicKey,
     kem_context: &[u8],
     suite_id: &[u8],
 ) -> Result<Vec<u8>, Error> {
+    #[cfg(feature = "legacy-draft")]
+    if version == HpkeVersion::Draft05 {
+        return crate::legacy::extract_and_expand::<Crypto>(alg, &pk, kem_context);
+    }
+    let _ = version;
+    Crypto::supports_kdf(alg.into())?;
     let prk = labeled_extract::<Crypto>(alg.into(), &[], suite_id, "eae_prk", &pk);
     labeled_expand::<Crypto>(
         alg.into(),
@@ -25,20 +33,45 @@ fn extract_and_expand<Crypto: HpkeCrypto>(
     )
 }
 
+/// DH with the public key `pk`, using the prepared key `prepared` if present.
+///
+/// An all-zero DH output means that `pk` is a point of low order.
+/// It is rejected as an invalid public key (RFC 9180, Section 7.1.4) instead
+/// of feeding a degenerate shared secret into the key schedule.
+#[inline]
+fn dh<Crypto: HpkeCrypto>(
+    alg: KemAlgorithm,
+    pk: &[u8],
+    prepared: Option<&Crypto::KemPublicKey>,
+    sk: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let dh = match prepared {
+        Some(pk) => Crypto::kem_derive_prepared(alg, pk, sk),
+        None => Crypto::kem_derive(alg, pk, sk),
+    }?;
+    // Check all bytes such that the time doesn't depend on the output.
+    if dh.iter().fold(0, |acc, b| acc | b) == 0 {
+        return Err(Error::KemInvalidPublicKey);
+    }
+    Ok(dh)
+}
+
 /// Serialize public key.
 /// This is an identity function for X25519.
-/// Because P256 public keys are already encoded before it is the identity
+/// Because P256 and P384 public keys are already encoded before it is the identity
 /// function here as well.
+/// The key is borrowed such that large public keys aren't copied.
 #[inline(always)]
-pub(super) fn serialize(pk: &[u8]) -> Vec<u8> {
-    pk.to_vec()
+pub(super) fn serialize(pk: &[u8]) -> &[u8] {
+    pk
 }
 
 #[inline(always)]
-pub(super) fn deserialize(enc: &[u8]) -> Vec<u8> {
-    enc.to_vec()
+pub(super) fn deserialize(enc: &[u8]) -> &[u8] {
+    enc
 }
 
+#[cfg(feature = "default-rng")]
 pub(super) fn key_gen<Crypto: HpkeCrypto>(
     alg: KemAlgorithm,
     prng: &mut Crypto::HpkePrng,
@@ -53,6 +86,7 @@ pub(super) fn derive_key_pair<Crypto: HpkeCrypto>(
     suite_id: &[u8],
     ikm: &[u8],
 ) -> Result<(PublicKey, PrivateKey), Error> {
+    Crypto::supports_kdf(alg.into())?;
     let dkp_prk = labeled_extract::<Crypto>(alg.into(), &[], suite_id, "dkp_prk", ikm);
 
     let sk = match alg {
@@ -64,7 +98,7 @@ pub(super) fn derive_key_pair<Crypto: HpkeCrypto>(
             &[],
             alg.private_key_len(),
         )?,
-        KemAlgorithm::DhKemP256 => {
+        KemAlgorithm::DhKemP256 | KemAlgorithm::DhKemP384 => {
             let mut ctr = 0u8;
             // Do rejection sampling trying to find a valid key.
             // It is expected that there aren't too many iteration and that
@@ -86,14 +120,14 @@ pub(super) fn derive_key_pair<Crypto: HpkeCrypto>(
                 if ctr == u8::MAX {
                     // If we get here we lost. This should never happen.
                     return Err(Error::CryptoLibraryError(
-                        "Unable to generate a valid P256 private key".to_string(),
+                        "Unable to generate a valid NIST curve private key".to_string(),
                     ));
                 }
                 ctr += 1;
             }
         }
         _ => {
-            panic!("This should be unreachable. Only x25519 and P256 KEMs are implemented")
+            panic!("This should be unreachable. Only x25519, P256, and P384 KEMs are implemented")
         }
     };
     Ok((Crypto::kem_derive_base(alg, &sk)?, sk))
@@ -101,40 +135,47 @@ pub(super) fn derive_key_pair<Crypto: HpkeCrypto>(
 
 pub(super) fn encaps<Crypto: HpkeCrypto>(
     alg: KemAlgorithm,
+    version: HpkeVersion,
     pk_r: &[u8],
+    pk_r_prepared: Option<&Crypto::KemPublicKey>,
     suite_id: &[u8],
     randomness: &[u8],
 ) -> Result<(Vec<u8>, Vec<u8>), Error> {
     debug_assert_eq!(randomness.len(), alg.private_key_len());
     let (pk_e, sk_e) = derive_key_pair::<Crypto>(alg, suite_id, randomness)?;
-    let dh_pk = Crypto::kem_derive(alg, pk_r, &sk_e)?;
+    let dh_pk = dh::<Crypto>(alg, pk_r, pk_r_prepared, &sk_e)?;
     let enc = serialize(&pk_e);
 
     let pk_rm = serialize(pk_r);
-    let kem_context = concat(&[&enc, &pk_rm]);
+    let kem_context = concat(&[enc, pk_rm]);
 
-    let zz = extract_and_expand::<Crypto>(alg, dh_pk, &kem_context, suite_id)?;
-    Ok((zz, enc))
+    let zz = extract_and_expand::<Crypto>(alg, version, dh_pk, &kem_context, suite_id)?;
+    Ok((zz, pk_e))
 }
 
 pub(super) fn decaps<Crypto: HpkeCrypto>(
     alg: KemAlgorithm,
+    version: HpkeVersion,
     enc: &[u8],
+    enc_prepared: Option<&Crypto::KemPublicKey>,
     sk_r: &[u8],
     suite_id: &[u8],
 ) -> Result<Vec<u8>, Error> {
     let pk_e = deserialize(enc);
-    let dh_pk = Crypto::kem_derive(alg, &pk_e, sk_r)?;
+    let dh_pk = dh::<Crypto>(alg, pk_e, enc_prepared, sk_r)?;
 
-    let pk_rm = serialize(&Crypto::kem_derive_base(alg, sk_r)?);
-    let kem_context = concat(&[enc, &pk_rm]);
+    let pk_r = Crypto::kem_derive_base(alg, sk_r)?;
+    let pk_rm = serialize(&pk_r);
+    let kem_context = concat(&[enc, pk_rm]);
 
-    extract_and_expand::<Crypto>(alg, dh_pk, &kem_context, suite_id)
+    extract_and_expand::<Crypto>(alg, version, dh_pk, &kem_context, suite_id)
 }
 
 pub(super) fn auth_encaps<Crypto: HpkeCrypto>(
     alg: KemAlgorithm,
+    version: HpkeVersion,
     pk_r: &[u8],
+    pk_r_prepared: Option<&Crypto::KemPublicKey>,
     sk_s: &[u8],
     suite_id: &[u8],
     randomness: &[u8],
@@ -142,36 +183,40 @@ pub(super) fn auth_encaps<Crypto: HpkeCrypto>(
     debug_assert_eq!(randomness.len(), alg.private_key_len());
     let (pk_e, sk_e) = derive_key_pair::<Crypto>(alg, suite_id, randomness)?;
     let dh_pk = concat(&[
-        &Crypto::kem_derive(alg, pk_r, &sk_e)?,
-        &Crypto::kem_derive(alg, pk_r, sk_s)?,
+        &dh::<Crypto>(alg, pk_r, pk_r_prepared, &sk_e)?,
+        &dh::<Crypto>(alg, pk_r, pk_r_prepared, sk_s)?,
     ]);
 
+    let pk_s = Crypto::kem_derive_base(alg, sk_s)?;
     let enc = serialize(&pk_e);
     let pk_rm = serialize(pk_r);
-    let pk_sm = serialize(&Crypto::kem_derive_base(alg, sk_s)?);
+    let pk_sm = serialize(&pk_s);
 
-    let kem_context = concat(&[&enc, &pk_rm, &pk_sm]);
+    let kem_context = concat(&[enc, pk_rm, pk_sm]);
 
-    let zz = extract_and_expand::<Crypto>(alg, dh_pk, &kem_context, suite_id)?;
-    Ok((zz, enc))
+    let zz = extract_and_expand::<Crypto>(alg, version, dh_pk, &kem_context, suite_id)?;
+    Ok((zz, pk_e))
 }
 
 pub(super) fn auth_decaps<Crypto: HpkeCrypto>(
     alg: KemAlgorithm,
+    version: HpkeVersion,
     enc: &[u8],
+    enc_prepared: Option<&Crypto::KemPublicKey>,
     sk_r: &[u8],
     pk_s: &[u8],
     suite_id: &[u8],
 ) -> Result<Vec<u8>, Error> {
     let pk_e = deserialize(enc);
     let dh_pk = concat(&[
-        &Crypto::kem_derive(alg, &pk_e, sk_r)?,
-        &Crypto::kem_derive(alg, pk_s, sk_r)?,
+        &dh::<Crypto>(alg, pk_e, enc_prepared, sk_r)?,
+        &dh::<Crypto>(alg, pk_s, None, sk_r)?,
     ]);
 
-    let pk_rm = serialize(&Crypto::kem_derive_base(alg, sk_r)?);
+    let pk_r = Crypto::kem_derive_base(alg, sk_r)?;
+    let pk_rm = serialize(&pk_r);
     let pk_sm = serialize(pk_s);
-    let kem_context = concat(&[enc, &pk_rm, &pk_sm]);
+    let kem_context = concat(&[enc, pk_rm, pk_sm]);
 
-    extract_and_expand::<Crypto>(alg, dh_pk, &kem_context, suite_id)
+    extract_and_expand::<Crypto>(alg, version, dh_pk, &kem_context, suite_id)
 }
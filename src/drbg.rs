@@ -0,0 +1,198 @@
+//! # Deterministic Random Bit Generator
+//!
+//! [`HmacDrbg`] is the HMAC_DRBG of NIST SP 800-90A Rev. 1 (Section 10.1.2)
+//! with the HMAC of an HKDF [`KdfAlgorithm`] of the crypto provider.
+//!
+//! It implements [`HpkeRng`](crate::rng::HpkeRng) and can be injected with
+//! [`Hpke::with_rng`](crate::Hpke::with_rng) or
+//! [`Kem::with_rng`](crate::kem::Kem::with_rng):
+//!
+//! * Seeded with fixed inputs, it makes key generation and encapsulation
+//!   reproducible, e.g. for integration tests and to generate test vectors.
+//! * Seeded and reseeded from an approved entropy source, it is the DRBG
+//!   layer on top of that entropy source.
+//!
+//! Prediction resistance is not provided; call [`HmacDrbg::reseed`] with
+//! fresh entropy when needed.
+//! [`HmacDrbg::generate`] fails with [`HpkeError::InsufficientRandomness`]
+//! once a reseed is required after [`RESEED_INTERVAL`] requests.
+
+use std::marker::PhantomData;
+
+use hpke_rs_crypto::{types::KdfAlgorithm, CryptoRng, HpkeCrypto, RngCore};
+use zeroize::Zeroizing;
+
+use crate::{util, HpkeError};
+
+/// The maximum number of generate requests between two reseeds (2^48).
+pub const RESEED_INTERVAL: u64 = 1 << 48;
+
+/// The maximum number of bytes of one generate request (2^19 bits).
+pub const MAX_REQUEST_LEN: usize = 1 << 16;
+
+/// The minimum length of the entropy input in bytes.
+///
+/// This is the 256 bit security strength of the DRBG.
+pub const MIN_ENTROPY_LEN: usize = 32;
+
+/// The HMAC_DRBG of NIST SP 800-90A, see the [module documentation](self).
+pub struct HmacDrbg<Crypto: HpkeCrypto> {
+    alg: KdfAlgorithm,
+    key: Zeroizing<Vec<u8>>,
+    value: Zeroizing<Vec<u8>>,
+    reseed_counter: u64,
+    _crypto: PhantomData<Crypto>,
+}
+
+impl<Crypto: HpkeCrypto> HmacDrbg<Crypto> {
+    /// Instantiate the DRBG with the HMAC of the KDF `alg` from the `entropy`
+    /// input, the `nonce`, and the `personalization` string.
+    ///
+    /// Returns [`HpkeError::InvalidConfig`] if `alg` is not one of the HKDF
+    /// algorithms with a hash function of SP 800-90A,
+    /// [`HpkeError::UnknownMode`] if the provider doesn't support it, and
+    /// [`HpkeError::InvalidInput`] if the `entropy` is shorter than
+    /// [`MIN_ENTROPY_LEN`].
+    pub fn new(
+        alg: KdfAlgorithm,
+        entropy: &[u8],
+        nonce: &[u8],
+        personalization: &[u8],
+    ) -> Result<Self, HpkeError> {
+        if !matches!(
+            alg,
+            KdfAlgorithm::HkdfSha256 | KdfAlgorithm::HkdfSha384 | KdfAlgorithm::HkdfSha512
+        ) {
+            return Err(HpkeError::InvalidConfig);
+        }
+        Crypto::supports_kdf(alg)?;
+        if entropy.len() < MIN_ENTROPY_LEN {
+            return Err(HpkeError::InvalidInput);
+        }
+        let mut drbg = Self {
+            alg,
+            key: Zeroizing::new(vec![0u8; alg.digest_length()]),
+            value: Zeroizing::new(vec![1u8; alg.digest_length()]),
+            reseed_counter: 1,
+            _crypto: PhantomData,
+        };
+        drbg.update(&[entropy, nonce, personalization]);
+        Ok(drbg)
+    }
+
+    /// Reseed the DRBG with fresh `entropy` and the `additional_input`.
+    ///
+    /// Returns [`HpkeError::InvalidInput`] if the `entropy` is shorter than
+    /// [`MIN_ENTROPY_LEN`].
+    pub fn reseed(&mut self, entropy: &[u8], additional_input: &[u8]) -> Result<(), HpkeError> {
+        if entropy.len() < MIN_ENTROPY_LEN {
+            return Err(HpkeError::InvalidInput);
+        }
+        self.update(&[entropy, additional_input]);
+        self.reseed_counter = 1;
+        Ok(())
+    }
+
+    /// Fill `out` with pseudorandom bytes for the `additional_input`.
+    ///
+    /// Returns [`HpkeError::InvalidInput`] if `out` is longer than
+    /// [`MAX_REQUEST_LEN`] and [`HpkeError::InsufficientRandomness`] if the
+    /// DRBG must be reseeded first.
+    pub fn generate(&mut self, out: &mut [u8], additional_input: &[u8]) -> Result<(), HpkeError> {
+        if out.len() > MAX_REQUEST_LEN {
+            return Err(HpkeError::InvalidInput);
+        }
+        if self.reseed_counter > RESEED_INTERVAL {
+            return Err(HpkeError::InsufficientRandomness);
+        }
+        if !additional_input.is_empty() {
+            self.update(&[additional_input]);
+        }
+        for chunk in out.chunks_mut(self.value.len()) {
+            *self.value = self.hmac(&[&self.value]);
+            chunk.copy_from_slice(&self.value[..chunk.len()]);
+        }
+        self.update(&[additional_input]);
+        self.reseed_counter += 1;
+        Ok(())
+    }
+
+    /// The HMAC_DRBG_Update function with the concatenation of
+    /// `provided_data`.
+    fn update(&mut self, provided_data: &[&[u8]]) {
+        let provided = provided_data.iter().any(|data| !data.is_empty());
+        for round in [0x00u8, 0x01] {
+            if round == 0x01 && !provided {
+                break;
+            }
+            let round = [round];
+            let mut input = vec![self.value.as_slice(), &round];
+            input.extend_from_slice(provided_data);
+            *self.key = self.hmac(&input);
+            *self.value = self.hmac(&[&self.value]);
+        }
+    }
+
+    /// HMAC(key, data) with the current key.
+    fn hmac(&self, data: &[&[u8]]) -> Vec<u8> {
+        // HKDF-Extract(salt, ikm) is HMAC(salt, ikm).
+        let data = Zeroizing::new(util::concat(data));
+        Crypto::kdf_extract(self.alg, &self.key, &data)
+    }
+}
+
+impl<Crypto: HpkeCrypto> RngCore for HmacDrbg<Crypto> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    /// Panics if the DRBG must be reseeded.
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest)
+            .expect("The HMAC_DRBG must be reseeded")
+    }
+
+    /// Longer requests are split into requests of [`MAX_REQUEST_LEN`] bytes.
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        for chunk in dest.chunks_mut(MAX_REQUEST_LEN) {
+            self.generate(chunk, &[]).map_err(rng_error)?;
+        }
+        Ok(())
+    }
+}
+
+/// The `rand_core` error for a failed request.
+#[cfg(feature = "default-rng")]
+fn rng_error(e: HpkeError) -> rand_core::Error {
+    rand_core::Error::new(e)
+}
+
+/// Without `std` a `rand_core` error is only a code.
+#[cfg(not(feature = "default-rng"))]
+fn rng_error(_: HpkeError) -> rand_core::Error {
+    const RESEED_REQUIRED: std::num::NonZeroU32 =
+        match std::num::NonZeroU32::new(rand_core::Error::CUSTOM_START) {
+            Some(code) => code,
+            None => unreachable!(),
+        };
+    rand_core::Error::from(RESEED_REQUIRED)
+}
+
+impl<Crypto: HpkeCrypto> CryptoRng for HmacDrbg<Crypto> {}
+
+impl<Crypto: HpkeCrypto> std::fmt::Debug for HmacDrbg<Crypto> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HmacDrbg")
+            .field("alg", &self.alg)
+            .field("reseed_counter", &self.reseed_counter)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,74 @@
+//! # Encrypted Configuration
+//!
+//! With the `serialization` feature, [`encrypt_from`] serializes a value
+//! with serde to JSON and seals it in an [`Envelope`], and [`decrypt_into`]
+//! opens the envelope and deserializes the value, such that applications can
+//! load typed, encrypted configuration in one call.
+//!
+//! ```
+//! use hpke_rs::{encrypted_config::{decrypt_into, encrypt_from}, prelude::*};
+//! use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+//! use hpke_rs_rust_crypto::HpkeRustCrypto;
+//!
+//! #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+//! struct Config {
+//!     database_url: String,
+//!     pool_size: u32,
+//! }
+//!
+//! let hpke = Hpke::<HpkeRustCrypto>::new(
+//!     HpkeMode::Base,
+//!     KemAlgorithm::DhKem25519,
+//!     KdfAlgorithm::HkdfSha256,
+//!     AeadAlgorithm::ChaCha20Poly1305,
+//! );
+//! let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+//!
+//! let config = Config {
+//!     database_url: "postgres://admin:hunter2@db".into(),
+//!     pool_size: 8,
+//! };
+//! let envelope = encrypt_from(&hpke, &config, &pk_r).unwrap();
+//! let loaded: Config = decrypt_into(&hpke, &envelope, &sk_r).unwrap();
+//! assert_eq!(loaded, config);
+//! ```
+//!
+//! The envelope is sealed with the `info` [`INFO`] and can be stored in any
+//! of the envelope encodings, e.g. [armored](crate::armor).
+//! TOML and other formats are not built in; use [`Envelope::seal`] with
+//! their serialization instead.
+
+use hpke_rs_crypto::HpkeCrypto;
+use serde::{de::DeserializeOwned, Serialize};
+use zeroize::Zeroizing;
+
+use crate::{envelope::Envelope, Hpke, HpkeError, HpkePrivateKey, HpkePublicKey};
+
+/// The `info` of encrypted configuration envelopes.
+pub const INFO: &[u8] = b"hpke-rs encrypted config";
+
+/// Serialize `value` to JSON and seal it to `pk_r`.
+///
+/// Returns [`HpkeError::InvalidInput`] if `value` can't be serialized and
+/// [`HpkeError::InvalidConfig`] if `hpke` is not in the Base mode.
+pub fn encrypt_from<Crypto: HpkeCrypto, T: Serialize + ?Sized>(
+    hpke: &Hpke<Crypto>,
+    value: &T,
+    pk_r: &HpkePublicKey,
+) -> Result<Envelope, HpkeError> {
+    let json = Zeroizing::new(serde_json::to_vec(value).map_err(|_| HpkeError::InvalidInput)?);
+    Envelope::seal(hpke, pk_r, INFO, &[], &json)
+}
+
+/// Open the `envelope` with `sk_r` and deserialize the JSON value in it.
+///
+/// Returns the errors of [`Envelope::open`], and
+/// [`HpkeError::InvalidInput`] if the plain text is not a JSON encoded `T`.
+pub fn decrypt_into<Crypto: HpkeCrypto, T: DeserializeOwned>(
+    hpke: &Hpke<Crypto>,
+    envelope: &Envelope,
+    sk_r: &HpkePrivateKey,
+) -> Result<T, HpkeError> {
+    let json = Zeroizing::new(envelope.open(hpke, sk_r, INFO, &[])?);
+    serde_json::from_slice(&json).map_err(|_| HpkeError::InvalidInput)
+}
@@ -0,0 +1,219 @@
+//! # Envelopes
+//!
+//! An [`Envelope`] is a single-shot HPKE message that carries everything the
+//! recipient needs to open it: the ciphersuite, the encapsulated secret, the
+//! ciphertext, and optionally the [`KeyId`] of the recipient key.
+//!
+//! Envelopes are sealed in the base mode.
+//! The `info` and `aad` are not part of the envelope and have to be known to
+//! the recipient.
+//!
+//! ```
+//! use hpke_rs::{envelope::Envelope, prelude::*};
+//! use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+//! use hpke_rs_rust_crypto::HpkeRustCrypto;
+//!
+//! let hpke = Hpke::<HpkeRustCrypto>::new(
+//!     HpkeMode::Base,
+//!     KemAlgorithm::DhKem25519,
+//!     KdfAlgorithm::HkdfSha256,
+//!     AeadAlgorithm::ChaCha20Poly1305,
+//! );
+//! let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+//!
+//! let envelope = Envelope::seal(&hpke, &pk_r, b"info", b"aad", b"message").unwrap();
+//! assert_eq!(
+//!     envelope.open(&hpke, &sk_r, b"info", b"aad").unwrap(),
+//!     b"message"
+//! );
+//! ```
+//!
+//! ## Key Rotation
+//!
+//! A [`MultiEnvelope`] sealed to the current and the previous key of a
+//! recipient can be opened with either key.
+//! [`MultiEnvelope::open`] picks the recipient whose key id is in the
+//! recipient's [`KeyStore`] such that recipients can rotate their keys
+//! without downtime: senders seal to both keys until every recipient has the
+//! new key, and recipients keep the previous key until no sender uses it
+//! anymore.
+
+use hpke_rs_crypto::HpkeCrypto;
+
+use crate::{
+    ciphersuite::Ciphersuite, keystore::KeyStore, Hpke, HpkeError, HpkePrivateKey, HpkePublicKey,
+    KeyId, Mode,
+};
+
+/// A single-shot HPKE message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Envelope {
+    /// The ciphersuite of the message.
+    pub suite: Ciphersuite,
+    /// The key id of the recipient key, if present.
+    pub key_id: Option<KeyId>,
+    /// The encapsulated secret.
+    pub enc: Vec<u8>,
+    /// The ciphertext.
+    pub ciphertext: Vec<u8>,
+}
+
+impl Envelope {
+    /// Seal the `plain_txt` to `pk_r` with the `hpke` configuration.
+    ///
+    /// The envelope includes the key id of `pk_r`.
+    /// Returns [`HpkeError::InvalidConfig`] if `hpke` is not in the base mode.
+    pub fn seal<Crypto: HpkeCrypto>(
+        hpke: &Hpke<Crypto>,
+        pk_r: &HpkePublicKey,
+        info: &[u8],
+        aad: &[u8],
+        plain_txt: &[u8],
+    ) -> Result<Self, HpkeError> {
+        if hpke.mode != Mode::Base {
+            return Err(HpkeError::InvalidConfig);
+        }
+        let (enc, ciphertext) = hpke.seal(pk_r, info, aad, plain_txt, None, None, None)?;
+        Ok(Self {
+            suite: hpke.ciphersuite(),
+            key_id: Some(hpke.key_id(pk_r)?),
+            enc,
+            ciphertext,
+        })
+    }
+
+    /// Open this envelope with the private key `sk_r`.
+    ///
+    /// Returns [`HpkeError::InvalidConfig`] if the ciphersuite of the
+    /// envelope is not the one of `hpke` or `hpke` is not in the base mode.
+    pub fn open<Crypto: HpkeCrypto>(
+        &self,
+        hpke: &Hpke<Crypto>,
+        sk_r: &HpkePrivateKey,
+        info: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, HpkeError> {
+        if hpke.mode != Mode::Base || hpke.ciphersuite() != self.suite {
+            return Err(HpkeError::InvalidConfig);
+        }
+        hpke.open(
+            &self.enc,
+            sk_r,
+            info,
+            aad,
+            &self.ciphertext,
+            None,
+            None,
+            None,
+        )
+    }
+}
+
+/// A recipient of a [`MultiEnvelope`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recipient {
+    /// The key id of the recipient key, if present.
+    pub key_id: Option<KeyId>,
+    /// The encapsulated secret for this recipient.
+    pub enc: Vec<u8>,
+    /// The ciphertext for this recipient.
+    pub ciphertext: Vec<u8>,
+}
+
+/// A single-shot HPKE message to many recipients.
+///
+/// Every recipient has its own encapsulated secret and ciphertext, as
+/// produced by [`Hpke::seal_to_many`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiEnvelope {
+    /// The ciphersuite of the message.
+    pub suite: Ciphersuite,
+    /// The recipients in the order they were sealed to.
+    pub recipients: Vec<Recipient>,
+}
+
+impl MultiEnvelope {
+    /// Seal the `plain_txt` to each of the public keys `pk_rs` with the
+    /// `hpke` configuration.
+    ///
+    /// The recipients include the key ids of their public keys.
+    /// Returns [`HpkeError::InvalidConfig`] if `hpke` is not in the base mode.
+    pub fn seal<Crypto: HpkeCrypto>(
+        hpke: &Hpke<Crypto>,
+        pk_rs: &[HpkePublicKey],
+        info: &[u8],
+        aad: &[u8],
+        plain_txt: &[u8],
+    ) -> Result<Self, HpkeError> {
+        if hpke.mode != Mode::Base {
+            return Err(HpkeError::InvalidConfig);
+        }
+        let recipients = pk_rs
+            .iter()
+            .map(|pk_r| {
+                let (enc, ciphertext) = hpke.seal(pk_r, info, aad, plain_txt, None, None, None)?;
+                Ok(Recipient {
+                    key_id: Some(hpke.key_id(pk_r)?),
+                    enc,
+                    ciphertext,
+                })
+            })
+            .collect::<Result<_, HpkeError>>()?;
+        Ok(Self {
+            suite: hpke.ciphersuite(),
+            recipients,
+        })
+    }
+
+    /// Get the [`Envelope`] of the recipient with the `key_id`, which can be
+    /// opened with [`Envelope::open`].
+    pub fn envelope_for(&self, key_id: &KeyId) -> Option<Envelope> {
+        self.recipients
+            .iter()
+            .find(|recipient| recipient.key_id.as_ref() == Some(key_id))
+            .map(|recipient| Envelope {
+                suite: self.suite,
+                key_id: recipient.key_id,
+                enc: recipient.enc.clone(),
+                ciphertext: recipient.ciphertext.clone(),
+            })
+    }
+
+    /// Open this envelope with the first key in the `store` that one of the
+    /// recipients is sealed to and that can be used for decryption at `now`,
+    /// see [`KeyStore::decryption_key`].
+    ///
+    /// Returns [`HpkeError::InvalidConfig`] if the ciphersuite of the
+    /// envelope is not the one of `hpke` or `hpke` is not in the base mode,
+    /// and [`HpkeError::InvalidInput`] if the `store` has none of the keys.
+    pub fn open<Crypto: HpkeCrypto, Store: KeyStore + ?Sized>(
+        &self,
+        hpke: &Hpke<Crypto>,
+        store: &Store,
+        info: &[u8],
+        aad: &[u8],
+        now: u64,
+    ) -> Result<Vec<u8>, HpkeError> {
+        if hpke.mode != Mode::Base || hpke.ciphersuite() != self.suite {
+            return Err(HpkeError::InvalidConfig);
+        }
+        let (recipient, sk_r) = self
+            .recipients
+            .iter()
+            .find_map(|recipient| {
+                let sk_r = store.decryption_key(recipient.key_id.as_ref()?, now)?;
+                Some((recipient, sk_r))
+            })
+            .ok_or(HpkeError::InvalidInput)?;
+        hpke.open(
+            &recipient.enc,
+            sk_r,
+            info,
+            aad,
+            &recipient.ciphertext,
+            None,
+            None,
+            None,
+        )
+    }
+}
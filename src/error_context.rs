@@ -0,0 +1,166 @@
+//! # Error Context
+//!
+//! An [`HpkeError`] on its own doesn't say which step of an operation failed,
+//! e.g. an [`HpkeError::InvalidInput`] during a setup may come from the
+//! encapsulated secret or from the key schedule inputs.
+//!
+//! A configuration with [`Hpke::with_error_context`] records an
+//! [`ErrorContext`] with the [`Stage`] that failed and the [`HpkeConfig`] for
+//! every error of its setups and of the contexts it sets up.
+//! The errors themselves are returned unchanged, such that they can still be
+//! matched and compared.
+//! The last recorded context is returned by
+//! [`Hpke::last_error_context`], [`Context::last_error_context`], and
+//! [`SyncSenderContext::last_error_context`](crate::SyncSenderContext::last_error_context):
+//!
+//! ```text
+//! HPKE Error: OpenError in open with base_dhkem25519_hkdfsha256_chacha20poly1305
+//! ```
+//!
+//! The configuration, its clones, and its contexts share the record, so the
+//! last error of any of them is returned, also if they are used from other
+//! threads.
+//! The context only carries the stage and the public configuration.
+//! It doesn't leak anything that depends on secrets, i.e. nothing beyond the
+//! failure of the operation that the error reveals anyway.
+
+use std::sync::{Arc, Mutex};
+
+use hpke_rs_crypto::HpkeCrypto;
+
+use crate::{Context, Hpke, HpkeConfig, HpkeError};
+
+/// The step of an operation that failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Encapsulating to the receiver's public key in a sender setup.
+    Encapsulation,
+    /// Decapsulating the encapsulated secret in a receiver setup.
+    Decapsulation,
+    /// The key schedule of a setup.
+    KeySchedule,
+    /// Sealing a message with a context.
+    Seal,
+    /// Opening a message with a context.
+    Open,
+    /// Exporting a secret from a context.
+    Export,
+    /// Decoding a serialized message, e.g. the payload of a
+    /// [sealed sender](Hpke::open_sealed_sender) message.
+    Serialization,
+}
+
+impl std::fmt::Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Stage::Encapsulation => "encapsulation",
+            Stage::Decapsulation => "decapsulation",
+            Stage::KeySchedule => "key schedule",
+            Stage::Seal => "seal",
+            Stage::Open => "open",
+            Stage::Export => "export",
+            Stage::Serialization => "serialization",
+        })
+    }
+}
+
+/// The stage and configuration of a recorded error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorContext {
+    /// The step that failed.
+    pub stage: Stage,
+    /// The configuration of the operation.
+    pub config: HpkeConfig,
+    /// The error that was returned.
+    pub error: HpkeError,
+}
+
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "HPKE Error: {:?} in {} with {}",
+            self.error, self.stage, self.config
+        )
+    }
+}
+
+/// The record of the last error, shared by a configuration, its clones, and
+/// its contexts.
+#[derive(Clone, Default)]
+pub(crate) struct Recorder(Arc<Mutex<Option<ErrorContext>>>);
+
+impl Recorder {
+    /// The last recorded error context.
+    pub(crate) fn last(&self) -> Option<ErrorContext> {
+        self.0.lock().ok()?.clone()
+    }
+
+    fn record(&self, context: ErrorContext) {
+        if let Ok(mut last) = self.0.lock() {
+            *last = Some(context);
+        }
+    }
+}
+
+impl std::fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Recorder").finish_non_exhaustive()
+    }
+}
+
+impl<Crypto: HpkeCrypto> Hpke<Crypto> {
+    /// Record the failed [`Stage`] and the configuration for the errors of
+    /// this configuration and its contexts, see the
+    /// [module documentation](self).
+    pub fn with_error_context(mut self) -> Self {
+        self.error_context = Some(Recorder::default());
+        self
+    }
+
+    /// The context of the last error of this configuration, its clones, or
+    /// its contexts.
+    ///
+    /// Returns `None` if nothing failed yet or if the error context isn't
+    /// [enabled](Self::with_error_context).
+    pub fn last_error_context(&self) -> Option<ErrorContext> {
+        self.error_context.as_ref()?.last()
+    }
+
+    /// Record the `stage` and the configuration for the error of `result`.
+    pub(crate) fn add_error_context<T>(
+        &self,
+        stage: Stage,
+        result: Result<T, HpkeError>,
+    ) -> Result<T, HpkeError> {
+        add(&self.error_context, stage, self.config(), result)
+    }
+}
+
+impl<Crypto: HpkeCrypto> Context<Crypto> {
+    /// Like [`Hpke::last_error_context`] for the configuration that set up
+    /// this context.
+    pub fn last_error_context(&self) -> Option<ErrorContext> {
+        self.error_context.as_ref()?.last()
+    }
+}
+
+/// Record the `stage` and `config` for the error of `result` with the
+/// `recorder`, if there is one.
+///
+/// The result is returned unchanged.
+pub(crate) fn add<T>(
+    recorder: &Option<Recorder>,
+    stage: Stage,
+    config: HpkeConfig,
+    result: Result<T, HpkeError>,
+) -> Result<T, HpkeError> {
+    if let (Some(recorder), Err(error)) = (recorder, &result) {
+        recorder.record(ErrorContext {
+            stage,
+            config,
+            error: error.clone(),
+        });
+    }
+    result
+}
@@ -0,0 +1,200 @@
+//! # Exporter Helpers
+//!
+//! Derive the keys of other record layers from an HPKE context with
+//! [`Context::export`], without hand-rolled exporter contexts.
+//!
+//! * [`Context::export_srtp`] derives SRTP master keys and salts like
+//!   DTLS-SRTP ([RFC 5764, Section 4.2]) with the exporter context
+//!   `"EXTRACTOR-dtls_srtp"`.
+//! * [`Context::export_record_keys`] derives the key, IV, and header
+//!   protection key of one direction of a QUIC-like record layer with the
+//!   exporter contexts `label || " key"`, `label || " iv"`, and
+//!   `label || " hp"`.
+//! * [`Context::sas`] derives a short authentication string that the users
+//!   of both endpoints compare, e.g. when pairing devices, to detect a
+//!   man-in-the-middle of a Base mode setup.
+//! * [`Context::session_id`] derives a public identifier of the context that
+//!   both endpoints can use in logs and control messages.
+//!
+//! Both sides of the HPKE context derive the same keys.
+//!
+//! [RFC 5764, Section 4.2]: https://www.rfc-editor.org/rfc/rfc5764#section-4.2
+
+use hpke_rs_crypto::{types::AeadAlgorithm, HpkeCrypto};
+use zeroize::Zeroize;
+
+use crate::{util::concat, Context, HpkeError};
+
+/// The exporter context for SRTP keying material.
+const SRTP_EXPORTER_CONTEXT: &[u8] = b"EXTRACTOR-dtls_srtp";
+
+/// The exporter context for short authentication strings.
+const SAS_EXPORTER_CONTEXT: &[u8] = b"HPKE SAS";
+
+/// The maximum number of digits of a short authentication string.
+///
+/// The string is reduced from 64 exported bits, such that the bias of
+/// longer strings would be noticeable.
+pub const MAX_SAS_DIGITS: usize = 12;
+
+/// The exporter context for session identifiers.
+const SESSION_ID_EXPORTER_CONTEXT: &[u8] = b"HPKE session id";
+
+/// SRTP protection profiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SrtpProfile {
+    /// `SRTP_AES128_CM_HMAC_SHA1_80`
+    Aes128CmHmacSha1_80,
+    /// `SRTP_AES128_CM_HMAC_SHA1_32`
+    Aes128CmHmacSha1_32,
+    /// `SRTP_AEAD_AES_128_GCM`
+    AeadAes128Gcm,
+    /// `SRTP_AEAD_AES_256_GCM`
+    AeadAes256Gcm,
+}
+
+impl SrtpProfile {
+    /// The length of the master key in bytes.
+    pub const fn key_len(&self) -> usize {
+        match self {
+            SrtpProfile::Aes128CmHmacSha1_80
+            | SrtpProfile::Aes128CmHmacSha1_32
+            | SrtpProfile::AeadAes128Gcm => 16,
+            SrtpProfile::AeadAes256Gcm => 32,
+        }
+    }
+
+    /// The length of the master salt in bytes.
+    pub const fn salt_len(&self) -> usize {
+        match self {
+            SrtpProfile::Aes128CmHmacSha1_80 | SrtpProfile::Aes128CmHmacSha1_32 => 14,
+            SrtpProfile::AeadAes128Gcm | SrtpProfile::AeadAes256Gcm => 12,
+        }
+    }
+}
+
+/// SRTP master keys and salts for both directions.
+///
+/// The client is the HPKE sender and the server the HPKE receiver.
+#[derive(Zeroize, PartialEq, Eq)]
+#[zeroize(drop)]
+pub struct SrtpKeyingMaterial {
+    /// The master key of the client.
+    pub client_key: Vec<u8>,
+    /// The master key of the server.
+    pub server_key: Vec<u8>,
+    /// The master salt of the client.
+    pub client_salt: Vec<u8>,
+    /// The master salt of the server.
+    pub server_salt: Vec<u8>,
+}
+
+impl std::fmt::Debug for SrtpKeyingMaterial {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SrtpKeyingMaterial").finish_non_exhaustive()
+    }
+}
+
+/// The keys of one direction of a record layer.
+#[derive(Zeroize, PartialEq, Eq)]
+#[zeroize(drop)]
+pub struct RecordKeys {
+    /// The AEAD key.
+    pub key: Vec<u8>,
+    /// The AEAD IV.
+    pub iv: Vec<u8>,
+    /// The header protection key.
+    pub hp: Vec<u8>,
+}
+
+impl std::fmt::Debug for RecordKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordKeys").finish_non_exhaustive()
+    }
+}
+
+impl<Crypto: HpkeCrypto> Context<Crypto> {
+    /// Export the SRTP keying material for `profile`.
+    ///
+    /// ```text
+    /// km = Export("EXTRACTOR-dtls_srtp", 2 * (key_len + salt_len))
+    /// client_key || server_key || client_salt || server_salt = km
+    /// ```
+    pub fn export_srtp(&self, profile: SrtpProfile) -> Result<SrtpKeyingMaterial, HpkeError> {
+        let (key_len, salt_len) = (profile.key_len(), profile.salt_len());
+        let mut km = self.export(SRTP_EXPORTER_CONTEXT, 2 * (key_len + salt_len))?;
+        let (keys, salts) = km.split_at(2 * key_len);
+        let material = SrtpKeyingMaterial {
+            client_key: keys[..key_len].to_vec(),
+            server_key: keys[key_len..].to_vec(),
+            client_salt: salts[..salt_len].to_vec(),
+            server_salt: salts[salt_len..].to_vec(),
+        };
+        km.zeroize();
+        Ok(material)
+    }
+
+    /// Export the record keys for `aead` with the `label` of the direction,
+    /// e.g. `b"client"` and `b"server"`.
+    ///
+    /// The header protection key has the length of the AEAD key.
+    /// Returns [`HpkeError::InvalidInput`] for the export-only AEAD.
+    ///
+    /// ```text
+    /// key = Export(concat(label, " key"), Nk)
+    /// iv = Export(concat(label, " iv"), Nn)
+    /// hp = Export(concat(label, " hp"), Nk)
+    /// ```
+    pub fn export_record_keys(
+        &self,
+        label: &[u8],
+        aead: AeadAlgorithm,
+    ) -> Result<RecordKeys, HpkeError> {
+        if aead == AeadAlgorithm::HpkeExport {
+            return Err(HpkeError::InvalidInput);
+        }
+        let (key_len, iv_len) = (aead.key_length(), aead.nonce_length());
+        Ok(RecordKeys {
+            key: self.export(&concat(&[label, b" key"]), key_len)?,
+            iv: self.export(&concat(&[label, b" iv"]), iv_len)?,
+            hp: self.export(&concat(&[label, b" hp"]), key_len)?,
+        })
+    }
+
+    /// Derive a short authentication string of `digits` decimal digits.
+    ///
+    /// A man-in-the-middle sets up a different context with each endpoint,
+    /// so the strings differ with a probability of `1 - 10^-digits`.
+    /// Returns [`HpkeError::InvalidInput`] unless `digits` is between 1 and
+    /// [`MAX_SAS_DIGITS`].
+    ///
+    /// ```text
+    /// sas = OS2IP(Export("HPKE SAS", 8)) mod 10^digits
+    /// ```
+    pub fn sas(&self, digits: usize) -> Result<String, HpkeError> {
+        if digits == 0 || digits > MAX_SAS_DIGITS {
+            return Err(HpkeError::InvalidInput);
+        }
+        let value = u64::from_be_bytes(self.export_array(SAS_EXPORTER_CONTEXT)?);
+        let sas = value % 10u64.pow(digits as u32);
+        Ok(format!("{sas:0digits$}"))
+    }
+
+    /// Derive a session identifier of `len` bytes.
+    ///
+    /// The identifier is exported under its own exporter context, so it
+    /// reveals nothing about the keys or other exported secrets and can be
+    /// logged or sent in the clear.
+    /// Returns [`HpkeError::InvalidInput`] if `len` is 0 or longer than the
+    /// exporter allows.
+    ///
+    /// ```text
+    /// session_id = Export("HPKE session id", len)
+    /// ```
+    pub fn session_id(&self, len: usize) -> Result<Vec<u8>, HpkeError> {
+        if len == 0 {
+            return Err(HpkeError::InvalidInput);
+        }
+        self.export(SESSION_ID_EXPORTER_CONTEXT, len)
+    }
+}
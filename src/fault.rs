@@ -0,0 +1,71 @@
+//! # Fault Checks
+//!
+//! Glitching the clock or the supply voltage of a smartcard or an enclave
+//! can make a single computation return a wrong result.
+//! A faulty nonce or key schedule output can leak the key through the
+//! ciphertexts, and a faulty ciphertext can leak the keystream.
+//!
+//! A configuration with [`Hpke::with_fault_checks`] computes the critical
+//! values of its setups and of the contexts it sets up twice and compares the
+//! results:
+//!
+//! * The shared secret and encapsulated secret of an encapsulation are
+//!   recomputed from the same ephemeral randomness.
+//! * The shared secret of a decapsulation is recomputed.
+//! * The key, base nonce, and exporter secret of the RFC 9180 key schedule
+//!   are derived twice.
+//! * The nonce of every seal and open is computed twice.
+//! * Every sealed ciphertext is opened again and compared to the plain text
+//!   before it is released.
+//!
+//! A mismatch fails the operation with [`HpkeError::FaultDetected`] and
+//! nothing computed from the faulty values is returned.
+//! This roughly doubles the cost of setups and seals.
+//!
+//! The checks are best-effort.
+//! The second computation goes through [`std::hint::black_box`] such that
+//! the compiler doesn't merge it with the first one, but `black_box` is only
+//! a hint and not a guarantee.
+//! The checks also don't protect against faults that hit both computations
+//! the same way, or the comparison itself.
+
+use std::hint::black_box;
+
+use hpke_rs_crypto::HpkeCrypto;
+
+use crate::{Hpke, HpkeError};
+
+impl<Crypto: HpkeCrypto> Hpke<Crypto> {
+    /// Recompute and compare the critical values of this configuration and
+    /// its contexts, see the [module documentation](self).
+    pub fn with_fault_checks(mut self) -> Self {
+        self.fault_checks = true;
+        self
+    }
+}
+
+/// Run `f`, and if `enabled` run it again and check that both results are
+/// `same`.
+///
+/// The second run and the comparison go through [`black_box`] such that the
+/// optimizer doesn't reuse the first result.
+///
+/// Returns [`HpkeError::FaultDetected`] if they differ or only the second
+/// run fails.
+pub(crate) fn checked<T, E>(
+    enabled: bool,
+    f: impl Fn() -> Result<T, E>,
+    same: impl FnOnce(&T, &T) -> bool,
+) -> Result<T, HpkeError>
+where
+    HpkeError: From<E>,
+{
+    let result = f()?;
+    if enabled {
+        let again = black_box(&f)().map_err(|_| HpkeError::FaultDetected)?;
+        if !same(black_box(&result), black_box(&again)) {
+            return Err(HpkeError::FaultDetected);
+        }
+    }
+    Ok(result)
+}
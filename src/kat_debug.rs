@@ -0,0 +1,54 @@
+//! # Intermediate Value Dump
+//!
+//! With the `kat-debug` feature a callback can be registered on a
+//! configuration with [`Hpke::with_intermediate_callback`].
+//! The key schedule calls it with the label and value of every intermediate
+//! value, in the order they are computed:
+//!
+//! `shared_secret`, `psk_id_hash`, `info_hash`, `key_schedule_context`,
+//! `secret`, `key`, `base_nonce`, `exporter_secret`
+//!
+//! The labels follow the names in RFC 9180 and its test vectors, which makes
+//! it easy to find the first value that differs from another implementation.
+//!
+//! ⚠️ The callback receives secret key material.
+//! Only use this to debug test vectors.
+
+use std::sync::Arc;
+
+use hpke_rs_crypto::HpkeCrypto;
+
+use crate::Hpke;
+
+/// A callback for intermediate values, see the [module documentation](self).
+pub type IntermediateCallback = dyn Fn(&'static str, &[u8]) + Send + Sync;
+
+#[derive(Clone)]
+pub(crate) struct Callback(Arc<IntermediateCallback>);
+
+impl std::fmt::Debug for Callback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Callback").finish()
+    }
+}
+
+impl<Crypto: HpkeCrypto> Hpke<Crypto> {
+    /// Call `callback` with every intermediate value of the key schedule.
+    ///
+    /// ⚠️ The callback receives secret key material.
+    /// Only use this to debug test vectors.
+    pub fn with_intermediate_callback(
+        mut self,
+        callback: impl Fn(&'static str, &[u8]) + Send + Sync + 'static,
+    ) -> Self {
+        self.intermediate_callback = Some(Callback(Arc::new(callback)));
+        self
+    }
+
+    #[inline]
+    pub(crate) fn dump(&self, label: &'static str, value: &[u8]) {
+        if let Some(Callback(callback)) = &self.intermediate_callback {
+            callback(label, value);
+        }
+    }
+}
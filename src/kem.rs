@@ -1,19 +1,79 @@
-use hpke_rs_crypto::{error::Error, types::KemAlgorithm, HpkeCrypto};
+//! # Key Encapsulation
+//!
+//! The KEM of HPKE on its own.
+//! A [`Kem`] encapsulates a [`SharedSecret`] to a public key and decapsulates
+//! it with the private key, with the same algorithm implementations as
+//! [`Hpke`](crate::Hpke).
+//! This can be used to build other protocols, e.g. a hybrid key exchange, on
+//! top of the KEM.
+//!
+//! The KEMs use the RFC 9180 labels.
+//!
+//! ## Hybrid KEMs
+//!
+//! A [`CombinedKem`] combines two KEMs, e.g. a classical and a post-quantum
+//! KEM, into a hybrid KEM that is secure as long as either of them is.
+//! Any [`KemScheme`] can be a component, including another [`CombinedKem`].
+//!
+//! The encapsulated secrets and the keys are the concatenations of the
+//! component values and the shared secret is derived from both component
+//! shared secrets with the KDF of the combined KEM, following the generic
+//! combiner of the CFRG hybrid KEM draft:
+//!
+//! ```text
+//! enc = enc_a || enc_b
+//! pk = pk_a || pk_b
+//! sk = sk_a || sk_b || pk_a || pk_b
+//! shared_secret = Extract("", ss_a || ss_b || enc_a || enc_b || pk_a || pk_b || label)
+//! ```
+//!
+//! The private key includes the public keys because the combiner binds them
+//! when decapsulating.
+
+use std::marker::PhantomData;
+
+use hpke_rs_crypto::{
+    error::Error,
+    types::{KdfAlgorithm, KemAlgorithm},
+    HpkeCrypto,
+};
+use zeroize::Zeroize;
 
 use crate::dh_kem;
+use crate::rng::{HpkeRng, Prng};
 use crate::util;
+use crate::{DecapError, HpkeError, HpkeKeyPair, HpkePrivateKey, HpkePublicKey, HpkeVersion};
 
 pub(crate) type PrivateKey = Vec<u8>;
 pub(crate) type PublicKey = Vec<u8>;
 
 #[inline(always)]
-fn ciphersuite(alg: KemAlgorithm) -> Vec<u8> {
-    util::concat(&[b"KEM", &(alg as u16).to_be_bytes()])
+pub(crate) fn ciphersuite(alg: KemAlgorithm) -> [u8; 5] {
+    let [hi, lo] = (alg as u16).to_be_bytes();
+    [b'K', b'E', b'M', hi, lo]
+}
+
+/// Check that `enc` is a valid encapsulated secret for `alg`.
+///
+/// Returns the prepared public value of `enc`.
+pub(crate) fn prepare_enc<Crypto: HpkeCrypto>(
+    alg: KemAlgorithm,
+    enc: &[u8],
+) -> Result<Crypto::KemPublicKey, HpkeError> {
+    if enc.len() != alg.enc_len() {
+        return Err(HpkeError::DecapError(DecapError::InvalidLength));
+    }
+    Crypto::kem_prepare_pk(alg, enc).map_err(|e| match e {
+        Error::KemInvalidPublicKey => HpkeError::DecapError(DecapError::InvalidPublicKey),
+        e => e.into(),
+    })
 }
 
 pub(crate) fn encaps<Crypto: HpkeCrypto>(
     alg: KemAlgorithm,
+    version: HpkeVersion,
     pk_r: &[u8],
+    pk_r_prepared: Option<&Crypto::KemPublicKey>,
     randomness: &[u8],
 ) -> Result<(Vec<u8>, Vec<u8>), Error> {
     match alg {
@@ -21,15 +81,22 @@ pub(crate) fn encaps<Crypto: HpkeCrypto>(
         | KemAlgorithm::DhKemP384
         | KemAlgorithm::DhKemP521
         | KemAlgorithm::DhKem25519
-        | KemAlgorithm::DhKem448 => {
-            dh_kem::encaps::<Crypto>(alg, pk_r, &ciphersuite(alg), randomness)
-        }
+        | KemAlgorithm::DhKem448 => dh_kem::encaps::<Crypto>(
+            alg,
+            version,
+            pk_r,
+            pk_r_prepared,
+            &ciphersuite(alg),
+            randomness,
+        ),
     }
 }
 
 pub(crate) fn decaps<Crypto: HpkeCrypto>(
     alg: KemAlgorithm,
+    version: HpkeVersion,
     enc: &[u8],
+    enc_prepared: Option<&Crypto::KemPublicKey>,
     sk_r: &[u8],
 ) -> Result<Vec<u8>, Error> {
     match alg {
@@ -37,13 +104,17 @@ pub(crate) fn decaps<Crypto: HpkeCrypto>(
         | KemAlgorithm::DhKemP384
         | KemAlgorithm::DhKemP521
         | KemAlgorithm::DhKem25519
-        | KemAlgorithm::DhKem448 => dh_kem::decaps::<Crypto>(alg, enc, sk_r, &ciphersuite(alg)),
+        | KemAlgorithm::DhKem448 => {
+            dh_kem::decaps::<Crypto>(alg, version, enc, enc_prepared, sk_r, &ciphersuite(alg))
+        }
     }
 }
 
 pub(crate) fn auth_encaps<Crypto: HpkeCrypto>(
     alg: KemAlgorithm,
+    version: HpkeVersion,
     pk_r: &[u8],
+    pk_r_prepared: Option<&Crypto::KemPublicKey>,
     sk_s: &[u8],
     randomness: &[u8],
 ) -> Result<(Vec<u8>, Vec<u8>), Error> {
@@ -52,15 +123,23 @@ pub(crate) fn auth_encaps<Crypto: HpkeCrypto>(
         | KemAlgorithm::DhKemP384
         | KemAlgorithm::DhKemP521
         | KemAlgorithm::DhKem25519
-        | KemAlgorithm::DhKem448 => {
-            dh_kem::auth_encaps::<Crypto>(alg, pk_r, sk_s, &ciphersuite(alg), randomness)
-        }
+        | KemAlgorithm::DhKem448 => dh_kem::auth_encaps::<Crypto>(
+            alg,
+            version,
+            pk_r,
+            pk_r_prepared,
+            sk_s,
+            &ciphersuite(alg),
+            randomness,
+        ),
     }
 }
 
 pub(crate) fn auth_decaps<Crypto: HpkeCrypto>(
     alg: KemAlgorithm,
+    version: HpkeVersion,
     enc: &[u8],
+    enc_prepared: Option<&Crypto::KemPublicKey>,
     sk_r: &[u8],
     pk_s: &[u8],
 ) -> Result<Vec<u8>, Error> {
@@ -69,12 +148,19 @@ pub(crate) fn auth_decaps<Crypto: HpkeCrypto>(
         | KemAlgorithm::DhKemP384
         | KemAlgorithm::DhKemP521
         | KemAlgorithm::DhKem25519
-        | KemAlgorithm::DhKem448 => {
-            dh_kem::auth_decaps::<Crypto>(alg, enc, sk_r, pk_s, &ciphersuite(alg))
-        }
+        | KemAlgorithm::DhKem448 => dh_kem::auth_decaps::<Crypto>(
+            alg,
+            version,
+            enc,
+            enc_prepared,
+            sk_r,
+            pk_s,
+            &ciphersuite(alg),
+        ),
     }
 }
 
+#[cfg(feature = "default-rng")]
 pub(crate) fn key_gen<Crypto: HpkeCrypto>(
     alg: KemAlgorithm,
     prng: &mut Crypto::HpkePrng,
@@ -97,3 +183,320 @@ pub(crate) fn derive_key_pair<Crypto: HpkeCrypto>(
 ) -> Result<(PublicKey, PrivateKey), Error> {
     dh_kem::derive_key_pair::<Crypto>(alg, &ciphersuite(alg), ikm)
 }
+
+/// A KEM shared secret.
+///
+/// The secret is zeroized when it is dropped.
+#[derive(Zeroize, PartialEq, Eq)]
+#[zeroize(drop)]
+pub struct SharedSecret {
+    value: Vec<u8>,
+}
+
+impl SharedSecret {
+    /// Get the shared secret as byte slice.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.value
+    }
+}
+
+impl std::fmt::Debug for SharedSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedSecret")
+            .field("value", &"***")
+            .finish()
+    }
+}
+
+/// A standalone HPKE KEM.
+pub struct Kem<Crypto: 'static + HpkeCrypto> {
+    alg: KemAlgorithm,
+    prng: Prng<Crypto>,
+}
+
+impl<Crypto: HpkeCrypto> std::fmt::Debug for Kem<Crypto> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Kem").field("alg", &self.alg).finish()
+    }
+}
+
+impl<Crypto: 'static + HpkeCrypto> Clone for Kem<Crypto> {
+    fn clone(&self) -> Self {
+        Self {
+            alg: self.alg,
+            prng: self.prng.clone(),
+        }
+    }
+}
+
+impl<Crypto: HpkeCrypto> Kem<Crypto> {
+    /// Create a new KEM with the algorithm `alg`.
+    pub fn new(alg: KemAlgorithm) -> Self {
+        Self {
+            alg,
+            prng: Prng::new(),
+        }
+    }
+
+    /// Draw all randomness from `rng` instead of the PRNG of the crypto
+    /// provider, see [`Hpke::with_rng`](crate::Hpke::with_rng).
+    pub fn with_rng(mut self, rng: impl HpkeRng + 'static) -> Self {
+        self.prng = Prng::injected(rng);
+        self
+    }
+
+    /// The KEM algorithm.
+    pub fn algorithm(&self) -> KemAlgorithm {
+        self.alg
+    }
+
+    /// Generate a new key pair.
+    pub fn generate_key_pair(&self) -> Result<HpkeKeyPair, HpkeError> {
+        let (sk, pk) = self.prng.key_gen(self.alg, 1)?.remove(0);
+        Ok(HpkeKeyPair::new(sk, pk))
+    }
+
+    /// Derive a key pair from the input key material `ikm`.
+    pub fn derive_key_pair(&self, ikm: &[u8]) -> Result<HpkeKeyPair, HpkeError> {
+        let (pk, sk) = derive_key_pair::<Crypto>(self.alg, ikm)?;
+        Ok(HpkeKeyPair::new(sk, pk))
+    }
+
+    /// `Encap(pkR)`
+    ///
+    /// Returns the shared secret and the encapsulated secret `enc`.
+    pub fn encaps(&self, pk_r: &HpkePublicKey) -> Result<(SharedSecret, Vec<u8>), HpkeError> {
+        let randomness = self.random()?;
+        let (value, enc) = encaps::<Crypto>(
+            self.alg,
+            HpkeVersion::Rfc9180,
+            pk_r.as_slice(),
+            None,
+            &randomness,
+        )?;
+        Ok((SharedSecret { value }, enc))
+    }
+
+    /// `Decap(enc, skR)`
+    ///
+    /// Returns [`HpkeError::DecapError`] if `enc` is not a valid encapsulated
+    /// secret.
+    pub fn decaps(&self, enc: &[u8], sk_r: &HpkePrivateKey) -> Result<SharedSecret, HpkeError> {
+        let pk_e = prepare_enc::<Crypto>(self.alg, enc)?;
+        let value = decaps::<Crypto>(
+            self.alg,
+            HpkeVersion::Rfc9180,
+            enc,
+            Some(&pk_e),
+            &sk_r.value,
+        )?;
+        Ok(SharedSecret { value })
+    }
+
+    /// `AuthEncap(pkR, skS)`
+    ///
+    /// Returns the shared secret and the encapsulated secret `enc`.
+    pub fn auth_encaps(
+        &self,
+        pk_r: &HpkePublicKey,
+        sk_s: &HpkePrivateKey,
+    ) -> Result<(SharedSecret, Vec<u8>), HpkeError> {
+        let randomness = self.random()?;
+        let (value, enc) = auth_encaps::<Crypto>(
+            self.alg,
+            HpkeVersion::Rfc9180,
+            pk_r.as_slice(),
+            None,
+            &sk_s.value,
+            &randomness,
+        )?;
+        Ok((SharedSecret { value }, enc))
+    }
+
+    /// `AuthDecap(enc, skR, pkS)`
+    ///
+    /// Returns [`HpkeError::DecapError`] if `enc` is not a valid encapsulated
+    /// secret.
+    pub fn auth_decaps(
+        &self,
+        enc: &[u8],
+        sk_r: &HpkePrivateKey,
+        pk_s: &HpkePublicKey,
+    ) -> Result<SharedSecret, HpkeError> {
+        let pk_e = prepare_enc::<Crypto>(self.alg, enc)?;
+        let value = auth_decaps::<Crypto>(
+            self.alg,
+            HpkeVersion::Rfc9180,
+            enc,
+            Some(&pk_e),
+            &sk_r.value,
+            pk_s.as_slice(),
+        )?;
+        Ok(SharedSecret { value })
+    }
+
+    /// Randomness for an ephemeral key pair.
+    fn random(&self) -> Result<Vec<u8>, HpkeError> {
+        self.prng.random(self.alg.private_key_len())
+    }
+}
+
+/// A KEM that can be a component of a [`CombinedKem`].
+pub trait KemScheme {
+    /// The crypto provider.
+    type Crypto: HpkeCrypto;
+
+    /// The length of an encoded public key in bytes (`Npk`).
+    fn public_key_len(&self) -> usize;
+
+    /// The length of an encoded private key in bytes (`Nsk`).
+    fn private_key_len(&self) -> usize;
+
+    /// The length of an encapsulated secret in bytes (`Nenc`).
+    fn enc_len(&self) -> usize;
+
+    /// Generate a new key pair.
+    fn generate_key_pair(&self) -> Result<HpkeKeyPair, HpkeError>;
+
+    /// `Encap(pkR)`
+    ///
+    /// Returns the shared secret and the encapsulated secret `enc`.
+    fn encaps(&self, pk_r: &HpkePublicKey) -> Result<(SharedSecret, Vec<u8>), HpkeError>;
+
+    /// `Decap(enc, skR)`
+    fn decaps(&self, enc: &[u8], sk_r: &HpkePrivateKey) -> Result<SharedSecret, HpkeError>;
+}
+
+impl<Crypto: HpkeCrypto> KemScheme for Kem<Crypto> {
+    type Crypto = Crypto;
+
+    fn public_key_len(&self) -> usize {
+        self.alg.public_key_len()
+    }
+
+    fn private_key_len(&self) -> usize {
+        self.alg.private_key_len()
+    }
+
+    fn enc_len(&self) -> usize {
+        self.alg.enc_len()
+    }
+
+    fn generate_key_pair(&self) -> Result<HpkeKeyPair, HpkeError> {
+        Kem::generate_key_pair(self)
+    }
+
+    fn encaps(&self, pk_r: &HpkePublicKey) -> Result<(SharedSecret, Vec<u8>), HpkeError> {
+        Kem::encaps(self, pk_r)
+    }
+
+    fn decaps(&self, enc: &[u8], sk_r: &HpkePrivateKey) -> Result<SharedSecret, HpkeError> {
+        Kem::decaps(self, enc, sk_r)
+    }
+}
+
+/// A hybrid KEM made of the KEMs `A` and `B`, see the
+/// [module documentation](self#hybrid-kems).
+pub struct CombinedKem<A: KemScheme, B: KemScheme<Crypto = A::Crypto>> {
+    a: A,
+    b: B,
+    kdf: KdfAlgorithm,
+    label: Vec<u8>,
+    _crypto: PhantomData<A::Crypto>,
+}
+
+impl<A: KemScheme, B: KemScheme<Crypto = A::Crypto>> std::fmt::Debug for CombinedKem<A, B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CombinedKem")
+            .field("kdf", &self.kdf)
+            .field("label", &self.label)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<A: KemScheme, B: KemScheme<Crypto = A::Crypto>> CombinedKem<A, B> {
+    /// Combine the KEMs `a` and `b`.
+    ///
+    /// The shared secrets are combined with the extract function of `kdf`
+    /// and the `label`, which identifies the hybrid KEM and has to be
+    /// unique for every combination.
+    pub fn new(a: A, b: B, kdf: KdfAlgorithm, label: &[u8]) -> Self {
+        Self {
+            a,
+            b,
+            kdf,
+            label: label.to_vec(),
+            _crypto: PhantomData,
+        }
+    }
+
+    /// The combiner.
+    fn combine(
+        &self,
+        ss_a: &SharedSecret,
+        ss_b: &SharedSecret,
+        enc: &[u8],
+        pk: &[u8],
+    ) -> SharedSecret {
+        let mut ikm = util::concat(&[ss_a.as_slice(), ss_b.as_slice(), enc, pk, &self.label]);
+        let value = <A::Crypto as HpkeCrypto>::kdf_extract(self.kdf, &[], &ikm);
+        ikm.zeroize();
+        SharedSecret { value }
+    }
+}
+
+impl<A: KemScheme, B: KemScheme<Crypto = A::Crypto>> KemScheme for CombinedKem<A, B> {
+    type Crypto = A::Crypto;
+
+    fn public_key_len(&self) -> usize {
+        self.a.public_key_len() + self.b.public_key_len()
+    }
+
+    fn private_key_len(&self) -> usize {
+        self.a.private_key_len() + self.b.private_key_len() + self.public_key_len()
+    }
+
+    fn enc_len(&self) -> usize {
+        self.a.enc_len() + self.b.enc_len()
+    }
+
+    fn generate_key_pair(&self) -> Result<HpkeKeyPair, HpkeError> {
+        let (sk_a, pk_a) = self.a.generate_key_pair()?.into_keys();
+        let (sk_b, pk_b) = self.b.generate_key_pair()?.into_keys();
+        let pk = util::concat(&[pk_a.as_slice(), pk_b.as_slice()]);
+        let sk = util::concat(&[&sk_a.value, &sk_b.value, &pk]);
+        Ok(HpkeKeyPair::new(sk, pk))
+    }
+
+    /// Returns [`HpkeError::InvalidInput`] if `pk_r` doesn't have `Npk`
+    /// bytes.
+    fn encaps(&self, pk_r: &HpkePublicKey) -> Result<(SharedSecret, Vec<u8>), HpkeError> {
+        let pk = pk_r.as_slice();
+        if pk.len() != self.public_key_len() {
+            return Err(HpkeError::InvalidInput);
+        }
+        let (pk_a, pk_b) = pk.split_at(self.a.public_key_len());
+        let (ss_a, enc_a) = self.a.encaps(&HpkePublicKey::new(pk_a.to_vec()))?;
+        let (ss_b, enc_b) = self.b.encaps(&HpkePublicKey::new(pk_b.to_vec()))?;
+        let enc = util::concat(&[&enc_a, &enc_b]);
+        Ok((self.combine(&ss_a, &ss_b, &enc, pk), enc))
+    }
+
+    /// Returns [`HpkeError::DecapError`] if `enc` is not a valid encapsulated
+    /// secret and [`HpkeError::InvalidInput`] if `sk_r` doesn't have `Nsk`
+    /// bytes.
+    fn decaps(&self, enc: &[u8], sk_r: &HpkePrivateKey) -> Result<SharedSecret, HpkeError> {
+        if enc.len() != self.enc_len() {
+            return Err(HpkeError::DecapError(DecapError::InvalidLength));
+        }
+        if sk_r.value.len() != self.private_key_len() {
+            return Err(HpkeError::InvalidInput);
+        }
+        let (sk_a, rest) = sk_r.value.split_at(self.a.private_key_len());
+        let (sk_b, pk) = rest.split_at(self.b.private_key_len());
+        let (enc_a, enc_b) = enc.split_at(self.a.enc_len());
+        let ss_a = self.a.decaps(enc_a, &HpkePrivateKey::new(sk_a.to_vec()))?;
+        let ss_b = self.b.decaps(enc_b, &HpkePrivateKey::new(sk_b.to_vec()))?;
+        Ok(self.combine(&ss_a, &ss_b, enc, pk))
+    }
+}
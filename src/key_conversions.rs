@@ -0,0 +1,145 @@
+//! # Conversions from and to RustCrypto and dalek Key Types
+//!
+//! With the `x25519-dalek` and `p256` features, [`HpkePublicKey`],
+//! [`HpkePrivateKey`], and [`HpkeKeyPair`] convert from and to the key types
+//! of the [`x25519_dalek`] and [`p256`] crates for the `DhKem25519` and
+//! `DhKemP256` KEMs.
+//!
+//! The conversions produce the serialization of the keys that HPKE uses:
+//! the 32 byte X25519 keys, uncompressed SEC1 P-256 public keys, and 32 byte
+//! P-256 scalars.
+//! Converting an HPKE key back fails with [`HpkeError::InvalidInput`] if it
+//! isn't a valid key of the other crate's type.
+
+use zeroize::Zeroizing;
+
+use crate::{HpkeError, HpkeKeyPair, HpkePrivateKey, HpkePublicKey};
+
+#[cfg(feature = "x25519-dalek")]
+mod x25519 {
+    use super::*;
+    use x25519_dalek::{PublicKey, StaticSecret};
+
+    impl From<&PublicKey> for HpkePublicKey {
+        fn from(pk: &PublicKey) -> Self {
+            Self::new(pk.as_bytes().to_vec())
+        }
+    }
+
+    impl From<PublicKey> for HpkePublicKey {
+        fn from(pk: PublicKey) -> Self {
+            Self::from(&pk)
+        }
+    }
+
+    impl TryFrom<&HpkePublicKey> for PublicKey {
+        type Error = HpkeError;
+
+        fn try_from(pk: &HpkePublicKey) -> Result<Self, Self::Error> {
+            let bytes: [u8; 32] = pk.try_into()?;
+            Ok(Self::from(bytes))
+        }
+    }
+
+    impl From<&StaticSecret> for HpkePrivateKey {
+        fn from(sk: &StaticSecret) -> Self {
+            Self::new(sk.as_bytes().to_vec())
+        }
+    }
+
+    impl From<StaticSecret> for HpkePrivateKey {
+        fn from(sk: StaticSecret) -> Self {
+            Self::from(&sk)
+        }
+    }
+
+    impl TryFrom<&HpkePrivateKey> for StaticSecret {
+        type Error = HpkeError;
+
+        fn try_from(sk: &HpkePrivateKey) -> Result<Self, Self::Error> {
+            let bytes: Zeroizing<[u8; 32]> = Zeroizing::new(
+                sk.value
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| HpkeError::InvalidInput)?,
+            );
+            Ok(Self::from(*bytes))
+        }
+    }
+
+    impl From<&StaticSecret> for HpkeKeyPair {
+        /// The key pair of the secret and its public key.
+        fn from(sk: &StaticSecret) -> Self {
+            Self::from_keys(sk.into(), PublicKey::from(sk).into())
+        }
+    }
+
+    impl From<StaticSecret> for HpkeKeyPair {
+        fn from(sk: StaticSecret) -> Self {
+            Self::from(&sk)
+        }
+    }
+}
+
+#[cfg(feature = "p256")]
+mod nist_p256 {
+    use super::*;
+    use p256::{elliptic_curve::sec1::ToEncodedPoint, PublicKey, SecretKey};
+
+    impl From<&PublicKey> for HpkePublicKey {
+        fn from(pk: &PublicKey) -> Self {
+            Self::new(pk.to_encoded_point(false).as_bytes().to_vec())
+        }
+    }
+
+    impl From<PublicKey> for HpkePublicKey {
+        fn from(pk: PublicKey) -> Self {
+            Self::from(&pk)
+        }
+    }
+
+    impl TryFrom<&HpkePublicKey> for PublicKey {
+        type Error = HpkeError;
+
+        fn try_from(pk: &HpkePublicKey) -> Result<Self, Self::Error> {
+            Self::from_sec1_bytes(pk.as_slice()).map_err(|_| HpkeError::InvalidInput)
+        }
+    }
+
+    impl From<&SecretKey> for HpkePrivateKey {
+        fn from(sk: &SecretKey) -> Self {
+            let bytes = Zeroizing::new(sk.to_bytes());
+            Self::new(bytes.to_vec())
+        }
+    }
+
+    impl From<SecretKey> for HpkePrivateKey {
+        fn from(sk: SecretKey) -> Self {
+            Self::from(&sk)
+        }
+    }
+
+    impl TryFrom<&HpkePrivateKey> for SecretKey {
+        type Error = HpkeError;
+
+        fn try_from(sk: &HpkePrivateKey) -> Result<Self, Self::Error> {
+            if sk.value.len() != 32 {
+                return Err(HpkeError::InvalidInput);
+            }
+            Self::from_slice(&sk.value).map_err(|_| HpkeError::InvalidInput)
+        }
+    }
+
+    impl From<&SecretKey> for HpkeKeyPair {
+        /// The key pair of the secret key and its public key.
+        fn from(sk: &SecretKey) -> Self {
+            Self::from_keys(sk.into(), sk.public_key().into())
+        }
+    }
+
+    impl From<SecretKey> for HpkeKeyPair {
+        fn from(sk: SecretKey) -> Self {
+            Self::from(&sk)
+        }
+    }
+}
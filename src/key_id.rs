@@ -0,0 +1,91 @@
+//! # Key Identifiers
+//!
+//! Compact, canonical identifiers for HPKE public keys.
+//!
+//! A [`KeyId`] is computed as
+//!
+//! ```text
+//! key_id = LabeledExtract("", "key_id", pkRm)[..8]
+//! ```
+//!
+//! with HKDF-SHA256 and the KEM suite id `"KEM" || I2OSP(kem_id, 2)`, i.e.
+//! the KEM identifier is always bound into the identifier.
+//! The key id is not a secret and can be used to reference recipient keys in
+//! envelopes and key stores.
+
+use hpke_rs_crypto::{
+    types::{KdfAlgorithm, KemAlgorithm},
+    HpkeCrypto,
+};
+
+use crate::{kdf::labeled_extract, kem, HpkeError, HpkeKeyPair, HpkePublicKey};
+
+/// The length of a [`KeyId`] in bytes.
+pub const KEY_ID_LEN: usize = 8;
+
+/// The identifier of an HPKE public key.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(
+    feature = "serialization",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct KeyId([u8; KEY_ID_LEN]);
+
+impl KeyId {
+    /// Compute the key id of the encoded public key `pk` for the KEM `kem_id`.
+    pub fn compute<Crypto: HpkeCrypto>(kem_id: KemAlgorithm, pk: &[u8]) -> Result<Self, HpkeError> {
+        Crypto::supports_kdf(KdfAlgorithm::HkdfSha256)?;
+        let prk = labeled_extract::<Crypto>(
+            KdfAlgorithm::HkdfSha256,
+            &[],
+            &kem::ciphersuite(kem_id),
+            "key_id",
+            pk,
+        );
+        let mut id = [0u8; KEY_ID_LEN];
+        id.copy_from_slice(&prk[..KEY_ID_LEN]);
+        Ok(Self(id))
+    }
+
+    /// Get the key id as byte slice.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<[u8; KEY_ID_LEN]> for KeyId {
+    fn from(id: [u8; KEY_ID_LEN]) -> Self {
+        Self(id)
+    }
+}
+
+impl TryFrom<&[u8]> for KeyId {
+    type Error = HpkeError;
+    fn try_from(id: &[u8]) -> Result<Self, HpkeError> {
+        id.try_into().map(Self).map_err(|_| HpkeError::InvalidInput)
+    }
+}
+
+impl std::fmt::Display for KeyId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for b in self.0 {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl HpkePublicKey {
+    /// Compute the [`KeyId`] of this public key for the KEM `kem_id`.
+    pub fn key_id<Crypto: HpkeCrypto>(&self, kem_id: KemAlgorithm) -> Result<KeyId, HpkeError> {
+        KeyId::compute::<Crypto>(kem_id, self.as_slice())
+    }
+}
+
+impl HpkeKeyPair {
+    /// Compute the [`KeyId`] of the public key in this key pair for the KEM
+    /// `kem_id`.
+    pub fn key_id<Crypto: HpkeCrypto>(&self, kem_id: KemAlgorithm) -> Result<KeyId, HpkeError> {
+        self.public_key().key_id::<Crypto>(kem_id)
+    }
+}
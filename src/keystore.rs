@@ -0,0 +1,872 @@
+//! # Key Store
+//!
+//! Storage for HPKE key pairs indexed by their [`KeyId`].
+//!
+//! The [`KeyStore`] trait is implemented by an in-memory store
+//! ([`MemoryKeyStore`]) and a file-backed store ([`FileKeyStore`]) that keeps
+//! one file per key pair in a directory.
+//! With the `keyring` feature, `KeyringKeyStore` keeps the key pairs in the
+//! platform keyring (macOS Keychain, Windows Credential Manager, or the
+//! Secret Service on Linux) instead of plaintext files.
+//! Incoming messages that carry a [`KeyId`] can be matched against the store
+//! with [`KeyStore::get`].
+//!
+//! Every key has a [`KeyStatus`] and a [`Validity`] period to enforce key
+//! rotation policies.
+//! [`KeyStore::encryption_key`] only selects active keys, while
+//! [`KeyStore::decryption_key`] also accepts retiring keys such that
+//! messages sealed to a key that is being phased out can still be opened.
+//! Revoked keys and keys outside of their validity period are never used.
+//!
+//! A key can also be limited to a number of uses, e.g. encapsulations or
+//! messages, with [`KeyEntry::with_usage_limit`].
+//! Every use has to be recorded with [`KeyStore::record_use`], which fails
+//! with [`HpkeError::KeyUsageLimitReached`] once the limit is reached.
+//! The use that reaches the limit moves an active key to
+//! [`KeyStatus::Retiring`] such that [`KeyStore::encryption_key`] selects
+//! its successor, and calls the callback registered with
+//! [`MemoryKeyStore::on_usage_limit`] (or [`FileKeyStore::on_usage_limit`])
+//! to rotate the key.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use hpke_rs_crypto::{types::KemAlgorithm, HpkeCrypto};
+use zeroize::Zeroize;
+
+use crate::{
+    util::{take, take_u16, take_u64},
+    validity, HpkeError, HpkeKeyPair, HpkePrivateKey, HpkePublicKey, KeyId, Validity, KEY_ID_LEN,
+};
+
+/// The lifecycle status of a key in a [`KeyStore`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyStatus {
+    /// The key is used for encryption and decryption.
+    #[default]
+    Active,
+    /// The key is being phased out and only used for decryption.
+    Retiring,
+    /// The key must not be used anymore.
+    Revoked,
+}
+
+impl KeyStatus {
+    fn encode(self) -> u8 {
+        match self {
+            KeyStatus::Active => 1,
+            KeyStatus::Retiring => 2,
+            KeyStatus::Revoked => 3,
+        }
+    }
+
+    fn decode(status: u8) -> Result<Self, HpkeError> {
+        match status {
+            1 => Ok(KeyStatus::Active),
+            2 => Ok(KeyStatus::Retiring),
+            3 => Ok(KeyStatus::Revoked),
+            _ => Err(HpkeError::InvalidInput),
+        }
+    }
+}
+
+/// A key pair in a [`KeyStore`] together with the KEM it is used with.
+#[derive(Debug)]
+pub struct KeyEntry {
+    kem_id: KemAlgorithm,
+    key_pair: HpkeKeyPair,
+    status: KeyStatus,
+    validity: Validity,
+    usage: u64,
+    usage_limit: Option<u64>,
+}
+
+impl KeyEntry {
+    /// Create a new, active key store entry without a validity period or a
+    /// usage limit.
+    pub fn new(kem_id: KemAlgorithm, key_pair: HpkeKeyPair) -> Self {
+        Self {
+            kem_id,
+            key_pair,
+            status: KeyStatus::Active,
+            validity: Validity::default(),
+            usage: 0,
+            usage_limit: None,
+        }
+    }
+
+    /// Set the status of this entry.
+    pub fn with_status(mut self, status: KeyStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Set the validity period of this entry.
+    pub fn with_validity(mut self, validity: Validity) -> Self {
+        self.validity = validity;
+        self
+    }
+
+    /// Limit this key to `limit` uses, see [`KeyStore::record_use`].
+    pub fn with_usage_limit(mut self, limit: u64) -> Self {
+        self.usage_limit = Some(limit);
+        self
+    }
+
+    /// Get the status of this key.
+    pub fn status(&self) -> KeyStatus {
+        self.status
+    }
+
+    /// Get the validity period of this key.
+    pub fn validity(&self) -> Validity {
+        self.validity
+    }
+
+    /// Get the number of recorded uses of this key.
+    pub fn usage(&self) -> u64 {
+        self.usage
+    }
+
+    /// Get the usage limit of this key.
+    pub fn usage_limit(&self) -> Option<u64> {
+        self.usage_limit
+    }
+
+    /// Get the number of uses left before this key reaches its limit, or
+    /// `None` if it has no limit.
+    pub fn remaining_uses(&self) -> Option<u64> {
+        self.usage_limit
+            .map(|limit| limit.saturating_sub(self.usage))
+    }
+
+    /// Returns `true` if this key may be used to seal new messages at `now`,
+    /// in seconds since the Unix epoch.
+    pub fn can_encrypt(&self, now: u64) -> bool {
+        self.status == KeyStatus::Active
+            && self.validity.check(now).is_ok()
+            && self.remaining_uses() != Some(0)
+    }
+
+    /// Count a use of this key.
+    ///
+    /// Returns `true` if this use reached the limit.
+    fn record_use(&mut self) -> Result<bool, HpkeError> {
+        if self.remaining_uses() == Some(0) {
+            return Err(HpkeError::KeyUsageLimitReached);
+        }
+        self.usage += 1;
+        let exhausted = self.remaining_uses() == Some(0);
+        if exhausted && self.status == KeyStatus::Active {
+            self.status = KeyStatus::Retiring;
+        }
+        Ok(exhausted)
+    }
+
+    /// Returns `true` if this key may be used to open messages at `now`, in
+    /// seconds since the Unix epoch.
+    pub fn can_decrypt(&self, now: u64) -> bool {
+        self.status != KeyStatus::Revoked && self.validity.check(now).is_ok()
+    }
+
+    /// Get the KEM of this key pair.
+    pub fn kem_id(&self) -> KemAlgorithm {
+        self.kem_id
+    }
+
+    /// Get a reference to the key pair.
+    pub fn key_pair(&self) -> &HpkeKeyPair {
+        &self.key_pair
+    }
+
+    /// Get a reference to the private key.
+    pub fn private_key(&self) -> &HpkePrivateKey {
+        self.key_pair.private_key()
+    }
+
+    /// Get a reference to the public key.
+    pub fn public_key(&self) -> &HpkePublicKey {
+        self.key_pair.public_key()
+    }
+
+    /// Get the [Bech32 string](crate::bech32) of the public key, e.g. to
+    /// share it with senders.
+    pub fn public_key_string(&self) -> String {
+        self.public_key().to_bech32(self.kem_id)
+    }
+}
+
+/// A store for HPKE key pairs, indexed by [`KeyId`].
+pub trait KeyStore {
+    /// Store the `entry` under `key_id`.
+    ///
+    /// Returns an error if an entry with the same id already exists.
+    fn insert_entry(&mut self, key_id: KeyId, entry: KeyEntry) -> Result<(), HpkeError>;
+
+    /// Get the entry for `key_id` if it exists.
+    fn get(&self, key_id: &KeyId) -> Option<&KeyEntry>;
+
+    /// Remove the entry for `key_id` and return it.
+    fn remove(&mut self, key_id: &KeyId) -> Result<Option<KeyEntry>, HpkeError>;
+
+    /// Get the ids of all keys in this store.
+    fn key_ids(&self) -> Vec<KeyId>;
+
+    /// Store the `key_pair` for the KEM `kem_id` under its [`KeyId`].
+    ///
+    /// Returns the [`KeyId`] of the key pair.
+    fn insert<Crypto: HpkeCrypto>(
+        &mut self,
+        kem_id: KemAlgorithm,
+        key_pair: HpkeKeyPair,
+    ) -> Result<KeyId, HpkeError>
+    where
+        Self: Sized,
+    {
+        let key_id = key_pair.key_id::<Crypto>(kem_id)?;
+        self.insert_entry(key_id, KeyEntry::new(kem_id, key_pair))?;
+        Ok(key_id)
+    }
+
+    /// Get the entry for the [public key string](crate::bech32) `pk` if it
+    /// exists.
+    ///
+    /// Returns the errors of [`HpkePublicKey::from_bech32`] if `pk` is not a
+    /// valid public key string.
+    fn get_by_public_key_string<Crypto: HpkeCrypto>(
+        &self,
+        pk: &str,
+    ) -> Result<Option<(KeyId, &KeyEntry)>, HpkeError>
+    where
+        Self: Sized,
+    {
+        let (kem_id, pk) = HpkePublicKey::from_bech32(pk)?;
+        let key_id = pk.key_id::<Crypto>(kem_id)?;
+        Ok(self
+            .get(&key_id)
+            .filter(|entry| entry.kem_id == kem_id && entry.public_key() == &pk)
+            .map(|entry| (key_id, entry)))
+    }
+
+    /// Get the private key for `key_id` if it exists.
+    ///
+    /// This ignores the status and validity period of the key, see
+    /// [`decryption_key`](Self::decryption_key).
+    fn private_key(&self, key_id: &KeyId) -> Option<&HpkePrivateKey> {
+        self.get(key_id).map(|entry| entry.private_key())
+    }
+
+    /// Set the status of the key `key_id`.
+    ///
+    /// Returns [`HpkeError::InvalidInput`] if there is no such key.
+    fn set_status(&mut self, key_id: &KeyId, status: KeyStatus) -> Result<(), HpkeError> {
+        let entry = self.remove(key_id)?.ok_or(HpkeError::InvalidInput)?;
+        self.insert_entry(*key_id, entry.with_status(status))
+    }
+
+    /// Record a use of the key `key_id`, e.g. an encapsulation or a message.
+    ///
+    /// Returns the number of uses of the key including this one,
+    /// [`HpkeError::KeyUsageLimitReached`] if the key already reached its
+    /// usage limit, and [`HpkeError::InvalidInput`] if there is no such key.
+    /// The use that reaches the limit moves an active key to
+    /// [`KeyStatus::Retiring`].
+    fn record_use(&mut self, key_id: &KeyId) -> Result<u64, HpkeError> {
+        let mut entry = self.remove(key_id)?.ok_or(HpkeError::InvalidInput)?;
+        let recorded = entry.record_use();
+        let usage = entry.usage;
+        self.insert_entry(*key_id, entry)?;
+        recorded.map(|_| usage)
+    }
+
+    /// Get the key to seal new messages to at `now`, in seconds since the
+    /// Unix epoch.
+    ///
+    /// This is the active key in its validity period with the latest
+    /// `not_before` that hasn't reached its usage limit, or `None` if there
+    /// is no such key.
+    fn encryption_key(&self, now: u64) -> Option<(KeyId, &KeyEntry)> {
+        self.key_ids()
+            .into_iter()
+            .filter_map(|key_id| Some((key_id, self.get(&key_id)?)))
+            .filter(|(_, entry)| entry.can_encrypt(now))
+            .max_by_key(|(_, entry)| entry.validity.not_before)
+    }
+
+    /// Get the private key for `key_id` to open a message at `now`, in
+    /// seconds since the Unix epoch.
+    ///
+    /// Returns `None` if the key doesn't exist, is revoked, or is outside of
+    /// its validity period.
+    fn decryption_key(&self, key_id: &KeyId, now: u64) -> Option<&HpkePrivateKey> {
+        self.get(key_id)
+            .filter(|entry| entry.can_decrypt(now))
+            .map(|entry| entry.private_key())
+    }
+}
+
+/// A callback for keys that reached their usage limit.
+#[derive(Clone)]
+struct UsageLimitCallback(Arc<dyn Fn(&KeyId) + Send + Sync>);
+
+impl std::fmt::Debug for UsageLimitCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("UsageLimitCallback").finish()
+    }
+}
+
+/// An in-memory [`KeyStore`].
+#[derive(Debug, Default)]
+pub struct MemoryKeyStore {
+    keys: BTreeMap<KeyId, KeyEntry>,
+    on_usage_limit: Option<UsageLimitCallback>,
+}
+
+impl MemoryKeyStore {
+    /// Create a new, empty key store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `callback` with the id of every key that reaches its usage
+    /// limit, e.g. to generate and insert its successor.
+    pub fn on_usage_limit(mut self, callback: impl Fn(&KeyId) + Send + Sync + 'static) -> Self {
+        self.on_usage_limit = Some(UsageLimitCallback(Arc::new(callback)));
+        self
+    }
+
+    fn usage_limit_reached(&self, key_id: &KeyId) {
+        if let Some(UsageLimitCallback(callback)) = &self.on_usage_limit {
+            callback(key_id);
+        }
+    }
+
+    /// Get the number of keys in the store.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns `true` if the store holds no keys.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl KeyStore for MemoryKeyStore {
+    fn insert_entry(&mut self, key_id: KeyId, entry: KeyEntry) -> Result<(), HpkeError> {
+        if self.keys.contains_key(&key_id) {
+            return Err(HpkeError::InvalidInput);
+        }
+        self.keys.insert(key_id, entry);
+        Ok(())
+    }
+
+    fn get(&self, key_id: &KeyId) -> Option<&KeyEntry> {
+        self.keys.get(key_id)
+    }
+
+    fn remove(&mut self, key_id: &KeyId) -> Result<Option<KeyEntry>, HpkeError> {
+        Ok(self.keys.remove(key_id))
+    }
+
+    fn key_ids(&self) -> Vec<KeyId> {
+        self.keys.keys().copied().collect()
+    }
+
+    fn set_status(&mut self, key_id: &KeyId, status: KeyStatus) -> Result<(), HpkeError> {
+        let entry = self.keys.get_mut(key_id).ok_or(HpkeError::InvalidInput)?;
+        entry.status = status;
+        Ok(())
+    }
+
+    fn record_use(&mut self, key_id: &KeyId) -> Result<u64, HpkeError> {
+        let entry = self.keys.get_mut(key_id).ok_or(HpkeError::InvalidInput)?;
+        let exhausted = entry.record_use()?;
+        let usage = entry.usage;
+        if exhausted {
+            self.usage_limit_reached(key_id);
+        }
+        Ok(usage)
+    }
+}
+
+/// A file-backed [`KeyStore`].
+///
+/// Every key pair is stored in its own file `<key id>.key` in the store
+/// directory.
+/// All keys are loaded into memory when the store is opened and changes are
+/// written through to the directory immediately.
+///
+/// **Note** that the private keys are stored unencrypted.
+/// On Unix the key files are only readable by the owner.
+#[derive(Debug)]
+pub struct FileKeyStore {
+    path: PathBuf,
+    keys: MemoryKeyStore,
+    compute_key_id: ComputeKeyId,
+    rejected: Vec<PathBuf>,
+}
+
+/// [`KeyId::compute`] for the crypto provider of a store.
+type ComputeKeyId = fn(KemAlgorithm, &[u8]) -> Result<KeyId, HpkeError>;
+
+/// Check that `key_id` is the [`KeyId`] of the public key in `entry`.
+fn check_key_id(
+    compute_key_id: ComputeKeyId,
+    key_id: &KeyId,
+    entry: &KeyEntry,
+) -> Result<(), HpkeError> {
+    if compute_key_id(entry.kem_id, entry.public_key().as_slice())? != *key_id {
+        return Err(HpkeError::InvalidInput);
+    }
+    Ok(())
+}
+
+/// Version of the key file encoding.
+const KEY_FILE_VERSION: u8 = 1;
+const KEY_FILE_EXTENSION: &str = "key";
+const TMP_FILE_EXTENSION: &str = "tmp";
+
+impl FileKeyStore {
+    /// Open the key store in the directory `path`.
+    ///
+    /// The directory is created if it doesn't exist.
+    /// The [`KeyId`] of every key is computed from its public key with
+    /// `Crypto`, when the key file is read and when a key is inserted.
+    /// Key files that can't be decoded or don't match their id are skipped
+    /// and listed in [`rejected_files`](Self::rejected_files).
+    pub fn open<Crypto: HpkeCrypto>(path: impl AsRef<Path>) -> Result<Self, HpkeError> {
+        let path = path.as_ref().to_path_buf();
+        fs::create_dir_all(&path)?;
+
+        let compute_key_id: ComputeKeyId = KeyId::compute::<Crypto>;
+        let mut keys = MemoryKeyStore::new();
+        let mut rejected = Vec::new();
+        for file in fs::read_dir(&path)? {
+            let file = file?.path();
+            if file.extension().and_then(|e| e.to_str()) != Some(KEY_FILE_EXTENSION) {
+                continue;
+            }
+            let mut bytes = fs::read(&file)?;
+            let decoded = decode_key_file(&bytes).and_then(|(key_id, entry)| {
+                check_key_id(compute_key_id, &key_id, &entry)?;
+                if file.file_stem().and_then(|s| s.to_str()) != Some(&key_id.to_string()) {
+                    return Err(HpkeError::InvalidInput);
+                }
+                Ok((key_id, entry))
+            });
+            bytes.zeroize();
+            match decoded {
+                Ok((key_id, entry)) => keys.insert_entry(key_id, entry)?,
+                Err(_) => rejected.push(file),
+            }
+        }
+
+        Ok(Self {
+            path,
+            keys,
+            compute_key_id,
+            rejected,
+        })
+    }
+
+    /// Get the key files that were skipped when the store was opened because
+    /// they can't be decoded or don't match their [`KeyId`].
+    pub fn rejected_files(&self) -> &[PathBuf] {
+        &self.rejected
+    }
+
+    /// Call `callback` with the id of every key that reaches its usage
+    /// limit, see [`MemoryKeyStore::on_usage_limit`].
+    pub fn on_usage_limit(mut self, callback: impl Fn(&KeyId) + Send + Sync + 'static) -> Self {
+        self.keys = self.keys.on_usage_limit(callback);
+        self
+    }
+
+    /// Get the directory of this key store.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn key_file(&self, key_id: &KeyId) -> PathBuf {
+        self.path
+            .join(key_id.to_string())
+            .with_extension(KEY_FILE_EXTENSION)
+    }
+
+    /// Replace the key file of `key_id` with `entry`.
+    ///
+    /// The file is replaced atomically such that a failure can't lose the
+    /// key.
+    fn rewrite(&self, key_id: &KeyId, entry: &KeyEntry) -> Result<(), HpkeError> {
+        let mut bytes = encode_key_file(key_id, entry)?;
+        let tmp = self.key_file(key_id).with_extension(TMP_FILE_EXTENSION);
+        let _ = fs::remove_file(&tmp);
+        let written = write_private_file(&tmp, &bytes);
+        bytes.zeroize();
+        written?;
+        fs::rename(&tmp, self.key_file(key_id))?;
+        Ok(())
+    }
+}
+
+impl KeyStore for FileKeyStore {
+    /// Returns [`HpkeError::InvalidInput`] if `key_id` isn't the [`KeyId`] of
+    /// the public key in `entry`.
+    fn insert_entry(&mut self, key_id: KeyId, entry: KeyEntry) -> Result<(), HpkeError> {
+        if self.keys.get(&key_id).is_some() {
+            return Err(HpkeError::InvalidInput);
+        }
+        check_key_id(self.compute_key_id, &key_id, &entry)?;
+        let mut bytes = encode_key_file(&key_id, &entry)?;
+        let written = write_private_file(&self.key_file(&key_id), &bytes);
+        bytes.zeroize();
+        written?;
+        self.keys.insert_entry(key_id, entry)
+    }
+
+    fn get(&self, key_id: &KeyId) -> Option<&KeyEntry> {
+        self.keys.get(key_id)
+    }
+
+    fn remove(&mut self, key_id: &KeyId) -> Result<Option<KeyEntry>, HpkeError> {
+        if self.keys.get(key_id).is_none() {
+            return Ok(None);
+        }
+        fs::remove_file(self.key_file(key_id))?;
+        self.keys.remove(key_id)
+    }
+
+    fn key_ids(&self) -> Vec<KeyId> {
+        self.keys.key_ids()
+    }
+
+    fn set_status(&mut self, key_id: &KeyId, status: KeyStatus) -> Result<(), HpkeError> {
+        let entry = self.keys.get(key_id).ok_or(HpkeError::InvalidInput)?;
+        let status_before = entry.status;
+        self.keys.set_status(key_id, status)?;
+        let written = self.rewrite(key_id, &self.keys.keys[key_id]);
+        if written.is_err() {
+            self.keys.set_status(key_id, status_before)?;
+        }
+        written
+    }
+
+    /// The use is written to the key file before it is counted such that
+    /// restarts don't reset the usage.
+    fn record_use(&mut self, key_id: &KeyId) -> Result<u64, HpkeError> {
+        let entry = self
+            .keys
+            .keys
+            .get_mut(key_id)
+            .ok_or(HpkeError::InvalidInput)?;
+        let (usage, status) = (entry.usage, entry.status);
+        let exhausted = entry.record_use()?;
+        let entry = &self.keys.keys[key_id];
+        if let Err(e) = self.rewrite(key_id, entry) {
+            let entry = self
+                .keys
+                .keys
+                .get_mut(key_id)
+                .ok_or(HpkeError::InvalidInput)?;
+            entry.usage = usage;
+            entry.status = status;
+            return Err(e);
+        }
+        if exhausted {
+            self.keys.usage_limit_reached(key_id);
+        }
+        Ok(usage + 1)
+    }
+}
+
+/// A [`KeyStore`] in the platform keyring.
+///
+/// Every key pair is stored in the encoding of the [`FileKeyStore`] key files
+/// as the secret of a keyring entry for the `service` of the store, with the
+/// [`KeyId`] as the user name.
+/// The keyrings can't list entries, so the store keeps the ids of its keys
+/// in an additional index entry.
+///
+/// Like [`FileKeyStore`], all keys are loaded into memory when the store is
+/// opened and changes are written through to the keyring immediately.
+/// Keyring errors are returned as [`HpkeError::IoError`].
+#[cfg(feature = "keyring")]
+#[derive(Debug)]
+pub struct KeyringKeyStore {
+    service: String,
+    keys: MemoryKeyStore,
+    compute_key_id: ComputeKeyId,
+    rejected: Vec<KeyId>,
+}
+
+/// The user name of the index entry of a [`KeyringKeyStore`].
+#[cfg(feature = "keyring")]
+const KEYRING_INDEX_USER: &str = "hpke-rs key index";
+
+#[cfg(feature = "keyring")]
+impl KeyringKeyStore {
+    /// Open the key store of `service` in the platform keyring.
+    ///
+    /// The store is empty if `service` has no keys yet.
+    /// Like [`FileKeyStore::open`], the [`KeyId`] of every key is computed
+    /// with `Crypto`, and keys that can't be decoded or don't match their id
+    /// are skipped and listed in [`rejected_keys`](Self::rejected_keys).
+    pub fn open<Crypto: HpkeCrypto>(service: impl Into<String>) -> Result<Self, HpkeError> {
+        let service = service.into();
+        let index = match keyring::Entry::new(&service, KEYRING_INDEX_USER)?.get_secret() {
+            Ok(index) => index,
+            Err(keyring::Error::NoEntry) => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        if index.len() % KEY_ID_LEN != 0 {
+            return Err(HpkeError::InvalidInput);
+        }
+
+        let compute_key_id: ComputeKeyId = KeyId::compute::<Crypto>;
+        let mut keys = MemoryKeyStore::new();
+        let mut rejected = Vec::new();
+        for key_id in index.chunks(KEY_ID_LEN) {
+            let key_id = KeyId::try_from(key_id)?;
+            let mut bytes = keyring::Entry::new(&service, &key_id.to_string())?.get_secret()?;
+            let decoded = decode_key_file(&bytes).and_then(|(stored_id, entry)| {
+                check_key_id(compute_key_id, &key_id, &entry)?;
+                if stored_id != key_id {
+                    return Err(HpkeError::InvalidInput);
+                }
+                Ok(entry)
+            });
+            bytes.zeroize();
+            match decoded {
+                Ok(entry) => keys.insert_entry(key_id, entry)?,
+                Err(_) => rejected.push(key_id),
+            }
+        }
+
+        Ok(Self {
+            service,
+            keys,
+            compute_key_id,
+            rejected,
+        })
+    }
+
+    /// Get the ids of the keys that were skipped when the store was opened
+    /// because they can't be decoded or don't match their [`KeyId`].
+    ///
+    /// The keys stay in the keyring and in the index.
+    pub fn rejected_keys(&self) -> &[KeyId] {
+        &self.rejected
+    }
+
+    /// Call `callback` with the id of every key that reaches its usage
+    /// limit, see [`MemoryKeyStore::on_usage_limit`].
+    pub fn on_usage_limit(mut self, callback: impl Fn(&KeyId) + Send + Sync + 'static) -> Self {
+        self.keys = self.keys.on_usage_limit(callback);
+        self
+    }
+
+    /// Get the keyring service of this key store.
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    fn entry(&self, key_id: &KeyId) -> Result<keyring::Entry, HpkeError> {
+        Ok(keyring::Entry::new(&self.service, &key_id.to_string())?)
+    }
+
+    /// Write the index of the `key_ids` and the rejected keys.
+    fn write_index(&self, key_ids: &[KeyId]) -> Result<(), HpkeError> {
+        let key_ids = key_ids.iter().chain(&self.rejected);
+        let index = crate::util::concat(&key_ids.map(KeyId::as_slice).collect::<Vec<_>>());
+        keyring::Entry::new(&self.service, KEYRING_INDEX_USER)?.set_secret(&index)?;
+        Ok(())
+    }
+
+    /// Write the keyring entry of `key_id`.
+    fn write(&self, key_id: &KeyId, entry: &KeyEntry) -> Result<(), HpkeError> {
+        let mut bytes = encode_key_file(key_id, entry)?;
+        let written = self.entry(key_id).and_then(|e| Ok(e.set_secret(&bytes)?));
+        bytes.zeroize();
+        written
+    }
+}
+
+#[cfg(feature = "keyring")]
+impl KeyStore for KeyringKeyStore {
+    /// The key is written before the index such that a failure can't leave
+    /// a key id in the index without its key.
+    ///
+    /// Returns [`HpkeError::InvalidInput`] if `key_id` isn't the [`KeyId`] of
+    /// the public key in `entry`.
+    fn insert_entry(&mut self, key_id: KeyId, entry: KeyEntry) -> Result<(), HpkeError> {
+        if self.keys.get(&key_id).is_some() || self.rejected.contains(&key_id) {
+            return Err(HpkeError::InvalidInput);
+        }
+        check_key_id(self.compute_key_id, &key_id, &entry)?;
+        self.write(&key_id, &entry)?;
+        let mut key_ids = self.keys.key_ids();
+        key_ids.push(key_id);
+        self.write_index(&key_ids)?;
+        self.keys.insert_entry(key_id, entry)
+    }
+
+    fn get(&self, key_id: &KeyId) -> Option<&KeyEntry> {
+        self.keys.get(key_id)
+    }
+
+    /// The key id is removed from the index before the key is deleted.
+    fn remove(&mut self, key_id: &KeyId) -> Result<Option<KeyEntry>, HpkeError> {
+        if self.keys.get(key_id).is_none() {
+            return Ok(None);
+        }
+        let mut key_ids = self.keys.key_ids();
+        key_ids.retain(|id| id != key_id);
+        self.write_index(&key_ids)?;
+        match self.entry(key_id)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => (),
+            Err(e) => return Err(e.into()),
+        }
+        self.keys.remove(key_id)
+    }
+
+    fn key_ids(&self) -> Vec<KeyId> {
+        self.keys.key_ids()
+    }
+
+    fn set_status(&mut self, key_id: &KeyId, status: KeyStatus) -> Result<(), HpkeError> {
+        let entry = self.keys.get(key_id).ok_or(HpkeError::InvalidInput)?;
+        let status_before = entry.status;
+        self.keys.set_status(key_id, status)?;
+        let written = self.write(key_id, &self.keys.keys[key_id]);
+        if written.is_err() {
+            self.keys.set_status(key_id, status_before)?;
+        }
+        written
+    }
+
+    /// The use is written to the keyring before it is counted such that
+    /// restarts don't reset the usage.
+    fn record_use(&mut self, key_id: &KeyId) -> Result<u64, HpkeError> {
+        let entry = self
+            .keys
+            .keys
+            .get_mut(key_id)
+            .ok_or(HpkeError::InvalidInput)?;
+        let (usage, status) = (entry.usage, entry.status);
+        let exhausted = entry.record_use()?;
+        let entry = &self.keys.keys[key_id];
+        if let Err(e) = self.write(key_id, entry) {
+            let entry = self
+                .keys
+                .keys
+                .get_mut(key_id)
+                .ok_or(HpkeError::InvalidInput)?;
+            entry.usage = usage;
+            entry.status = status;
+            return Err(e);
+        }
+        if exhausted {
+            self.keys.usage_limit_reached(key_id);
+        }
+        Ok(usage + 1)
+    }
+}
+
+#[cfg(feature = "keyring")]
+impl From<keyring::Error> for HpkeError {
+    fn from(e: keyring::Error) -> Self {
+        HpkeError::IoError(e.to_string())
+    }
+}
+
+/// Key file encoding
+///
+/// ```text
+/// struct {
+///     uint8 version;
+///     opaque key_id[8];
+///     uint16 kem_id;
+///     opaque private_key<0..2^16-1>;
+///     opaque public_key<0..2^16-1>;
+///     uint8 status;              // 1: active, 2: retiring, 3: revoked
+///     uint64 not_before;         // 0: no lower bound
+///     uint64 not_after;          // 2^64-1: no upper bound
+///     uint64 usage;
+///     uint64 usage_limit;        // 2^64-1: no limit
+/// } KeyFile;
+/// ```
+///
+/// Returns [`HpkeError::InvalidInput`] if a key is too long for the encoding.
+fn encode_key_file(key_id: &KeyId, entry: &KeyEntry) -> Result<Vec<u8>, HpkeError> {
+    let sk = &entry.private_key().value;
+    let pk = entry.public_key().as_slice();
+    let sk_len = u16::try_from(sk.len()).map_err(|_| HpkeError::InvalidInput)?;
+    let pk_len = u16::try_from(pk.len()).map_err(|_| HpkeError::InvalidInput)?;
+    let mut out = Vec::with_capacity(
+        1 + KEY_ID_LEN + 2 + 2 + sk.len() + 2 + pk.len() + 1 + validity::ENCODED_LEN + 8 + 8,
+    );
+    out.push(KEY_FILE_VERSION);
+    out.extend_from_slice(key_id.as_slice());
+    out.extend_from_slice(&(entry.kem_id as u16).to_be_bytes());
+    out.extend_from_slice(&sk_len.to_be_bytes());
+    out.extend_from_slice(sk);
+    out.extend_from_slice(&pk_len.to_be_bytes());
+    out.extend_from_slice(pk);
+    out.push(entry.status.encode());
+    out.extend_from_slice(&entry.validity.encode());
+    out.extend_from_slice(&entry.usage.to_be_bytes());
+    out.extend_from_slice(&entry.usage_limit.unwrap_or(u64::MAX).to_be_bytes());
+    Ok(out)
+}
+
+/// Decode a key file.
+fn decode_key_file(bytes: &[u8]) -> Result<(KeyId, KeyEntry), HpkeError> {
+    let mut reader = bytes;
+    let version = take(&mut reader, 1)?[0];
+    if version != KEY_FILE_VERSION {
+        return Err(HpkeError::InvalidInput);
+    }
+    let key_id = KeyId::try_from(take(&mut reader, KEY_ID_LEN)?)?;
+    let kem_id = KemAlgorithm::try_from(take_u16(&mut reader)?)?;
+    let sk_len = take_u16(&mut reader)?.into();
+    let sk = take(&mut reader, sk_len)?.to_vec();
+    let pk_len = take_u16(&mut reader)?.into();
+    let pk = take(&mut reader, pk_len)?.to_vec();
+    let mut entry = KeyEntry::new(kem_id, HpkeKeyPair::new(sk, pk));
+    entry.status = KeyStatus::decode(take(&mut reader, 1)?[0])?;
+    entry.validity = Validity::decode(take(&mut reader, validity::ENCODED_LEN)?)?;
+    entry.usage = take_u64(&mut reader)?;
+    let usage_limit = take_u64(&mut reader)?;
+    entry.usage_limit = (usage_limit != u64::MAX).then_some(usage_limit);
+    if !reader.is_empty() {
+        return Err(HpkeError::InvalidInput);
+    }
+    Ok((key_id, entry))
+}
+
+/// Write `bytes` to the new file `path` that only the owner can read.
+///
+/// The file is removed again if writing fails.
+fn write_private_file(path: &Path, bytes: &[u8]) -> Result<(), HpkeError> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    let written = file.write_all(bytes).and_then(|()| file.sync_all());
+    if written.is_err() {
+        // Don't leave a truncated key file behind.
+        drop(file);
+        let _ = fs::remove_file(path);
+    }
+    Ok(written?)
+}
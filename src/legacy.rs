@@ -0,0 +1,151 @@
+//! # Legacy Draft Labels
+//!
+//! With the `legacy-draft` feature, [`HpkeVersion::Draft05`](crate::HpkeVersion::Draft05) (or
+//! [`Hpke::with_legacy_draft_labels`]) switches a configuration to the labels
+//! of draft-irtf-cfrg-hpke-05.
+//! This allows peers that still use the draft to be migrated gradually.
+//! New applications must use the RFC 9180 labels (the default).
+//!
+//! The draft doesn't bind the suite id into the labels and uses a different
+//! key schedule.
+//!
+//! ```text
+//! def LabeledExtract(salt, label, IKM):
+//!   labeledIKM = concat("RFCXXXX ", label, IKM)
+//!   return Extract(salt, labeledIKM)
+//!
+//! def LabeledExpand(PRK, label, info, L):
+//!   labeledInfo = concat(I2OSP(L, 2), "RFCXXXX ", label, info)
+//!   return Expand(PRK, labeledInfo, L)
+//!
+//! def ExtractAndExpand(dh, kemContext):
+//!   prk = LabeledExtract(zero(Nh), "dh", dh)
+//!   return LabeledExpand(prk, "prk", kemContext, Nzz)
+//!
+//! def KeySchedule(mode, zz, info, psk, pskID, pkSm):
+//!   ciphersuite = concat(I2OSP(kem_id, 2), I2OSP(kdf_id, 2), I2OSP(aead_id, 2))
+//!   pskID_hash = LabeledExtract(zero(Nh), "pskID_hash", pskID)
+//!   info_hash = LabeledExtract(zero(Nh), "info", info)
+//!   context = concat(ciphersuite, mode, pskID_hash, info_hash)
+//!
+//!   psk_hash = LabeledExtract(zero(Nh), "psk_hash", psk)
+//!   secret = LabeledExtract(psk_hash, "zz", zz)
+//!
+//!   key = LabeledExpand(secret, "key", context, Nk)
+//!   nonce = LabeledExpand(secret, "nonce", context, Nn)
+//!   exporter_secret = LabeledExpand(secret, "exp", context, Nh)
+//!
+//! def Context.Export(exporter_context, L):
+//!   return Expand(self.exporter_secret, exporter_context, L)
+//! ```
+//!
+//! Key generation, key derivation, and [`Context::ratchet`](crate::Context::ratchet)
+//! always use the RFC 9180 labels.
+
+use hpke_rs_crypto::{
+    error::Error,
+    types::{KdfAlgorithm, KemAlgorithm},
+    HpkeCrypto,
+};
+
+use crate::{util::concat, ExporterSecret, Hpke, HpkeError, Key, Nonce};
+
+const DRAFT_LABEL: &[u8] = b"RFCXXXX ";
+
+fn labeled_extract<Crypto: HpkeCrypto>(
+    alg: KdfAlgorithm,
+    salt: &[u8],
+    label: &str,
+    ikm: &[u8],
+) -> Vec<u8> {
+    let labeled_ikm = concat(&[DRAFT_LABEL, label.as_bytes(), ikm]);
+    Crypto::kdf_extract(alg, salt, &labeled_ikm)
+}
+
+fn labeled_expand<Crypto: HpkeCrypto>(
+    alg: KdfAlgorithm,
+    prk: &[u8],
+    label: &str,
+    info: &[u8],
+    len: usize,
+) -> Result<Vec<u8>, Error> {
+    let len_bytes = u16::try_from(len)
+        .map_err(|_| Error::HpkeInvalidOutputLength)?
+        .to_be_bytes();
+    let labeled_info = concat(&[&len_bytes, DRAFT_LABEL, label.as_bytes(), info]);
+    Crypto::kdf_expand(alg, prk, &labeled_info, len)
+}
+
+/// The draft KEM shared secret from the DH value `dh`.
+pub(crate) fn extract_and_expand<Crypto: HpkeCrypto>(
+    alg: KemAlgorithm,
+    dh: &[u8],
+    kem_context: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let prk = labeled_extract::<Crypto>(alg.into(), &[], "dh", dh);
+    labeled_expand::<Crypto>(
+        alg.into(),
+        &prk,
+        "prk",
+        kem_context,
+        alg.shared_secret_len(),
+    )
+}
+
+/// The draft key schedule.
+/// Returns the key, base nonce, and exporter secret.
+pub(crate) fn key_schedule_secrets<Crypto: HpkeCrypto>(
+    hpke: &Hpke<Crypto>,
+    shared_secret: &[u8],
+    info: &[u8],
+    psk: &[u8],
+    psk_id: &[u8],
+) -> Result<(Key, Nonce, ExporterSecret), HpkeError> {
+    let kdf = hpke.kdf_id;
+    let nh = Crypto::kdf_digest_length(kdf);
+
+    let psk_id_hash = labeled_extract::<Crypto>(kdf, &[], "pskID_hash", psk_id);
+    let info_hash = labeled_extract::<Crypto>(kdf, &[], "info", info);
+    let context = concat(&[
+        &(hpke.kem_id as u16).to_be_bytes(),
+        &(kdf as u16).to_be_bytes(),
+        &(hpke.aead_id as u16).to_be_bytes(),
+        &[hpke.mode as u8],
+        &psk_id_hash,
+        &info_hash,
+    ]);
+
+    // The draft uses zero(Nh) as default PSK.
+    let default_psk = vec![0u8; nh];
+    let psk = if psk.is_empty() { &default_psk } else { psk };
+    let psk_hash = labeled_extract::<Crypto>(kdf, &[], "psk_hash", psk);
+    let secret = labeled_extract::<Crypto>(kdf, &psk_hash, "zz", shared_secret);
+
+    let key = Key::from_vec(labeled_expand::<Crypto>(
+        kdf,
+        &secret,
+        "key",
+        &context,
+        Crypto::aead_key_length(hpke.aead_id),
+    )?)?;
+    let base_nonce = Nonce::from_vec(labeled_expand::<Crypto>(
+        kdf,
+        &secret,
+        "nonce",
+        &context,
+        Crypto::aead_nonce_length(hpke.aead_id),
+    )?)?;
+    let exporter_secret =
+        ExporterSecret::from_vec(labeled_expand::<Crypto>(kdf, &secret, "exp", &context, nh)?)?;
+    Ok((key, base_nonce, exporter_secret))
+}
+
+/// The draft secret export.
+pub(crate) fn export<Crypto: HpkeCrypto>(
+    alg: KdfAlgorithm,
+    exporter_secret: &[u8],
+    exporter_context: &[u8],
+    length: usize,
+) -> Result<Vec<u8>, HpkeError> {
+    Crypto::kdf_expand(alg, exporter_secret, exporter_context, length).map_err(HpkeError::from)
+}
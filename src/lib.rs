@@ -9,12 +9,8 @@
     unused_qualifications
 )]
 
-use std::sync::RwLock;
+use std::{hint::black_box, sync::Arc};
 
-#[cfg(feature = "hpke-test-prng")]
-use hpke_rs_crypto::HpkeTestRng;
-#[cfg(not(feature = "hpke-test-prng"))]
-use hpke_rs_crypto::RngCore;
 use hpke_rs_crypto::{
     types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm},
     HpkeCrypto,
@@ -22,18 +18,88 @@ use hpke_rs_crypto::{
 use prelude::kdf::{labeled_expand, labeled_extract};
 #[cfg(feature = "serialization")]
 pub(crate) use serde::{Deserialize, Serialize};
-use zeroize::Zeroize;
-
+use zeroize::{Zeroize, Zeroizing};
+
+pub use key_id::{KeyId, KEY_ID_LEN};
+pub use sync_context::SyncSenderContext;
+pub use template::KeyScheduleTemplate;
+pub use validity::Validity;
+
+pub mod application;
+pub mod armor;
+#[cfg(any(feature = "futures-io", feature = "tokio"))]
+pub mod async_stream;
+pub mod audit;
+pub mod backup;
+pub mod bech32;
+#[cfg(feature = "cbor")]
+mod cbor;
+pub mod ciphersuite;
+#[cfg(feature = "cms")]
+pub mod cms;
+#[cfg(feature = "tokio-util")]
+pub mod codec;
 mod dh_kem;
+pub mod drbg;
+#[cfg(feature = "serialization")]
+pub mod encrypted_config;
+pub mod envelope;
+pub mod error_context;
+pub mod exporter;
+pub mod fault;
+#[cfg(feature = "kat-debug")]
+pub mod kat_debug;
 pub(crate) mod kdf;
-mod kem;
+pub mod kem;
+#[cfg(any(feature = "x25519-dalek", feature = "p256"))]
+mod key_conversions;
+pub mod key_id;
+pub mod keystore;
+#[cfg(feature = "legacy-draft")]
+pub mod legacy;
+pub mod limits;
+pub mod managed;
+mod mlock;
+#[cfg(feature = "ohttp")]
+pub mod ohttp;
+#[cfg(feature = "passphrase")]
+pub mod password_psk;
+mod pk_cache;
+pub mod policy;
 pub mod prelude;
+#[cfg(feature = "passphrase")]
+pub mod protected_key;
+#[cfg(feature = "protobuf")]
+pub mod proto;
+mod reorder;
+pub mod rng;
+#[cfg(feature = "rustls")]
+pub mod rustls_provider;
+mod sealed_sender;
+#[cfg(feature = "secrecy")]
+mod secret;
+#[cfg(feature = "secrets-file")]
+pub mod secrets_file;
+pub mod self_test;
+pub mod sender_verifier;
+pub mod session;
+#[cfg(feature = "signcryption")]
+mod signcrypt;
+pub mod stream;
+mod sync_context;
+mod template;
+#[cfg(feature = "tink")]
+pub mod tink;
+pub mod token;
+mod validity;
 
 mod util;
 
 #[cfg(test)]
 mod test_aead;
 #[cfg(test)]
+mod test_dh_kem;
+#[cfg(test)]
 mod test_kdf;
 
 #[deprecated(
@@ -45,7 +111,11 @@ mod test_kdf;
 type HPKEError = HpkeError;
 
 /// HPKE Error types.
+///
+/// New variants may be added in minor releases, so matches on this enum need
+/// a wildcard arm. Use [`HpkeError::kind`] to handle classes of errors.
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum HpkeError {
     /// Error opening an HPKE ciphertext.
     OpenError,
@@ -80,8 +150,135 @@ pub enum HpkeError {
     /// Unable to collect enough randomness.
     InsufficientRandomness,
 
-    /// A concurrency issue with an [`RwLock`].
+    /// A concurrency issue with an [`RwLock`](std::sync::RwLock).
     LockPoisoned,
+
+    /// An I/O error occurred.
+    IoError(String),
+
+    /// A signature is invalid.
+    InvalidSignature,
+
+    /// The context is poisoned after a failed [`Context::open`].
+    ContextPoisoned,
+
+    /// The context is export-only and can't seal or open.
+    ExportOnly,
+
+    /// The algorithm isn't approved in FIPS mode.
+    NotAllowedInFipsMode,
+
+    /// The algorithm isn't allowed by the ciphersuite [`Policy`](policy::Policy).
+    NotAllowedByPolicy,
+
+    /// The encapsulated secret is invalid.
+    DecapError(DecapError),
+
+    /// The plain text or additional data exceeds the limit of the AEAD.
+    PayloadTooLarge,
+
+    /// The message has expired.
+    Expired,
+
+    /// The message is not valid yet.
+    NotYetValid,
+
+    /// The key reached its usage limit in a [`KeyStore`](keystore::KeyStore).
+    KeyUsageLimitReached,
+
+    /// The sender's public key was rejected by a
+    /// [`SenderVerifier`](sender_verifier::SenderVerifier).
+    UntrustedSender,
+
+    /// The sender's public key is not one of the
+    /// [`PinnedSenders`](sender_verifier::PinnedSenders).
+    UnknownSender,
+
+    /// The session is not in the [`SessionManager`](session::SessionManager)
+    /// or expired.
+    UnknownSession,
+
+    /// The input is longer than its [limit](limits::InputLimits).
+    InputTooLong(limits::Input),
+
+    /// Two computations of the same value differ, see
+    /// [`Hpke::with_fault_checks`].
+    FaultDetected,
+}
+
+/// The reason an encapsulated secret was rejected before decapsulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecapError {
+    /// The encapsulated secret doesn't have `Nenc` bytes.
+    InvalidLength,
+
+    /// The encapsulated secret doesn't decode to a valid public value.
+    InvalidPublicKey,
+}
+
+/// The kind of an [`HpkeError`].
+///
+/// Applications can use this to react differently to tampering and to
+/// programming errors, e.g. by dropping forged messages but logging misuse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Authentication failed.
+    /// The ciphertext or signature was forged, tampered with, or created for
+    /// a different key or context.
+    AuthenticationFailure,
+
+    /// Invalid input or configuration, e.g. wrong lengths or an unknown mode.
+    InvalidInput,
+
+    /// The operation isn't possible in the current state of the context,
+    /// e.g. because the message limit is reached.
+    InvalidState,
+
+    /// An error in the crypto provider or the environment, e.g. a lack of
+    /// randomness or an I/O error.
+    Internal,
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl HpkeError {
+    /// Get the [`ErrorKind`] of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            HpkeError::OpenError
+            | HpkeError::InvalidSignature
+            | HpkeError::UntrustedSender
+            | HpkeError::UnknownSender => ErrorKind::AuthenticationFailure,
+            HpkeError::InvalidConfig
+            | HpkeError::InvalidInput
+            | HpkeError::UnknownMode
+            | HpkeError::InconsistentPsk
+            | HpkeError::MissingPsk
+            | HpkeError::UnnecessaryPsk
+            | HpkeError::InsecurePsk
+            | HpkeError::NotAllowedInFipsMode
+            | HpkeError::NotAllowedByPolicy
+            | HpkeError::DecapError(_)
+            | HpkeError::PayloadTooLarge
+            | HpkeError::Expired
+            | HpkeError::NotYetValid
+            | HpkeError::InputTooLong(_) => ErrorKind::InvalidInput,
+            HpkeError::MessageLimitReached
+            | HpkeError::KeyUsageLimitReached
+            | HpkeError::UnknownSession
+            | HpkeError::ContextPoisoned
+            | HpkeError::ExportOnly
+            | HpkeError::LockPoisoned => ErrorKind::InvalidState,
+            HpkeError::CryptoError(_)
+            | HpkeError::InsufficientRandomness
+            | HpkeError::IoError(_)
+            | HpkeError::FaultDetected => ErrorKind::Internal,
+        }
+    }
 }
 
 impl std::error::Error for HpkeError {}
@@ -119,9 +316,36 @@ pub type HPKEPrivateKey = HpkePrivateKey;
 #[derive(Default, Zeroize)]
 #[zeroize(drop)] // XXX: Change to `ZeroizeOnDrop` when moving to 1.5
 #[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
-#[cfg_attr(feature = "hazmat", derive(Clone))]
+#[cfg_attr(feature = "serialization", serde(from = "SerializedPrivateKey"))]
 pub struct HpkePrivateKey {
     value: Vec<u8>,
+    #[zeroize(skip)]
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    lock: mlock::MemoryLock,
+}
+
+/// The serialized form of an [`HpkePrivateKey`].
+/// Deserialized keys go through [`HpkePrivateKey::new`] such that they are
+/// locked in memory.
+#[cfg(feature = "serialization")]
+#[derive(Deserialize)]
+#[serde(rename = "HpkePrivateKey")]
+struct SerializedPrivateKey {
+    value: Vec<u8>,
+}
+
+#[cfg(feature = "serialization")]
+impl From<SerializedPrivateKey> for HpkePrivateKey {
+    fn from(key: SerializedPrivateKey) -> Self {
+        Self::new(key.value)
+    }
+}
+
+#[cfg(feature = "hazmat")]
+impl Clone for HpkePrivateKey {
+    fn clone(&self) -> Self {
+        Self::new(self.value.clone())
+    }
 }
 
 #[deprecated(
@@ -165,6 +389,29 @@ impl std::fmt::Display for Mode {
     }
 }
 
+/// The HPKE version, i.e. the labels and key schedule, to use.
+///
+/// [`HpkeVersion::Rfc9180`] is the default.
+/// Older versions are only available with the `legacy-draft` feature.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum HpkeVersion {
+    /// draft-irtf-cfrg-hpke-05 (see the [`legacy`] module).
+    #[cfg(feature = "legacy-draft")]
+    Draft05,
+
+    /// RFC 9180.
+    #[default]
+    Rfc9180,
+}
+
+impl std::fmt::Display for HpkeVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
 impl TryFrom<u8> for Mode {
     type Error = HpkeError;
     fn try_from(x: u8) -> Result<Mode, HpkeError> {
@@ -190,15 +437,73 @@ type Ciphertext = Vec<u8>;
 /// A byte vector.
 type Plaintext = Vec<u8>;
 
+/// The largest AEAD key (`Nk`) of any supported AEAD.
+const MAX_AEAD_KEY_LEN: usize = 32;
+
+/// The largest AEAD nonce (`Nn`) of any supported AEAD.
+const MAX_AEAD_NONCE_LEN: usize = 12;
+
+/// The largest KDF output (`Nh`) of any supported KDF.
+const MAX_DIGEST_LEN: usize = 64;
+
+/// An AEAD key stored inline.
+type Key = util::FixedBytes<MAX_AEAD_KEY_LEN>;
+
+/// An AEAD nonce stored inline.
+type Nonce = util::FixedBytes<MAX_AEAD_NONCE_LEN>;
+
+/// An exporter secret stored inline.
+type ExporterSecret = util::FixedBytes<MAX_DIGEST_LEN>;
+
+/// The secrets of a context.
+///
+/// They are boxed such that their address is stable and can be locked in
+/// memory.
+struct ContextSecrets {
+    key: Key,
+    nonce: Nonce,
+    exporter_secret: ExporterSecret,
+    lock: mlock::MemoryLock,
+}
+
+impl ContextSecrets {
+    fn new(key: Key, nonce: Nonce, exporter_secret: ExporterSecret) -> Box<Self> {
+        let mut secrets = Box::new(Self {
+            key,
+            nonce,
+            exporter_secret,
+            lock: mlock::MemoryLock::default(),
+        });
+        secrets.lock = mlock::MemoryLock::value(secrets.as_ref());
+        secrets
+    }
+}
+
+/// The default maximum number of epochs that
+/// [`Context::advance_epoch_to`] advances at once.
+pub const DEFAULT_MAX_EPOCH_JUMP: u16 = 16;
+
 /// The HPKE context.
 /// Note that the RFC currently doesn't define this.
 /// Also see <https://github.com/cfrg/draft-irtf-cfrg-hpke/issues/161>.
 pub struct Context<Crypto: 'static + HpkeCrypto> {
-    key: Vec<u8>,
-    nonce: Vec<u8>,
-    exporter_secret: Vec<u8>,
+    secrets: Box<ContextSecrets>,
+    /// The AEAD cipher initialized with the key (`None` for export-only).
+    cipher: Option<Crypto::AeadCipher>,
     sequence_number: u32,
-    hpke: Hpke<Crypto>,
+    epoch: u16,
+    max_epoch_jump: u16,
+    ratchet_interval: Option<u32>,
+    poison_on_failure: bool,
+    poisoned: bool,
+    reorder: Option<Box<reorder::ReorderWindow>>,
+    /// Scratch buffer to assemble multi-part associated data.
+    aad_buffer: Vec<u8>,
+    config: HpkeConfig,
+    auditor: Option<audit::Auditor>,
+    error_context: Option<error_context::Recorder>,
+    limits: limits::InputLimits,
+    fault_checks: bool,
 }
 
 #[cfg(feature = "hazmat")]
@@ -207,7 +512,10 @@ impl<Crypto: HpkeCrypto> std::fmt::Debug for Context<Crypto> {
         write!(
             f,
             "Context {{\n  key: {:?}\n  nonce: {:?}\n exporter_secret: {:?}\n seq no: {:?}\n}}",
-            self.key, self.nonce, self.exporter_secret, self.sequence_number
+            self.secrets.key,
+            self.secrets.nonce,
+            self.secrets.exporter_secret,
+            self.sequence_number
         )
     }
 }
@@ -235,16 +543,27 @@ impl<Crypto: HpkeCrypto> Context<Crypto> {
     ///   self.IncrementSeq()
     ///   return ct
     /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            err(level = "debug"),
+            fields(seq = self.sequence_number, len = plain_txt.len())
+        )
+    )]
     pub fn seal(&mut self, aad: &[u8], plain_txt: &[u8]) -> Result<Ciphertext, HpkeError> {
-        let ctxt = Crypto::aead_seal(
-            self.hpke.aead_id,
-            &self.key,
-            &self.compute_nonce(),
-            aad,
-            plain_txt,
-        )?;
-        self.increment_seq()?;
-        Ok(ctxt)
+        self.audited(audit::AuditOperation::Seal, |ctx| {
+            ctx.check_poisoned()?;
+            ctx.check_payload(aad.len(), plain_txt.len())?;
+            ctx.auto_ratchet()?;
+            let nonce = ctx.current_nonce()?;
+            let ctxt = Crypto::aead_cipher_seal(ctx.cipher()?, &nonce, aad, plain_txt)?;
+            ctx.verify_seal(&nonce, aad, &ctxt, plain_txt)?;
+            ctx.increment_seq()?;
+            ctx.ratchet_after_message();
+            Ok(ctxt)
+        })
     }
 
     /// 5.2. Encryption and Decryption
@@ -260,120 +579,1115 @@ impl<Crypto: HpkeCrypto> Context<Crypto> {
     ///   self.IncrementSeq()
     ///   return pt
     /// ```
+    ///
+    /// A failed open leaves the context untouched (unless
+    /// [poisoning](Self::set_poison_on_failure) is enabled) such that the
+    /// next ciphertexts can still be opened.
+    /// This is important on lossy transports where bogus packets arrive
+    /// routinely.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            err(level = "debug"),
+            fields(seq = self.sequence_number, len = cipher_txt.len())
+        )
+    )]
     pub fn open(&mut self, aad: &[u8], cipher_txt: &[u8]) -> Result<Plaintext, HpkeError> {
-        let ptxt = Crypto::aead_open(
-            self.hpke.aead_id,
-            &self.key,
-            &self.compute_nonce(),
-            aad,
-            cipher_txt,
-        )?;
-        self.increment_seq()?;
-        Ok(ptxt)
+        self.audited(audit::AuditOperation::Open, |ctx| {
+            ctx.check_poisoned()?;
+            ctx.check_payload(aad.len(), ctx.plain_txt_len(cipher_txt.len()))?;
+            ctx.auto_ratchet()?;
+            let next = ctx.next_seq()?;
+            let ptxt =
+                Crypto::aead_cipher_open(ctx.cipher()?, &ctx.current_nonce()?, aad, cipher_txt)
+                    .map_err(|e| ctx.poison(e))?;
+            ctx.sequence_number = next;
+            ctx.ratchet_after_message();
+            Ok(ptxt)
+        })
+    }
+
+    /// Encrypt the plain text in `buffer` in place.
+    ///
+    /// On success `buffer` holds the ciphertext.
+    /// Reusing the same buffer for many messages avoids allocations when the
+    /// crypto provider supports in-place encryption.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            err(level = "debug"),
+            fields(seq = self.sequence_number, len = buffer.len())
+        )
+    )]
+    pub fn seal_in_place(&mut self, aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), HpkeError> {
+        self.audited(audit::AuditOperation::Seal, |ctx| {
+            ctx.check_poisoned()?;
+            ctx.check_payload(aad.len(), buffer.len())?;
+            ctx.auto_ratchet()?;
+            let nonce = ctx.current_nonce()?;
+            let plain_txt = ctx.fault_checks.then(|| Zeroizing::new(buffer.clone()));
+            Crypto::aead_cipher_seal_in_place(ctx.cipher()?, &nonce, aad, buffer)?;
+            if let Some(plain_txt) = plain_txt {
+                if let Err(e) = ctx.verify_seal(&nonce, aad, buffer, &plain_txt) {
+                    buffer.zeroize();
+                    return Err(e);
+                }
+            }
+            ctx.increment_seq()?;
+            ctx.ratchet_after_message();
+            Ok(())
+        })
+    }
+
+    /// Decrypt the ciphertext in `buffer` in place.
+    ///
+    /// On success `buffer` holds the plain text.
+    /// On error `buffer` is zeroized and cleared such that no unauthenticated
+    /// plain text is released.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            err(level = "debug"),
+            fields(seq = self.sequence_number, len = buffer.len())
+        )
+    )]
+    pub fn open_in_place(&mut self, aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), HpkeError> {
+        self.audited(audit::AuditOperation::Open, |ctx| {
+            ctx.check_poisoned()?;
+            ctx.check_payload(aad.len(), ctx.plain_txt_len(buffer.len()))?;
+            ctx.auto_ratchet()?;
+            let next = ctx.next_seq()?;
+            let nonce = ctx.current_nonce()?;
+            let cipher = ctx.cipher()?;
+            if let Err(e) = Crypto::aead_cipher_open_in_place(cipher, &nonce, aad, buffer) {
+                buffer.zeroize();
+                return Err(ctx.poison(e));
+            }
+            ctx.sequence_number = next;
+            ctx.ratchet_after_message();
+            Ok(())
+        })
+    }
+
+    /// Encrypt `plain_txt` into the scratch buffer `out`.
+    ///
+    /// The previous content of `out` is replaced with the ciphertext.
+    /// Servers that seal many messages can keep one buffer per context such
+    /// that its capacity is reused.
+    pub fn seal_into(
+        &mut self,
+        aad: &[u8],
+        plain_txt: &[u8],
+        out: &mut Vec<u8>,
+    ) -> Result<(), HpkeError> {
+        out.clear();
+        out.extend_from_slice(plain_txt);
+        self.seal_in_place(aad, out)
+    }
+
+    /// Decrypt `cipher_txt` into the scratch buffer `out`.
+    ///
+    /// The previous content of `out` is replaced with the plain text.
+    /// On error `out` is zeroized and cleared.
+    pub fn open_into(
+        &mut self,
+        aad: &[u8],
+        cipher_txt: &[u8],
+        out: &mut Vec<u8>,
+    ) -> Result<(), HpkeError> {
+        out.clear();
+        out.extend_from_slice(cipher_txt);
+        self.open_in_place(aad, out)
+    }
+
+    /// [Seal](Self::seal) `plain_txt` with the associated data made of the
+    /// `aad` parts in order.
+    ///
+    /// This is the same as sealing with the concatenated parts.
+    /// The parts are assembled in a buffer of the context that is reused for
+    /// every message such that callers don't have to concatenate headers and
+    /// metadata themselves.
+    pub fn seal_with_aad_parts(
+        &mut self,
+        aad: &[&[u8]],
+        plain_txt: &[u8],
+    ) -> Result<Ciphertext, HpkeError> {
+        let aad_buffer = self.take_aad_buffer(aad);
+        let ctxt = self.seal(&aad_buffer, plain_txt);
+        self.aad_buffer = aad_buffer;
+        ctxt
+    }
+
+    /// [Open](Self::open) `cipher_txt` with the associated data made of the
+    /// `aad` parts in order.
+    ///
+    /// See [`seal_with_aad_parts`](Self::seal_with_aad_parts).
+    pub fn open_with_aad_parts(
+        &mut self,
+        aad: &[&[u8]],
+        cipher_txt: &[u8],
+    ) -> Result<Plaintext, HpkeError> {
+        let aad_buffer = self.take_aad_buffer(aad);
+        let ptxt = self.open(&aad_buffer, cipher_txt);
+        self.aad_buffer = aad_buffer;
+        ptxt
+    }
+
+    /// 5.3. Secret Export
+    ///
+    /// Takes a serialised exporter context as byte slice and a length for the
+    /// output secret and returns an exporter secret as byte vector.
+    ///
+    /// ```text
+    /// def Context.Export(exporter_context, L):
+    ///  return LabeledExpand(self.exporter_secret, "sec", exporter_context, L)
+    ///```
+    pub fn export(&self, exporter_context: &[u8], length: usize) -> Result<Vec<u8>, HpkeError> {
+        let result = self.check_export(exporter_context).and_then(|()| {
+            self.config
+                .export::<Crypto>(&self.secrets.exporter_secret, exporter_context, length)
+        });
+        let result = error_context::add(
+            &self.error_context,
+            error_context::Stage::Export,
+            self.config,
+            result,
+        );
+        audit::report(
+            &self.auditor,
+            audit::AuditOperation::Export,
+            self.config,
+            None,
+            &result,
+        );
+        result
+    }
+
+    /// [`export`](Self::export) a secret with the statically known length
+    /// `N`, e.g. a 32 byte key.
+    ///
+    /// ```text
+    /// let key: [u8; 32] = context.export_array(b"key")?;
+    /// ```
+    pub fn export_array<const N: usize>(
+        &self,
+        exporter_context: &[u8],
+    ) -> Result<[u8; N], HpkeError> {
+        let result = self.check_export(exporter_context).and_then(|()| {
+            self.config
+                .export_array::<Crypto, N>(&self.secrets.exporter_secret, exporter_context)
+        });
+        let result = error_context::add(
+            &self.error_context,
+            error_context::Stage::Export,
+            self.config,
+            result,
+        );
+        audit::report(
+            &self.auditor,
+            audit::AuditOperation::Export,
+            self.config,
+            None,
+            &result,
+        );
+        result
+    }
+
+    /// Check the additional data and plain text lengths against the input
+    /// limits and the limits of the AEAD.
+    fn check_payload(&self, aad_len: usize, plain_txt_len: usize) -> Result<(), HpkeError> {
+        self.limits.check(limits::Input::Aad, aad_len)?;
+        check_payload(self.config.aead_id, aad_len, plain_txt_len)
+    }
+
+    /// Check that this context can export with the `exporter_context`.
+    fn check_export(&self, exporter_context: &[u8]) -> Result<(), HpkeError> {
+        self.check_poisoned()?;
+        self.limits
+            .check(limits::Input::ExporterContext, exporter_context.len())
+    }
+
+    /// Run `operation` on this context and report its result with the
+    /// current sequence number.
+    #[inline]
+    fn audited<T>(
+        &mut self,
+        operation: audit::AuditOperation,
+        f: impl FnOnce(&mut Self) -> Result<T, HpkeError>,
+    ) -> Result<T, HpkeError> {
+        self.audited_at(self.sequence_number, operation, f)
+    }
+
+    /// Run `operation` on this context and report its result with the
+    /// `sequence_number`.
+    #[inline]
+    fn audited_at<T>(
+        &mut self,
+        sequence_number: u32,
+        operation: audit::AuditOperation,
+        f: impl FnOnce(&mut Self) -> Result<T, HpkeError>,
+    ) -> Result<T, HpkeError> {
+        let stage = match operation {
+            audit::AuditOperation::Open => error_context::Stage::Open,
+            _ => error_context::Stage::Seal,
+        };
+        let result = f(self);
+        let result = error_context::add(&self.error_context, stage, self.config, result);
+        audit::report(
+            &self.auditor,
+            operation,
+            self.config,
+            Some(sequence_number),
+            &result,
+        );
+        result
+    }
+
+    /// Ratchet the context forward.
+    ///
+    /// This derives a fresh key, base nonce, and exporter secret from the
+    /// current exporter secret and resets the sequence number.
+    /// The previous secrets are erased such that a compromise of the context
+    /// doesn't reveal messages protected before the ratchet.
+    ///
+    /// Sender and receiver have to ratchet at the same point in the message
+    /// stream.
+    /// Note that exports after a ratchet are derived from the new exporter
+    /// secret.
+    ///
+    /// ```text
+    /// key = LabeledExpand(exporter_secret, "ratchet_key", "", Nk)
+    /// base_nonce = LabeledExpand(exporter_secret, "ratchet_nonce", "", Nn)
+    /// exporter_secret = LabeledExpand(exporter_secret, "ratchet_exp", "", Nh)
+    /// ```
+    pub fn ratchet(&mut self) -> Result<(), HpkeError> {
+        self.check_poisoned()?;
+        let suite_id = self.config.suite_id();
+        let key = Key::from_vec(labeled_expand::<Crypto>(
+            self.config.kdf_id,
+            &self.secrets.exporter_secret,
+            &suite_id,
+            "ratchet_key",
+            &[],
+            Crypto::aead_key_length(self.config.aead_id),
+        )?)?;
+        let nonce = Nonce::from_vec(labeled_expand::<Crypto>(
+            self.config.kdf_id,
+            &self.secrets.exporter_secret,
+            &suite_id,
+            "ratchet_nonce",
+            &[],
+            Crypto::aead_nonce_length(self.config.aead_id),
+        )?)?;
+        let exporter_secret = ExporterSecret::from_vec(labeled_expand::<Crypto>(
+            self.config.kdf_id,
+            &self.secrets.exporter_secret,
+            &suite_id,
+            "ratchet_exp",
+            &[],
+            Crypto::kdf_digest_length(self.config.kdf_id),
+        )?)?;
+
+        // The previous secrets are zeroized when they are dropped here.
+        self.cipher = init_cipher::<Crypto>(self.config.aead_id, &key)?;
+        self.secrets.key = key;
+        self.secrets.nonce = nonce;
+        self.secrets.exporter_secret = exporter_secret;
+        self.sequence_number = 0;
+        Ok(())
+    }
+
+    /// The current epoch of this context.
+    ///
+    /// Contexts start in epoch `0`.
+    pub fn epoch(&self) -> u16 {
+        self.epoch
+    }
+
+    /// Rekey this context and move it to the next epoch.
+    ///
+    /// This [ratchets](Self::ratchet) the context and increments the epoch,
+    /// which is encoded in the nonce next to the sequence number (like the
+    /// epoch in DTLS 1.3):
+    ///
+    /// ```text
+    /// nonce = xor(base_nonce, I2OSP(epoch, 2) || I2OSP(seq, 4))
+    /// ```
+    ///
+    /// Long-lived connections advance the epoch to bound the number of
+    /// messages per key.
+    /// Both sides have to advance to the same epoch, see
+    /// [`advance_epoch_to`](Self::advance_epoch_to) for receivers.
+    /// Returns [`HpkeError::MessageLimitReached`] if all epochs are used up.
+    pub fn advance_epoch(&mut self) -> Result<(), HpkeError> {
+        self.check_poisoned()?;
+        let epoch = self
+            .epoch
+            .checked_add(1)
+            .ok_or(HpkeError::MessageLimitReached)?;
+        self.ratchet()?;
+        self.epoch = epoch;
+        Ok(())
+    }
+
+    /// Advance this context to `epoch`, e.g. when a receiver sees a message
+    /// from a later epoch.
+    ///
+    /// The epoch can't go backwards: an `epoch` below the current one returns
+    /// [`HpkeError::InvalidInput`], and so does an `epoch` more than the
+    /// [maximum jump](Self::set_max_epoch_jump) ahead.
+    /// Advancing to the current epoch doesn't change the context.
+    pub fn advance_epoch_to(&mut self, epoch: u16) -> Result<(), HpkeError> {
+        if epoch < self.epoch || epoch - self.epoch > self.max_epoch_jump {
+            return Err(HpkeError::InvalidInput);
+        }
+        while self.epoch < epoch {
+            self.advance_epoch()?;
+        }
+        Ok(())
+    }
+
+    /// Limit [`advance_epoch_to`](Self::advance_epoch_to) to advance at most
+    /// `max` epochs at once.
+    ///
+    /// Every epoch costs a [ratchet](Self::ratchet), so this bounds the work
+    /// a receiver does for the epoch of a single (possibly forged) message.
+    /// Defaults to [`DEFAULT_MAX_EPOCH_JUMP`].
+    pub fn set_max_epoch_jump(&mut self, max: u16) {
+        self.max_epoch_jump = max;
+    }
+
+    /// Automatically [`ratchet`](Self::ratchet) this context after every
+    /// `interval` messages.
+    ///
+    /// Set to `None` (the default) to disable automatic ratcheting.
+    /// Sender and receiver have to use the same interval.
+    /// An interval of `0` is invalid, and so is ratcheting with a
+    /// [reorder window](Self::set_reorder_window).
+    /// A message that completes an interval is returned even if the ratchet
+    /// after it fails; the ratchet is then retried before the next message.
+    pub fn set_ratchet_interval(&mut self, interval: Option<u32>) -> Result<(), HpkeError> {
+        if interval == Some(0) || (interval.is_some() && self.reorder.is_some()) {
+            return Err(HpkeError::InvalidConfig);
+        }
+        self.ratchet_interval = interval;
+        Ok(())
+    }
+
+    /// The sequence number of the next message this context seals or opens.
+    pub fn sequence(&self) -> u32 {
+        self.sequence_number
+    }
+
+    /// Move the sequence number forward to `seq`.
+    ///
+    /// A sender that resumes after a crash skips the sequence numbers it may
+    /// have used, and a receiver skips the messages it knows are lost.
+    /// The sequence number can't go backwards such that nonces are never
+    /// reused: `seq` below the current sequence number returns
+    /// [`HpkeError::InvalidInput`], and so does skipping past the next
+    /// automatic [ratchet](Self::set_ratchet_interval).
+    /// Returns [`HpkeError::MessageLimitReached`] if `seq` exceeds the message
+    /// limit of the AEAD.
+    pub fn skip_to(&mut self, seq: u32) -> Result<(), HpkeError> {
+        self.check_poisoned()?;
+        if seq < self.sequence_number {
+            return Err(HpkeError::InvalidInput);
+        }
+        if matches!(self.ratchet_interval, Some(interval) if seq > interval) {
+            return Err(HpkeError::InvalidInput);
+        }
+        // The last sequence number is `seq - 1`, which must be valid.
+        if seq > 0 {
+            next_seq::<Crypto>(self.config.aead_id, seq - 1)?;
+        }
+        self.sequence_number = seq;
+        self.auto_ratchet()
+    }
+
+    /// Poison this context when [`open`](Self::open) fails.
+    ///
+    /// A poisoned context refuses to seal, open, export, or ratchet and
+    /// returns [`HpkeError::ContextPoisoned`] until
+    /// [`reset_poison`](Self::reset_poison) is called.
+    /// This protects long-lived contexts from attackers that probe them with
+    /// forged ciphertexts.
+    ///
+    /// This is disabled by default.
+    /// Independent of this setting, a failed open never advances the context.
+    pub fn set_poison_on_failure(&mut self, poison: bool) {
+        self.poison_on_failure = poison;
+    }
+
+    /// Returns the [`HpkeConfig`] this context was set up with.
+    pub fn config(&self) -> HpkeConfig {
+        self.config
+    }
+
+    /// The number of bytes that sealing adds to a message (`Nt`).
+    pub fn overhead(&self) -> usize {
+        self.config.aead_id.tag_length()
+    }
+
+    /// The length of the ciphertext of a plain text of `pt_len` bytes, see
+    /// [`HpkeConfig::ct_len`].
+    pub fn ct_len(&self, pt_len: usize) -> Result<usize, HpkeError> {
+        self.config.ct_len(pt_len)
+    }
+
+    /// The length of the plain text of a ciphertext of `ct_len` bytes, see
+    /// [`HpkeConfig::pt_len`].
+    pub fn pt_len(&self, ct_len: usize) -> Result<usize, HpkeError> {
+        self.config.pt_len(ct_len)
+    }
+
+    /// Returns `true` if this context is poisoned.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Make a poisoned context usable again.
+    pub fn reset_poison(&mut self) {
+        self.poisoned = false;
+    }
+
+    #[inline]
+    fn check_poisoned(&self) -> Result<(), HpkeError> {
+        if self.poisoned {
+            return Err(HpkeError::ContextPoisoned);
+        }
+        Ok(())
+    }
+
+    /// Poison the context (if enabled) after the failed open with `error`.
+    #[inline]
+    fn poison(&mut self, error: hpke_rs_crypto::error::Error) -> HpkeError {
+        self.poisoned = self.poison_on_failure;
+        error.into()
+    }
+
+    /// Ratchet if the [ratchet interval](Self::set_ratchet_interval) is
+    /// reached.
+    ///
+    /// This runs before every message such that a ratchet that failed after
+    /// the previous message is retried before the next nonce is used.
+    #[inline]
+    fn auto_ratchet(&mut self) -> Result<(), HpkeError> {
+        match self.ratchet_interval {
+            Some(interval) if self.sequence_number >= interval => self.ratchet(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Ratchet right after a message if the interval is reached.
+    ///
+    /// The message was already sealed or opened, so an error here must not
+    /// drop it: the ratchet is retried by [`auto_ratchet`](Self::auto_ratchet)
+    /// before the next message instead.
+    #[inline]
+    fn ratchet_after_message(&mut self) {
+        let _ = self.auto_ratchet();
+    }
+
+    /// Take the scratch buffer out of the context, filled with the `parts`.
+    fn take_aad_buffer(&mut self, parts: &[&[u8]]) -> Vec<u8> {
+        let mut aad_buffer = std::mem::take(&mut self.aad_buffer);
+        aad_buffer.clear();
+        for part in parts {
+            aad_buffer.extend_from_slice(part);
+        }
+        aad_buffer
+    }
+
+    #[inline]
+    fn cipher(&self) -> Result<&Crypto::AeadCipher, HpkeError> {
+        self.cipher.as_ref().ok_or(HpkeError::ExportOnly)
+    }
+
+    /// The nonce for the current sequence number, computed twice with fault
+    /// checks.
+    fn current_nonce(&self) -> Result<Nonce, HpkeError> {
+        self.nonce_at(self.sequence_number)
+    }
+
+    /// The nonce for the sequence number `seq`, computed twice with fault
+    /// checks.
+    ///
+    /// The inputs go through [`black_box`] such that
+    /// both computations load them again.
+    fn nonce_at(&self, seq: u32) -> Result<Nonce, HpkeError> {
+        fault::checked(
+            self.fault_checks,
+            || {
+                Ok::<_, HpkeError>(compute_nonce(
+                    black_box(&self.secrets.nonce),
+                    black_box(self.epoch),
+                    black_box(seq),
+                ))
+            },
+            |a: &Nonce, b: &Nonce| util::ct_eq(a, b),
+        )
+    }
+
+    /// Open the just sealed `cipher_txt` again and compare it with the
+    /// `plain_txt` if fault checks are enabled.
+    fn verify_seal(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        cipher_txt: &[u8],
+        plain_txt: &[u8],
+    ) -> Result<(), HpkeError> {
+        if self.fault_checks {
+            verify_seal::<Crypto>(self.cipher()?, nonce, aad, cipher_txt, plain_txt)?;
+        }
+        Ok(())
+    }
+
+    fn increment_seq(&mut self) -> Result<(), HpkeError> {
+        self.sequence_number = self.next_seq()?;
+        Ok(())
+    }
+
+    /// The length of the plain text in a ciphertext of `len` bytes.
+    #[inline]
+    fn plain_txt_len(&self, len: usize) -> usize {
+        len.saturating_sub(self.config.aead_id.tag_length())
+    }
+
+    #[inline]
+    fn next_seq(&self) -> Result<u32, HpkeError> {
+        next_seq::<Crypto>(self.config.aead_id, self.sequence_number)
+    }
+}
+
+/// def Context<ROLE>.ComputeNonce(seq):
+///     seq_bytes = I2OSP(seq, Nn)
+///     return xor(self.base_nonce, seq_bytes)
+///
+/// The epoch is encoded in the two bytes before the sequence number, such
+/// that the nonce in epoch `0` is the one of RFC 9180.
+fn compute_nonce(base_nonce: &Nonce, epoch: u16, seq: u32) -> Nonce {
+    let mut nonce = base_nonce.clone();
+    let mut seq_bytes = [0u8; 6];
+    seq_bytes[..2].copy_from_slice(&epoch.to_be_bytes());
+    seq_bytes[2..].copy_from_slice(&seq.to_be_bytes());
+    for (n, s) in nonce.iter_mut().rev().zip(seq_bytes.iter().rev()) {
+        *n ^= s;
+    }
+    nonce
+}
+
+/// def Context<ROLE>.IncrementSeq():
+///     if self.seq >= (1 << (8*Nn)) - 1:
+///       raise MessageLimitReached
+///     self.seq += 1
+///
+/// Returns the sequence number following `seq`.
+/// The sequence number is stored in a `u32` such that the limit is reached
+/// at `u32::MAX` for all nonce lengths in use.
+fn next_seq<Crypto: HpkeCrypto>(aead_id: AeadAlgorithm, seq: u32) -> Result<u32, HpkeError> {
+    if u128::from(seq) >= ((1u128 << (8 * Crypto::aead_nonce_length(aead_id))) - 1) {
+        return Err(HpkeError::MessageLimitReached);
+    }
+    seq.checked_add(1).ok_or(HpkeError::MessageLimitReached)
+}
+
+/// Open the `cipher_txt` sealed with the `nonce` and `aad` and check that it
+/// is the `plain_txt`.
+///
+/// Returns [`HpkeError::FaultDetected`] otherwise.
+fn verify_seal<Crypto: HpkeCrypto>(
+    cipher: &Crypto::AeadCipher,
+    nonce: &[u8],
+    aad: &[u8],
+    cipher_txt: &[u8],
+    plain_txt: &[u8],
+) -> Result<(), HpkeError> {
+    let opened = Zeroizing::new(
+        Crypto::aead_cipher_open(cipher, nonce, aad, cipher_txt)
+            .map_err(|_| HpkeError::FaultDetected)?,
+    );
+    if !util::ct_eq(&opened, plain_txt) {
+        return Err(HpkeError::FaultDetected);
+    }
+    Ok(())
+}
+
+/// Check the lengths of the additional data and the plain text against the
+/// limits of the AEAD.
+///
+/// Returns [`HpkeError::PayloadTooLarge`] if one of them is too long.
+/// Export-only contexts are rejected when they try to use the cipher.
+pub(crate) fn check_payload(
+    aead_id: AeadAlgorithm,
+    aad_len: usize,
+    plain_txt_len: usize,
+) -> Result<(), HpkeError> {
+    if aead_id == AeadAlgorithm::HpkeExport {
+        return Ok(());
+    }
+    let too_large = |len: usize, max: u64| u64::try_from(len).map_or(true, |len| len > max);
+    if too_large(plain_txt_len, aead_id.max_plaintext_length())
+        || too_large(aad_len, aead_id.max_aad_length())
+    {
+        return Err(HpkeError::PayloadTooLarge);
+    }
+    Ok(())
+}
+
+/// Initialize the AEAD cipher for a context.
+/// Export-only contexts don't have a cipher.
+fn init_cipher<Crypto: HpkeCrypto>(
+    aead_id: AeadAlgorithm,
+    key: &[u8],
+) -> Result<Option<Crypto::AeadCipher>, HpkeError> {
+    match aead_id {
+        AeadAlgorithm::HpkeExport => Ok(None),
+        _ => Ok(Some(Crypto::aead_init(aead_id, key)?)),
+    }
+}
+
+/// The HPKE configuration struct.
+/// This holds the configuration for HPKE but no state.
+/// To use HPKE first instantiate the configuration with
+/// `let hpke = Hpke::new(mode, kem_mode, kdf_mode, aead_mode)`.
+/// Now one can use the `hpke` configuration.
+///
+/// All operations take `&self` and `Hpke` is `Send + Sync` if the PRNG of
+/// the crypto provider is, so a single configuration can be shared between
+/// threads (e.g. in an `Arc`) to set up contexts with many different
+/// recipients concurrently.
+/// The PRNG is only locked while drawing randomness.
+///
+/// Cloning is cheap: the configuration is `Copy` and everything else is
+/// shared behind an `Arc`.
+/// Note that cloning does NOT clone the PRNG state.
+/// A clone creates its own PRNG of the crypto provider when it first needs
+/// randomness, and an RNG injected with [`with_rng`](Hpke::with_rng) is
+/// shared by the clones.
+#[derive(Debug)]
+pub struct Hpke<Crypto: 'static + HpkeCrypto> {
+    mode: Mode,
+    kem_id: KemAlgorithm,
+    kdf_id: KdfAlgorithm,
+    aead_id: AeadAlgorithm,
+    prng: rng::Prng<Crypto>,
+    pk_cache: Option<Arc<pk_cache::PublicKeyCache<Crypto>>>,
+    fips: bool,
+    policy: Option<Arc<policy::Policy>>,
+    version: HpkeVersion,
+    auditor: Option<audit::Auditor>,
+    suite_binding: Option<Arc<[u8]>>,
+    application: Option<Arc<str>>,
+    sender_verifier: Option<sender_verifier::Verifier>,
+    error_context: Option<error_context::Recorder>,
+    limits: limits::InputLimits,
+    fault_checks: bool,
+    #[cfg(feature = "kat-debug")]
+    intermediate_callback: Option<kat_debug::Callback>,
+}
+
+impl<Crypto: 'static + HpkeCrypto> Clone for Hpke<Crypto> {
+    fn clone(&self) -> Self {
+        Self {
+            mode: self.mode,
+            kem_id: self.kem_id,
+            kdf_id: self.kdf_id,
+            aead_id: self.aead_id,
+            prng: self.prng.clone(),
+            pk_cache: self.pk_cache.clone(),
+            fips: self.fips,
+            policy: self.policy.clone(),
+            version: self.version,
+            auditor: self.auditor.clone(),
+            suite_binding: self.suite_binding.clone(),
+            application: self.application.clone(),
+            sender_verifier: self.sender_verifier.clone(),
+            error_context: self.error_context.clone(),
+            limits: self.limits,
+            fault_checks: self.fault_checks,
+            #[cfg(feature = "kat-debug")]
+            intermediate_callback: self.intermediate_callback.clone(),
+        }
+    }
+}
+
+impl<Crypto: HpkeCrypto> std::fmt::Display for Hpke<Crypto> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.config().fmt(f)
+    }
+}
+
+/// The HPKE suite of an [`Hpke`] configuration.
+///
+/// This is the mode, the algorithms, and the version, without any state.
+/// It is `Copy` and is kept by the [`Context`]s an [`Hpke`] sets up instead
+/// of a copy of the [`Hpke`] with its PRNG.
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct HpkeConfig {
+    /// The HPKE mode.
+    pub mode: Mode,
+    /// The KEM.
+    pub kem_id: KemAlgorithm,
+    /// The KDF.
+    pub kdf_id: KdfAlgorithm,
+    /// The AEAD.
+    pub aead_id: AeadAlgorithm,
+    /// The HPKE version.
+    pub version: HpkeVersion,
+}
+
+impl std::fmt::Display for HpkeConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}_{}_{}_{}",
+            self.mode.to_string().to_lowercase(),
+            self.kem_id.to_string().to_lowercase(),
+            self.kdf_id.to_string().to_lowercase(),
+            self.aead_id.to_string().to_lowercase()
+        )
+    }
+}
+
+impl HpkeConfig {
+    /// The [`Ciphersuite`](ciphersuite::Ciphersuite) of this configuration.
+    pub fn ciphersuite(&self) -> ciphersuite::Ciphersuite {
+        ciphersuite::Ciphersuite::new(self.kem_id, self.kdf_id, self.aead_id)
+    }
+
+    /// The number of bytes that single-shot sealing adds to a message: the
+    /// encapsulated secret and the authentication tag (`Nenc + Nt`).
+    ///
+    /// Messages sealed with a [`Context`] only carry the tag, see
+    /// [`Context::overhead`].
+    pub fn overhead(&self) -> usize {
+        self.kem_id.enc_len() + self.aead_id.tag_length()
+    }
+
+    /// The length of the ciphertext of a plain text of `pt_len` bytes
+    /// (`pt_len + Nt`), not including the encapsulated secret.
+    ///
+    /// Returns [`HpkeError::ExportOnly`] for export-only configurations and
+    /// [`HpkeError::PayloadTooLarge`] if the AEAD can't seal plain texts of
+    /// this length.
+    pub fn ct_len(&self, pt_len: usize) -> Result<usize, HpkeError> {
+        if self.aead_id == AeadAlgorithm::HpkeExport {
+            return Err(HpkeError::ExportOnly);
+        }
+        check_payload(self.aead_id, 0, pt_len)?;
+        pt_len
+            .checked_add(self.aead_id.tag_length())
+            .ok_or(HpkeError::PayloadTooLarge)
+    }
+
+    /// The length of the plain text of a ciphertext of `ct_len` bytes
+    /// (`ct_len - Nt`).
+    ///
+    /// This allows rejecting ciphertexts of the wrong length and allocating
+    /// the plain text before opening them.
+    /// Returns [`HpkeError::ExportOnly`] for export-only configurations and
+    /// [`HpkeError::InvalidInput`] if the ciphertext is shorter than a tag.
+    pub fn pt_len(&self, ct_len: usize) -> Result<usize, HpkeError> {
+        if self.aead_id == AeadAlgorithm::HpkeExport {
+            return Err(HpkeError::ExportOnly);
+        }
+        ct_len
+            .checked_sub(self.aead_id.tag_length())
+            .ok_or(HpkeError::InvalidInput)
+    }
+
+    #[inline]
+    fn suite_id(&self) -> [u8; 10] {
+        self.ciphersuite().suite_id()
+    }
+
+    /// Export a secret of `length` bytes from the `exporter_secret` of a
+    /// context.
+    fn export<Crypto: HpkeCrypto>(
+        &self,
+        exporter_secret: &[u8],
+        exporter_context: &[u8],
+        length: usize,
+    ) -> Result<Vec<u8>, HpkeError> {
+        match self.version {
+            HpkeVersion::Rfc9180 => labeled_expand::<Crypto>(
+                self.kdf_id,
+                exporter_secret,
+                &self.suite_id(),
+                "sec",
+                exporter_context,
+                length,
+            )
+            .map_err(HpkeError::from),
+            #[cfg(feature = "legacy-draft")]
+            HpkeVersion::Draft05 => {
+                legacy::export::<Crypto>(self.kdf_id, exporter_secret, exporter_context, length)
+            }
+        }
+    }
+
+    /// [`export`](Self::export) a secret of `N` bytes.
+    fn export_array<Crypto: HpkeCrypto, const N: usize>(
+        &self,
+        exporter_secret: &[u8],
+        exporter_context: &[u8],
+    ) -> Result<[u8; N], HpkeError> {
+        let exported =
+            Zeroizing::new(self.export::<Crypto>(exporter_secret, exporter_context, N)?);
+        let mut out = [0u8; N];
+        out.copy_from_slice(&exported);
+        Ok(out)
+    }
+}
+
+impl<Crypto: HpkeCrypto> From<HpkeConfig> for Hpke<Crypto> {
+    fn from(config: HpkeConfig) -> Self {
+        Self::new(config.mode, config.kem_id, config.kdf_id, config.aead_id)
+            .with_version(config.version)
+    }
+}
+
+impl<Crypto: HpkeCrypto> Hpke<Crypto> {
+    /// Set up the configuration for HPKE.
+    pub fn new(
+        mode: Mode,
+        kem_id: KemAlgorithm,
+        kdf_id: KdfAlgorithm,
+        aead_id: AeadAlgorithm,
+    ) -> Self {
+        Self {
+            mode,
+            kem_id,
+            kdf_id,
+            aead_id,
+            prng: rng::Prng::new(),
+            pk_cache: None,
+            fips: false,
+            policy: None,
+            version: HpkeVersion::default(),
+            auditor: None,
+            suite_binding: None,
+            application: None,
+            sender_verifier: None,
+            error_context: None,
+            limits: limits::InputLimits::default(),
+            fault_checks: false,
+            #[cfg(feature = "kat-debug")]
+            intermediate_callback: None,
+        }
+    }
+
+    /// Set up the configuration from the numeric identifiers of the mode and
+    /// the algorithms, e.g. as received on the wire.
+    ///
+    /// Returns [`HpkeError::UnknownMode`] if an identifier is unknown or the
+    /// crypto provider `Crypto` doesn't support the algorithm.
+    pub fn try_from_ids(mode: u8, kem: u16, kdf: u16, aead: u16) -> Result<Self, HpkeError> {
+        let mode = Mode::try_from(mode)?;
+        let suite = ciphersuite::Ciphersuite::from_ids(kem, kdf, aead)?;
+        Crypto::supports_kem(suite.kem)?;
+        Crypto::supports_kdf(suite.kdf)?;
+        Crypto::supports_aead(suite.aead)?;
+        Ok(Self::new(mode, suite.kem, suite.kdf, suite.aead))
+    }
+
+    /// Restrict this configuration to FIPS approved algorithms.
+    ///
+    /// In FIPS mode only the NIST curves (P-256, P-384, P-521), HKDF with
+    /// SHA-2, and AES-GCM (or export-only) are allowed.
+    /// The private-use KDFs are not.
+    /// Returns [`HpkeError::NotAllowedInFipsMode`] if this configuration uses
+    /// any other algorithm.
+    ///
+    /// **Note** that FIPS mode only restricts the algorithms.
+    /// The crypto provider `Crypto` has to be a validated module.
+    pub fn with_fips_mode(mut self) -> Result<Self, HpkeError> {
+        self.fips = true;
+        self.check_policy()?;
+        Ok(self)
+    }
+
+    /// Returns `true` if this configuration is in FIPS mode.
+    pub fn is_fips_mode(&self) -> bool {
+        self.fips
+    }
+
+    /// Restrict this configuration with the ciphersuite `policy`.
+    ///
+    /// The policy applies in addition to a process-wide policy (see
+    /// [`Policy::install`](policy::Policy::install)).
+    /// Returns [`HpkeError::NotAllowedByPolicy`] if this configuration
+    /// violates the policy.
+    pub fn with_policy(mut self, policy: policy::Policy) -> Result<Self, HpkeError> {
+        self.policy = Some(Arc::new(policy));
+        self.check_policy()?;
+        Ok(self)
+    }
+
+    /// Use the labels and key schedule of HPKE `version`.
+    ///
+    /// This defaults to [`HpkeVersion::Rfc9180`].
+    /// Older versions are only meant for talking to peers that have not been
+    /// migrated to RFC 9180 yet.
+    pub fn with_version(mut self, version: HpkeVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Draw all randomness of this configuration from `rng` instead of the
+    /// PRNG of the crypto provider.
+    ///
+    /// This is required without the `default-rng` feature, see [`rng`].
+    pub fn with_rng(mut self, rng: impl rng::HpkeRng + 'static) -> Self {
+        self.prng = rng::Prng::injected(rng);
+        self
+    }
+
+    /// Returns the HPKE version of this configuration.
+    pub fn version(&self) -> HpkeVersion {
+        self.version
+    }
+
+    /// The length of an AEAD key in bytes (`Nk`).
+    ///
+    /// This is `0` for export-only configurations.
+    pub fn nk(&self) -> usize {
+        self.aead_id.key_length()
+    }
+
+    /// The length of an AEAD nonce in bytes (`Nn`).
+    ///
+    /// This is `0` for export-only configurations.
+    pub fn nn(&self) -> usize {
+        self.aead_id.nonce_length()
+    }
+
+    /// The length of an AEAD authentication tag in bytes (`Nt`), i.e. the
+    /// ciphertext expansion.
+    ///
+    /// This is `0` for export-only configurations.
+    pub fn nt(&self) -> usize {
+        self.aead_id.tag_length()
+    }
+
+    /// The output size of the KDF in bytes (`Nh`).
+    pub fn nh(&self) -> usize {
+        self.kdf_id.digest_length()
+    }
+
+    /// The length of an encapsulated secret in bytes (`Nenc`).
+    pub fn nenc(&self) -> usize {
+        self.kem_id.enc_len()
+    }
+
+    /// The length of an encoded public key in bytes (`Npk`).
+    pub fn npk(&self) -> usize {
+        self.kem_id.public_key_len()
+    }
+
+    /// The length of an encoded private key in bytes (`Nsk`).
+    pub fn nsk(&self) -> usize {
+        self.kem_id.private_key_len()
     }
 
-    /// 5.3. Secret Export
-    ///
-    /// Takes a serialised exporter context as byte slice and a length for the
-    /// output secret and returns an exporter secret as byte vector.
-    ///
-    /// ```text
-    /// def Context.Export(exporter_context, L):
-    ///  return LabeledExpand(self.exporter_secret, "sec", exporter_context, L)
-    ///```
-    pub fn export(&self, exporter_context: &[u8], length: usize) -> Result<Vec<u8>, HpkeError> {
-        labeled_expand::<Crypto>(
-            self.hpke.kdf_id,
-            &self.exporter_secret,
-            &self.hpke.ciphersuite(),
-            "sec",
-            exporter_context,
-            length,
-        )
-        .map_err(|e| HpkeError::CryptoError(format!("Crypto error: {}", e)))
+    /// The length of the KEM shared secret in bytes (`Nsecret`).
+    pub fn nsecret(&self) -> usize {
+        self.kem_id.shared_secret_len()
     }
 
-    /// def Context<ROLE>.ComputeNonce(seq):
-    ///     seq_bytes = I2OSP(seq, Nn)
-    ///     return xor(self.base_nonce, seq_bytes)
-    fn compute_nonce(&self) -> Vec<u8> {
-        let seq = self.sequence_number.to_be_bytes();
-        let mut enc_seq = vec![0u8; self.nonce.len() - seq.len()];
-        enc_seq.extend_from_slice(&seq);
-        util::xor_bytes(&enc_seq, &self.nonce)
+    /// The number of bytes that single-shot sealing adds to a message, see
+    /// [`HpkeConfig::overhead`].
+    pub fn overhead(&self) -> usize {
+        self.config().overhead()
     }
 
-    /// def Context<ROLE>.IncrementSeq():
-    ///     if self.seq >= (1 << (8*Nn)) - 1:
-    ///       raise MessageLimitReached
-    ///     self.seq += 1
-    fn increment_seq(&mut self) -> Result<(), HpkeError> {
-        if u128::from(self.sequence_number)
-            >= ((1u128 << (8 * Crypto::aead_nonce_length(self.hpke.aead_id))) - 1)
-        {
-            return Err(HpkeError::MessageLimitReached);
-        }
-        self.sequence_number += 1;
-        Ok(())
+    /// The length of the ciphertext of a plain text of `pt_len` bytes, see
+    /// [`HpkeConfig::ct_len`].
+    pub fn ct_len(&self, pt_len: usize) -> Result<usize, HpkeError> {
+        self.config().ct_len(pt_len)
     }
-}
 
-/// The HPKE configuration struct.
-/// This holds the configuration for HPKE but no state.
-/// To use HPKE first instantiate the configuration with
-/// `let hpke = Hpke::new(mode, kem_mode, kdf_mode, aead_mode)`.
-/// Now one can use the `hpke` configuration.
-///
-/// Note that cloning does NOT clone the PRNG state.
-#[derive(Debug)]
-pub struct Hpke<Crypto: 'static + HpkeCrypto> {
-    mode: Mode,
-    kem_id: KemAlgorithm,
-    kdf_id: KdfAlgorithm,
-    aead_id: AeadAlgorithm,
-    prng: RwLock<Crypto::HpkePrng>,
-}
+    /// The length of the plain text of a ciphertext of `ct_len` bytes, see
+    /// [`HpkeConfig::pt_len`].
+    pub fn pt_len(&self, ct_len: usize) -> Result<usize, HpkeError> {
+        self.config().pt_len(ct_len)
+    }
 
-impl<Crypto: 'static + HpkeCrypto> Clone for Hpke<Crypto> {
-    fn clone(&self) -> Self {
-        Self {
+    /// Returns the stateless [`HpkeConfig`] of this configuration.
+    pub fn config(&self) -> HpkeConfig {
+        HpkeConfig {
             mode: self.mode,
             kem_id: self.kem_id,
             kdf_id: self.kdf_id,
             aead_id: self.aead_id,
-            prng: RwLock::new(Crypto::prng()),
+            version: self.version,
         }
     }
-}
 
-impl<Crypto: HpkeCrypto> std::fmt::Display for Hpke<Crypto> {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(
-            f,
-            "{}_{}_{}_{}",
-            self.mode.to_string().to_lowercase(),
-            self.kem_id.to_string().to_lowercase(),
-            self.kdf_id.to_string().to_lowercase(),
-            self.aead_id.to_string().to_lowercase()
-        )
+    /// Use the labels of draft-irtf-cfrg-hpke-05 instead of RFC 9180.
+    ///
+    /// This is the same as `with_version(HpkeVersion::Draft05)`.
+    /// See the [`legacy`] module for details.
+    #[cfg(feature = "legacy-draft")]
+    pub fn with_legacy_draft_labels(self) -> Self {
+        self.with_version(HpkeVersion::Draft05)
     }
-}
 
-impl<Crypto: HpkeCrypto> Hpke<Crypto> {
-    /// Set up the configuration for HPKE.
-    pub fn new(
-        mode: Mode,
-        kem_id: KemAlgorithm,
-        kdf_id: KdfAlgorithm,
-        aead_id: AeadAlgorithm,
-    ) -> Self {
-        Self {
-            mode,
-            kem_id,
-            kdf_id,
-            aead_id,
-            prng: RwLock::new(Crypto::prng()),
+    /// Check that the crypto provider implements this configuration, and
+    /// check it against FIPS mode, its own policy, and the process-wide policy.
+    #[inline]
+    fn check_policy(&self) -> Result<(), HpkeError> {
+        Crypto::supports_kem(self.kem_id)?;
+        Crypto::supports_kdf(self.kdf_id)?;
+        Crypto::supports_aead(self.aead_id)?;
+        self.check_fips()?;
+        if let Some(policy) = &self.policy {
+            policy.check(self.kem_id, self.kdf_id, self.aead_id)?;
+        }
+        policy::Policy::check_installed(self.kem_id, self.kdf_id, self.aead_id)
+    }
+
+    #[inline]
+    fn check_fips(&self) -> Result<(), HpkeError> {
+        if !self.fips {
+            return Ok(());
+        }
+        let kem = matches!(
+            self.kem_id,
+            KemAlgorithm::DhKemP256 | KemAlgorithm::DhKemP384 | KemAlgorithm::DhKemP521
+        );
+        let kdf = matches!(
+            self.kdf_id,
+            KdfAlgorithm::HkdfSha256 | KdfAlgorithm::HkdfSha384 | KdfAlgorithm::HkdfSha512
+        );
+        let aead = matches!(
+            self.aead_id,
+            AeadAlgorithm::Aes128Gcm | AeadAlgorithm::Aes256Gcm | AeadAlgorithm::HpkeExport
+        );
+        if !kem || !kdf || !aead {
+            return Err(HpkeError::NotAllowedInFipsMode);
         }
+        Ok(())
+    }
+
+    /// Cache up to `capacity` prepared recipient public keys.
+    ///
+    /// Encapsulations to a cached public key skip decoding and validating the
+    /// key. This speeds up senders that encrypt many messages to the same few
+    /// recipients.
+    /// Clones of this configuration share the cache.
+    pub fn with_public_key_cache(mut self, capacity: usize) -> Self {
+        self.pk_cache = Some(Arc::new(pk_cache::PublicKeyCache::new(capacity)));
+        self
     }
 
     /// Set up an HPKE sender.
@@ -390,6 +1704,15 @@ impl<Crypto: HpkeCrypto> Hpke<Crypto> {
     ///
     /// The encapsulated secret is returned together with the context.
     /// If the secret key is missing in an authenticated mode, an error is returned.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            err(level = "debug"),
+            fields(suite = %self)
+        )
+    )]
     pub fn setup_sender(
         &self,
         pk_r: &HpkePublicKey,
@@ -398,30 +1721,93 @@ impl<Crypto: HpkeCrypto> Hpke<Crypto> {
         psk_id: Option<&[u8]>,
         sk_s: Option<&HpkePrivateKey>,
     ) -> Result<(EncapsulatedSecret, Context<Crypto>), HpkeError> {
-        let randomness = self.random(self.kem_id.private_key_len())?;
-        let (zz, enc) = match self.mode {
-            Mode::Base | Mode::Psk => {
-                kem::encaps::<Crypto>(self.kem_id, pk_r.value.as_slice(), &randomness)?
-            }
-            Mode::Auth | Mode::AuthPsk => {
-                let sk_s = match sk_s {
-                    Some(s) => &s.value,
-                    None => return Err(HpkeError::InvalidInput),
-                };
-                kem::auth_encaps::<Crypto>(self.kem_id, pk_r.value.as_slice(), sk_s, &randomness)?
+        let mut result = self.sender_context(pk_r, info, psk, psk_id, sk_s);
+        if let Some(auditor) = &self.auditor {
+            // The contexts report the key id of the recipient as well.
+            let auditor = auditor.with_key_id(self.key_id(pk_r).ok());
+            auditor.report(
+                audit::AuditOperation::SetupSender,
+                self.config(),
+                None,
+                &result,
+            );
+            if let Ok((_, context)) = &mut result {
+                context.auditor = Some(auditor);
             }
-        };
+        }
+        result
+    }
+
+    /// [`setup_sender`](Self::setup_sender) without auditing.
+    fn sender_context(
+        &self,
+        pk_r: &HpkePublicKey,
+        info: &[u8],
+        psk: Option<&[u8]>,
+        psk_id: Option<&[u8]>,
+        sk_s: Option<&HpkePrivateKey>,
+    ) -> Result<(EncapsulatedSecret, Context<Crypto>), HpkeError> {
+        let encapsulated = self
+            .random(self.kem_id.private_key_len())
+            .and_then(|randomness| self.encaps(pk_r, sk_s, &randomness));
+        let (zz, enc) =
+            self.add_error_context(error_context::Stage::Encapsulation, encapsulated)?;
+        let context = self.key_schedule(
+            &zz,
+            info,
+            psk.unwrap_or_default(),
+            psk_id.unwrap_or_default(),
+        );
         Ok((
             enc,
-            self.clone().key_schedule(
-                &zz,
-                info,
-                psk.unwrap_or_default(),
-                psk_id.unwrap_or_default(),
-            )?,
+            self.add_error_context(error_context::Stage::KeySchedule, context)?,
         ))
     }
 
+    /// Encapsulate to `pk_r` (authenticated with `sk_s` in the Auth and AuthPSK
+    /// modes) with the ephemeral key pair derived from `randomness`.
+    ///
+    /// Returns the shared secret and the encapsulated secret.
+    fn encaps(
+        &self,
+        pk_r: &HpkePublicKey,
+        sk_s: Option<&HpkePrivateKey>,
+        randomness: &[u8],
+    ) -> Result<(Vec<u8>, EncapsulatedSecret), HpkeError> {
+        self.check_policy()?;
+        let pk_r_prepared = match &self.pk_cache {
+            Some(cache) => Some(cache.get(self.kem_id, pk_r.as_slice())?),
+            None => None,
+        };
+        let pk_r_prepared = pk_r_prepared.as_deref();
+        let sk_s = match (self.mode, sk_s) {
+            (Mode::Base | Mode::Psk, _) => None,
+            (Mode::Auth | Mode::AuthPsk, Some(s)) => Some(&s.value),
+            (Mode::Auth | Mode::AuthPsk, None) => return Err(HpkeError::InvalidInput),
+        };
+        fault::checked(
+            self.fault_checks,
+            || match sk_s {
+                None => kem::encaps::<Crypto>(
+                    self.kem_id,
+                    self.version,
+                    pk_r.value.as_slice(),
+                    pk_r_prepared,
+                    randomness,
+                ),
+                Some(sk_s) => kem::auth_encaps::<Crypto>(
+                    self.kem_id,
+                    self.version,
+                    pk_r.value.as_slice(),
+                    pk_r_prepared,
+                    sk_s,
+                    randomness,
+                ),
+            },
+            |a, b| util::ct_eq(&a.0, &b.0) && a.1 == b.1,
+        )
+    }
+
     /// Set up an HPKE receiver.
     ///
     /// For the base and PSK modes this decapsulates `enc` with the secret key
@@ -437,6 +1823,15 @@ impl<Crypto: HpkeCrypto> Hpke<Crypto> {
     /// The context based on the decapsulated values and, if present, the PSK is
     /// returned.
     /// If the secret key is missing in an authenticated mode, an error is returned.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            err(level = "debug"),
+            fields(suite = %self)
+        )
+    )]
     pub fn setup_receiver(
         &self,
         enc: &[u8],
@@ -446,25 +1841,66 @@ impl<Crypto: HpkeCrypto> Hpke<Crypto> {
         psk_id: Option<&[u8]>,
         pk_s: Option<&HpkePublicKey>,
     ) -> Result<Context<Crypto>, HpkeError> {
-        let zz = match self.mode {
-            Mode::Base | Mode::Psk => kem::decaps::<Crypto>(self.kem_id, enc, &sk_r.value)?,
-            Mode::Auth | Mode::AuthPsk => {
-                let pk_s = match pk_s {
-                    Some(s) => s.value.as_slice(),
-                    None => return Err(HpkeError::InvalidInput),
-                };
-                kem::auth_decaps::<Crypto>(self.kem_id, enc, &sk_r.value, pk_s)?
+        let decapsulated = self.decaps(enc, sk_r, pk_s);
+        let result = self
+            .add_error_context(error_context::Stage::Decapsulation, decapsulated)
+            .and_then(|zz| {
+                let context = self.key_schedule(
+                    &zz,
+                    info,
+                    psk.unwrap_or_default(),
+                    psk_id.unwrap_or_default(),
+                );
+                self.add_error_context(error_context::Stage::KeySchedule, context)
+            });
+        audit::report(
+            &self.auditor,
+            audit::AuditOperation::SetupReceiver,
+            self.config(),
+            None,
+            &result,
+        );
+        result
+    }
+
+    fn decaps(
+        &self,
+        enc: &[u8],
+        sk_r: &HpkePrivateKey,
+        pk_s: Option<&HpkePublicKey>,
+    ) -> Result<Vec<u8>, HpkeError> {
+        self.check_policy()?;
+        // Validate the encapsulated secret before doing anything with it.
+        let pk_e = kem::prepare_enc::<Crypto>(self.kem_id, enc)?;
+        let pk_s = match (self.mode, pk_s) {
+            (Mode::Base | Mode::Psk, _) => None,
+            (Mode::Auth | Mode::AuthPsk, Some(s)) => {
+                self.verify_sender(s)?;
+                Some(&s.value)
             }
+            (Mode::Auth | Mode::AuthPsk, None) => return Err(HpkeError::InvalidInput),
         };
-        self.clone().key_schedule(
-            &zz,
-            info,
-            psk.unwrap_or_default(),
-            psk_id.unwrap_or_default(),
+        fault::checked(
+            self.fault_checks,
+            || match pk_s {
+                None => {
+                    kem::decaps::<Crypto>(self.kem_id, self.version, enc, Some(&pk_e), &sk_r.value)
+                }
+                Some(pk_s) => kem::auth_decaps::<Crypto>(
+                    self.kem_id,
+                    self.version,
+                    enc,
+                    Some(&pk_e),
+                    &sk_r.value,
+                    pk_s,
+                ),
+            },
+            |a: &Vec<u8>, b: &Vec<u8>| util::ct_eq(a, b),
         )
     }
 
     /// 6. Single-Shot APIs
+    ///
     /// 6.1. Encryption and Decryption
     ///
     /// Single shot API to encrypt the bytes in `plain_text` to the public key
@@ -493,6 +1929,7 @@ impl<Crypto: HpkeCrypto> Hpke<Crypto> {
     }
 
     /// 6. Single-Shot APIs
+    ///
     /// 6.1. Encryption and Decryption
     ///
     /// Single shot API to decrypt the bytes in `ct` with the private key `sk_r`.
@@ -519,7 +1956,68 @@ impl<Crypto: HpkeCrypto> Hpke<Crypto> {
         context.open(aad, ct)
     }
 
+    /// Single shot API to encrypt the bytes in `plain_text` to each of the
+    /// public keys in `pk_rs`.
+    ///
+    /// Every recipient gets its own encapsulation and ciphertext, as if
+    /// [`Hpke::seal`] was called for each of them.
+    /// With the `parallel` feature the recipients are processed concurrently.
+    ///
+    /// Returns the encapsulated secrets and ciphertexts in the order of `pk_rs`,
+    /// or the first error.
+    #[cfg(not(feature = "parallel"))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn seal_to_many(
+        &self,
+        pk_rs: &[HpkePublicKey],
+        info: &[u8],
+        aad: &[u8],
+        plain_txt: &[u8],
+        psk: Option<&[u8]>,
+        psk_id: Option<&[u8]>,
+        sk_s: Option<&HpkePrivateKey>,
+    ) -> Result<Vec<(EncapsulatedSecret, Ciphertext)>, HpkeError> {
+        pk_rs
+            .iter()
+            .map(|pk_r| self.seal(pk_r, info, aad, plain_txt, psk, psk_id, sk_s))
+            .collect()
+    }
+
+    /// Single shot API to encrypt the bytes in `plain_text` to each of the
+    /// public keys in `pk_rs`.
+    ///
+    /// Every recipient gets its own encapsulation and ciphertext, as if
+    /// [`Hpke::seal`] was called for each of them.
+    /// With the `parallel` feature the recipients are processed concurrently,
+    /// which needs a PRNG of the crypto provider that is `Send + Sync`.
+    ///
+    /// Returns the encapsulated secrets and ciphertexts in the order of `pk_rs`,
+    /// or the first error.
+    #[cfg(feature = "parallel")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn seal_to_many(
+        &self,
+        pk_rs: &[HpkePublicKey],
+        info: &[u8],
+        aad: &[u8],
+        plain_txt: &[u8],
+        psk: Option<&[u8]>,
+        psk_id: Option<&[u8]>,
+        sk_s: Option<&HpkePrivateKey>,
+    ) -> Result<Vec<(EncapsulatedSecret, Ciphertext)>, HpkeError>
+    where
+        Crypto::HpkePrng: Send + Sync,
+    {
+        use rayon::prelude::*;
+
+        pk_rs
+            .par_iter()
+            .map(|pk_r| self.seal(pk_r, info, aad, plain_txt, psk, psk_id, sk_s))
+            .collect()
+    }
+
     /// 6. Single-Shot APIs
+    ///
     /// 6.2. Secret Export
     ///
     /// Single shot API to derive an exporter secret for receiver with public key
@@ -548,6 +2046,7 @@ impl<Crypto: HpkeCrypto> Hpke<Crypto> {
     }
 
     /// 6. Single-Shot APIs
+    ///
     /// 6.2. Secret Export
     ///
     /// Single shot API to derive an exporter secret for receiver with private key
@@ -578,6 +2077,7 @@ impl<Crypto: HpkeCrypto> Hpke<Crypto> {
     /// Verify PSKs.
     #[inline(always)]
     fn verify_psk_inputs(&self, psk: &[u8], psk_id: &[u8]) -> Result<(), HpkeError> {
+        self.limits.check(limits::Input::PskId, psk_id.len())?;
         let got_psk = !psk.is_empty();
         let got_psk_id = !psk_id.is_empty();
         if (got_psk && !got_psk_id) || (!got_psk && got_psk_id) {
@@ -599,26 +2099,80 @@ impl<Crypto: HpkeCrypto> Hpke<Crypto> {
         Ok(())
     }
 
+    /// The [`Ciphersuite`](ciphersuite::Ciphersuite) of this configuration.
+    pub fn ciphersuite(&self) -> ciphersuite::Ciphersuite {
+        self.config().ciphersuite()
+    }
+
     #[inline]
-    fn ciphersuite(&self) -> Vec<u8> {
-        util::concat(&[
-            b"HPKE",
-            &(self.kem_id as u16).to_be_bytes(),
-            &(self.kdf_id as u16).to_be_bytes(),
-            &(self.aead_id as u16).to_be_bytes(),
-        ])
+    fn suite_id(&self) -> [u8; 10] {
+        self.config().suite_id()
     }
 
     #[inline]
     fn key_schedule_context(&self, info: &[u8], psk_id: &[u8], suite_id: &[u8]) -> Vec<u8> {
+        let info = self.bound_info(info);
+        let info_hash = labeled_extract::<Crypto>(self.kdf_id, &[0], suite_id, "info_hash", &info);
+        self.key_schedule_context_prehashed(&info_hash, psk_id, suite_id)
+    }
+
+    #[inline]
+    fn key_schedule_context_prehashed(
+        &self,
+        info_hash: &[u8],
+        psk_id: &[u8],
+        suite_id: &[u8],
+    ) -> Vec<u8> {
         let psk_id_hash =
             labeled_extract::<Crypto>(self.kdf_id, &[0], suite_id, "psk_id_hash", psk_id);
-        let info_hash = labeled_extract::<Crypto>(self.kdf_id, &[0], suite_id, "info_hash", info);
-        util::concat(&[&[self.mode as u8], &psk_id_hash, &info_hash])
+        #[cfg(feature = "kat-debug")]
+        {
+            self.dump("psk_id_hash", &psk_id_hash);
+            self.dump("info_hash", info_hash);
+        }
+        util::concat(&[&[self.mode as u8], &psk_id_hash, info_hash])
     }
 
-    /// Creating the Encryption Context
-    /// Generate the HPKE context from the given input.
+    /// 5.1. Creating the Encryption Context
+    ///
+    /// Run the key schedule of this configuration on the `shared_secret` and
+    /// return the context.
+    ///
+    /// This is the entry point for protocols that do their own encapsulation,
+    /// e.g. with a [`kem::Kem`], and for test harnesses that drive the key
+    /// schedule with known answers.
+    /// The mode of the configuration is used.
+    /// Without a PSK, `psk` and `psk_id` are empty.
+    ///
+    /// ```text
+    /// def KeySchedule<ROLE>(mode, shared_secret, info, psk, psk_id):
+    ///   VerifyPSKInputs(mode, psk, psk_id)
+    ///
+    ///   psk_id_hash = LabeledExtract("", "psk_id_hash", psk_id)
+    ///   info_hash = LabeledExtract("", "info_hash", info)
+    ///   key_schedule_context = concat(mode, psk_id_hash, info_hash)
+    ///
+    ///   secret = LabeledExtract(shared_secret, "secret", psk)
+    ///
+    ///   key = LabeledExpand(secret, "key", key_schedule_context, Nk)
+    ///   base_nonce = LabeledExpand(secret, "base_nonce", key_schedule_context, Nn)
+    ///   exporter_secret = LabeledExpand(secret, "exp", key_schedule_context, Nh)
+    ///
+    ///   return Context<ROLE>(key, base_nonce, 0, exporter_secret)
+    /// ```
+    ///
+    /// Returns an error if the PSK inputs don't match the mode (see
+    /// [`HpkeError::InconsistentPsk`] and the related variants) or if the
+    /// configuration is not allowed by its [policy](Self::with_policy).
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            err(level = "debug"),
+            fields(suite = %self)
+        )
+    )]
     pub fn key_schedule(
         &self,
         shared_secret: &[u8],
@@ -626,82 +2180,229 @@ impl<Crypto: HpkeCrypto> Hpke<Crypto> {
         psk: &[u8],
         psk_id: &[u8],
     ) -> Result<Context<Crypto>, HpkeError> {
+        self.check_policy()?;
+        self.limits.check(limits::Input::Info, info.len())?;
         self.verify_psk_inputs(psk, psk_id)?;
-        let suite_id = self.ciphersuite();
-        let key_schedule_context = self.key_schedule_context(info, psk_id, &suite_id);
-        let secret =
-            labeled_extract::<Crypto>(self.kdf_id, shared_secret, &suite_id, "secret", psk);
+        #[cfg(feature = "kat-debug")]
+        self.dump("shared_secret", shared_secret);
+        let (key, base_nonce, exporter_secret) = match self.version {
+            HpkeVersion::Rfc9180 => self.key_schedule_secrets(shared_secret, info, psk, psk_id)?,
+            #[cfg(feature = "legacy-draft")]
+            HpkeVersion::Draft05 => legacy::key_schedule_secrets(
+                self,
+                shared_secret,
+                &self.bound_info(info),
+                psk,
+                psk_id,
+            )?,
+        };
+        self.new_context(key, base_nonce, exporter_secret)
+    }
 
-        let key = labeled_expand::<Crypto>(
-            self.kdf_id,
-            &secret,
-            &suite_id,
-            "key",
-            &key_schedule_context,
-            Crypto::aead_key_length(self.aead_id),
-        )
-        .map_err(|e| HpkeError::CryptoError(format!("Crypto error: {}", e)))?;
-        let base_nonce = labeled_expand::<Crypto>(
-            self.kdf_id,
-            &secret,
-            &suite_id,
-            "base_nonce",
-            &key_schedule_context,
-            Crypto::aead_nonce_length(self.aead_id),
-        )
-        .map_err(|e| HpkeError::CryptoError(format!("Crypto error: {}", e)))?;
-        let exporter_secret = labeled_expand::<Crypto>(
-            self.kdf_id,
-            &secret,
-            &suite_id,
-            "exp",
-            &key_schedule_context,
-            Crypto::kdf_digest_length(self.kdf_id),
-        )
-        .map_err(|e| HpkeError::CryptoError(format!("Crypto error: {}", e)))?;
+    /// Hash the `info` for [`key_schedule_prehashed`](Self::key_schedule_prehashed).
+    ///
+    /// ```text
+    /// info_hash = LabeledExtract("", "info_hash", info)
+    /// ```
+    ///
+    /// This is `HMAC-Hash` with an empty key over
+    /// `"HPKE-v1" || suite_id || "info_hash" || info`, such that protocols
+    /// with a large `info` can compute it incrementally with their own HMAC
+    /// instead.
+    pub fn info_hash(&self, info: &[u8]) -> Vec<u8> {
+        let info = self.bound_info(info);
+        labeled_extract::<Crypto>(self.kdf_id, &[0], &self.suite_id(), "info_hash", &info)
+    }
+
+    /// The [`key_schedule`](Self::key_schedule) with the
+    /// [hash of the `info`](Self::info_hash) instead of the `info`.
+    ///
+    /// Returns [`HpkeError::InvalidInput`] if `info_hash` doesn't have `Nh`
+    /// bytes, and [`HpkeError::InvalidConfig`] for versions other than
+    /// RFC 9180, which hash the `info` differently.
+    pub fn key_schedule_prehashed(
+        &self,
+        shared_secret: &[u8],
+        info_hash: &[u8],
+        psk: &[u8],
+        psk_id: &[u8],
+    ) -> Result<Context<Crypto>, HpkeError> {
+        self.check_policy()?;
+        if self.version != HpkeVersion::Rfc9180 {
+            return Err(HpkeError::InvalidConfig);
+        }
+        if info_hash.len() != self.nh() {
+            return Err(HpkeError::InvalidInput);
+        }
+        self.verify_psk_inputs(psk, psk_id)?;
+        #[cfg(feature = "kat-debug")]
+        self.dump("shared_secret", shared_secret);
+        let suite_id = self.suite_id();
+        let key_schedule_context =
+            self.key_schedule_context_prehashed(info_hash, psk_id, &suite_id);
+        let (key, base_nonce, exporter_secret) =
+            self.derive_secrets(shared_secret, psk, &suite_id, &key_schedule_context)?;
+        self.new_context(key, base_nonce, exporter_secret)
+    }
+
+    /// Create a context from the output of the key schedule.
+    fn new_context(
+        &self,
+        key: Key,
+        base_nonce: Nonce,
+        exporter_secret: ExporterSecret,
+    ) -> Result<Context<Crypto>, HpkeError> {
+        #[cfg(feature = "kat-debug")]
+        {
+            self.dump("key", &key);
+            self.dump("base_nonce", &base_nonce);
+            self.dump("exporter_secret", &exporter_secret);
+        }
 
         Ok(Context {
-            key,
-            nonce: base_nonce,
-            exporter_secret,
+            cipher: init_cipher::<Crypto>(self.aead_id, &key)?,
+            secrets: ContextSecrets::new(key, base_nonce, exporter_secret),
             sequence_number: 0,
-            hpke: self.clone(),
+            epoch: 0,
+            max_epoch_jump: DEFAULT_MAX_EPOCH_JUMP,
+            ratchet_interval: None,
+            poison_on_failure: false,
+            reorder: None,
+            aad_buffer: Vec::new(),
+            poisoned: false,
+            config: self.config(),
+            auditor: self.auditor.clone(),
+            error_context: self.error_context.clone(),
+            limits: self.limits,
+            fault_checks: self.fault_checks,
         })
     }
 
+    /// The RFC 9180 key schedule.
+    /// Returns the key, base nonce, and exporter secret.
+    fn key_schedule_secrets(
+        &self,
+        shared_secret: &[u8],
+        info: &[u8],
+        psk: &[u8],
+        psk_id: &[u8],
+    ) -> Result<(Key, Nonce, ExporterSecret), HpkeError> {
+        let suite_id = self.suite_id();
+        let key_schedule_context = self.key_schedule_context(info, psk_id, &suite_id);
+        self.derive_secrets(shared_secret, psk, &suite_id, &key_schedule_context)
+    }
+
+    /// The part of the RFC 9180 key schedule that depends on the shared
+    /// secret, derived twice with fault checks.
+    fn derive_secrets(
+        &self,
+        shared_secret: &[u8],
+        psk: &[u8],
+        suite_id: &[u8],
+        key_schedule_context: &[u8],
+    ) -> Result<(Key, Nonce, ExporterSecret), HpkeError> {
+        fault::checked(
+            self.fault_checks,
+            || self.derive_secrets_once(shared_secret, psk, suite_id, key_schedule_context),
+            |a, b| util::ct_eq(&a.0, &b.0) && util::ct_eq(&a.1, &b.1) && util::ct_eq(&a.2, &b.2),
+        )
+    }
+
+    fn derive_secrets_once(
+        &self,
+        shared_secret: &[u8],
+        psk: &[u8],
+        suite_id: &[u8],
+        key_schedule_context: &[u8],
+    ) -> Result<(Key, Nonce, ExporterSecret), HpkeError> {
+        let secret = labeled_extract::<Crypto>(self.kdf_id, shared_secret, suite_id, "secret", psk);
+        #[cfg(feature = "kat-debug")]
+        {
+            self.dump("key_schedule_context", key_schedule_context);
+            self.dump("secret", &secret);
+        }
+
+        let key = Key::from_vec(
+            labeled_expand::<Crypto>(
+                self.kdf_id,
+                &secret,
+                suite_id,
+                "key",
+                key_schedule_context,
+                Crypto::aead_key_length(self.aead_id),
+            )
+            .map_err(|e| HpkeError::CryptoError(format!("Crypto error: {}", e)))?,
+        )?;
+        let base_nonce = Nonce::from_vec(
+            labeled_expand::<Crypto>(
+                self.kdf_id,
+                &secret,
+                suite_id,
+                "base_nonce",
+                key_schedule_context,
+                Crypto::aead_nonce_length(self.aead_id),
+            )
+            .map_err(|e| HpkeError::CryptoError(format!("Crypto error: {}", e)))?,
+        )?;
+        let exporter_secret = ExporterSecret::from_vec(
+            labeled_expand::<Crypto>(
+                self.kdf_id,
+                &secret,
+                suite_id,
+                "exp",
+                key_schedule_context,
+                Crypto::kdf_digest_length(self.kdf_id),
+            )
+            .map_err(|e| HpkeError::CryptoError(format!("Crypto error: {}", e)))?,
+        )?;
+        Ok((key, base_nonce, exporter_secret))
+    }
+
     /// 4. Cryptographic Dependencies
+    ///
     /// Randomized algorithm to generate a key pair `(skX, pkX)` for the KEM.
     /// This is equivalent to `derive_key_pair(random_vector(sk.len()))`
     ///
     /// Returns an `HpkeKeyPair`.
     pub fn generate_key_pair(&self) -> Result<HpkeKeyPair, HpkeError> {
-        let mut prng = self.prng.write().map_err(|_| HpkeError::LockPoisoned)?;
-        let (sk, pk) = kem::key_gen::<Crypto>(self.kem_id, &mut prng)?;
+        self.check_policy()?;
+        let (sk, pk) = self.prng.key_gen(self.kem_id, 1)?.remove(0);
         Ok(HpkeKeyPair::new(sk, pk))
     }
 
+    /// Generate `n` key pairs as with [`generate_key_pair`](Self::generate_key_pair).
+    ///
+    /// The PRNG is locked once for all key pairs, which amortizes the setup
+    /// for workloads that need many ephemeral keys.
+    pub fn generate_key_pairs(&self, n: usize) -> Result<Vec<HpkeKeyPair>, HpkeError> {
+        self.check_policy()?;
+        Ok(self
+            .prng
+            .key_gen(self.kem_id, n)?
+            .into_iter()
+            .map(|(sk, pk)| HpkeKeyPair::new(sk, pk))
+            .collect())
+    }
+
     /// 7.1.2. DeriveKeyPair
     /// Derive a key pair for the used KEM with the given input key material.
     ///
     /// Returns an `HpkeKeyPair` result or an `HpkeError` if key derivation fails.
     pub fn derive_key_pair(&self, ikm: &[u8]) -> Result<HpkeKeyPair, HpkeError> {
+        self.check_policy()?;
         let (pk, sk) = kem::derive_key_pair::<Crypto>(self.kem_id, ikm)?;
         Ok(HpkeKeyPair::new(sk, pk))
     }
 
+    /// Compute the [`KeyId`] of the public key `pk` for the KEM of this
+    /// configuration.
+    pub fn key_id(&self, pk: &HpkePublicKey) -> Result<KeyId, HpkeError> {
+        pk.key_id::<Crypto>(self.kem_id)
+    }
+
     #[inline]
     pub(crate) fn random(&self, len: usize) -> Result<Vec<u8>, HpkeError> {
-        let mut prng = self.prng.write().map_err(|_| HpkeError::LockPoisoned)?;
-        let mut out = vec![0u8; len];
-
-        #[cfg(feature = "hpke-test-prng")]
-        prng.try_fill_test_bytes(&mut out)
-            .map_err(|_| HpkeError::InsufficientRandomness)?;
-        #[cfg(not(feature = "hpke-test-prng"))]
-        prng.try_fill_bytes(&mut out)
-            .map_err(|_| HpkeError::InsufficientRandomness)?;
-
-        Ok(out)
+        self.prng.random(len)
     }
 }
 
@@ -755,7 +2456,10 @@ impl HpkePrivateKey {
     /// Create a new HPKE private key.
     /// Consumes the private key bytes.
     pub fn new(b: Vec<u8>) -> Self {
-        Self { value: b }
+        Self {
+            lock: mlock::MemoryLock::bytes(&b),
+            value: b,
+        }
     }
 
     /// Get the raw key as byte slice.
@@ -836,6 +2540,26 @@ impl From<&[u8]> for HpkePublicKey {
     }
 }
 
+impl<const N: usize> From<[u8; N]> for HpkePublicKey {
+    fn from(b: [u8; N]) -> Self {
+        Self::new(b.to_vec())
+    }
+}
+
+impl<const N: usize> TryFrom<&HpkePublicKey> for [u8; N] {
+    type Error = HpkeError;
+
+    /// The public key as array of its statically known length, e.g. the 32
+    /// bytes of an X25519 key.
+    ///
+    /// Returns [`HpkeError::InvalidInput`] if the key doesn't have `N` bytes.
+    fn try_from(pk: &HpkePublicKey) -> Result<Self, Self::Error> {
+        pk.as_slice()
+            .try_into()
+            .map_err(|_| HpkeError::InvalidInput)
+    }
+}
+
 #[cfg(feature = "serialization")]
 impl tls_codec::Size for HpkePublicKey {
     #[inline(always)]
@@ -891,15 +2615,43 @@ impl tls_codec::Deserialize for &HpkePublicKey {
 /// Test util module. Should be moved really.
 #[cfg(feature = "hpke-test")]
 pub mod test_util {
-    use crate::HpkeError;
-    use hpke_rs_crypto::{HpkeCrypto, HpkeTestRng};
+    use crate::{kdf::labeled_extract, HpkeError, HpkePrivateKey, HpkePublicKey};
+    use hpke_rs_crypto::HpkeCrypto;
 
     impl<Crypto: HpkeCrypto> super::Hpke<Crypto> {
         /// Set PRNG state for testing.
         pub fn seed(&self, seed: &[u8]) -> Result<(), HpkeError> {
-            let mut prng = self.prng.write().map_err(|_| HpkeError::LockPoisoned)?;
-            prng.seed(seed);
-            Ok(())
+            self.prng.seed(seed)
+        }
+
+        /// Encapsulate to `pk_r` with the ephemeral key pair derived from
+        /// `ikm_e`.
+        /// Returns the shared secret and the encapsulated secret.
+        #[doc(hidden)]
+        pub fn encaps_with_ikm(
+            &self,
+            pk_r: &HpkePublicKey,
+            sk_s: Option<&HpkePrivateKey>,
+            ikm_e: &[u8],
+        ) -> Result<(Vec<u8>, Vec<u8>), HpkeError> {
+            self.encaps(pk_r, sk_s, ikm_e)
+        }
+
+        /// Get the `key_schedule_context` and `secret` computed in the key
+        /// schedule.
+        #[doc(hidden)]
+        pub fn key_schedule_intermediates(
+            &self,
+            shared_secret: &[u8],
+            info: &[u8],
+            psk: &[u8],
+            psk_id: &[u8],
+        ) -> (Vec<u8>, Vec<u8>) {
+            let suite_id = self.suite_id();
+            let key_schedule_context = self.key_schedule_context(info, psk_id, &suite_id);
+            let secret =
+                labeled_extract::<Crypto>(self.kdf_id, shared_secret, &suite_id, "secret", psk);
+            (key_schedule_context, secret)
         }
     }
 
@@ -907,17 +2659,17 @@ pub mod test_util {
         /// Get a reference to the key in the context.
         #[doc(hidden)]
         pub fn key(&self) -> &[u8] {
-            &self.key
+            &self.secrets.key
         }
         /// Get a reference to the nonce in the context.
         #[doc(hidden)]
         pub fn nonce(&self) -> &[u8] {
-            &self.nonce
+            &self.secrets.nonce
         }
         /// Get a reference to the exporter secret in the context.
         #[doc(hidden)]
         pub fn exporter_secret(&self) -> &[u8] {
-            &self.exporter_secret
+            &self.secrets.exporter_secret
         }
         /// Get a reference to the sequence number in the context.
         #[doc(hidden)]
@@ -965,29 +2717,26 @@ pub mod test_util {
     }
 }
 
+impl From<std::io::Error> for HpkeError {
+    fn from(e: std::io::Error) -> Self {
+        HpkeError::IoError(e.to_string())
+    }
+}
+
 impl From<hpke_rs_crypto::error::Error> for HpkeError {
     fn from(e: hpke_rs_crypto::error::Error) -> Self {
         match e {
             hpke_rs_crypto::error::Error::AeadOpenError => HpkeError::OpenError,
-            hpke_rs_crypto::error::Error::AeadInvalidNonce
-            | hpke_rs_crypto::error::Error::AeadInvalidCiphertext => HpkeError::InvalidInput,
-            hpke_rs_crypto::error::Error::UnknownAeadAlgorithm => HpkeError::UnknownMode,
+            hpke_rs_crypto::error::Error::AeadInvalidKey
+            | hpke_rs_crypto::error::Error::AeadInvalidNonce
+            | hpke_rs_crypto::error::Error::AeadInvalidCiphertext
+            | hpke_rs_crypto::error::Error::KemInvalidSecretKey
+            | hpke_rs_crypto::error::Error::KemInvalidPublicKey
+            | hpke_rs_crypto::error::Error::HpkeInvalidOutputLength => HpkeError::InvalidInput,
+            hpke_rs_crypto::error::Error::UnknownAeadAlgorithm
+            | hpke_rs_crypto::error::Error::UnknownKdfAlgorithm
+            | hpke_rs_crypto::error::Error::UnknownKemAlgorithm => HpkeError::UnknownMode,
             hpke_rs_crypto::error::Error::CryptoLibraryError(s) => HpkeError::CryptoError(s),
-            hpke_rs_crypto::error::Error::HpkeInvalidOutputLength => {
-                HpkeError::CryptoError("Invalid HPKE output length".to_string())
-            }
-            hpke_rs_crypto::error::Error::UnknownKdfAlgorithm => {
-                HpkeError::CryptoError("Unknown KDF algorithm.".to_string())
-            }
-            hpke_rs_crypto::error::Error::KemInvalidSecretKey => {
-                HpkeError::CryptoError("Invalid KEM secret key".to_string())
-            }
-            hpke_rs_crypto::error::Error::KemInvalidPublicKey => {
-                HpkeError::CryptoError("Invalid KEM public key".to_string())
-            }
-            hpke_rs_crypto::error::Error::UnknownKemAlgorithm => {
-                HpkeError::CryptoError("Unknown KEM algorithm".to_string())
-            }
             hpke_rs_crypto::error::Error::InsufficientRandomness => {
                 HpkeError::InsufficientRandomness
             }
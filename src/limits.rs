@@ -0,0 +1,144 @@
+//! # Input Limits
+//!
+//! The `info`, `psk_id`, and exporter contexts are hashed with labels, i.e.
+//! copied into a labeled input before they are hashed, and the additional
+//! data is passed to the AEAD.
+//! RFC 9180 only bounds their lengths by the input limits of the KDF and the
+//! AEAD, which are far beyond what fits into memory.
+//! A peer that controls one of these inputs, e.g. the `info` of a protocol
+//! that passes it along with the message, can make every operation allocate
+//! and hash as much as it sends.
+//!
+//! [`InputLimits`] cap the lengths of these inputs per configuration
+//! ([`Hpke::with_input_limits`]), and the contexts it sets up.
+//! Longer inputs are rejected with [`HpkeError::InputTooLong`] before they
+//! are copied or hashed.
+//!
+//! The defaults are far above what protocols use and the 64 bytes that
+//! RFC 9180, Section 7.2.1 requires implementations to support:
+//!
+//! | Input              | Default limit |
+//! | ------------------ | ------------- |
+//! | `info`             | 64 KiB        |
+//! | `psk_id`           | 64 KiB        |
+//! | exporter context   | 64 KiB        |
+//! | additional data    | 16 MiB        |
+
+use hpke_rs_crypto::HpkeCrypto;
+
+use crate::{Hpke, HpkeError};
+
+/// The default limit for the `info`, `psk_id`, and exporter contexts.
+pub const DEFAULT_MAX_LABELED_INPUT: usize = 1 << 16;
+
+/// The default limit for the additional data.
+pub const DEFAULT_MAX_AAD: usize = 1 << 24;
+
+/// An input with a length limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    /// The `info` of a setup.
+    Info,
+    /// The `psk_id` of a setup.
+    PskId,
+    /// The additional data of a seal or open.
+    Aad,
+    /// The exporter context of an export.
+    ExporterContext,
+}
+
+/// The maximum lengths of inputs in bytes, see the
+/// [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputLimits {
+    info: usize,
+    psk_id: usize,
+    aad: usize,
+    exporter_context: usize,
+}
+
+impl Default for InputLimits {
+    fn default() -> Self {
+        Self {
+            info: DEFAULT_MAX_LABELED_INPUT,
+            psk_id: DEFAULT_MAX_LABELED_INPUT,
+            aad: DEFAULT_MAX_AAD,
+            exporter_context: DEFAULT_MAX_LABELED_INPUT,
+        }
+    }
+}
+
+impl InputLimits {
+    /// The default limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// No limits other than those of the KDF and the AEAD.
+    pub fn unlimited() -> Self {
+        Self {
+            info: usize::MAX,
+            psk_id: usize::MAX,
+            aad: usize::MAX,
+            exporter_context: usize::MAX,
+        }
+    }
+
+    /// Limit the `info` to `len` bytes.
+    pub fn max_info(mut self, len: usize) -> Self {
+        self.info = len;
+        self
+    }
+
+    /// Limit the `psk_id` to `len` bytes.
+    pub fn max_psk_id(mut self, len: usize) -> Self {
+        self.psk_id = len;
+        self
+    }
+
+    /// Limit the additional data to `len` bytes.
+    pub fn max_aad(mut self, len: usize) -> Self {
+        self.aad = len;
+        self
+    }
+
+    /// Limit the exporter contexts to `len` bytes.
+    pub fn max_exporter_context(mut self, len: usize) -> Self {
+        self.exporter_context = len;
+        self
+    }
+
+    /// Get the limit for the `input`.
+    pub fn max(&self, input: Input) -> usize {
+        match input {
+            Input::Info => self.info,
+            Input::PskId => self.psk_id,
+            Input::Aad => self.aad,
+            Input::ExporterContext => self.exporter_context,
+        }
+    }
+
+    /// Check the length `len` of the `input` against its limit.
+    ///
+    /// Returns [`HpkeError::InputTooLong`] if it is too long.
+    pub fn check(&self, input: Input, len: usize) -> Result<(), HpkeError> {
+        if len > self.max(input) {
+            return Err(HpkeError::InputTooLong(input));
+        }
+        Ok(())
+    }
+}
+
+impl<Crypto: HpkeCrypto> Hpke<Crypto> {
+    /// Set the input `limits` of this configuration and the contexts it sets
+    /// up, see the [module documentation](self).
+    pub fn with_input_limits(mut self, limits: InputLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Get the input limits of this configuration.
+    pub fn input_limits(&self) -> InputLimits {
+        self.limits
+    }
+}
@@ -0,0 +1,276 @@
+//! # Managed Senders
+//!
+//! A [`Context`] can seal a limited number of messages before its nonces
+//! run out (see [`HpkeError::MessageLimitReached`]).
+//! A [`ManagedSender`] seals with a context until it has sealed
+//! `messages_per_context` messages, and then sets up a new context to the
+//! same recipient with a fresh encapsulation.
+//! The new encapsulated secret is sent in-band with the next message, such
+//! that long-running senders never hit the message limit.
+//!
+//! Every message is framed with a flag that tells whether it starts a new
+//! context:
+//!
+//! ```text
+//! frame = I2OSP(0, 1) || ct
+//!       | I2OSP(1, 1) || enc || ct
+//! ```
+//!
+//! The first message always carries the encapsulated secret, so nothing
+//! has to be sent ahead of it.
+//! A [`ManagedReceiver`] with the recipient's private key opens the frames
+//! in the order they were sealed, like a receiver [`Context`].
+//! It only switches to a new context once a message sealed with it opened,
+//! such that a forged frame can't replace the current context.
+//!
+//! ```
+//! use hpke_rs::{managed::{ManagedReceiver, ManagedSender}, prelude::*};
+//! use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+//! use hpke_rs_rust_crypto::HpkeRustCrypto;
+//!
+//! let hpke = Hpke::<HpkeRustCrypto>::new(
+//!     HpkeMode::Base,
+//!     KemAlgorithm::DhKem25519,
+//!     KdfAlgorithm::HkdfSha256,
+//!     AeadAlgorithm::ChaCha20Poly1305,
+//! );
+//! let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+//! let mut sender = ManagedSender::new(&hpke, &pk_r, b"info", None, None, None)
+//!     .unwrap()
+//!     .with_messages_per_context(2);
+//! let mut receiver = ManagedReceiver::new(&hpke, &sk_r, b"info", None, None, None);
+//!
+//! for i in 0..5u8 {
+//!     let frame = sender.seal(b"", &[i]).unwrap();
+//!     assert_eq!(receiver.open(b"", &frame).unwrap(), [i]);
+//! }
+//! assert_eq!(sender.encapsulations(), 3);
+//! ```
+
+use hpke_rs_crypto::HpkeCrypto;
+use zeroize::Zeroizing;
+
+use crate::{
+    util::concat, Context, EncapsulatedSecret, Hpke, HpkeError, HpkePrivateKey, HpkePublicKey,
+    Plaintext,
+};
+
+/// The flag of a frame sealed with the current context.
+const CONTINUE: u8 = 0;
+
+/// The flag of a frame that starts a new context.
+const NEW_CONTEXT: u8 = 1;
+
+/// The setup inputs that are shared by all contexts.
+struct SetupInputs {
+    info: Vec<u8>,
+    psk: Option<Zeroizing<Vec<u8>>>,
+    psk_id: Option<Vec<u8>>,
+}
+
+impl SetupInputs {
+    fn new(info: &[u8], psk: Option<&[u8]>, psk_id: Option<&[u8]>) -> Self {
+        Self {
+            info: info.to_vec(),
+            psk: psk.map(|psk| Zeroizing::new(psk.to_vec())),
+            psk_id: psk_id.map(<[u8]>::to_vec),
+        }
+    }
+
+    fn psk(&self) -> Option<&[u8]> {
+        self.psk.as_deref().map(Vec::as_slice)
+    }
+
+    fn psk_id(&self) -> Option<&[u8]> {
+        self.psk_id.as_deref()
+    }
+}
+
+/// A sender that re-encapsulates to the same recipient before the message
+/// limit of its context, see the [module documentation](self).
+pub struct ManagedSender<Crypto: 'static + HpkeCrypto> {
+    hpke: Hpke<Crypto>,
+    pk_r: HpkePublicKey,
+    sk_s: Option<HpkePrivateKey>,
+    inputs: SetupInputs,
+    context: Context<Crypto>,
+    /// The encapsulated secret of the context if it wasn't sent yet.
+    pending_enc: Option<EncapsulatedSecret>,
+    messages_per_context: u32,
+    encapsulations: u64,
+}
+
+impl<Crypto: HpkeCrypto> std::fmt::Debug for ManagedSender<Crypto> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ManagedSender")
+            .field("config", &self.context.config())
+            .field("messages_per_context", &self.messages_per_context)
+            .field("encapsulations", &self.encapsulations)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Crypto: HpkeCrypto> ManagedSender<Crypto> {
+    /// Set up a sender to `pk_r` with `hpke`, see [`Hpke::setup_sender`].
+    ///
+    /// The setup inputs are kept for the following encapsulations.
+    /// By default a context seals as many messages as the AEAD allows.
+    pub fn new(
+        hpke: &Hpke<Crypto>,
+        pk_r: &HpkePublicKey,
+        info: &[u8],
+        psk: Option<&[u8]>,
+        psk_id: Option<&[u8]>,
+        sk_s: Option<&HpkePrivateKey>,
+    ) -> Result<Self, HpkeError> {
+        let inputs = SetupInputs::new(info, psk, psk_id);
+        let (enc, context) =
+            hpke.setup_sender(pk_r, &inputs.info, inputs.psk(), inputs.psk_id(), sk_s)?;
+        Ok(Self {
+            hpke: hpke.clone(),
+            pk_r: pk_r.clone(),
+            sk_s: sk_s.map(|sk_s| HpkePrivateKey::new(sk_s.value.clone())),
+            inputs,
+            context,
+            pending_enc: Some(enc),
+            messages_per_context: u32::MAX,
+            encapsulations: 1,
+        })
+    }
+
+    /// Re-encapsulate after `messages` messages (at least one) per context.
+    ///
+    /// Limits above the message limit of the AEAD re-encapsulate at the
+    /// message limit.
+    pub fn with_messages_per_context(mut self, messages: u32) -> Self {
+        self.messages_per_context = messages.max(1);
+        self
+    }
+
+    /// Get the number of messages a context seals before re-encapsulating.
+    pub fn messages_per_context(&self) -> u32 {
+        self.messages_per_context
+    }
+
+    /// Get the number of encapsulations so far, including the initial one.
+    pub fn encapsulations(&self) -> u64 {
+        self.encapsulations
+    }
+
+    /// Seal `plain_txt` with the additional data `aad` and frame it.
+    ///
+    /// Sets up a new context first if the current one sealed
+    /// `messages_per_context` messages or reached the message limit.
+    /// Returns the frame, or an error.
+    pub fn seal(&mut self, aad: &[u8], plain_txt: &[u8]) -> Result<Vec<u8>, HpkeError> {
+        if self.context.sequence() >= self.messages_per_context {
+            self.reencapsulate()?;
+        }
+        let ct = match self.context.seal(aad, plain_txt) {
+            Err(HpkeError::MessageLimitReached) => {
+                self.reencapsulate()?;
+                self.context.seal(aad, plain_txt)?
+            }
+            result => result?,
+        };
+        Ok(match self.pending_enc.take() {
+            Some(enc) => concat(&[&[NEW_CONTEXT], &enc, &ct]),
+            None => concat(&[&[CONTINUE], &ct]),
+        })
+    }
+
+    fn reencapsulate(&mut self) -> Result<(), HpkeError> {
+        let (enc, context) = self.hpke.setup_sender(
+            &self.pk_r,
+            &self.inputs.info,
+            self.inputs.psk(),
+            self.inputs.psk_id(),
+            self.sk_s.as_ref(),
+        )?;
+        // The previous context is zeroized when it is dropped here.
+        self.context = context;
+        self.pending_enc = Some(enc);
+        self.encapsulations += 1;
+        Ok(())
+    }
+}
+
+/// The receiver of the frames of a [`ManagedSender`], see the
+/// [module documentation](self).
+pub struct ManagedReceiver<Crypto: 'static + HpkeCrypto> {
+    hpke: Hpke<Crypto>,
+    sk_r: HpkePrivateKey,
+    pk_s: Option<HpkePublicKey>,
+    inputs: SetupInputs,
+    context: Option<Context<Crypto>>,
+}
+
+impl<Crypto: HpkeCrypto> std::fmt::Debug for ManagedReceiver<Crypto> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ManagedReceiver")
+            .field("config", &self.hpke.config())
+            .field("has_context", &self.context.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Crypto: HpkeCrypto> ManagedReceiver<Crypto> {
+    /// Create a receiver with the private key `sk_r` and the setup inputs of
+    /// the sender, see [`Hpke::setup_receiver`].
+    pub fn new(
+        hpke: &Hpke<Crypto>,
+        sk_r: &HpkePrivateKey,
+        info: &[u8],
+        psk: Option<&[u8]>,
+        psk_id: Option<&[u8]>,
+        pk_s: Option<&HpkePublicKey>,
+    ) -> Self {
+        Self {
+            hpke: hpke.clone(),
+            sk_r: HpkePrivateKey::new(sk_r.value.clone()),
+            pk_s: pk_s.cloned(),
+            inputs: SetupInputs::new(info, psk, psk_id),
+            context: None,
+        }
+    }
+
+    /// Open the `frame` sealed with the additional data `aad`.
+    ///
+    /// A frame with a new encapsulated secret sets up a new context, which
+    /// replaces the current one if the message opens.
+    /// Returns [`HpkeError::InvalidInput`] if the frame is malformed or
+    /// continues a context before any context was set up.
+    pub fn open(&mut self, aad: &[u8], frame: &[u8]) -> Result<Plaintext, HpkeError> {
+        let (&flag, rest) = frame.split_first().ok_or(HpkeError::InvalidInput)?;
+        match flag {
+            CONTINUE => self
+                .context
+                .as_mut()
+                .ok_or(HpkeError::InvalidInput)?
+                .open(aad, rest),
+            NEW_CONTEXT => {
+                if rest.len() < self.hpke.nenc() {
+                    return Err(HpkeError::InvalidInput);
+                }
+                let (enc, ct) = rest.split_at(self.hpke.nenc());
+                let mut context = self.hpke.setup_receiver(
+                    enc,
+                    &self.sk_r,
+                    &self.inputs.info,
+                    self.inputs.psk(),
+                    self.inputs.psk_id(),
+                    self.pk_s.as_ref(),
+                )?;
+                let plain_txt = context.open(aad, ct)?;
+                self.context = Some(context);
+                Ok(plain_txt)
+            }
+            _ => Err(HpkeError::InvalidInput),
+        }
+    }
+
+    /// Returns `true` once a context was set up.
+    pub fn has_context(&self) -> bool {
+        self.context.is_some()
+    }
+}
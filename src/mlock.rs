@@ -0,0 +1,52 @@
+//! Memory locking for secrets with the `mlock` feature.
+//!
+//! Locking is best effort.
+//! If the operating system refuses to lock memory (e.g. because the
+//! `RLIMIT_MEMLOCK` limit is reached) the buffer is used without the lock.
+//! Locks are page granular and not reference counted, so dropping a buffer
+//! unlocks all pages it shares with other locked buffers.
+//! Without the feature, this is a no-op.
+
+/// A lock of a memory region that is released on drop.
+#[derive(Default)]
+pub(crate) struct MemoryLock {
+    #[cfg(feature = "mlock")]
+    _guard: Option<region::LockGuard>,
+}
+
+impl std::fmt::Debug for MemoryLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryLock").finish()
+    }
+}
+
+impl MemoryLock {
+    /// Lock the heap buffer `bytes`.
+    ///
+    /// The buffer must not be reallocated while the lock is held.
+    pub(crate) fn bytes(bytes: &[u8]) -> Self {
+        if bytes.is_empty() {
+            return Self::default();
+        }
+        Self::lock(bytes.as_ptr(), bytes.len())
+    }
+
+    /// Lock the memory of `value`.
+    ///
+    /// The value must not move while the lock is held.
+    pub(crate) fn value<T>(value: &T) -> Self {
+        Self::lock(std::ptr::from_ref(value), size_of::<T>())
+    }
+
+    #[cfg(feature = "mlock")]
+    fn lock<T>(address: *const T, size: usize) -> Self {
+        Self {
+            _guard: region::lock(address, size).ok(),
+        }
+    }
+
+    #[cfg(not(feature = "mlock"))]
+    fn lock<T>(_address: *const T, _size: usize) -> Self {
+        Self {}
+    }
+}
@@ -0,0 +1,194 @@
+//! # Oblivious HTTP Key Configurations
+//!
+//! The key configuration of an Oblivious HTTP gateway ([RFC 9458, Section 3])
+//! and the `application/ohttp-keys` media type, a list of key
+//! configurations that are each prefixed with their length.
+//!
+//! ```text
+//! HPKE Symmetric Algorithms {
+//!   HPKE KDF ID (16),
+//!   HPKE AEAD ID (16),
+//! }
+//!
+//! Key Config {
+//!   Key Identifier (8),
+//!   HPKE KEM ID (16),
+//!   HPKE Public Key (Npk * 8),
+//!   HPKE Symmetric Algorithms Length (16) = 4..65532,
+//!   HPKE Symmetric Algorithms (32) = 1..,
+//! }
+//! ```
+//!
+//! Symmetric algorithms with unknown identifiers are ignored when decoding a
+//! key configuration, and so are key configurations with an unknown KEM in
+//! an `application/ohttp-keys` list.
+//!
+//! [RFC 9458, Section 3]: https://www.rfc-editor.org/rfc/rfc9458#section-3
+
+use hpke_rs_crypto::{
+    types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm},
+    HpkeCrypto,
+};
+
+use crate::{
+    ciphersuite::Ciphersuite,
+    util::{take, take_u16},
+    HpkeError, HpkePublicKey,
+};
+
+/// An Oblivious HTTP key configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyConfig {
+    /// The key identifier.
+    pub key_id: u8,
+    /// The KEM of the public key.
+    pub kem: KemAlgorithm,
+    /// The public key of the gateway.
+    pub public_key: HpkePublicKey,
+    /// The supported KDF and AEAD pairs in order of preference.
+    pub symmetric: Vec<(KdfAlgorithm, AeadAlgorithm)>,
+}
+
+impl KeyConfig {
+    /// Create a new key configuration.
+    ///
+    /// Returns [`HpkeError::InvalidInput`] if the public key doesn't have
+    /// `Npk` bytes or if there are no symmetric algorithms, or too many
+    /// to encode.
+    pub fn new(
+        key_id: u8,
+        kem: KemAlgorithm,
+        public_key: HpkePublicKey,
+        symmetric: Vec<(KdfAlgorithm, AeadAlgorithm)>,
+    ) -> Result<Self, HpkeError> {
+        if public_key.as_slice().len() != kem.public_key_len()
+            || symmetric.is_empty()
+            || symmetric.len() > usize::from(u16::MAX / 4)
+        {
+            return Err(HpkeError::InvalidInput);
+        }
+        Ok(Self {
+            key_id,
+            kem,
+            public_key,
+            symmetric,
+        })
+    }
+
+    /// Encode the key configuration.
+    pub fn encode(&self) -> Vec<u8> {
+        let pk = self.public_key.as_slice();
+        let mut out = Vec::with_capacity(1 + 2 + pk.len() + 2 + 4 * self.symmetric.len());
+        out.push(self.key_id);
+        out.extend_from_slice(&(self.kem as u16).to_be_bytes());
+        out.extend_from_slice(pk);
+        out.extend_from_slice(&((4 * self.symmetric.len()) as u16).to_be_bytes());
+        for (kdf, aead) in &self.symmetric {
+            out.extend_from_slice(&(*kdf as u16).to_be_bytes());
+            out.extend_from_slice(&(*aead as u16).to_be_bytes());
+        }
+        out
+    }
+
+    /// Decode a single key configuration.
+    ///
+    /// Returns [`HpkeError::UnknownMode`] if the KEM is unknown or none of the
+    /// symmetric algorithms are known, and [`HpkeError::InvalidInput`] if the
+    /// encoding is malformed.
+    pub fn decode(bytes: &[u8]) -> Result<Self, HpkeError> {
+        let mut reader = bytes;
+        let config = Self::read(&mut reader)?;
+        if !reader.is_empty() {
+            return Err(HpkeError::InvalidInput);
+        }
+        config
+    }
+
+    /// Read a key configuration from the front of `reader`.
+    ///
+    /// The outer error is a malformed encoding, the inner one an unknown
+    /// algorithm, in which case the key configuration was skipped.
+    fn read(reader: &mut &[u8]) -> Result<Result<Self, HpkeError>, HpkeError> {
+        let key_id = take(reader, 1)?[0];
+        let kem = KemAlgorithm::try_from(take_u16(reader)?);
+        let kem = match kem {
+            Ok(kem) => kem,
+            Err(e) => {
+                // Without the KEM the length of the public key is unknown.
+                *reader = &[];
+                return Ok(Err(e.into()));
+            }
+        };
+        let public_key = take(reader, kem.public_key_len())?.to_vec();
+        let symmetric_len = usize::from(take_u16(reader)?);
+        if symmetric_len < 4 || symmetric_len % 4 != 0 {
+            return Err(HpkeError::InvalidInput);
+        }
+        let mut symmetric_bytes = take(reader, symmetric_len)?;
+        let mut symmetric = Vec::with_capacity(symmetric_len / 4);
+        while !symmetric_bytes.is_empty() {
+            let kdf = KdfAlgorithm::try_from(take_u16(&mut symmetric_bytes)?);
+            let aead = AeadAlgorithm::try_from(take_u16(&mut symmetric_bytes)?);
+            if let (Ok(kdf), Ok(aead)) = (kdf, aead) {
+                symmetric.push((kdf, aead));
+            }
+        }
+        if symmetric.is_empty() {
+            return Ok(Err(HpkeError::UnknownMode));
+        }
+        Ok(Ok(Self {
+            key_id,
+            kem,
+            public_key: public_key.into(),
+            symmetric,
+        }))
+    }
+
+    /// Encode the key configurations as `application/ohttp-keys`.
+    pub fn encode_list(configs: &[KeyConfig]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for config in configs {
+            let encoded = config.encode();
+            out.extend_from_slice(&(encoded.len() as u16).to_be_bytes());
+            out.extend_from_slice(&encoded);
+        }
+        out
+    }
+
+    /// Decode `application/ohttp-keys`.
+    ///
+    /// Key configurations with an unknown KEM or without known symmetric
+    /// algorithms are skipped.
+    /// Returns [`HpkeError::InvalidInput`] if the encoding is malformed.
+    pub fn decode_list(bytes: &[u8]) -> Result<Vec<KeyConfig>, HpkeError> {
+        let mut reader = bytes;
+        let mut configs = Vec::new();
+        while !reader.is_empty() {
+            let len = usize::from(take_u16(&mut reader)?);
+            let mut config_bytes = take(&mut reader, len)?;
+            let config = Self::read(&mut config_bytes)?;
+            if !config_bytes.is_empty() {
+                return Err(HpkeError::InvalidInput);
+            }
+            if let Ok(config) = config {
+                configs.push(config);
+            }
+        }
+        Ok(configs)
+    }
+
+    /// Select the first ciphersuite of this key configuration that the crypto
+    /// provider supports.
+    ///
+    /// Returns [`HpkeError::UnknownMode`] if there is none.
+    pub fn select_suite<Crypto: HpkeCrypto>(&self) -> Result<Ciphersuite, HpkeError> {
+        Crypto::supports_kem(self.kem)?;
+        self.symmetric
+            .iter()
+            .find(|(kdf, aead)| {
+                Crypto::supports_kdf(*kdf).is_ok() && Crypto::supports_aead(*aead).is_ok()
+            })
+            .map(|&(kdf, aead)| Ciphersuite::new(self.kem, kdf, aead))
+            .ok_or(HpkeError::UnknownMode)
+    }
+}
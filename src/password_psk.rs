@@ -0,0 +1,128 @@
+//! # Password-Based PSKs
+//!
+//! Derive the inputs of the PSK modes from a password such that a message
+//! can require both the recipient's private key and a password.
+//!
+//! Passwords don't have the 32 bytes of entropy the PSK modes require and
+//! must not be used as `psk` directly.
+//! Instead, the `psk` is derived from the password with Argon2id (or scrypt)
+//! and a random salt.
+//! The `psk_id` encodes the KDF, its parameters, and the salt such that the
+//! recipient can derive the same `psk` from the `psk_id` and the password.
+//!
+//! ```text
+//! struct {
+//!     opaque magic[7] = "HPKE-PW";
+//!     uint8 version = 1;
+//!     uint8 kdf;             // 1: Argon2id, 2: scrypt
+//!     select (kdf) {
+//!         case Argon2id: uint32 m_cost; uint32 t_cost; uint32 p_cost;
+//!         case scrypt: uint8 log_n; uint32 r; uint32 p;
+//!     };
+//!     opaque salt[16];
+//! } PasswordPskId;
+//! ```
+//!
+//! The `psk_id` is not secret and has to be sent along with the message.
+//! Because it is an input to the key schedule, tampering with the parameters
+//! makes the message fail to open.
+
+#[cfg(feature = "default-rng")]
+use hpke_rs_crypto::HpkeCrypto;
+use hpke_rs_crypto::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+use crate::{
+    protected_key::{PassphraseKdf, SALT_LEN},
+    util::take,
+    HpkeError,
+};
+
+const MAGIC: &[u8] = b"HPKE-PW";
+const VERSION: u8 = 1;
+const PSK_LEN: usize = 32;
+
+/// A `psk` and `psk_id` derived from a password.
+pub struct PasswordPsk {
+    psk: Vec<u8>,
+    psk_id: Vec<u8>,
+}
+
+impl Drop for PasswordPsk {
+    fn drop(&mut self) {
+        self.psk.zeroize();
+    }
+}
+
+impl std::fmt::Debug for PasswordPsk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PasswordPsk")
+            .field("psk_id", &self.psk_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PasswordPsk {
+    /// Derive a new `psk` from the `password` with the `kdf` and a random
+    /// salt.
+    #[cfg(feature = "default-rng")]
+    pub fn new<Crypto: HpkeCrypto>(
+        password: &[u8],
+        kdf: &PassphraseKdf,
+    ) -> Result<Self, HpkeError> {
+        Self::new_with_rng(password, kdf, &mut Crypto::prng())
+    }
+
+    /// [`new`](Self::new) with the salt drawn from `rng`.
+    pub fn new_with_rng(
+        password: &[u8],
+        kdf: &PassphraseKdf,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Self, HpkeError> {
+        let mut salt = [0u8; SALT_LEN];
+        rng.try_fill_bytes(&mut salt)
+            .map_err(|_| HpkeError::InsufficientRandomness)?;
+
+        let mut psk_id = MAGIC.to_vec();
+        psk_id.push(VERSION);
+        kdf.encode(&mut psk_id);
+        psk_id.extend_from_slice(&salt);
+
+        let mut psk = vec![0u8; PSK_LEN];
+        kdf.derive(password, &salt, &mut psk)?;
+        Ok(Self { psk, psk_id })
+    }
+
+    /// Derive the `psk` for the `psk_id` of a [`PasswordPsk`] from the
+    /// `password`.
+    ///
+    /// Returns [`HpkeError::InvalidInput`] if the `psk_id` is malformed.
+    pub fn from_psk_id(password: &[u8], psk_id: &[u8]) -> Result<Self, HpkeError> {
+        let mut reader = psk_id;
+        if take(&mut reader, MAGIC.len())? != MAGIC || take(&mut reader, 1)? != [VERSION] {
+            return Err(HpkeError::InvalidInput);
+        }
+        let kdf = PassphraseKdf::decode(&mut reader)?;
+        let salt = take(&mut reader, SALT_LEN)?;
+        if !reader.is_empty() {
+            return Err(HpkeError::InvalidInput);
+        }
+
+        let mut psk = vec![0u8; PSK_LEN];
+        kdf.derive(password, salt, &mut psk)?;
+        Ok(Self {
+            psk,
+            psk_id: psk_id.to_vec(),
+        })
+    }
+
+    /// The `psk` to use with the PSK modes.
+    pub fn psk(&self) -> &[u8] {
+        &self.psk
+    }
+
+    /// The `psk_id` to use with the PSK modes and to send to the recipient.
+    pub fn psk_id(&self) -> &[u8] {
+        &self.psk_id
+    }
+}
@@ -0,0 +1,75 @@
+//! # Public Key Cache
+//!
+//! A bounded cache of prepared recipient public keys.
+//!
+//! Senders that encrypt many messages to the same few recipients can enable
+//! the cache with [`Hpke::with_public_key_cache`](crate::Hpke::with_public_key_cache).
+//! Every encapsulation to a cached recipient then skips decoding and
+//! validating the public key (see [`HpkeCrypto::kem_prepare_pk`]).
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use hpke_rs_crypto::{types::KemAlgorithm, HpkeCrypto};
+
+use crate::HpkeError;
+
+/// A cache of prepared public keys, indexed by their encoding.
+///
+/// When the cache is full an arbitrary entry is evicted.
+pub(crate) struct PublicKeyCache<Crypto: HpkeCrypto> {
+    capacity: usize,
+    keys: RwLock<HashMap<Vec<u8>, Arc<Crypto::KemPublicKey>>>,
+}
+
+impl<Crypto: HpkeCrypto> std::fmt::Debug for PublicKeyCache<Crypto> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PublicKeyCache")
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+impl<Crypto: HpkeCrypto> PublicKeyCache<Crypto> {
+    /// Create a new cache that holds at most `capacity` keys.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            keys: RwLock::new(HashMap::with_capacity(capacity)),
+        }
+    }
+
+    /// Get the prepared public key `pk`, preparing and caching it if it isn't
+    /// in the cache yet.
+    ///
+    /// Invalid public keys are never cached.
+    pub(crate) fn get(
+        &self,
+        alg: KemAlgorithm,
+        pk: &[u8],
+    ) -> Result<Arc<Crypto::KemPublicKey>, HpkeError> {
+        if let Some(prepared) = self
+            .keys
+            .read()
+            .map_err(|_| HpkeError::LockPoisoned)?
+            .get(pk)
+        {
+            return Ok(prepared.clone());
+        }
+
+        let prepared = Arc::new(Crypto::kem_prepare_pk(alg, pk)?);
+        if self.capacity == 0 {
+            return Ok(prepared);
+        }
+        let mut keys = self.keys.write().map_err(|_| HpkeError::LockPoisoned)?;
+        if keys.len() >= self.capacity && !keys.contains_key(pk) {
+            if let Some(evict) = keys.keys().next().cloned() {
+                keys.remove(&evict);
+            }
+        }
+        keys.insert(pk.to_vec(), prepared.clone());
+        Ok(prepared)
+    }
+}
@@ -0,0 +1,157 @@
+//! # Ciphersuite Policy
+//!
+//! A [`Policy`] restricts the KEMs, KDFs, and AEADs that may be used, either
+//! through allow and deny lists or a minimum security level.
+//!
+//! A policy can be set on a single configuration with
+//! [`Hpke::with_policy`](crate::Hpke::with_policy) or installed process-wide
+//! with [`Policy::install`].
+//! Every operation of a configuration that violates a policy fails with
+//! [`HpkeError::NotAllowedByPolicy`].
+//!
+//! ```
+//! use hpke_rs::policy::Policy;
+//! use hpke_rs_crypto::types::KemAlgorithm;
+//!
+//! Policy::new()
+//!     .deny_kem(KemAlgorithm::DhKemP256)
+//!     .min_security_level(128)
+//!     .install();
+//! # Policy::uninstall();
+//! ```
+
+use std::sync::RwLock;
+
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+
+use crate::{ciphersuite::Ciphersuite, HpkeError};
+
+static INSTALLED: RwLock<Option<Policy>> = RwLock::new(None);
+
+/// A ciphersuite policy.
+///
+/// The default policy allows everything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Policy {
+    allowed_kems: Option<Vec<KemAlgorithm>>,
+    allowed_kdfs: Option<Vec<KdfAlgorithm>>,
+    allowed_aeads: Option<Vec<AeadAlgorithm>>,
+    denied_kems: Vec<KemAlgorithm>,
+    denied_kdfs: Vec<KdfAlgorithm>,
+    denied_aeads: Vec<AeadAlgorithm>,
+    min_security_level: u16,
+}
+
+impl Policy {
+    /// Create a new policy that allows everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only allow the KEMs in `kems`.
+    pub fn allow_kems(mut self, kems: &[KemAlgorithm]) -> Self {
+        self.allowed_kems = Some(kems.to_vec());
+        self
+    }
+
+    /// Only allow the KDFs in `kdfs`.
+    pub fn allow_kdfs(mut self, kdfs: &[KdfAlgorithm]) -> Self {
+        self.allowed_kdfs = Some(kdfs.to_vec());
+        self
+    }
+
+    /// Only allow the AEADs in `aeads`.
+    pub fn allow_aeads(mut self, aeads: &[AeadAlgorithm]) -> Self {
+        self.allowed_aeads = Some(aeads.to_vec());
+        self
+    }
+
+    /// Deny the KEM `kem`.
+    pub fn deny_kem(mut self, kem: KemAlgorithm) -> Self {
+        self.denied_kems.push(kem);
+        self
+    }
+
+    /// Deny the KDF `kdf`.
+    pub fn deny_kdf(mut self, kdf: KdfAlgorithm) -> Self {
+        self.denied_kdfs.push(kdf);
+        self
+    }
+
+    /// Deny the AEAD `aead`.
+    pub fn deny_aead(mut self, aead: AeadAlgorithm) -> Self {
+        self.denied_aeads.push(aead);
+        self
+    }
+
+    /// Require a security level of at least `bits` for every algorithm.
+    ///
+    /// The security levels are 128 bits for P-256, X25519, HKDF-SHA256, and
+    /// AES-128-GCM, 192 bits for P-384, X448, and HKDF-SHA384, and 256 bits
+    /// for P-521, HKDF-SHA512, AES-256-GCM, and ChaCha20Poly1305.
+    /// The export-only AEAD meets every level.
+    /// See [`Ciphersuite::security_level`].
+    pub fn min_security_level(mut self, bits: u16) -> Self {
+        self.min_security_level = bits;
+        self
+    }
+
+    /// Check that the algorithms are allowed by this policy.
+    ///
+    /// Returns [`HpkeError::NotAllowedByPolicy`] otherwise.
+    pub fn check(
+        &self,
+        kem: KemAlgorithm,
+        kdf: KdfAlgorithm,
+        aead: AeadAlgorithm,
+    ) -> Result<(), HpkeError> {
+        let allowed = allowed(&self.allowed_kems, &self.denied_kems, kem)
+            && allowed(&self.allowed_kdfs, &self.denied_kdfs, kdf)
+            && allowed(&self.allowed_aeads, &self.denied_aeads, aead)
+            && Ciphersuite::new(kem, kdf, aead).security_level() >= self.min_security_level;
+        if !allowed {
+            return Err(HpkeError::NotAllowedByPolicy);
+        }
+        Ok(())
+    }
+
+    /// Install this policy process-wide.
+    ///
+    /// It applies to all configurations in addition to their own policy and
+    /// replaces a previously installed policy.
+    pub fn install(self) {
+        // A panic while holding the lock can't leave the policy in an
+        // inconsistent state.
+        *INSTALLED.write().unwrap_or_else(|e| e.into_inner()) = Some(self);
+    }
+
+    /// Remove the process-wide policy.
+    pub fn uninstall() {
+        *INSTALLED.write().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Get a copy of the process-wide policy if one is installed.
+    pub fn installed() -> Option<Policy> {
+        INSTALLED.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Check the algorithms against the process-wide policy.
+    pub(crate) fn check_installed(
+        kem: KemAlgorithm,
+        kdf: KdfAlgorithm,
+        aead: AeadAlgorithm,
+    ) -> Result<(), HpkeError> {
+        match INSTALLED
+            .read()
+            .map_err(|_| HpkeError::LockPoisoned)?
+            .as_ref()
+        {
+            Some(policy) => policy.check(kem, kdf, aead),
+            None => Ok(()),
+        }
+    }
+}
+
+fn allowed<T: PartialEq>(allow: &Option<Vec<T>>, deny: &[T], alg: T) -> bool {
+    allow.as_ref().is_none_or(|allow| allow.contains(&alg)) && !deny.contains(&alg)
+}
@@ -0,0 +1,336 @@
+//! # Passphrase-Protected Private Keys
+//!
+//! Encrypt-at-rest encoding for [`HpkePrivateKey`]s.
+//!
+//! A wrapping key is derived from a passphrase with Argon2id (or scrypt where
+//! Argon2id is not an option) and used to encrypt the private key with an
+//! AEAD.
+//! The encoding starts with a versioned header that records the KDF, its
+//! parameters, and the salt.
+//! The header is authenticated as additional data.
+//!
+//! ```text
+//! struct {
+//!     opaque magic[7] = "HPKE-SK";
+//!     uint8 version = 1;
+//!     uint8 kdf;             // 1: Argon2id, 2: scrypt
+//!     select (kdf) {
+//!         case Argon2id: uint32 m_cost; uint32 t_cost; uint32 p_cost;
+//!         case scrypt: uint8 log_n; uint32 r; uint32 p;
+//!     };
+//!     opaque salt[16];
+//!     uint16 aead_id;
+//!     opaque nonce[Nn];
+//!     opaque ciphertext[];
+//! } ProtectedPrivateKey;
+//! ```
+//!
+//! The KDF parameters of an encoding are untrusted.
+//! They are checked against [`KdfLimits`] before the KDF runs, such that a
+//! crafted header can't make decoding exhaust memory or CPU.
+
+use hpke_rs_crypto::{types::AeadAlgorithm, CryptoRng, HpkeCrypto, RngCore};
+use zeroize::Zeroize;
+
+use crate::{
+    util::{take, take_u16, take_u32},
+    HpkeError, HpkePrivateKey,
+};
+
+const MAGIC: &[u8] = b"HPKE-SK";
+const VERSION: u8 = 1;
+pub(crate) const SALT_LEN: usize = 16;
+
+const KDF_ARGON2ID: u8 = 1;
+const KDF_SCRYPT: u8 = 2;
+
+/// The passphrase-based KDF used to derive the wrapping key.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PassphraseKdf {
+    /// Argon2id (version 0x13).
+    Argon2id {
+        /// Memory cost in KiB.
+        m_cost: u32,
+        /// Number of iterations.
+        t_cost: u32,
+        /// Degree of parallelism.
+        p_cost: u32,
+    },
+
+    /// scrypt
+    Scrypt {
+        /// log2 of the CPU/memory cost parameter N.
+        log_n: u8,
+        /// Block size.
+        r: u32,
+        /// Parallelization parameter.
+        p: u32,
+    },
+}
+
+impl PassphraseKdf {
+    /// Argon2id with the default parameters (19 MiB, 2 iterations, 1 lane).
+    pub const ARGON2ID: Self = Self::Argon2id {
+        m_cost: argon2::Params::DEFAULT_M_COST,
+        t_cost: argon2::Params::DEFAULT_T_COST,
+        p_cost: argon2::Params::DEFAULT_P_COST,
+    };
+
+    /// scrypt with the recommended parameters (N = 2^17, r = 8, p = 1).
+    pub const SCRYPT: Self = Self::Scrypt {
+        log_n: scrypt::Params::RECOMMENDED_LOG_N,
+        r: scrypt::Params::RECOMMENDED_R,
+        p: scrypt::Params::RECOMMENDED_P,
+    };
+
+    pub(crate) fn derive(
+        &self,
+        passphrase: &[u8],
+        salt: &[u8],
+        out: &mut [u8],
+    ) -> Result<(), HpkeError> {
+        match *self {
+            PassphraseKdf::Argon2id {
+                m_cost,
+                t_cost,
+                p_cost,
+            } => {
+                let params = argon2::Params::new(m_cost, t_cost, p_cost, Some(out.len()))
+                    .map_err(|_| HpkeError::InvalidConfig)?;
+                argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+                    .hash_password_into(passphrase, salt, out)
+                    .map_err(|_| HpkeError::InvalidConfig)
+            }
+            PassphraseKdf::Scrypt { log_n, r, p } => {
+                let params = scrypt::Params::new(log_n, r, p, out.len())
+                    .map_err(|_| HpkeError::InvalidConfig)?;
+                scrypt::scrypt(passphrase, salt, &params, out).map_err(|_| HpkeError::InvalidConfig)
+            }
+        }
+    }
+
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        match *self {
+            PassphraseKdf::Argon2id {
+                m_cost,
+                t_cost,
+                p_cost,
+            } => {
+                out.push(KDF_ARGON2ID);
+                out.extend_from_slice(&m_cost.to_be_bytes());
+                out.extend_from_slice(&t_cost.to_be_bytes());
+                out.extend_from_slice(&p_cost.to_be_bytes());
+            }
+            PassphraseKdf::Scrypt { log_n, r, p } => {
+                out.push(KDF_SCRYPT);
+                out.push(log_n);
+                out.extend_from_slice(&r.to_be_bytes());
+                out.extend_from_slice(&p.to_be_bytes());
+            }
+        }
+    }
+
+    /// Decode the KDF and its parameters with the default [`KdfLimits`].
+    pub(crate) fn decode(reader: &mut &[u8]) -> Result<Self, HpkeError> {
+        Self::decode_with_limits(reader, &KdfLimits::default())
+    }
+
+    /// Decode the KDF and its parameters.
+    ///
+    /// Returns [`HpkeError::InvalidInput`] if a parameter exceeds `limits`.
+    pub(crate) fn decode_with_limits(
+        reader: &mut &[u8],
+        limits: &KdfLimits,
+    ) -> Result<Self, HpkeError> {
+        let kdf = match take(reader, 1)?[0] {
+            KDF_ARGON2ID => Ok(PassphraseKdf::Argon2id {
+                m_cost: take_u32(reader)?,
+                t_cost: take_u32(reader)?,
+                p_cost: take_u32(reader)?,
+            }),
+            KDF_SCRYPT => Ok(PassphraseKdf::Scrypt {
+                log_n: take(reader, 1)?[0],
+                r: take_u32(reader)?,
+                p: take_u32(reader)?,
+            }),
+            _ => Err(HpkeError::InvalidInput),
+        }?;
+        if !kdf.is_within(limits) {
+            return Err(HpkeError::InvalidInput);
+        }
+        Ok(kdf)
+    }
+
+    fn is_within(&self, limits: &KdfLimits) -> bool {
+        match *self {
+            PassphraseKdf::Argon2id {
+                m_cost,
+                t_cost,
+                p_cost,
+            } => {
+                m_cost <= limits.max_m_cost
+                    && t_cost <= limits.max_t_cost
+                    && p_cost <= limits.max_p_cost
+            }
+            PassphraseKdf::Scrypt { log_n, r, p } => {
+                log_n <= limits.max_log_n && r <= limits.max_r && p <= limits.max_p
+            }
+        }
+    }
+}
+
+impl Default for PassphraseKdf {
+    fn default() -> Self {
+        Self::ARGON2ID
+    }
+}
+
+/// Upper bounds on the [`PassphraseKdf`] parameters that are accepted when
+/// decoding.
+///
+/// The [default](Self::default) allows Argon2id with up to 1 GiB of memory and
+/// scrypt with up to N = 2^20 and r = 8 (1 GiB), i.e. well above the
+/// [`PassphraseKdf::ARGON2ID`] and [`PassphraseKdf::SCRYPT`] parameters.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct KdfLimits {
+    /// Maximum Argon2id memory cost in KiB.
+    pub max_m_cost: u32,
+
+    /// Maximum number of Argon2id iterations.
+    pub max_t_cost: u32,
+
+    /// Maximum Argon2id degree of parallelism.
+    pub max_p_cost: u32,
+
+    /// Maximum log2 of the scrypt cost parameter N.
+    pub max_log_n: u8,
+
+    /// Maximum scrypt block size.
+    pub max_r: u32,
+
+    /// Maximum scrypt parallelization parameter.
+    pub max_p: u32,
+}
+
+impl Default for KdfLimits {
+    fn default() -> Self {
+        Self {
+            max_m_cost: 1 << 20,
+            max_t_cost: 16,
+            max_p_cost: 16,
+            max_log_n: 20,
+            max_r: 8,
+            max_p: 16,
+        }
+    }
+}
+
+/// Parameters for protecting a private key with a passphrase.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ProtectionParams {
+    /// The KDF used to derive the wrapping key from the passphrase.
+    pub kdf: PassphraseKdf,
+
+    /// The AEAD used to encrypt the private key.
+    pub aead: AeadAlgorithm,
+}
+
+impl Default for ProtectionParams {
+    fn default() -> Self {
+        Self {
+            kdf: PassphraseKdf::default(),
+            aead: AeadAlgorithm::ChaCha20Poly1305,
+        }
+    }
+}
+
+impl HpkePrivateKey {
+    /// Encrypt this private key with a key derived from `passphrase`.
+    ///
+    /// Returns the encoded, protected private key that can be decrypted with
+    /// [`HpkePrivateKey::from_protected`].
+    #[cfg(feature = "default-rng")]
+    pub fn to_protected<Crypto: HpkeCrypto>(
+        &self,
+        passphrase: &[u8],
+        params: &ProtectionParams,
+    ) -> Result<Vec<u8>, HpkeError> {
+        self.to_protected_with_rng::<Crypto>(passphrase, params, &mut Crypto::prng())
+    }
+
+    /// [`to_protected`](Self::to_protected) with the salt and nonce drawn from
+    /// `rng`.
+    pub fn to_protected_with_rng<Crypto: HpkeCrypto>(
+        &self,
+        passphrase: &[u8],
+        params: &ProtectionParams,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Vec<u8>, HpkeError> {
+        if params.aead == AeadAlgorithm::HpkeExport {
+            return Err(HpkeError::InvalidConfig);
+        }
+        Crypto::supports_aead(params.aead)?;
+
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = vec![0u8; Crypto::aead_nonce_length(params.aead)];
+        rng.try_fill_bytes(&mut salt)
+            .map_err(|_| HpkeError::InsufficientRandomness)?;
+        rng.try_fill_bytes(&mut nonce)
+            .map_err(|_| HpkeError::InsufficientRandomness)?;
+
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+        params.kdf.encode(&mut out);
+        out.extend_from_slice(&salt);
+        out.extend_from_slice(&(params.aead as u16).to_be_bytes());
+        out.extend_from_slice(&nonce);
+
+        let mut key = vec![0u8; Crypto::aead_key_length(params.aead)];
+        params.kdf.derive(passphrase, &salt, &mut key)?;
+        let ct = Crypto::aead_seal(params.aead, &key, &nonce, &out, &self.value);
+        key.zeroize();
+        out.extend_from_slice(&ct?);
+        Ok(out)
+    }
+
+    /// Decrypt a private key that was protected with
+    /// [`HpkePrivateKey::to_protected`].
+    ///
+    /// Returns an [`HpkeError::OpenError`] if the passphrase is wrong or the
+    /// encoding has been tampered with, and an [`HpkeError::InvalidInput`] if
+    /// the KDF parameters exceed the default [`KdfLimits`].
+    pub fn from_protected<Crypto: HpkeCrypto>(
+        protected: &[u8],
+        passphrase: &[u8],
+    ) -> Result<Self, HpkeError> {
+        Self::from_protected_with_limits::<Crypto>(protected, passphrase, &KdfLimits::default())
+    }
+
+    /// [`from_protected`](Self::from_protected) with the KDF parameters
+    /// limited to `limits` instead of the default [`KdfLimits`].
+    pub fn from_protected_with_limits<Crypto: HpkeCrypto>(
+        protected: &[u8],
+        passphrase: &[u8],
+        limits: &KdfLimits,
+    ) -> Result<Self, HpkeError> {
+        let mut reader = protected;
+        if take(&mut reader, MAGIC.len())? != MAGIC || take(&mut reader, 1)? != [VERSION] {
+            return Err(HpkeError::InvalidInput);
+        }
+        let kdf = PassphraseKdf::decode_with_limits(&mut reader, limits)?;
+        let salt = take(&mut reader, SALT_LEN)?;
+        let aead = AeadAlgorithm::try_from(take_u16(&mut reader)?)?;
+        if aead == AeadAlgorithm::HpkeExport {
+            return Err(HpkeError::InvalidInput);
+        }
+        Crypto::supports_aead(aead)?;
+        let nonce = take(&mut reader, Crypto::aead_nonce_length(aead))?;
+        let header = &protected[..protected.len() - reader.len()];
+
+        let mut key = vec![0u8; Crypto::aead_key_length(aead)];
+        kdf.derive(passphrase, salt, &mut key)?;
+        let sk = Crypto::aead_open(aead, &key, nonce, header, reader);
+        key.zeroize();
+        Ok(Self::new(sk?))
+    }
+}
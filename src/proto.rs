@@ -0,0 +1,195 @@
+//! # Protobuf Envelopes
+//!
+//! With the `protobuf` feature, [`Envelope`]s and [`MultiEnvelope`]s convert
+//! to and from [prost] messages such that gRPC-based services can carry HPKE
+//! ciphertexts as message fields.
+//!
+//! The messages are the ones of the `hpke.v1` package in `proto/hpke.proto`.
+//! An empty `key_id` means that the key id is not present.
+//!
+//! [prost]: https://docs.rs/prost
+
+use prost::Message;
+
+use crate::{
+    ciphersuite::Ciphersuite,
+    envelope::{self, Envelope, MultiEnvelope},
+    HpkeError, KeyId,
+};
+
+/// The `hpke.v1.Envelope` message, a single-shot HPKE message.
+#[derive(Clone, PartialEq, Message)]
+pub struct ProtoEnvelope {
+    /// The KEM identifier.
+    #[prost(uint32, tag = "1")]
+    pub kem_id: u32,
+    /// The KDF identifier.
+    #[prost(uint32, tag = "2")]
+    pub kdf_id: u32,
+    /// The AEAD identifier.
+    #[prost(uint32, tag = "3")]
+    pub aead_id: u32,
+    /// The key id of the recipient key, or empty.
+    #[prost(bytes = "vec", tag = "4")]
+    pub key_id: Vec<u8>,
+    /// The encapsulated secret.
+    #[prost(bytes = "vec", tag = "5")]
+    pub enc: Vec<u8>,
+    /// The ciphertext.
+    #[prost(bytes = "vec", tag = "6")]
+    pub ciphertext: Vec<u8>,
+}
+
+/// The `hpke.v1.Recipient` message, a recipient of a multi-recipient
+/// envelope.
+#[derive(Clone, PartialEq, Message)]
+pub struct ProtoRecipient {
+    /// The key id of the recipient key, or empty.
+    #[prost(bytes = "vec", tag = "1")]
+    pub key_id: Vec<u8>,
+    /// The encapsulated secret for this recipient.
+    #[prost(bytes = "vec", tag = "2")]
+    pub enc: Vec<u8>,
+    /// The ciphertext for this recipient.
+    #[prost(bytes = "vec", tag = "3")]
+    pub ciphertext: Vec<u8>,
+}
+
+/// The `hpke.v1.MultiEnvelope` message, a single-shot HPKE message to many
+/// recipients.
+#[derive(Clone, PartialEq, Message)]
+pub struct ProtoMultiEnvelope {
+    /// The KEM identifier.
+    #[prost(uint32, tag = "1")]
+    pub kem_id: u32,
+    /// The KDF identifier.
+    #[prost(uint32, tag = "2")]
+    pub kdf_id: u32,
+    /// The AEAD identifier.
+    #[prost(uint32, tag = "3")]
+    pub aead_id: u32,
+    /// The recipients.
+    #[prost(message, repeated, tag = "4")]
+    pub recipients: Vec<ProtoRecipient>,
+}
+
+impl From<&Envelope> for ProtoEnvelope {
+    fn from(envelope: &Envelope) -> Self {
+        let (kem_id, kdf_id, aead_id) = envelope.suite.ids();
+        Self {
+            kem_id: kem_id.into(),
+            kdf_id: kdf_id.into(),
+            aead_id: aead_id.into(),
+            key_id: encode_key_id(&envelope.key_id),
+            enc: envelope.enc.clone(),
+            ciphertext: envelope.ciphertext.clone(),
+        }
+    }
+}
+
+impl TryFrom<ProtoEnvelope> for Envelope {
+    type Error = HpkeError;
+
+    fn try_from(envelope: ProtoEnvelope) -> Result<Self, HpkeError> {
+        Ok(Self {
+            suite: suite(envelope.kem_id, envelope.kdf_id, envelope.aead_id)?,
+            key_id: decode_key_id(&envelope.key_id)?,
+            enc: envelope.enc,
+            ciphertext: envelope.ciphertext,
+        })
+    }
+}
+
+impl From<&MultiEnvelope> for ProtoMultiEnvelope {
+    fn from(envelope: &MultiEnvelope) -> Self {
+        let (kem_id, kdf_id, aead_id) = envelope.suite.ids();
+        Self {
+            kem_id: kem_id.into(),
+            kdf_id: kdf_id.into(),
+            aead_id: aead_id.into(),
+            recipients: envelope
+                .recipients
+                .iter()
+                .map(|recipient| ProtoRecipient {
+                    key_id: encode_key_id(&recipient.key_id),
+                    enc: recipient.enc.clone(),
+                    ciphertext: recipient.ciphertext.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<ProtoMultiEnvelope> for MultiEnvelope {
+    type Error = HpkeError;
+
+    fn try_from(envelope: ProtoMultiEnvelope) -> Result<Self, HpkeError> {
+        Ok(Self {
+            suite: suite(envelope.kem_id, envelope.kdf_id, envelope.aead_id)?,
+            recipients: envelope
+                .recipients
+                .into_iter()
+                .map(|recipient| {
+                    Ok(envelope::Recipient {
+                        key_id: decode_key_id(&recipient.key_id)?,
+                        enc: recipient.enc,
+                        ciphertext: recipient.ciphertext,
+                    })
+                })
+                .collect::<Result<_, HpkeError>>()?,
+        })
+    }
+}
+
+impl Envelope {
+    /// Encode this envelope as protobuf [`ProtoEnvelope`].
+    pub fn to_protobuf(&self) -> Vec<u8> {
+        ProtoEnvelope::from(self).encode_to_vec()
+    }
+
+    /// Decode an envelope from a protobuf [`ProtoEnvelope`].
+    ///
+    /// Returns [`HpkeError::UnknownMode`] if an algorithm is not registered
+    /// and [`HpkeError::InvalidInput`] if the encoding is malformed.
+    pub fn from_protobuf(bytes: &[u8]) -> Result<Self, HpkeError> {
+        ProtoEnvelope::decode(bytes)
+            .map_err(|_| HpkeError::InvalidInput)?
+            .try_into()
+    }
+}
+
+impl MultiEnvelope {
+    /// Encode this envelope as protobuf [`ProtoMultiEnvelope`].
+    pub fn to_protobuf(&self) -> Vec<u8> {
+        ProtoMultiEnvelope::from(self).encode_to_vec()
+    }
+
+    /// Decode an envelope from a protobuf [`ProtoMultiEnvelope`].
+    ///
+    /// Returns [`HpkeError::UnknownMode`] if an algorithm is not registered
+    /// and [`HpkeError::InvalidInput`] if the encoding is malformed.
+    pub fn from_protobuf(bytes: &[u8]) -> Result<Self, HpkeError> {
+        ProtoMultiEnvelope::decode(bytes)
+            .map_err(|_| HpkeError::InvalidInput)?
+            .try_into()
+    }
+}
+
+fn suite(kem_id: u32, kdf_id: u32, aead_id: u32) -> Result<Ciphersuite, HpkeError> {
+    let id = |id: u32| u16::try_from(id).map_err(|_| HpkeError::UnknownMode);
+    Ciphersuite::from_ids(id(kem_id)?, id(kdf_id)?, id(aead_id)?)
+}
+
+fn encode_key_id(key_id: &Option<KeyId>) -> Vec<u8> {
+    key_id
+        .as_ref()
+        .map(|key_id| key_id.as_slice().to_vec())
+        .unwrap_or_default()
+}
+
+fn decode_key_id(key_id: &[u8]) -> Result<Option<KeyId>, HpkeError> {
+    if key_id.is_empty() {
+        return Ok(None);
+    }
+    KeyId::try_from(key_id).map(Some)
+}
@@ -0,0 +1,136 @@
+//! # Reordering Receiver
+//!
+//! [`Context::open`] has to see the ciphertexts in the order they were
+//! sealed.
+//! On unreliable transports they may arrive out of order.
+//! With a reorder window, [`Context::open_in_order`] opens ciphertexts that
+//! arrive ahead of the expected sequence number right away, buffers their
+//! plain texts, and releases them in order once the gap is filled.
+//! Because only authenticated plain texts are buffered, a forged ciphertext
+//! can't take the place of the genuine one for its sequence number.
+
+use std::collections::BTreeMap;
+
+use hpke_rs_crypto::HpkeCrypto;
+use zeroize::Zeroizing;
+
+use crate::{audit, next_seq, Context, HpkeError, Plaintext};
+
+/// The plain texts of the ciphertexts that arrived early.
+pub(crate) struct ReorderWindow {
+    depth: u32,
+    pending: BTreeMap<u32, Zeroizing<Plaintext>>,
+}
+
+impl<Crypto: HpkeCrypto> Context<Crypto> {
+    /// Buffer up to `depth` ciphertexts that arrive ahead of the expected
+    /// sequence number in [`open_in_order`](Self::open_in_order).
+    ///
+    /// Set to `None` (the default) to disable the reorder window, which
+    /// drops any buffered ciphertexts.
+    /// A reorder window can't be combined with automatic
+    /// [ratcheting](Self::set_ratchet_interval) and returns
+    /// [`HpkeError::InvalidConfig`] in that case.
+    pub fn set_reorder_window(&mut self, depth: Option<u32>) -> Result<(), HpkeError> {
+        if depth.is_some() && self.ratchet_interval.is_some() {
+            return Err(HpkeError::InvalidConfig);
+        }
+        self.reorder = depth.map(|depth| {
+            Box::new(ReorderWindow {
+                depth,
+                pending: BTreeMap::new(),
+            })
+        });
+        Ok(())
+    }
+
+    /// Open the ciphertext that was sealed with the sequence number `seq`.
+    ///
+    /// If `seq` is the expected sequence number, the ciphertext is opened and
+    /// its plain text is returned together with the buffered plain texts that
+    /// follow it, in order.
+    /// If `seq` is ahead by at most the depth of the
+    /// [reorder window](Self::set_reorder_window), the ciphertext is opened
+    /// right away, its plain text is buffered, and nothing is returned.
+    ///
+    /// Returns [`HpkeError::InvalidInput`] for sequence numbers that were
+    /// already opened, buffered, or skipped and for those beyond the reorder
+    /// window.
+    /// A ciphertext that fails to open returns the error of
+    /// [`open`](Self::open) (and poisons the context if
+    /// [enabled](Self::set_poison_on_failure)) whether it is expected or
+    /// ahead, such that a later genuine ciphertext for the same sequence
+    /// number still opens.
+    pub fn open_in_order(
+        &mut self,
+        seq: u32,
+        aad: &[u8],
+        cipher_txt: &[u8],
+    ) -> Result<Vec<Plaintext>, HpkeError> {
+        self.check_poisoned()?;
+        let expected = self.sequence_number;
+        if seq < expected {
+            return Err(HpkeError::InvalidInput);
+        }
+        if seq > expected {
+            let window = self.reorder.as_ref().ok_or(HpkeError::InvalidInput)?;
+            if seq - expected > window.depth || window.pending.contains_key(&seq) {
+                return Err(HpkeError::InvalidInput);
+            }
+            let plain_txt = Zeroizing::new(self.open_ahead(seq, aad, cipher_txt)?);
+            if let Some(window) = self.reorder.as_mut() {
+                window.pending.insert(seq, plain_txt);
+            }
+            return Ok(Vec::new());
+        }
+
+        let mut plain_txts = vec![self.open(aad, cipher_txt)?];
+        if let Some(window) = self.reorder.as_mut() {
+            // Drop what was skipped in the meantime.
+            window.pending = window.pending.split_off(&self.sequence_number);
+        }
+        while let Some(plain_txt) = self.release_pending() {
+            plain_txts.push(plain_txt);
+        }
+        Ok(plain_txts)
+    }
+
+    /// The number of buffered plain texts.
+    pub fn pending(&self) -> usize {
+        self.reorder
+            .as_ref()
+            .map_or(0, |window| window.pending.len())
+    }
+
+    /// Open the `cipher_txt` that was sealed with the sequence number `seq`
+    /// ahead of the current one, without changing the context.
+    fn open_ahead(
+        &mut self,
+        seq: u32,
+        aad: &[u8],
+        cipher_txt: &[u8],
+    ) -> Result<Plaintext, HpkeError> {
+        self.audited_at(seq, audit::AuditOperation::Open, |ctx| {
+            ctx.check_payload(aad.len(), ctx.plain_txt_len(cipher_txt.len()))?;
+            next_seq::<Crypto>(ctx.config.aead_id, seq)?;
+            Crypto::aead_cipher_open(ctx.cipher()?, &ctx.nonce_at(seq)?, aad, cipher_txt)
+                .map_err(|e| ctx.poison(e))
+        })
+    }
+
+    /// Take the buffered plain text for the current sequence number and move
+    /// past it.
+    ///
+    /// The next sequence number is computed before anything is taken out of
+    /// the window, such that an exhausted context keeps the plain text
+    /// buffered instead of dropping it.
+    /// This can't happen for plain texts that are buffered because
+    /// [`open_ahead`](Self::open_ahead) checks the sequence number.
+    fn release_pending(&mut self) -> Option<Plaintext> {
+        let seq = self.sequence_number;
+        let next = self.next_seq().ok()?;
+        let mut plain_txt = self.reorder.as_mut()?.pending.remove(&seq)?;
+        self.sequence_number = next;
+        Some(std::mem::take(&mut *plain_txt))
+    }
+}
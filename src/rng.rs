@@ -0,0 +1,172 @@
+//! # Randomness
+//!
+//! An [`Hpke`](crate::Hpke) configuration or a [`Kem`](crate::kem::Kem)
+//! draws its randomness from the PRNG of the crypto provider
+//! ([`HpkeCrypto::prng`]) or from an RNG that is injected with `with_rng`.
+//!
+//! Without the `default-rng` feature the provider's PRNG is never used and
+//! hpke-rs doesn't depend on `getrandom`, e.g. for firmware or enclaves
+//! without an OS entropy source.
+//! Every operation that needs randomness then fails with
+//! [`HpkeError::InsufficientRandomness`] unless an RNG is injected.
+//! Key pairs generated with an injected RNG are derived from `Nsk` random
+//! bytes with `DeriveKeyPair`.
+//!
+//! [`HmacDrbg`](crate::drbg::HmacDrbg) is a seedable DRBG that can be
+//! injected for reproducible runs or on top of an external entropy source.
+
+#[cfg(not(feature = "default-rng"))]
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "default-rng")]
+use std::sync::{OnceLock, RwLock};
+
+#[cfg(all(feature = "default-rng", feature = "hpke-test-prng"))]
+use hpke_rs_crypto::HpkeTestRng;
+use hpke_rs_crypto::{types::KemAlgorithm, CryptoRng, HpkeCrypto, RngCore};
+
+use crate::{kem, HpkeError};
+
+/// A cryptographically secure RNG that can be injected into an
+/// [`Hpke`](crate::Hpke) configuration or a [`Kem`](crate::kem::Kem).
+pub trait HpkeRng: RngCore + CryptoRng + Send {}
+
+impl<T: RngCore + CryptoRng + Send> HpkeRng for T {}
+
+/// The source of randomness.
+pub(crate) enum Prng<Crypto: HpkeCrypto> {
+    /// The PRNG of the crypto provider, created when it is first used.
+    #[cfg(feature = "default-rng")]
+    Provider(OnceLock<RwLock<Crypto::HpkePrng>>),
+    /// An injected RNG that is shared by all clones.
+    Injected(Arc<Mutex<dyn HpkeRng>>),
+    /// No randomness.
+    #[cfg(not(feature = "default-rng"))]
+    None(PhantomData<Crypto>),
+}
+
+impl<Crypto: HpkeCrypto> Prng<Crypto> {
+    /// The default source of randomness.
+    pub(crate) fn new() -> Self {
+        #[cfg(feature = "default-rng")]
+        return Self::Provider(OnceLock::new());
+        #[cfg(not(feature = "default-rng"))]
+        return Self::None(PhantomData);
+    }
+
+    /// The PRNG of the crypto provider, created on first use.
+    #[cfg(feature = "default-rng")]
+    fn provider(prng: &OnceLock<RwLock<Crypto::HpkePrng>>) -> &RwLock<Crypto::HpkePrng> {
+        prng.get_or_init(|| RwLock::new(Crypto::prng()))
+    }
+
+    /// Use the injected `rng`.
+    pub(crate) fn injected(rng: impl HpkeRng + 'static) -> Self {
+        Self::Injected(Arc::new(Mutex::new(rng)))
+    }
+
+    /// Fill `out` with random bytes.
+    pub(crate) fn fill(&self, out: &mut [u8]) -> Result<(), HpkeError> {
+        match self {
+            #[cfg(feature = "default-rng")]
+            Self::Provider(prng) => {
+                let mut prng = Self::provider(prng)
+                    .write()
+                    .map_err(|_| HpkeError::LockPoisoned)?;
+                #[cfg(feature = "hpke-test-prng")]
+                return prng
+                    .try_fill_test_bytes(out)
+                    .map_err(|_| HpkeError::InsufficientRandomness);
+                #[cfg(not(feature = "hpke-test-prng"))]
+                return prng
+                    .try_fill_bytes(out)
+                    .map_err(|_| HpkeError::InsufficientRandomness);
+            }
+            Self::Injected(rng) => rng
+                .lock()
+                .map_err(|_| HpkeError::LockPoisoned)?
+                .try_fill_bytes(out)
+                .map_err(|_| HpkeError::InsufficientRandomness),
+            #[cfg(not(feature = "default-rng"))]
+            Self::None(_) => Err(HpkeError::InsufficientRandomness),
+        }
+    }
+
+    /// `len` random bytes.
+    pub(crate) fn random(&self, len: usize) -> Result<Vec<u8>, HpkeError> {
+        let mut out = vec![0u8; len];
+        self.fill(&mut out)?;
+        Ok(out)
+    }
+
+    /// Generate `n` key pairs for the KEM `alg`.
+    ///
+    /// Returns the private and public keys.
+    pub(crate) fn key_gen(
+        &self,
+        alg: KemAlgorithm,
+        n: usize,
+    ) -> Result<Vec<(kem::PrivateKey, kem::PublicKey)>, HpkeError> {
+        #[cfg(feature = "default-rng")]
+        if let Self::Provider(prng) = self {
+            // Lock once for all key pairs.
+            let mut prng = Self::provider(prng)
+                .write()
+                .map_err(|_| HpkeError::LockPoisoned)?;
+            return (0..n)
+                .map(|_| Ok(kem::key_gen::<Crypto>(alg, &mut prng)?))
+                .collect();
+        }
+        (0..n)
+            .map(|_| {
+                let ikm = zeroize::Zeroizing::new(self.random(alg.private_key_len())?);
+                let (pk, sk) = kem::derive_key_pair::<Crypto>(alg, &ikm)?;
+                Ok((sk, pk))
+            })
+            .collect()
+    }
+
+    /// Seed the test PRNG of the crypto provider.
+    #[cfg(feature = "hpke-test")]
+    #[cfg_attr(not(feature = "default-rng"), allow(unused_variables))]
+    pub(crate) fn seed(&self, seed: &[u8]) -> Result<(), HpkeError> {
+        match self {
+            #[cfg(feature = "default-rng")]
+            Self::Provider(prng) => {
+                use hpke_rs_crypto::HpkeTestRng;
+                Self::provider(prng)
+                    .write()
+                    .map_err(|_| HpkeError::LockPoisoned)?
+                    .seed(seed);
+                Ok(())
+            }
+            _ => Err(HpkeError::InvalidConfig),
+        }
+    }
+}
+
+impl<Crypto: HpkeCrypto> Clone for Prng<Crypto> {
+    /// The provider's PRNG is not cloned.
+    /// The clone creates its own when it is first used.
+    fn clone(&self) -> Self {
+        match self {
+            #[cfg(feature = "default-rng")]
+            Self::Provider(_) => Self::new(),
+            Self::Injected(rng) => Self::Injected(rng.clone()),
+            #[cfg(not(feature = "default-rng"))]
+            Self::None(_) => Self::new(),
+        }
+    }
+}
+
+impl<Crypto: HpkeCrypto> std::fmt::Debug for Prng<Crypto> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            #[cfg(feature = "default-rng")]
+            Self::Provider(_) => f.write_str("Provider"),
+            Self::Injected(_) => f.write_str("Injected"),
+            #[cfg(not(feature = "default-rng"))]
+            Self::None(_) => f.write_str("None"),
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! # rustls HPKE Provider
+//!
+//! With the `rustls` feature, [`RustlsHpke`] implements the HPKE provider
+//! traits of [rustls] such that Encrypted Client Hello (ECH) can use this
+//! crate instead of another HPKE implementation.
+//!
+//! rustls expects `&'static dyn Hpke` suites.
+//! They can be created once, e.g. with [`Box::leak`].
+//!
+//! ```
+//! # #[cfg(feature = "rustls")]
+//! # {
+//! use hpke_rs::rustls_provider::RustlsHpke;
+//! use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+//! use hpke_rs_rust_crypto::HpkeRustCrypto;
+//! use rustls::crypto::hpke::Hpke;
+//!
+//! let suite: &'static dyn Hpke = Box::leak(Box::new(RustlsHpke::<HpkeRustCrypto>::new(
+//!     KemAlgorithm::DhKem25519,
+//!     KdfAlgorithm::HkdfSha256,
+//!     AeadAlgorithm::Aes128Gcm,
+//! )));
+//! # }
+//! ```
+//!
+//! [rustls]: https://docs.rs/rustls
+
+use hpke_rs_crypto::{
+    types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm},
+    HpkeCrypto,
+};
+use rustls::{
+    crypto::hpke::{
+        EncapsulatedSecret, Hpke as RustlsHpkeProvider, HpkeOpener, HpkePrivateKey, HpkePublicKey,
+        HpkeSealer, HpkeSuite,
+    },
+    internal::msgs::handshake::HpkeSymmetricCipherSuite,
+    Error,
+};
+
+use crate::{Context, Hpke, HpkeError, Mode};
+
+/// An HPKE suite in the base mode for rustls.
+pub struct RustlsHpke<Crypto: 'static + HpkeCrypto> {
+    hpke: Hpke<Crypto>,
+}
+
+impl<Crypto: HpkeCrypto> std::fmt::Debug for RustlsHpke<Crypto> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RustlsHpke")
+            .field("suite", &self.hpke.config())
+            .finish()
+    }
+}
+
+impl<Crypto: HpkeCrypto> RustlsHpke<Crypto> {
+    /// Create the rustls HPKE suite for the algorithms.
+    pub fn new(kem: KemAlgorithm, kdf: KdfAlgorithm, aead: AeadAlgorithm) -> Self {
+        Self {
+            hpke: Hpke::new(Mode::Base, kem, kdf, aead),
+        }
+    }
+
+    /// Use the `hpke` configuration for rustls.
+    ///
+    /// Returns [`HpkeError::InvalidConfig`] if it is not in the base mode.
+    pub fn from_hpke(hpke: Hpke<Crypto>) -> Result<Self, HpkeError> {
+        if hpke.mode != Mode::Base {
+            return Err(HpkeError::InvalidConfig);
+        }
+        Ok(Self { hpke })
+    }
+}
+
+fn rustls_error(e: HpkeError) -> Error {
+    Error::General(e.to_string())
+}
+
+/// A [`Context`] as rustls sealer or opener.
+#[derive(Debug)]
+struct RustlsContext<Crypto: 'static + HpkeCrypto>(Context<Crypto>);
+
+impl<Crypto: HpkeCrypto> HpkeSealer for RustlsContext<Crypto>
+where
+    Context<Crypto>: Send + Sync,
+{
+    fn seal(&mut self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        self.0.seal(aad, plaintext).map_err(rustls_error)
+    }
+}
+
+impl<Crypto: HpkeCrypto> HpkeOpener for RustlsContext<Crypto>
+where
+    Context<Crypto>: Send + Sync,
+{
+    fn open(&mut self, aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        self.0.open(aad, ciphertext).map_err(rustls_error)
+    }
+}
+
+impl<Crypto: HpkeCrypto> RustlsHpkeProvider for RustlsHpke<Crypto>
+where
+    Self: Send + Sync,
+    Context<Crypto>: Send + Sync,
+{
+    fn seal(
+        &self,
+        info: &[u8],
+        aad: &[u8],
+        plaintext: &[u8],
+        pub_key: &HpkePublicKey,
+    ) -> Result<(EncapsulatedSecret, Vec<u8>), Error> {
+        let (enc, ct) = self
+            .hpke
+            .seal(
+                &pub_key.0.clone().into(),
+                info,
+                aad,
+                plaintext,
+                None,
+                None,
+                None,
+            )
+            .map_err(rustls_error)?;
+        Ok((EncapsulatedSecret(enc), ct))
+    }
+
+    fn setup_sealer(
+        &self,
+        info: &[u8],
+        pub_key: &HpkePublicKey,
+    ) -> Result<(EncapsulatedSecret, Box<dyn HpkeSealer + 'static>), Error> {
+        let (enc, context) = self
+            .hpke
+            .setup_sender(&pub_key.0.clone().into(), info, None, None, None)
+            .map_err(rustls_error)?;
+        Ok((EncapsulatedSecret(enc), Box::new(RustlsContext(context))))
+    }
+
+    fn open(
+        &self,
+        enc: &EncapsulatedSecret,
+        info: &[u8],
+        aad: &[u8],
+        ciphertext: &[u8],
+        secret_key: &HpkePrivateKey,
+    ) -> Result<Vec<u8>, Error> {
+        self.hpke
+            .open(
+                &enc.0,
+                &secret_key.secret_bytes().into(),
+                info,
+                aad,
+                ciphertext,
+                None,
+                None,
+                None,
+            )
+            .map_err(rustls_error)
+    }
+
+    fn setup_opener(
+        &self,
+        enc: &EncapsulatedSecret,
+        info: &[u8],
+        secret_key: &HpkePrivateKey,
+    ) -> Result<Box<dyn HpkeOpener + 'static>, Error> {
+        let context = self
+            .hpke
+            .setup_receiver(
+                &enc.0,
+                &secret_key.secret_bytes().into(),
+                info,
+                None,
+                None,
+                None,
+            )
+            .map_err(rustls_error)?;
+        Ok(Box::new(RustlsContext(context)))
+    }
+
+    fn generate_key_pair(&self) -> Result<(HpkePublicKey, HpkePrivateKey), Error> {
+        let (sk, pk) = self
+            .hpke
+            .generate_key_pair()
+            .map_err(rustls_error)?
+            .into_keys();
+        Ok((
+            HpkePublicKey(pk.as_slice().to_vec()),
+            sk.value.clone().into(),
+        ))
+    }
+
+    fn fips(&self) -> bool {
+        self.hpke.is_fips_mode()
+    }
+
+    fn suite(&self) -> HpkeSuite {
+        HpkeSuite {
+            kem: (self.hpke.kem_id as u16).into(),
+            sym: HpkeSymmetricCipherSuite {
+                kdf_id: (self.hpke.kdf_id as u16).into(),
+                aead_id: (self.hpke.aead_id as u16).into(),
+            },
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! # Sealed Sender
+//!
+//! Hide the identity of the sender from the transport while the recipient
+//! can still authenticate it.
+//!
+//! The plain text is first sealed in the Auth mode with the sender's key
+//! pair.
+//! The sender's public key and this inner message are then sealed in the
+//! Base mode such that only the recipient learns who sent the message.
+//!
+//! ```text
+//! enc_inner, ct_inner = SealAuth(pkR, info, aad, pt, skS)
+//! payload = I2OSP(len(pkSm), 2) || pkSm || enc_inner || ct_inner
+//! enc, ct = SealBase(pkR, info, aad, payload)
+//! ```
+//!
+//! The recipient learns the sender's public key and has to decide whether it
+//! trusts it.
+
+use hpke_rs_crypto::HpkeCrypto;
+
+use crate::{
+    error_context::Stage,
+    util::{self, take, take_u16},
+    Ciphertext, EncapsulatedSecret, Hpke, HpkeError, HpkeKeyPair, HpkePrivateKey, HpkePublicKey,
+    Mode, Plaintext,
+};
+
+impl<Crypto: HpkeCrypto> Hpke<Crypto> {
+    /// Seal `plain_txt` to `pk_r` such that only the recipient learns that
+    /// it was sent by the owner of the `sender` key pair.
+    ///
+    /// This must be used with the Base mode and returns
+    /// [`HpkeError::InvalidConfig`] otherwise.
+    /// Returns the encapsulated secret and the ciphertext, or an error.
+    pub fn seal_sealed_sender(
+        &self,
+        pk_r: &HpkePublicKey,
+        sender: &HpkeKeyPair,
+        info: &[u8],
+        aad: &[u8],
+        plain_txt: &[u8],
+    ) -> Result<(EncapsulatedSecret, Ciphertext), HpkeError> {
+        let (enc_inner, ct_inner) = self.sealed_sender_inner()?.seal(
+            pk_r,
+            info,
+            aad,
+            plain_txt,
+            None,
+            None,
+            Some(sender.private_key()),
+        )?;
+        let pk_s = sender.public_key().as_slice();
+        let pk_s_len = u16::try_from(pk_s.len()).map_err(|_| HpkeError::InvalidInput)?;
+        let payload = util::concat(&[&pk_s_len.to_be_bytes(), pk_s, &enc_inner, &ct_inner]);
+        self.seal(pk_r, info, aad, &payload, None, None, None)
+    }
+
+    /// Open a message sealed with
+    /// [`seal_sealed_sender`](Self::seal_sealed_sender) with the private key
+    /// `sk_r`.
+    ///
+    /// Returns the authenticated public key of the sender and the plain text.
+    /// Returns [`HpkeError::InvalidConfig`] if this is not the Base mode.
+    pub fn open_sealed_sender(
+        &self,
+        enc: &[u8],
+        sk_r: &HpkePrivateKey,
+        info: &[u8],
+        aad: &[u8],
+        ct: &[u8],
+    ) -> Result<(HpkePublicKey, Plaintext), HpkeError> {
+        let inner = self.sealed_sender_inner()?;
+        let payload = self.open(enc, sk_r, info, aad, ct, None, None, None)?;
+        let mut reader = payload.as_slice();
+        let decoded = take_u16(&mut reader).and_then(|pk_s_len| {
+            let pk_s = HpkePublicKey::from(take(&mut reader, pk_s_len.into())?);
+            Ok((pk_s, take(&mut reader, self.nenc())?))
+        });
+        let (pk_s, enc_inner) = self.add_error_context(Stage::Serialization, decoded)?;
+        let plain_txt = inner.open(enc_inner, sk_r, info, aad, reader, None, None, Some(&pk_s))?;
+        Ok((pk_s, plain_txt))
+    }
+
+    /// The Auth mode configuration for the inner message.
+    fn sealed_sender_inner(&self) -> Result<Self, HpkeError> {
+        if self.mode != Mode::Base {
+            return Err(HpkeError::InvalidConfig);
+        }
+        let mut inner = self.clone();
+        inner.mode = Mode::Auth;
+        Ok(inner)
+    }
+}
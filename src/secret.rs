@@ -0,0 +1,90 @@
+//! # Secret Wrappers
+//!
+//! With the `secrecy` feature, exported secrets and KEM shared secrets are
+//! also available as [`SecretVec`]s.
+//! The secrets are zeroized on drop and can only be read through
+//! [`ExposeSecret::expose_secret`](secrecy::ExposeSecret::expose_secret),
+//! which makes every use of a secret explicit.
+
+use hpke_rs_crypto::HpkeCrypto;
+use secrecy::SecretVec;
+
+use crate::{Context, EncapsulatedSecret, Hpke, HpkeError, HpkePrivateKey, HpkePublicKey};
+
+impl<Crypto: HpkeCrypto> Context<Crypto> {
+    /// Like [`Context::export`] but returns the exported secret as a
+    /// [`SecretVec`].
+    pub fn export_secret(
+        &self,
+        exporter_context: &[u8],
+        length: usize,
+    ) -> Result<SecretVec<u8>, HpkeError> {
+        self.export(exporter_context, length).map(SecretVec::new)
+    }
+}
+
+impl<Crypto: HpkeCrypto> Hpke<Crypto> {
+    /// Like [`Hpke::send_export`] but returns the exported secret as a
+    /// [`SecretVec`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn send_export_secret(
+        &self,
+        pk_r: &HpkePublicKey,
+        info: &[u8],
+        psk: Option<&[u8]>,
+        psk_id: Option<&[u8]>,
+        sk_s: Option<&HpkePrivateKey>,
+        exporter_context: &[u8],
+        length: usize,
+    ) -> Result<(EncapsulatedSecret, SecretVec<u8>), HpkeError> {
+        let (enc, context) = self.setup_sender(pk_r, info, psk, psk_id, sk_s)?;
+        Ok((enc, context.export_secret(exporter_context, length)?))
+    }
+
+    /// Like [`Hpke::receiver_export`] but returns the exported secret as a
+    /// [`SecretVec`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn receiver_export_secret(
+        &self,
+        enc: &[u8],
+        sk_r: &HpkePrivateKey,
+        info: &[u8],
+        psk: Option<&[u8]>,
+        psk_id: Option<&[u8]>,
+        pk_s: Option<&HpkePublicKey>,
+        exporter_context: &[u8],
+        length: usize,
+    ) -> Result<SecretVec<u8>, HpkeError> {
+        let context = self.setup_receiver(enc, sk_r, info, psk, psk_id, pk_s)?;
+        context.export_secret(exporter_context, length)
+    }
+
+    /// Encapsulate a fresh KEM shared secret to the public key `pk_r`.
+    ///
+    /// In the Auth and AuthPSK modes the sender's secret key `sk_s` is
+    /// required.
+    /// Returns the encapsulation and the shared secret.
+    pub fn encapsulate_secret(
+        &self,
+        pk_r: &HpkePublicKey,
+        sk_s: Option<&HpkePrivateKey>,
+    ) -> Result<(EncapsulatedSecret, SecretVec<u8>), HpkeError> {
+        let randomness = self.random(self.kem_id.private_key_len())?;
+        let (shared_secret, enc) = self.encaps(pk_r, sk_s, &randomness)?;
+        Ok((enc, SecretVec::new(shared_secret)))
+    }
+
+    /// Decapsulate the KEM shared secret from `enc` with the secret key
+    /// `sk_r`.
+    ///
+    /// In the Auth and AuthPSK modes the sender's public key `pk_s` is
+    /// required.
+    pub fn decapsulate_secret(
+        &self,
+        enc: &[u8],
+        sk_r: &HpkePrivateKey,
+        pk_s: Option<&HpkePublicKey>,
+    ) -> Result<SecretVec<u8>, HpkeError> {
+        self.decaps(enc, sk_r, pk_s).map(SecretVec::new)
+    }
+}
@@ -0,0 +1,342 @@
+//! # Encrypted Secrets Files
+//!
+//! With the `secrets-file` feature, [`encrypt`] encrypts the values of a
+//! structured document such as a JSON configuration file to a set of
+//! recipients, like [sops](https://github.com/getsops/sops) does.
+//! The keys and the structure of the document stay readable, such that the
+//! encrypted file can be kept in git and its changes can be reviewed.
+//!
+//! ```text
+//! {
+//!   "db": {
+//!     "user_unencrypted": "admin",
+//!     "password": "ENC[HPKE,data:H2Wh...]"
+//!   },
+//!   "hpke": { "version": 1, "suite": ..., "recipients": [...], "mac": "..." }
+//! }
+//! ```
+//!
+//! A random data key is sealed to every recipient with a [`MultiEnvelope`]
+//! and stored in the `"hpke"` metadata object.
+//! Every string, number, and boolean is encrypted on its own with the AEAD of
+//! the suite under a key derived from the data key, a random nonce, and its
+//! JSON pointer (RFC 6901) as associated data, such that values can't be
+//! moved.
+//! A MAC over the pointers and the encrypted values detects removed values.
+//!
+//! Values of keys ending in [`UNENCRYPTED_SUFFIX`] and `null`s are not
+//! encrypted and not covered by the MAC.
+//! Other formats, e.g. YAML, can be encrypted by converting them to a
+//! [`serde_json::Value`].
+
+use hpke_rs_crypto::{
+    types::{AeadAlgorithm, KdfAlgorithm},
+    HpkeCrypto,
+};
+use serde_json::{Map, Value};
+use zeroize::Zeroizing;
+
+use crate::{
+    ciphersuite::Ciphersuite,
+    envelope::{MultiEnvelope, Recipient},
+    util::{base64_decode, base64_encode, concat, ct_eq, BASE64},
+    Hpke, HpkeError, HpkePrivateKey, HpkePublicKey, KeyId, Mode,
+};
+
+/// The key of the metadata object in an encrypted document.
+pub const METADATA_KEY: &str = "hpke";
+
+/// Values of keys with this suffix are not encrypted.
+pub const UNENCRYPTED_SUFFIX: &str = "_unencrypted";
+
+/// The version of the format.
+const VERSION: u64 = 1;
+
+/// The `info` of the data key envelope.
+const INFO: &[u8] = b"hpke-rs secrets file";
+
+const PREFIX: &str = "ENC[HPKE,data:";
+const SUFFIX: &str = "]";
+
+/// Encrypt the values of the JSON object `document` to the recipients
+/// `pk_rs`, see the [module documentation](self).
+///
+/// Returns [`HpkeError::InvalidConfig`] if `hpke` is not in the Base mode or
+/// has the export-only AEAD, and [`HpkeError::InvalidInput`] if `document`
+/// is not an object or already has a [`METADATA_KEY`].
+pub fn encrypt<Crypto: HpkeCrypto>(
+    hpke: &Hpke<Crypto>,
+    pk_rs: &[HpkePublicKey],
+    document: &Value,
+) -> Result<Value, HpkeError> {
+    if hpke.aead_id == AeadAlgorithm::HpkeExport {
+        return Err(HpkeError::InvalidConfig);
+    }
+    let Value::Object(object) = document else {
+        return Err(HpkeError::InvalidInput);
+    };
+    if object.contains_key(METADATA_KEY) {
+        return Err(HpkeError::InvalidInput);
+    }
+    let data_key = Zeroizing::new(hpke.random(hpke.kdf_id.digest_length())?);
+    let envelope = MultiEnvelope::seal(hpke, pk_rs, INFO, &[], &data_key)?;
+    let keys = Keys::derive::<Crypto>(hpke.kdf_id, hpke.aead_id, &data_key)?;
+
+    let mut mac_input = Vec::new();
+    let mut encrypted = Value::Object(object.clone());
+    walk(&mut encrypted, "", &mut |pointer, value| {
+        let plain_txt =
+            Zeroizing::new(serde_json::to_vec(value).map_err(|_| HpkeError::InvalidInput)?);
+        let nonce = hpke.random(hpke.aead_id.nonce_length())?;
+        let ct = Crypto::aead_seal(
+            hpke.aead_id,
+            &keys.value_key,
+            &nonce,
+            pointer.as_bytes(),
+            &plain_txt,
+        )?;
+        let data = format!(
+            "{PREFIX}{}{SUFFIX}",
+            base64_encode(BASE64, &concat(&[&nonce, &ct]), true)
+        );
+        add_mac_input(&mut mac_input, pointer, &data);
+        *value = Value::String(data);
+        Ok(())
+    })?;
+
+    let mac = Crypto::kdf_extract(hpke.kdf_id, &keys.mac_key, &mac_input);
+    let (kem, kdf, aead) = envelope.suite.ids();
+    let recipients = envelope
+        .recipients
+        .iter()
+        .map(|recipient| {
+            serde_json::json!({
+                "key_id": recipient.key_id.map(|key_id| key_id.to_string()),
+                "enc": base64_encode(BASE64, &recipient.enc, true),
+                "ciphertext": base64_encode(BASE64, &recipient.ciphertext, true),
+            })
+        })
+        .collect();
+    let metadata = serde_json::json!({
+        "version": VERSION,
+        "suite": { "kem": kem, "kdf": kdf, "aead": aead },
+        "recipients": Value::Array(recipients),
+        "mac": base64_encode(BASE64, &mac, true),
+    });
+    if let Value::Object(object) = &mut encrypted {
+        object.insert(METADATA_KEY.to_owned(), metadata);
+    }
+    Ok(encrypted)
+}
+
+/// Decrypt a `document` encrypted with [`encrypt`] with the private key
+/// `sk_r` of one of its recipients.
+///
+/// The metadata object is removed.
+/// Returns [`HpkeError::InvalidConfig`] if the suite of the document is not
+/// the one of `hpke` or `hpke` is not in the Base mode,
+/// [`HpkeError::OpenError`] if `sk_r` is not one of the recipients, the MAC
+/// is invalid, or a value was modified, and [`HpkeError::InvalidInput`] if
+/// the document is malformed or has a value that should be encrypted but
+/// isn't.
+pub fn decrypt<Crypto: HpkeCrypto>(
+    hpke: &Hpke<Crypto>,
+    sk_r: &HpkePrivateKey,
+    document: &Value,
+) -> Result<Value, HpkeError> {
+    let Value::Object(object) = document else {
+        return Err(HpkeError::InvalidInput);
+    };
+    let mut decrypted = object.clone();
+    let metadata = decrypted
+        .remove(METADATA_KEY)
+        .ok_or(HpkeError::InvalidInput)?;
+    let metadata = Metadata::decode(&metadata)?;
+    if hpke.mode != Mode::Base || hpke.ciphersuite() != metadata.envelope.suite {
+        return Err(HpkeError::InvalidConfig);
+    }
+    let data_key = Zeroizing::new(
+        metadata
+            .envelope
+            .recipients
+            .iter()
+            .find_map(|recipient| {
+                hpke.open(
+                    &recipient.enc,
+                    sk_r,
+                    INFO,
+                    &[],
+                    &recipient.ciphertext,
+                    None,
+                    None,
+                    None,
+                )
+                .ok()
+            })
+            .ok_or(HpkeError::OpenError)?,
+    );
+    let keys = Keys::derive::<Crypto>(hpke.kdf_id, hpke.aead_id, &data_key)?;
+
+    // Check the MAC before decrypting anything.
+    let mut decrypted = Value::Object(decrypted);
+    let mut mac_input = Vec::new();
+    walk(&mut decrypted, "", &mut |pointer, value| {
+        let Value::String(data) = value else {
+            return Err(HpkeError::InvalidInput);
+        };
+        add_mac_input(&mut mac_input, pointer, data);
+        Ok(())
+    })?;
+    let mac = Crypto::kdf_extract(hpke.kdf_id, &keys.mac_key, &mac_input);
+    if !ct_eq(&mac, &metadata.mac) {
+        return Err(HpkeError::OpenError);
+    }
+
+    walk(&mut decrypted, "", &mut |pointer, value| {
+        let data = value
+            .as_str()
+            .and_then(|data| data.strip_prefix(PREFIX)?.strip_suffix(SUFFIX))
+            .ok_or(HpkeError::InvalidInput)?;
+        let data = base64_decode(BASE64, data, true)?;
+        if data.len() < hpke.aead_id.nonce_length() {
+            return Err(HpkeError::InvalidInput);
+        }
+        let (nonce, ct) = data.split_at(hpke.aead_id.nonce_length());
+        let plain_txt = Zeroizing::new(Crypto::aead_open(
+            hpke.aead_id,
+            &keys.value_key,
+            nonce,
+            pointer.as_bytes(),
+            ct,
+        )?);
+        *value = serde_json::from_slice(&plain_txt).map_err(|_| HpkeError::InvalidInput)?;
+        Ok(())
+    })?;
+    Ok(decrypted)
+}
+
+/// The keys derived from the data key.
+struct Keys {
+    value_key: Zeroizing<Vec<u8>>,
+    mac_key: Zeroizing<Vec<u8>>,
+}
+
+impl Keys {
+    fn derive<Crypto: HpkeCrypto>(
+        kdf: KdfAlgorithm,
+        aead: AeadAlgorithm,
+        data_key: &[u8],
+    ) -> Result<Self, HpkeError> {
+        Ok(Self {
+            value_key: Zeroizing::new(Crypto::kdf_expand(
+                kdf,
+                data_key,
+                b"value key",
+                aead.key_length(),
+            )?),
+            mac_key: Zeroizing::new(Crypto::kdf_expand(
+                kdf,
+                data_key,
+                b"mac key",
+                kdf.digest_length(),
+            )?),
+        })
+    }
+}
+
+/// The decoded metadata object.
+struct Metadata {
+    envelope: MultiEnvelope,
+    mac: Vec<u8>,
+}
+
+impl Metadata {
+    fn decode(metadata: &Value) -> Result<Self, HpkeError> {
+        if metadata["version"].as_u64() != Some(VERSION) {
+            return Err(HpkeError::InvalidInput);
+        }
+        let id = |name| {
+            metadata["suite"][name]
+                .as_u64()
+                .and_then(|id| u16::try_from(id).ok())
+                .ok_or(HpkeError::InvalidInput)
+        };
+        let suite = Ciphersuite::from_ids(id("kem")?, id("kdf")?, id("aead")?)?;
+        let recipients = metadata["recipients"]
+            .as_array()
+            .ok_or(HpkeError::InvalidInput)?
+            .iter()
+            .map(|recipient| {
+                let key_id = match &recipient["key_id"] {
+                    Value::Null => None,
+                    Value::String(key_id) => Some(KeyId::try_from(hex(key_id)?.as_slice())?),
+                    _ => return Err(HpkeError::InvalidInput),
+                };
+                Ok(Recipient {
+                    key_id,
+                    enc: base64_field(recipient, "enc")?,
+                    ciphertext: base64_field(recipient, "ciphertext")?,
+                })
+            })
+            .collect::<Result<_, HpkeError>>()?;
+        Ok(Self {
+            envelope: MultiEnvelope { suite, recipients },
+            mac: base64_field(metadata, "mac")?,
+        })
+    }
+}
+
+/// Call `f` with the JSON pointer of every value to encrypt.
+fn walk(
+    value: &mut Value,
+    pointer: &str,
+    f: &mut impl FnMut(&str, &mut Value) -> Result<(), HpkeError>,
+) -> Result<(), HpkeError> {
+    match value {
+        Value::Null => Ok(()),
+        Value::Object(object) => walk_object(object, pointer, f),
+        Value::Array(values) => values
+            .iter_mut()
+            .enumerate()
+            .try_for_each(|(i, value)| walk(value, &format!("{pointer}/{i}"), f)),
+        _ => f(pointer, value),
+    }
+}
+
+fn walk_object(
+    object: &mut Map<String, Value>,
+    pointer: &str,
+    f: &mut impl FnMut(&str, &mut Value) -> Result<(), HpkeError>,
+) -> Result<(), HpkeError> {
+    for (key, value) in object.iter_mut() {
+        if key.ends_with(UNENCRYPTED_SUFFIX) {
+            continue;
+        }
+        let key = key.replace('~', "~0").replace('/', "~1");
+        walk(value, &format!("{pointer}/{key}"), f)?;
+    }
+    Ok(())
+}
+
+/// Append the length-prefixed `pointer` and encrypted `data`.
+fn add_mac_input(mac_input: &mut Vec<u8>, pointer: &str, data: &str) {
+    for part in [pointer.as_bytes(), data.as_bytes()] {
+        mac_input.extend_from_slice(&(part.len() as u64).to_be_bytes());
+        mac_input.extend_from_slice(part);
+    }
+}
+
+fn base64_field(object: &Value, name: &str) -> Result<Vec<u8>, HpkeError> {
+    let encoded = object[name].as_str().ok_or(HpkeError::InvalidInput)?;
+    base64_decode(BASE64, encoded, true)
+}
+
+fn hex(hex: &str) -> Result<Vec<u8>, HpkeError> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return Err(HpkeError::InvalidInput);
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| HpkeError::InvalidInput))
+        .collect()
+}
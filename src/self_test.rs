@@ -0,0 +1,360 @@
+//! # Known-Answer Self Tests
+//!
+//! [`Hpke::self_test`] runs an embedded set of RFC 9180 test vectors in the
+//! Base mode against the crypto provider, e.g. as power-on self test before
+//! a deployment starts using it.
+//!
+//! For every suite of the set the self test
+//!
+//! * derives the recipient and ephemeral key pairs from their `ikm` and
+//!   compares the public keys,
+//! * sets up the receiver context from `enc` with the KEM, KDF, and key
+//!   schedule,
+//! * opens the first ciphertext of the vector with the AEAD, and
+//! * compares the first exported value.
+//!
+//! The suites cover every KEM, AEAD, and the HKDF-SHA256 and HKDF-SHA512
+//! KDFs with a vector in RFC 9180.
+//! Suites with an algorithm that the provider doesn't support are reported as
+//! [`SelfTestOutcome::Unsupported`].
+//! The self test also tests the algorithms that are not approved in
+//! [FIPS mode](crate::Hpke::with_fips_mode).
+
+use hpke_rs_crypto::{
+    types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm},
+    HpkeCrypto,
+};
+
+use crate::{ciphersuite::Ciphersuite, kem, Hpke, HpkeError, Mode};
+
+/// The outcome of the self test of a suite.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelfTestOutcome {
+    /// All known answers matched.
+    Passed,
+    /// The provider doesn't support an algorithm of the suite.
+    Unsupported,
+    /// A known answer didn't match ([`HpkeError::CryptoError`]) or an
+    /// operation failed.
+    Failed(HpkeError),
+}
+
+/// The outcome of [`Hpke::self_test`] for every tested suite.
+#[derive(Debug, Clone)]
+pub struct SelfTestReport {
+    /// The suites and their outcome.
+    pub results: Vec<(Ciphersuite, SelfTestOutcome)>,
+}
+
+impl SelfTestReport {
+    /// The suites that passed.
+    pub fn passed(&self) -> impl Iterator<Item = Ciphersuite> + '_ {
+        self.with_outcome(|outcome| *outcome == SelfTestOutcome::Passed)
+    }
+
+    /// The suites that failed.
+    pub fn failed(&self) -> impl Iterator<Item = Ciphersuite> + '_ {
+        self.with_outcome(|outcome| matches!(outcome, SelfTestOutcome::Failed(_)))
+    }
+
+    /// Returns `true` if no suite failed.
+    ///
+    /// Check [`passed`](Self::passed) as well to require that the suites of
+    /// a deployment were tested.
+    pub fn is_ok(&self) -> bool {
+        self.failed().next().is_none()
+    }
+
+    fn with_outcome(
+        &self,
+        predicate: fn(&SelfTestOutcome) -> bool,
+    ) -> impl Iterator<Item = Ciphersuite> + '_ {
+        self.results
+            .iter()
+            .filter(move |(_, outcome)| predicate(outcome))
+            .map(|(suite, _)| *suite)
+    }
+}
+
+impl<Crypto: HpkeCrypto> Hpke<Crypto> {
+    /// Run the known-answer self tests for the algorithms of the provider,
+    /// see the [module documentation](crate::self_test).
+    pub fn self_test() -> SelfTestReport {
+        let results = VECTORS
+            .iter()
+            .map(|vector| {
+                let outcome = if supports::<Crypto>(&vector.suite) {
+                    match vector.check::<Crypto>() {
+                        Ok(()) => SelfTestOutcome::Passed,
+                        Err(e) => SelfTestOutcome::Failed(e),
+                    }
+                } else {
+                    SelfTestOutcome::Unsupported
+                };
+                (vector.suite, outcome)
+            })
+            .collect();
+        SelfTestReport { results }
+    }
+}
+
+fn supports<Crypto: HpkeCrypto>(suite: &Ciphersuite) -> bool {
+    Crypto::supports_kem(suite.kem).is_ok()
+        && Crypto::supports_kdf(suite.kdf).is_ok()
+        && Crypto::supports_aead(suite.aead).is_ok()
+}
+
+/// A Base mode test vector with the first encryption and export.
+struct Vector {
+    suite: Ciphersuite,
+    info: &'static str,
+    ikm_r: &'static str,
+    ikm_e: &'static str,
+    pk_r: &'static str,
+    enc: &'static str,
+    aad: &'static str,
+    pt: &'static str,
+    ct: &'static str,
+    exporter_context: &'static str,
+    export_len: usize,
+    exported_value: &'static str,
+}
+
+impl Vector {
+    fn check<Crypto: 'static + HpkeCrypto>(&self) -> Result<(), HpkeError> {
+        let (pk_r, sk_r) = kem::derive_key_pair::<Crypto>(self.suite.kem, &hex(self.ikm_r))?;
+        expect("pkRm", &pk_r, self.pk_r)?;
+        let (pk_e, _) = kem::derive_key_pair::<Crypto>(self.suite.kem, &hex(self.ikm_e))?;
+        expect("enc", &pk_e, self.enc)?;
+
+        let hpke = Hpke::<Crypto>::new(Mode::Base, self.suite.kem, self.suite.kdf, self.suite.aead);
+        let mut context = hpke.setup_receiver(
+            &hex(self.enc),
+            &sk_r.into(),
+            &hex(self.info),
+            None,
+            None,
+            None,
+        )?;
+        if self.suite.aead != AeadAlgorithm::HpkeExport {
+            let pt = context.open(&hex(self.aad), &hex(self.ct))?;
+            expect("pt", &pt, self.pt)?;
+        }
+        let exported = context.export(&hex(self.exporter_context), self.export_len)?;
+        expect("exported_value", &exported, self.exported_value)
+    }
+}
+
+/// Compare `value` to the known answer `expected`.
+fn expect(name: &str, value: &[u8], expected: &str) -> Result<(), HpkeError> {
+    if value == hex(expected) {
+        Ok(())
+    } else {
+        Err(HpkeError::CryptoError(format!(
+            "Self test: {name} doesn't match the known answer"
+        )))
+    }
+}
+
+fn hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("Invalid test vector"))
+        .collect()
+}
+
+/// The RFC 9180 Base mode test vectors.
+const VECTORS: &[Vector] = &[
+    Vector {
+        suite: Ciphersuite::new(
+            KemAlgorithm::DhKem25519,
+            KdfAlgorithm::HkdfSha256,
+            AeadAlgorithm::Aes128Gcm,
+        ),
+        info: "4f6465206f6e2061204772656369616e2055726e",
+        ikm_r: "6db9df30aa07dd42ee5e8181afdb977e538f5e1fec8a06223f33f7013e525037",
+        ikm_e: "7268600d403fce431561aef583ee1613527cff655c1343f29812e66706df3234",
+        pk_r: "3948cfe0ad1ddb695d780e59077195da6c56506b027329794ab02bca80815c4d",
+        enc: "37fda3567bdbd628e88668c3c8d7e97d1d1253b6d4ea6d44c150f741f1bf4431",
+        aad: "436f756e742d30",
+        pt: "4265617574792069732074727574682c20747275746820626561757479",
+        ct: concat!(
+            "f938558b5d72f1a23810b4be2ab4f84331acc02fc97babc53a52ae8218a355a9",
+            "6d8770ac83d07bea87e13c512a",
+        ),
+        exporter_context: "",
+        export_len: 32,
+        exported_value: "3853fe2b4035195a573ffc53856e77058e15d9ea064de3e59f4961d0095250ee",
+    },
+    Vector {
+        suite: Ciphersuite::new(
+            KemAlgorithm::DhKem25519,
+            KdfAlgorithm::HkdfSha256,
+            AeadAlgorithm::ChaCha20Poly1305,
+        ),
+        info: "4f6465206f6e2061204772656369616e2055726e",
+        ikm_r: "1ac01f181fdf9f352797655161c58b75c656a6cc2716dcb66372da835542e1df",
+        ikm_e: "909a9b35d3dc4713a5e72a4da274b55d3d3821a37e5d099e74a647db583a904b",
+        pk_r: "4310ee97d88cc1f088a5576c77ab0cf5c3ac797f3d95139c6c84b5429c59662a",
+        enc: "1afa08d3dec047a643885163f1180476fa7ddb54c6a8029ea33f95796bf2ac4a",
+        aad: "436f756e742d30",
+        pt: "4265617574792069732074727574682c20747275746820626561757479",
+        ct: concat!(
+            "1c5250d8034ec2b784ba2cfd69dbdb8af406cfe3ff938e131f0def8c8b60b4db",
+            "21993c62ce81883d2dd1b51a28",
+        ),
+        exporter_context: "",
+        export_len: 32,
+        exported_value: "4bbd6243b8bb54cec311fac9df81841b6fd61f56538a775e7c80a9f40160606e",
+    },
+    Vector {
+        suite: Ciphersuite::new(
+            KemAlgorithm::DhKem25519,
+            KdfAlgorithm::HkdfSha256,
+            AeadAlgorithm::HpkeExport,
+        ),
+        info: "4f6465206f6e2061204772656369616e2055726e",
+        ikm_r: "683ae0da1d22181e74ed2e503ebf82840deb1d5e872cade20f4b458d99783e31",
+        ikm_e: "55bc245ee4efda25d38f2d54d5bb6665291b99f8108a8c4b686c2b14893ea5d9",
+        pk_r: "194141ca6c3c3beb4792cd97ba0ea1faff09d98435012345766ee33aae2d7664",
+        enc: "e5e8f9bfff6c2f29791fc351d2c25ce1299aa5eaca78a757c0b4fb4bcd830918",
+        aad: "",
+        pt: "",
+        ct: "",
+        exporter_context: "",
+        export_len: 32,
+        exported_value: "7a36221bd56d50fb51ee65edfd98d06a23c4dc87085aa5866cb7087244bd2a36",
+    },
+    Vector {
+        suite: Ciphersuite::new(
+            KemAlgorithm::DhKemP256,
+            KdfAlgorithm::HkdfSha256,
+            AeadAlgorithm::Aes256Gcm,
+        ),
+        info: "4f6465206f6e2061204772656369616e2055726e",
+        ikm_r: "a0ce15d49e28bd47a18a97e147582d814b08cbe00109fed5ec27d1b4e9f6f5e3",
+        ikm_e: "a90d3417c3da9cb6c6ae19b4b5dd6cc9529a4cc24efb7ae0ace1f31887a8cd6c",
+        pk_r: concat!(
+            "04abc7e49a4c6b3566d77d0304addc6ed0e98512ffccf505e6a8e3eb25c68513",
+            "6f853148544876de76c0f2ef99cdc3a05ccf5ded7860c7c021238f9e2073d235",
+            "6c",
+        ),
+        enc: concat!(
+            "04c06b4f6bebc7bb495cb797ab753f911aff80aefb86fd8b6fcc35525f3ab5f0",
+            "3e0b21bd31a86c6048af3cb2d98e0d3bf01da5cc4c39ff5370d331a4f1f7d5a4",
+            "e0",
+        ),
+        aad: "436f756e742d30",
+        pt: "4265617574792069732074727574682c20747275746820626561757479",
+        ct: concat!(
+            "58c61a45059d0c5704560e9d88b564a8b63f1364b8d1fcb3c4c6ddc1d2917424",
+            "65e902cd216f8908da49f8f96f",
+        ),
+        exporter_context: "",
+        export_len: 32,
+        exported_value: "7a4c2b89e1909fb0e3ca42d5040f4c2d8346dc0643d787b8474e804f8f72798e",
+    },
+    Vector {
+        suite: Ciphersuite::new(
+            KemAlgorithm::DhKemP256,
+            KdfAlgorithm::HkdfSha512,
+            AeadAlgorithm::Aes128Gcm,
+        ),
+        info: "4f6465206f6e2061204772656369616e2055726e",
+        ikm_r: "ea9ff7cc5b2705b188841c7ace169290ff312a9cb31467784ca92d7a2e6e1be8",
+        ikm_e: "4ab11a9dd78c39668f7038f921ffc0993b368171d3ddde8031501ee1e08c4c9a",
+        pk_r: concat!(
+            "04085aa5b665dc3826f9650ccbcc471be268c8ada866422f739e2d531d4a8818",
+            "a9466bc6b449357096232919ec4fe9070ccbac4aac30f4a1a53efcf7af90610e",
+            "dd",
+        ),
+        enc: concat!(
+            "0493ed86735bdfb978cc055c98b45695ad7ce61ce748f4dd63c525a3b8d53a15",
+            "565c6897888070070c1579db1f86aaa56deb8297e64db7e8924e72866f9a4725",
+            "80",
+        ),
+        aad: "436f756e742d30",
+        pt: "4265617574792069732074727574682c20747275746820626561757479",
+        ct: concat!(
+            "d3cf4984931484a080f74c1bb2a6782700dc1fef9abe8442e44a6f09044c8890",
+            "7200b332003543754eb51917ba",
+        ),
+        exporter_context: "",
+        export_len: 32,
+        exported_value: "a32186b8946f61aeead1c093fe614945f85833b165b28c46bf271abf16b57208",
+    },
+    Vector {
+        suite: Ciphersuite::new(
+            KemAlgorithm::DhKemP521,
+            KdfAlgorithm::HkdfSha512,
+            AeadAlgorithm::Aes256Gcm,
+        ),
+        info: "4f6465206f6e2061204772656369616e2055726e",
+        ikm_r: concat!(
+            "2ad954bbe39b7122529f7dde780bff626cd97f850d0784a432784e69d86eccaa",
+            "de43b6c10a8ffdb94bf943c6da479db137914ec835a7e715e36e45e29b587bab",
+            "3bf1",
+        ),
+        ikm_e: concat!(
+            "7f06ab8215105fc46aceeb2e3dc5028b44364f960426eb0d8e4026c2f8b5d7e7",
+            "a986688f1591abf5ab753c357a5d6f0440414b4ed4ede71317772ac98d9239f7",
+            "0904",
+        ),
+        pk_r: concat!(
+            "0401b45498c1714e2dce167d3caf162e45e0642afc7ed435df7902ccae0e84ba",
+            "0f7d373f646b7738bbbdca11ed91bdeae3cdcba3301f2457be452f271fa68375",
+            "80e661012af49583a62e48d44bed350c7118c0d8dc861c238c72a2bda17f6470",
+            "4f464b57338e7f40b60959480c0e58e6559b190d81663ed816e523b6b6a418f6",
+            "6d2451ec64",
+        ),
+        enc: concat!(
+            "040138b385ca16bb0d5fa0c0665fbbd7e69e3ee29f63991d3e9b5fa740aab890",
+            "0aaeed46ed73a49055758425a0ce36507c54b29cc5b85a5cee6bae0cf1c21f27",
+            "31ece2013dc3fb7c8d21654bb161b463962ca19e8c654ff24c94dd2898de1205",
+            "1f1ed0692237fb02b2f8d1dc1c73e9b366b529eb436e98a996ee522aef863dd5",
+            "739d2f29b0",
+        ),
+        aad: "436f756e742d30",
+        pt: "4265617574792069732074727574682c20747275746820626561757479",
+        ct: concat!(
+            "170f8beddfe949b75ef9c387e201baf4132fa7374593dfafa90768788b7b2b20",
+            "0aafcc6d80ea4c795a7c5b841a",
+        ),
+        exporter_context: "",
+        export_len: 32,
+        exported_value: "05e2e5bd9f0c30832b80a279ff211cc65eceb0d97001524085d609ead60d0412",
+    },
+    Vector {
+        suite: Ciphersuite::new(
+            KemAlgorithm::DhKem448,
+            KdfAlgorithm::HkdfSha512,
+            AeadAlgorithm::ChaCha20Poly1305,
+        ),
+        info: "4f6465206f6e2061204772656369616e2055726e",
+        ikm_r: concat!(
+            "1a91ec4a112661d663caad07437e07486dcc80b499c83c6bf17fb2faba77c180",
+            "404d983bd32ed4284fa1aee3bb3887b61402036b058c3c8d",
+        ),
+        ikm_e: concat!(
+            "178e4db14a03ebf5b5205e11a3c3918431b4d4bb143b62a52bebdd61d107d231",
+            "22868395cca3dbc46e98964d4c1dfdc4b0e05cbb2934d9e5",
+        ),
+        pk_r: concat!(
+            "f2fdb31a7829a6d2d78b9d8b670397457c92cb2417af37dbe0c1c12a9547e4ed",
+            "a9fde09fc3fe0f359bb7b4151e8a6fb592530af71d9dc0b5",
+        ),
+        enc: concat!(
+            "3d4f6aa08c635205bcd96a0791695d08638714474b4d2c0132b69e25cdb826e1",
+            "a2a84bc0c40c4fc75f52051b034e0afa82b8457e28794f92",
+        ),
+        aad: "436f756e742d30",
+        pt: "4265617574792069732074727574682c20747275746820626561757479",
+        ct: concat!(
+            "4df124bd68d45b84dd5b82146597cdab8b56ab618166f814c2fe98ce35f43b09",
+            "917283a58810aac71e852bff0a",
+        ),
+        exporter_context: "",
+        export_len: 32,
+        exported_value: "d13d9f30a9de3369f25b8de6a733d9c5b68a79b148a662a44cb84e9296419ed6",
+    },
+];
@@ -0,0 +1,168 @@
+//! # Sender Verification
+//!
+//! In the Auth and AuthPSK modes the receiver passes the sender's public key
+//! `pk_s` to the setup, and a successful setup proves that the sender holds
+//! the matching private key.
+//! Whether that key belongs to a sender the receiver trusts is up to the
+//! application.
+//!
+//! A [`SenderVerifier`] registered with [`Hpke::with_sender_verifier`] is
+//! given `pk_s` before every authenticated setup of the configuration,
+//! including [sealed sender](Hpke::open_sealed_sender) messages, such that
+//! the application can check it against a certificate chain or a trust store
+//! in one place.
+//! The setup fails with the verifier's error if it rejects the key.
+//!
+//! For the common case of a fixed set of senders, [`PinnedSenders`] accepts
+//! only pinned public keys or [`KeyId`]s without a full PKI.
+
+use std::{collections::HashSet, marker::PhantomData, sync::Arc};
+
+use hpke_rs_crypto::HpkeCrypto;
+
+use crate::{Hpke, HpkeConfig, HpkeError, HpkePublicKey, KeyId};
+
+/// A verifier of the sender's public key in the Auth and AuthPSK modes, see
+/// the [module documentation](self).
+///
+/// This is implemented for closures that take the public key and the
+/// configuration.
+pub trait SenderVerifier: Send + Sync {
+    /// Check that `pk_s` is the public key of a trusted sender for a setup
+    /// with `config`.
+    ///
+    /// Return [`HpkeError::UntrustedSender`] to reject the key.
+    /// Other errors, e.g. an [`HpkeError::IoError`] if the trust store is not
+    /// available, are passed on as well.
+    fn verify(&self, pk_s: &HpkePublicKey, config: &HpkeConfig) -> Result<(), HpkeError>;
+}
+
+impl<F: Fn(&HpkePublicKey, &HpkeConfig) -> Result<(), HpkeError> + Send + Sync> SenderVerifier
+    for F
+{
+    fn verify(&self, pk_s: &HpkePublicKey, config: &HpkeConfig) -> Result<(), HpkeError> {
+        self(pk_s, config)
+    }
+}
+
+/// A [`SenderVerifier`] that only accepts pinned sender keys and fails with
+/// [`HpkeError::UnknownSender`] for all others.
+///
+/// Keys are pinned by their encoding or by their [`KeyId`], which binds the
+/// KEM of the configuration.
+#[derive(Debug)]
+pub struct PinnedSenders<Crypto: HpkeCrypto> {
+    keys: HashSet<Vec<u8>>,
+    key_ids: HashSet<KeyId>,
+    _crypto: PhantomData<Crypto>,
+}
+
+impl<Crypto: HpkeCrypto> PinnedSenders<Crypto> {
+    /// An empty set of pinned senders that rejects every sender.
+    pub fn new() -> Self {
+        Self {
+            keys: HashSet::new(),
+            key_ids: HashSet::new(),
+            _crypto: PhantomData,
+        }
+    }
+
+    /// Pin the sender public key `pk_s`.
+    pub fn with_key(mut self, pk_s: &HpkePublicKey) -> Self {
+        self.pin_key(pk_s);
+        self
+    }
+
+    /// Pin the sender public key with the id `key_id`.
+    pub fn with_key_id(mut self, key_id: KeyId) -> Self {
+        self.pin_key_id(key_id);
+        self
+    }
+
+    /// Pin the sender public key `pk_s`.
+    pub fn pin_key(&mut self, pk_s: &HpkePublicKey) {
+        self.keys.insert(pk_s.as_slice().to_vec());
+    }
+
+    /// Pin the sender public key with the id `key_id`.
+    pub fn pin_key_id(&mut self, key_id: KeyId) {
+        self.key_ids.insert(key_id);
+    }
+
+    /// Check whether `pk_s` is pinned, directly or by its id for the KEM of
+    /// `config`.
+    pub fn is_pinned(&self, pk_s: &HpkePublicKey, config: &HpkeConfig) -> Result<bool, HpkeError> {
+        if self.keys.contains(pk_s.as_slice()) {
+            return Ok(true);
+        }
+        if self.key_ids.is_empty() {
+            return Ok(false);
+        }
+        let key_id = pk_s.key_id::<Crypto>(config.kem_id)?;
+        Ok(self.key_ids.contains(&key_id))
+    }
+}
+
+impl<Crypto: HpkeCrypto> Default for PinnedSenders<Crypto> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Crypto: HpkeCrypto> Clone for PinnedSenders<Crypto> {
+    fn clone(&self) -> Self {
+        Self {
+            keys: self.keys.clone(),
+            key_ids: self.key_ids.clone(),
+            _crypto: PhantomData,
+        }
+    }
+}
+
+impl<Crypto: HpkeCrypto> SenderVerifier for PinnedSenders<Crypto> {
+    fn verify(&self, pk_s: &HpkePublicKey, config: &HpkeConfig) -> Result<(), HpkeError> {
+        if self.is_pinned(pk_s, config)? {
+            Ok(())
+        } else {
+            Err(HpkeError::UnknownSender)
+        }
+    }
+}
+
+/// The sender verifier of a configuration.
+#[derive(Clone)]
+pub(crate) struct Verifier(Arc<dyn SenderVerifier>);
+
+impl std::fmt::Debug for Verifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Verifier").finish_non_exhaustive()
+    }
+}
+
+impl<Crypto: HpkeCrypto> Hpke<Crypto> {
+    /// Verify the sender's public key with `verifier` before every Auth and
+    /// AuthPSK setup of this configuration.
+    ///
+    /// Clones of this configuration share the verifier.
+    pub fn with_sender_verifier(mut self, verifier: impl SenderVerifier + 'static) -> Self {
+        self.sender_verifier = Some(Verifier(Arc::new(verifier)));
+        self
+    }
+
+    /// Only accept the `pinned` senders in the Auth and AuthPSK setups of
+    /// this configuration.
+    ///
+    /// This replaces a verifier set with
+    /// [`with_sender_verifier`](Self::with_sender_verifier).
+    pub fn with_pinned_senders(self, pinned: PinnedSenders<Crypto>) -> Self {
+        self.with_sender_verifier(pinned)
+    }
+
+    /// Check `pk_s` with the sender verifier if there is one.
+    pub(crate) fn verify_sender(&self, pk_s: &HpkePublicKey) -> Result<(), HpkeError> {
+        match &self.sender_verifier {
+            Some(Verifier(verifier)) => verifier.verify(pk_s, &self.config()),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! # Sessions
+//!
+//! Servers that receive several messages per HPKE context keep the receiver
+//! [`Context`]s of all open sessions between requests.
+//! A [`SessionManager`] stores these contexts by [`SessionId`], either
+//! derived from the encapsulated secret with [`SessionId::from_enc`] or
+//! supplied by the application, e.g. from a connection id.
+//!
+//! Sessions expire after an idle timeout and are evicted the next time they
+//! are used or with [`SessionManager::evict_expired`].
+//! With a capacity, the least recently used session is evicted when a full
+//! manager accepts a new one.
+//!
+//! The sessions are spread over a number of shards with their own lock, so
+//! requests for different sessions rarely wait for each other.
+//! A manager takes `&self` everywhere and can be shared between threads in
+//! an `Arc`.
+//! Like the key store, the manager takes the current time `now` in seconds
+//! since the Unix epoch as argument.
+//!
+//! ```
+//! use hpke_rs::{prelude::*, session::SessionManager};
+//! use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+//! use hpke_rs_rust_crypto::HpkeRustCrypto;
+//!
+//! let hpke = Hpke::<HpkeRustCrypto>::new(
+//!     HpkeMode::Base,
+//!     KemAlgorithm::DhKem25519,
+//!     KdfAlgorithm::HkdfSha256,
+//!     AeadAlgorithm::ChaCha20Poly1305,
+//! );
+//! let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+//! let sessions = SessionManager::new(300).with_capacity(10_000);
+//!
+//! // The client sets up a context and sends `enc` with the first message.
+//! let (enc, mut client) = hpke.setup_sender(&pk_r, b"info", None, None, None).unwrap();
+//! let first = client.seal(b"", b"first").unwrap();
+//! let second = client.seal(b"", b"second").unwrap();
+//!
+//! let now = 1_700_000_000;
+//! let id = sessions
+//!     .accept(&hpke, &enc, &sk_r, b"info", None, None, None, now)
+//!     .unwrap();
+//! assert_eq!(sessions.open(&id, b"", &first, now).unwrap(), b"first");
+//! assert_eq!(sessions.open(&id, b"", &second, now + 10).unwrap(), b"second");
+//! ```
+
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::BuildHasher,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use hpke_rs_crypto::{
+    types::{KdfAlgorithm, KemAlgorithm},
+    HpkeCrypto,
+};
+
+use crate::{
+    kdf::labeled_extract, kem, Context, Hpke, HpkeError, HpkePrivateKey, HpkePublicKey, Plaintext,
+};
+
+/// The length of a [`SessionId`] in bytes.
+pub const SESSION_ID_LEN: usize = 16;
+
+/// The default number of shards of a [`SessionManager`].
+pub const DEFAULT_SHARDS: usize = 16;
+
+/// The identifier of a session in a [`SessionManager`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub struct SessionId([u8; SESSION_ID_LEN]);
+
+impl SessionId {
+    /// Derive the session id from the encapsulated secret `enc` for the KEM
+    /// `kem_id`.
+    ///
+    /// ```text
+    /// session_id = LabeledExtract("", "session_id", enc)[..16]
+    /// ```
+    ///
+    /// with HKDF-SHA256 and the KEM suite id, like a [`KeyId`](crate::KeyId).
+    pub fn from_enc<Crypto: HpkeCrypto>(
+        kem_id: KemAlgorithm,
+        enc: &[u8],
+    ) -> Result<Self, HpkeError> {
+        Crypto::supports_kdf(KdfAlgorithm::HkdfSha256)?;
+        let prk = labeled_extract::<Crypto>(
+            KdfAlgorithm::HkdfSha256,
+            &[],
+            &kem::ciphersuite(kem_id),
+            "session_id",
+            enc,
+        );
+        let mut id = [0u8; SESSION_ID_LEN];
+        id.copy_from_slice(&prk[..SESSION_ID_LEN]);
+        Ok(Self(id))
+    }
+
+    /// Get the session id as byte slice.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<[u8; SESSION_ID_LEN]> for SessionId {
+    fn from(id: [u8; SESSION_ID_LEN]) -> Self {
+        Self(id)
+    }
+}
+
+impl TryFrom<&[u8]> for SessionId {
+    type Error = HpkeError;
+    fn try_from(id: &[u8]) -> Result<Self, HpkeError> {
+        id.try_into().map(Self).map_err(|_| HpkeError::InvalidInput)
+    }
+}
+
+impl std::fmt::Display for SessionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for b in self.0 {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+/// A session in a [`SessionManager`].
+struct Session<Crypto: 'static + HpkeCrypto> {
+    context: Context<Crypto>,
+    last_used: u64,
+}
+
+type Shard<Crypto> = HashMap<SessionId, Session<Crypto>>;
+
+/// A store for the receiver contexts of open sessions, see the
+/// [module documentation](self).
+pub struct SessionManager<Crypto: 'static + HpkeCrypto> {
+    shards: Box<[Mutex<Shard<Crypto>>]>,
+    hasher: RandomState,
+    idle_timeout: u64,
+    capacity: Option<usize>,
+}
+
+impl<Crypto: HpkeCrypto> std::fmt::Debug for SessionManager<Crypto> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionManager")
+            .field("shards", &self.shards.len())
+            .field("idle_timeout", &self.idle_timeout)
+            .field("capacity", &self.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Crypto: HpkeCrypto> SessionManager<Crypto> {
+    /// Create a new, empty session manager with [`DEFAULT_SHARDS`] shards and
+    /// no capacity limit.
+    ///
+    /// Sessions expire `idle_timeout` seconds after they were last used.
+    pub fn new(idle_timeout: u64) -> Self {
+        Self {
+            shards: new_shards(DEFAULT_SHARDS),
+            hasher: RandomState::new(),
+            idle_timeout,
+            capacity: None,
+        }
+    }
+
+    /// Spread the sessions over `shards` shards (at least one).
+    ///
+    /// This drops all sessions in the manager.
+    pub fn with_shards(mut self, shards: usize) -> Self {
+        self.shards = new_shards(shards.max(1));
+        self
+    }
+
+    /// Hold at most about `capacity` sessions.
+    ///
+    /// The capacity is split evenly between the shards, rounded up.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Get the idle timeout of the sessions in seconds.
+    pub fn idle_timeout(&self) -> u64 {
+        self.idle_timeout
+    }
+
+    fn shard(&self, id: &SessionId) -> Result<MutexGuard<'_, Shard<Crypto>>, HpkeError> {
+        let index = self.hasher.hash_one(id) as usize % self.shards.len();
+        self.shards[index]
+            .lock()
+            .map_err(|_| HpkeError::LockPoisoned)
+    }
+
+    fn is_expired(&self, session: &Session<Crypto>, now: u64) -> bool {
+        now.saturating_sub(session.last_used) > self.idle_timeout
+    }
+
+    /// Store the receiver `context` as the session `id`, used at `now`.
+    ///
+    /// If the shard of the session is full, its expired sessions are evicted
+    /// and then the least recently used one.
+    /// Returns [`HpkeError::InvalidInput`] if the session already exists.
+    pub fn insert(
+        &self,
+        id: SessionId,
+        context: Context<Crypto>,
+        now: u64,
+    ) -> Result<(), HpkeError> {
+        let mut shard = self.shard(&id)?;
+        if shard
+            .get(&id)
+            .is_some_and(|session| !self.is_expired(session, now))
+        {
+            return Err(HpkeError::InvalidInput);
+        }
+        if let Some(capacity) = self.capacity {
+            let per_shard = capacity.div_ceil(self.shards.len()).max(1);
+            if shard.len() >= per_shard {
+                shard.retain(|_, session| !self.is_expired(session, now));
+            }
+            if shard.len() >= per_shard {
+                let lru = shard
+                    .iter()
+                    .min_by_key(|(_, session)| session.last_used)
+                    .map(|(id, _)| *id);
+                if let Some(lru) = lru {
+                    shard.remove(&lru);
+                }
+            }
+        }
+        shard.insert(
+            id,
+            Session {
+                context,
+                last_used: now,
+            },
+        );
+        Ok(())
+    }
+
+    /// Set up a receiver context for `enc` with `hpke` (see
+    /// [`Hpke::setup_receiver`]) and store it at `now` as the session
+    /// [derived from `enc`](SessionId::from_enc).
+    ///
+    /// Returns the session id, or [`HpkeError::InvalidInput`] if the session
+    /// already exists, e.g. because `enc` was replayed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn accept(
+        &self,
+        hpke: &Hpke<Crypto>,
+        enc: &[u8],
+        sk_r: &HpkePrivateKey,
+        info: &[u8],
+        psk: Option<&[u8]>,
+        psk_id: Option<&[u8]>,
+        pk_s: Option<&HpkePublicKey>,
+        now: u64,
+    ) -> Result<SessionId, HpkeError> {
+        let id = SessionId::from_enc::<Crypto>(hpke.config().kem_id, enc)?;
+        let context = hpke.setup_receiver(enc, sk_r, info, psk, psk_id, pk_s)?;
+        self.insert(id, context, now)?;
+        Ok(id)
+    }
+
+    /// Call `f` with the context of the session `id` at `now`.
+    ///
+    /// This counts as a use of the session.
+    /// Returns [`HpkeError::UnknownSession`] if there is no such session or
+    /// it expired.
+    /// The lock of the session's shard is held while `f` runs.
+    pub fn with_session<R>(
+        &self,
+        id: &SessionId,
+        now: u64,
+        f: impl FnOnce(&mut Context<Crypto>) -> R,
+    ) -> Result<R, HpkeError> {
+        let mut shard = self.shard(id)?;
+        match shard.get_mut(id) {
+            Some(session) if !self.is_expired(session, now) => {
+                session.last_used = session.last_used.max(now);
+                Ok(f(&mut session.context))
+            }
+            Some(_) => {
+                shard.remove(id);
+                Err(HpkeError::UnknownSession)
+            }
+            None => Err(HpkeError::UnknownSession),
+        }
+    }
+
+    /// Open the ciphertext `ct` with the additional data `aad` in the
+    /// session `id` at `now`, see [`Context::open`].
+    pub fn open(
+        &self,
+        id: &SessionId,
+        aad: &[u8],
+        ct: &[u8],
+        now: u64,
+    ) -> Result<Plaintext, HpkeError> {
+        self.with_session(id, now, |context| context.open(aad, ct))?
+    }
+
+    /// Remove the session `id` and return its context if it exists.
+    pub fn remove(&self, id: &SessionId) -> Result<Option<Context<Crypto>>, HpkeError> {
+        Ok(self.shard(id)?.remove(id).map(|session| session.context))
+    }
+
+    /// Evict all sessions that expired at `now`.
+    ///
+    /// Returns the number of evicted sessions.
+    pub fn evict_expired(&self, now: u64) -> Result<usize, HpkeError> {
+        let mut evicted = 0;
+        for shard in self.shards.iter() {
+            let mut shard = shard.lock().map_err(|_| HpkeError::LockPoisoned)?;
+            let len = shard.len();
+            shard.retain(|_, session| !self.is_expired(session, now));
+            evicted += len - shard.len();
+        }
+        Ok(evicted)
+    }
+
+    /// Get the number of sessions, including expired sessions that haven't
+    /// been evicted yet.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap_or_else(PoisonError::into_inner).len())
+            .sum()
+    }
+
+    /// Returns `true` if the manager holds no sessions.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn new_shards<Crypto: HpkeCrypto>(shards: usize) -> Box<[Mutex<Shard<Crypto>>]> {
+    (0..shards).map(|_| Mutex::new(HashMap::new())).collect()
+}
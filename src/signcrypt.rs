@@ -0,0 +1,113 @@
+//! # Signcryption
+//!
+//! Sign-then-encrypt with Ed25519 and HPKE.
+//!
+//! The HPKE Auth modes authenticate the sender but are deniable: the receiver
+//! can't convince a third party that the sender produced a message.
+//! [`Hpke::signcrypt`] instead signs the plaintext with the sender's Ed25519
+//! key and seals the signature together with the plaintext.
+//! The signature is bound to the recipient and the HPKE context through the
+//! signed transcript
+//!
+//! ```text
+//! transcript = "hpke-rs signcrypt" || suite_id
+//!              || I2OSP(len(enc), 8) || enc
+//!              || I2OSP(len(pkRm), 8) || pkRm
+//!              || I2OSP(len(info), 8) || info
+//!              || I2OSP(len(aad), 8) || aad
+//!              || plaintext
+//! ```
+//!
+//! The HPKE plaintext is `signature || plaintext`.
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey, SIGNATURE_LENGTH};
+use hpke_rs_crypto::HpkeCrypto;
+
+use crate::{
+    util, Ciphertext, EncapsulatedSecret, Hpke, HpkeError, HpkePrivateKey, HpkePublicKey, Plaintext,
+};
+
+const SIGNCRYPT_LABEL: &[u8] = b"hpke-rs signcrypt";
+
+impl<Crypto: HpkeCrypto> Hpke<Crypto> {
+    /// Sign `plain_txt` with the Ed25519 `signing_key` and encrypt the
+    /// signature and the plain text to the public key `pk_r`.
+    ///
+    /// This must be used with the Base or PSK mode.
+    /// Returns the encapsulated secret and the ciphertext, or an error.
+    #[allow(clippy::too_many_arguments)]
+    pub fn signcrypt(
+        &self,
+        pk_r: &HpkePublicKey,
+        info: &[u8],
+        aad: &[u8],
+        plain_txt: &[u8],
+        psk: Option<&[u8]>,
+        psk_id: Option<&[u8]>,
+        signing_key: &SigningKey,
+    ) -> Result<(EncapsulatedSecret, Ciphertext), HpkeError> {
+        let (enc, mut context) = self.setup_sender(pk_r, info, psk, psk_id, None)?;
+        let transcript = self.signcrypt_transcript(&enc, pk_r.as_slice(), info, aad, plain_txt);
+        let signature = signing_key.sign(&transcript);
+        let payload = util::concat(&[&signature.to_bytes(), plain_txt]);
+        let ctxt = context.seal(aad, &payload)?;
+        Ok((enc, ctxt))
+    }
+
+    /// Decrypt the ciphertext `ct` with the private key `sk_r` and verify the
+    /// signature of the sender with the Ed25519 `verifying_key`.
+    ///
+    /// Returns the plain text if decryption and signature verification
+    /// succeed, [`HpkeError::InvalidSignature`] if the signature is invalid,
+    /// or another error.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_open(
+        &self,
+        enc: &[u8],
+        sk_r: &HpkePrivateKey,
+        info: &[u8],
+        aad: &[u8],
+        ct: &[u8],
+        psk: Option<&[u8]>,
+        psk_id: Option<&[u8]>,
+        verifying_key: &VerifyingKey,
+    ) -> Result<Plaintext, HpkeError> {
+        let mut context = self.setup_receiver(enc, sk_r, info, psk, psk_id, None)?;
+        let mut payload = context.open(aad, ct)?;
+        if payload.len() < SIGNATURE_LENGTH {
+            return Err(HpkeError::InvalidSignature);
+        }
+        let plain_txt = payload.split_off(SIGNATURE_LENGTH);
+        let signature = Signature::from_slice(&payload).map_err(|_| HpkeError::InvalidSignature)?;
+
+        let pk_r = Crypto::kem_derive_base(self.kem_id, &sk_r.value)?;
+        let transcript = self.signcrypt_transcript(enc, &pk_r, info, aad, &plain_txt);
+        verifying_key
+            .verify(&transcript, &signature)
+            .map_err(|_| HpkeError::InvalidSignature)?;
+        Ok(plain_txt)
+    }
+
+    fn signcrypt_transcript(
+        &self,
+        enc: &[u8],
+        pk_r: &[u8],
+        info: &[u8],
+        aad: &[u8],
+        plain_txt: &[u8],
+    ) -> Vec<u8> {
+        util::concat(&[
+            SIGNCRYPT_LABEL,
+            &self.suite_id(),
+            &(enc.len() as u64).to_be_bytes(),
+            enc,
+            &(pk_r.len() as u64).to_be_bytes(),
+            pk_r,
+            &(info.len() as u64).to_be_bytes(),
+            info,
+            &(aad.len() as u64).to_be_bytes(),
+            aad,
+            plain_txt,
+        ])
+    }
+}
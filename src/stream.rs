@@ -0,0 +1,391 @@
+//! # Streaming Encryption
+//!
+//! [`EncryptingWriter`] and [`DecryptingReader`] pipe data of any length
+//! through a [`Context`] with [`std::io::Write`] and [`std::io::Read`].
+//!
+//! The plain text is split into chunks of at most `chunk_size` bytes
+//! ([`DEFAULT_CHUNK_SIZE`] by default) that are sealed one after the other
+//! with the context.
+//! Each sealed chunk is written as a record with a 4 byte header that holds
+//! the length of the ciphertext and whether this is the last chunk:
+//!
+//! ```text
+//! ct = Seal(aad || I2OSP(last, 1), chunk)
+//! record = I2OSP(last << 31 | len(ct), 4) || ct
+//! ```
+//!
+//! Because the `last` flag is authenticated, a stream that is cut off at a
+//! record boundary fails to decrypt instead of appearing complete.
+//! The sequence numbers of the context ensure that records can't be
+//! reordered or dropped.
+//!
+//! The encapsulated secret of the context is not part of the stream and has
+//! to be sent ahead of it.
+//! Both sides have to use the same `aad` and chunk size.
+
+use std::io::{self, Read, Write};
+
+use hpke_rs_crypto::HpkeCrypto;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::{Context, HpkeError};
+
+/// The default size of the plain text chunks (64 KiB).
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The maximum size of the plain text chunks (16 MiB).
+pub const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+/// The length of the record header.
+pub(crate) const HEADER_LEN: usize = 4;
+
+/// The flag of the last record in the header.
+const LAST: u32 = 1 << 31;
+
+/// The framing of a stream, shared by the readers and writers.
+#[derive(Debug)]
+pub(crate) struct Framing {
+    /// The associated data with a trailing byte for the `last` flag.
+    aad: Vec<u8>,
+    chunk_size: usize,
+}
+
+impl Framing {
+    pub(crate) fn new(aad: &[u8]) -> Self {
+        let mut framed_aad = Vec::with_capacity(aad.len() + 1);
+        framed_aad.extend_from_slice(aad);
+        framed_aad.push(0);
+        Self {
+            aad: framed_aad,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+
+    /// Set the chunk size.
+    ///
+    /// Returns [`HpkeError::InvalidInput`] if it is `0` or larger than
+    /// [`MAX_CHUNK_SIZE`].
+    pub(crate) fn set_chunk_size(&mut self, chunk_size: usize) -> Result<(), HpkeError> {
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(HpkeError::InvalidInput);
+        }
+        self.chunk_size = chunk_size;
+        Ok(())
+    }
+
+    pub(crate) fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// The length of a stream of `pt_len` bytes sealed with `context`
+    /// without intermediate flushes.
+    pub(crate) fn ct_len<Crypto: HpkeCrypto>(
+        &self,
+        context: &Context<Crypto>,
+        pt_len: usize,
+    ) -> usize {
+        let records = pt_len.div_ceil(self.chunk_size).max(1);
+        pt_len + records * (HEADER_LEN + context.overhead())
+    }
+
+    fn aad(&mut self, last: bool) -> &[u8] {
+        *self.aad.last_mut().unwrap() = last.into();
+        &self.aad
+    }
+
+    /// Seal `chunk` with the `context` and append the record to `out`.
+    pub(crate) fn seal<Crypto: HpkeCrypto>(
+        &mut self,
+        context: &mut Context<Crypto>,
+        chunk: &[u8],
+        last: bool,
+        out: &mut Vec<u8>,
+    ) -> Result<(), HpkeError> {
+        debug_assert!(chunk.len() <= self.chunk_size);
+        let mut record = Zeroizing::new(Vec::with_capacity(
+            chunk.len() + context.config.aead_id.tag_length(),
+        ));
+        record.extend_from_slice(chunk);
+        context.seal_in_place(self.aad(last), &mut record)?;
+        let header = record.len() as u32 | if last { LAST } else { 0 };
+        out.extend_from_slice(&header.to_be_bytes());
+        out.extend_from_slice(&record);
+        Ok(())
+    }
+
+    /// Parse a record `header` of a stream sealed with `context`.
+    ///
+    /// Returns the length of the ciphertext and whether this is the last
+    /// record, or [`HpkeError::InvalidInput`] if the record is larger than
+    /// the chunk size allows.
+    pub(crate) fn header<Crypto: HpkeCrypto>(
+        &self,
+        context: &Context<Crypto>,
+        header: [u8; HEADER_LEN],
+    ) -> Result<(usize, bool), HpkeError> {
+        let header = u32::from_be_bytes(header);
+        let len = (header & !LAST) as usize;
+        if len > self.chunk_size + context.config.aead_id.tag_length() {
+            return Err(HpkeError::InvalidInput);
+        }
+        Ok((len, header & LAST != 0))
+    }
+
+    /// Open the ciphertext of a record in `buffer` in place.
+    pub(crate) fn open<Crypto: HpkeCrypto>(
+        &mut self,
+        context: &mut Context<Crypto>,
+        buffer: &mut Vec<u8>,
+        last: bool,
+    ) -> Result<(), HpkeError> {
+        context.open_in_place(self.aad(last), buffer)
+    }
+}
+
+/// Map an [`HpkeError`] to an [`io::Error`].
+pub(crate) fn io_error(e: HpkeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// The error for streams that end before the last record.
+pub(crate) fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "truncated HPKE stream")
+}
+
+/// A writer that encrypts everything written to it with a sender
+/// [`Context`], see the [module documentation](self).
+///
+/// [`finish`](Self::finish) has to be called after the last write to seal
+/// the last chunk.
+/// Dropping the writer without finishing leaves a stream that the reader
+/// rejects as truncated.
+pub struct EncryptingWriter<Crypto: 'static + HpkeCrypto, W: Write> {
+    inner: W,
+    context: Context<Crypto>,
+    framing: Framing,
+    /// The plain text of the current chunk.
+    chunk: Zeroizing<Vec<u8>>,
+    /// Sealed records that are not written to `inner` yet.
+    pending: Vec<u8>,
+}
+
+impl<Crypto: HpkeCrypto, W: Write> EncryptingWriter<Crypto, W> {
+    /// Encrypt everything written to this writer with the `context` and the
+    /// associated data `aad`, and write the records to `inner`.
+    pub fn new(context: Context<Crypto>, aad: &[u8], inner: W) -> Self {
+        Self {
+            inner,
+            context,
+            framing: Framing::new(aad),
+            chunk: Zeroizing::new(Vec::new()),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Split the plain text into chunks of `chunk_size` bytes.
+    ///
+    /// Returns [`HpkeError::InvalidInput`] if `chunk_size` is `0` or larger
+    /// than [`MAX_CHUNK_SIZE`].
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Result<Self, HpkeError> {
+        self.framing.set_chunk_size(chunk_size)?;
+        Ok(self)
+    }
+
+    /// The length of the stream for `pt_len` bytes of plain text.
+    ///
+    /// This is the plain text plus a header and a tag for every chunk.
+    /// Every [`flush`](Write::flush) that seals a partial chunk adds another
+    /// header and tag.
+    pub fn ct_len(&self, pt_len: usize) -> usize {
+        self.framing.ct_len(&self.context, pt_len)
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the underlying writer.
+    ///
+    /// Writing to it directly corrupts the stream.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Seal the last chunk, write all records, and flush the underlying
+    /// writer.
+    ///
+    /// Returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_pending()?;
+        self.seal_chunk(true)?;
+        self.write_pending()?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn seal_chunk(&mut self, last: bool) -> io::Result<()> {
+        self.framing
+            .seal(&mut self.context, &self.chunk, last, &mut self.pending)
+            .map_err(io_error)?;
+        self.chunk.zeroize();
+        Ok(())
+    }
+
+    fn write_pending(&mut self) -> io::Result<()> {
+        while !self.pending.is_empty() {
+            match self.inner.write(&self.pending) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.pending.drain(..n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Crypto: HpkeCrypto, W: Write> Write for EncryptingWriter<Crypto, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_pending()?;
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // A full chunk is only sealed once more data arrives because the
+        // last chunk has to be marked as such.
+        if self.chunk.len() == self.framing.chunk_size() {
+            self.seal_chunk(false)?;
+            self.write_pending()?;
+        }
+        let n = buf.len().min(self.framing.chunk_size() - self.chunk.len());
+        self.chunk.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    /// Seal the buffered plain text as a (shorter) chunk, write all records,
+    /// and flush the underlying writer.
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_pending()?;
+        if !self.chunk.is_empty() {
+            self.seal_chunk(false)?;
+            self.write_pending()?;
+        }
+        self.inner.flush()
+    }
+}
+
+/// A reader that decrypts a stream written by an [`EncryptingWriter`] with a
+/// receiver [`Context`], see the [module documentation](self).
+///
+/// Reading returns an [`io::ErrorKind::InvalidData`] error if a record
+/// fails to decrypt and an [`io::ErrorKind::UnexpectedEof`] error if the
+/// stream ends before the last record.
+/// Once an error occurred, every further read fails, including errors of
+/// the underlying reader because a record may have been read partially.
+/// Data after the last record is not read.
+pub struct DecryptingReader<Crypto: 'static + HpkeCrypto, R: Read> {
+    inner: R,
+    context: Context<Crypto>,
+    framing: Framing,
+    /// The plain text of the current record.
+    chunk: Zeroizing<Vec<u8>>,
+    /// The number of bytes of `chunk` that have been read.
+    position: usize,
+    finished: bool,
+    failed: bool,
+}
+
+impl<Crypto: HpkeCrypto, R: Read> DecryptingReader<Crypto, R> {
+    /// Decrypt the stream read from `inner` with the `context` and the
+    /// associated data `aad`.
+    pub fn new(context: Context<Crypto>, aad: &[u8], inner: R) -> Self {
+        Self {
+            inner,
+            context,
+            framing: Framing::new(aad),
+            chunk: Zeroizing::new(Vec::new()),
+            position: 0,
+            finished: false,
+            failed: false,
+        }
+    }
+
+    /// Accept chunks of up to `chunk_size` bytes.
+    ///
+    /// This has to be at least the chunk size of the writer.
+    /// Returns [`HpkeError::InvalidInput`] if `chunk_size` is `0` or larger
+    /// than [`MAX_CHUNK_SIZE`].
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Result<Self, HpkeError> {
+        self.framing.set_chunk_size(chunk_size)?;
+        Ok(self)
+    }
+
+    /// Whether the last record has been decrypted.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Unwrap the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn read_record(&mut self) -> io::Result<()> {
+        let mut header = [0u8; HEADER_LEN];
+        self.inner.read_exact(&mut header).map_err(|e| {
+            if e.kind() == io::ErrorKind::UnexpectedEof {
+                truncated()
+            } else {
+                e
+            }
+        })?;
+        let (len, last) = self
+            .framing
+            .header(&self.context, header)
+            .map_err(io_error)?;
+
+        self.chunk.zeroize();
+        self.chunk.resize(len, 0);
+        self.inner.read_exact(&mut self.chunk)?;
+        self.framing
+            .open(&mut self.context, &mut self.chunk, last)
+            .map_err(io_error)?;
+        self.position = 0;
+        self.finished = last;
+        Ok(())
+    }
+}
+
+impl<Crypto: HpkeCrypto, R: Read> Read for DecryptingReader<Crypto, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.failed {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "HPKE stream failed",
+                ));
+            }
+            if self.position < self.chunk.len() {
+                let n = buf.len().min(self.chunk.len() - self.position);
+                buf[..n].copy_from_slice(&self.chunk[self.position..self.position + n]);
+                self.position += n;
+                return Ok(n);
+            }
+            if self.finished || buf.is_empty() {
+                return Ok(0);
+            }
+            if let Err(e) = self.read_record() {
+                self.failed = true;
+                self.chunk.zeroize();
+                return Err(e);
+            }
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! # Thread-safe Sender Context
+//!
+//! [`Context::seal`] takes `&mut self` to advance the sequence number.
+//! A [`SyncSenderContext`] advances it atomically instead such that many
+//! threads can seal with the same context without a lock.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use hpke_rs_crypto::HpkeCrypto;
+
+use crate::{
+    audit::{self, AuditOperation},
+    check_payload, compute_nonce,
+    error_context::{self, Stage},
+    fault,
+    limits::{Input, InputLimits},
+    next_seq,
+    util::ct_eq,
+    verify_seal, Ciphertext, Context, ContextSecrets, HpkeConfig, HpkeError, Nonce,
+};
+
+/// A sender context that can be shared between threads.
+///
+/// Create it from a sender [`Context`] with
+/// [`Context::into_sync_sender`].
+///
+/// Every call to [`seal`](Self::seal) reserves the next sequence number.
+/// When sealing concurrently, the order in which ciphertexts are produced is
+/// not the order of their sequence numbers.
+/// The receiver opens messages in sequence order, so the returned sequence
+/// number has to be used to deliver the ciphertexts in that order.
+pub struct SyncSenderContext<Crypto: 'static + HpkeCrypto> {
+    secrets: Box<ContextSecrets>,
+    cipher: Option<Crypto::AeadCipher>,
+    sequence_number: AtomicU32,
+    epoch: u16,
+    config: HpkeConfig,
+    auditor: Option<audit::Auditor>,
+    error_context: Option<error_context::Recorder>,
+    limits: InputLimits,
+    fault_checks: bool,
+}
+
+impl<Crypto: HpkeCrypto> std::fmt::Debug for SyncSenderContext<Crypto> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SyncSenderContext")
+            .field("sequence_number", &self.sequence_number)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Crypto: HpkeCrypto> Context<Crypto> {
+    /// Turn this sender context into a [`SyncSenderContext`] that can seal
+    /// through a shared reference.
+    ///
+    /// Returns [`HpkeError::InvalidConfig`] if automatic ratcheting is enabled
+    /// because ratcheting requires exclusive access to the context, and
+    /// [`HpkeError::ContextPoisoned`] if the context is poisoned.
+    pub fn into_sync_sender(self) -> Result<SyncSenderContext<Crypto>, HpkeError> {
+        self.check_poisoned()?;
+        if self.ratchet_interval.is_some() {
+            return Err(HpkeError::InvalidConfig);
+        }
+        Ok(SyncSenderContext {
+            secrets: self.secrets,
+            cipher: self.cipher,
+            sequence_number: AtomicU32::new(self.sequence_number),
+            epoch: self.epoch,
+            config: self.config,
+            auditor: self.auditor,
+            error_context: self.error_context,
+            limits: self.limits,
+            fault_checks: self.fault_checks,
+        })
+    }
+}
+
+impl<Crypto: HpkeCrypto> SyncSenderContext<Crypto> {
+    /// Seal the plain text with the next sequence number.
+    ///
+    /// Returns the sequence number that was used together with the
+    /// ciphertext.
+    /// Once all sequence numbers are used up, this returns
+    /// [`HpkeError::MessageLimitReached`].
+    /// Oversized inputs are rejected with [`HpkeError::PayloadTooLarge`]
+    /// before a sequence number is reserved.
+    /// A sequence number is consumed even if sealing fails.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            err(level = "debug"),
+            fields(len = plain_txt.len())
+        )
+    )]
+    pub fn seal(&self, aad: &[u8], plain_txt: &[u8]) -> Result<(u32, Ciphertext), HpkeError> {
+        let mut sequence_number = None;
+        let result = self.seal_with_seq(aad, plain_txt, &mut sequence_number);
+        let result = error_context::add(&self.error_context, Stage::Seal, self.config, result);
+        audit::report(
+            &self.auditor,
+            AuditOperation::Seal,
+            self.config,
+            sequence_number,
+            &result,
+        );
+        result
+    }
+
+    /// [`seal`](Self::seal) that sets `sequence_number` once it is reserved.
+    fn seal_with_seq(
+        &self,
+        aad: &[u8],
+        plain_txt: &[u8],
+        sequence_number: &mut Option<u32>,
+    ) -> Result<(u32, Ciphertext), HpkeError> {
+        let cipher = self.cipher.as_ref().ok_or(HpkeError::ExportOnly)?;
+        let aead_id = self.config.aead_id;
+        self.limits.check(Input::Aad, aad.len())?;
+        check_payload(aead_id, aad.len(), plain_txt.len())?;
+        let seq = self
+            .sequence_number
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |seq| {
+                next_seq::<Crypto>(aead_id, seq).ok()
+            })
+            .map_err(|_| HpkeError::MessageLimitReached)?;
+        *sequence_number = Some(seq);
+        let nonce = fault::checked(
+            self.fault_checks,
+            || Ok::<_, HpkeError>(compute_nonce(&self.secrets.nonce, self.epoch, seq)),
+            |a: &Nonce, b: &Nonce| ct_eq(a, b),
+        )?;
+        let ctxt = Crypto::aead_cipher_seal(cipher, &nonce, aad, plain_txt)?;
+        if self.fault_checks {
+            verify_seal::<Crypto>(cipher, &nonce, aad, &ctxt, plain_txt)?;
+        }
+        Ok((seq, ctxt))
+    }
+
+    /// Like [`Context::export`].
+    pub fn export(&self, exporter_context: &[u8], length: usize) -> Result<Vec<u8>, HpkeError> {
+        let result = self
+            .limits
+            .check(Input::ExporterContext, exporter_context.len())
+            .and_then(|()| {
+                self.config.export::<Crypto>(
+                    &self.secrets.exporter_secret,
+                    exporter_context,
+                    length,
+                )
+            });
+        let result = error_context::add(&self.error_context, Stage::Export, self.config, result);
+        audit::report(
+            &self.auditor,
+            AuditOperation::Export,
+            self.config,
+            None,
+            &result,
+        );
+        result
+    }
+
+    /// Like [`Context::export_array`].
+    pub fn export_array<const N: usize>(
+        &self,
+        exporter_context: &[u8],
+    ) -> Result<[u8; N], HpkeError> {
+        let result = self
+            .limits
+            .check(Input::ExporterContext, exporter_context.len())
+            .and_then(|()| {
+                self.config
+                    .export_array::<Crypto, N>(&self.secrets.exporter_secret, exporter_context)
+            });
+        let result = error_context::add(&self.error_context, Stage::Export, self.config, result);
+        audit::report(
+            &self.auditor,
+            AuditOperation::Export,
+            self.config,
+            None,
+            &result,
+        );
+        result
+    }
+
+    /// The sequence number the next [`seal`](Self::seal) will use.
+    pub fn sequence_number(&self) -> u32 {
+        self.sequence_number.load(Ordering::Relaxed)
+    }
+
+    /// Like [`Context::last_error_context`].
+    pub fn last_error_context(&self) -> Option<error_context::ErrorContext> {
+        self.error_context.as_ref()?.last()
+    }
+}
@@ -0,0 +1,110 @@
+//! # Key Schedule Templates
+//!
+//! The key schedule hashes the `info` and `psk_id` into the
+//! `key_schedule_context` on every setup.
+//! Servers that use the same `info` for every connection can compute this
+//! once with a [`KeyScheduleTemplate`] such that each setup only derives the
+//! secrets from the fresh shared secret.
+
+use hpke_rs_crypto::HpkeCrypto;
+use zeroize::Zeroize;
+
+use crate::{
+    Context, EncapsulatedSecret, Hpke, HpkeError, HpkePrivateKey, HpkePublicKey, HpkeVersion,
+};
+
+/// The precomputed, static inputs of the key schedule.
+///
+/// Create it with [`Hpke::key_schedule_template`].
+/// Contexts set up with a template are the same as the ones set up with the
+/// [`Hpke`] configuration, `info`, and PSK of the template.
+pub struct KeyScheduleTemplate<Crypto: 'static + HpkeCrypto> {
+    hpke: Hpke<Crypto>,
+    suite_id: [u8; 10],
+    key_schedule_context: Vec<u8>,
+    psk: Vec<u8>,
+}
+
+impl<Crypto: HpkeCrypto> std::fmt::Debug for KeyScheduleTemplate<Crypto> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyScheduleTemplate")
+            .field("config", &self.hpke.config())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Crypto: HpkeCrypto> Drop for KeyScheduleTemplate<Crypto> {
+    fn drop(&mut self) {
+        self.psk.zeroize();
+    }
+}
+
+impl<Crypto: HpkeCrypto> Hpke<Crypto> {
+    /// Precompute the key schedule for `info` and the optional PSK.
+    ///
+    /// The PSK inputs are checked as in [`key_schedule`](Self::key_schedule).
+    /// Templates are only available for RFC 9180 and return
+    /// [`HpkeError::InvalidConfig`] for other versions.
+    pub fn key_schedule_template(
+        &self,
+        info: &[u8],
+        psk: Option<&[u8]>,
+        psk_id: Option<&[u8]>,
+    ) -> Result<KeyScheduleTemplate<Crypto>, HpkeError> {
+        self.check_policy()?;
+        if self.version != HpkeVersion::Rfc9180 {
+            return Err(HpkeError::InvalidConfig);
+        }
+        let psk = psk.unwrap_or_default();
+        let psk_id = psk_id.unwrap_or_default();
+        self.verify_psk_inputs(psk, psk_id)?;
+        let suite_id = self.suite_id();
+        Ok(KeyScheduleTemplate {
+            hpke: self.clone(),
+            suite_id,
+            key_schedule_context: self.key_schedule_context(info, psk_id, &suite_id),
+            psk: psk.to_vec(),
+        })
+    }
+}
+
+impl<Crypto: HpkeCrypto> KeyScheduleTemplate<Crypto> {
+    /// Run the key schedule with the `shared_secret`.
+    pub fn key_schedule(&self, shared_secret: &[u8]) -> Result<Context<Crypto>, HpkeError> {
+        #[cfg(feature = "kat-debug")]
+        self.hpke.dump("shared_secret", shared_secret);
+        let (key, base_nonce, exporter_secret) = self.hpke.derive_secrets(
+            shared_secret,
+            &self.psk,
+            &self.suite_id,
+            &self.key_schedule_context,
+        )?;
+        self.hpke.new_context(key, base_nonce, exporter_secret)
+    }
+
+    /// Set up an HPKE sender to `pk_r` with this template.
+    ///
+    /// See [`Hpke::setup_sender`].
+    pub fn setup_sender(
+        &self,
+        pk_r: &HpkePublicKey,
+        sk_s: Option<&HpkePrivateKey>,
+    ) -> Result<(EncapsulatedSecret, Context<Crypto>), HpkeError> {
+        let randomness = self.hpke.random(self.hpke.kem_id.private_key_len())?;
+        let (zz, enc) = self.hpke.encaps(pk_r, sk_s, &randomness)?;
+        Ok((enc, self.key_schedule(&zz)?))
+    }
+
+    /// Set up an HPKE receiver for `enc` with this template.
+    ///
+    /// See [`Hpke::setup_receiver`].
+    pub fn setup_receiver(
+        &self,
+        enc: &[u8],
+        sk_r: &HpkePrivateKey,
+        pk_s: Option<&HpkePublicKey>,
+    ) -> Result<Context<Crypto>, HpkeError> {
+        let zz = self.hpke.decaps(enc, sk_r, pk_s)?;
+        self.key_schedule(&zz)
+    }
+}
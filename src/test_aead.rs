@@ -18,3 +18,117 @@ fn test_aes_gcm_128_self() {
         HpkeRustCrypto::aead_open(AeadAlgorithm::Aes128Gcm, &key, &nonce, &aad, &ctxt).unwrap();
     assert_eq!(&ptxt, msg);
 }
+
+/// Read a variable-length quantity from a blobby file.
+fn read_vlq(data: &[u8], pos: &mut usize) -> usize {
+    let mut b = data[*pos];
+    *pos += 1;
+    let mut val = (b & 0x7f) as usize;
+    while b & 0x80 != 0 {
+        b = data[*pos];
+        *pos += 1;
+        val = ((val + 1) << 7) + (b & 0x7f) as usize;
+    }
+    val
+}
+
+/// Decode all blobs in a blobby file.
+fn read_blobs(data: &[u8]) -> Vec<&[u8]> {
+    let mut pos = 0;
+    let mut dedup = vec![];
+    for _ in 0..read_vlq(data, &mut pos) {
+        let len = read_vlq(data, &mut pos);
+        dedup.push(&data[pos..pos + len]);
+        pos += len;
+    }
+    let mut blobs = vec![];
+    while pos < data.len() {
+        let val = read_vlq(data, &mut pos);
+        if val & 1 == 1 {
+            blobs.push(dedup[val >> 1]);
+        } else {
+            let len = val >> 1;
+            blobs.push(&data[pos..pos + len]);
+            pos += len;
+        }
+    }
+    blobs
+}
+
+/// Run the Wycheproof test vectors in `data`.
+///
+/// Every test case consists of key, nonce, aad, plaintext, ciphertext (with
+/// tag), and a pass/fail flag.
+fn wycheproof(alg: AeadAlgorithm, data: &[u8]) {
+    let blobs = read_blobs(data);
+    assert_eq!(blobs.len() % 6, 0);
+    for (i, test) in blobs.chunks(6).enumerate() {
+        let [key, nonce, aad, pt, ct, result] = test else {
+            unreachable!()
+        };
+        match result {
+            [1] => {
+                let ctxt = HpkeRustCrypto::aead_seal(alg, key, nonce, aad, pt).unwrap();
+                assert_eq!(&ctxt, ct, "{:?} test case {}", alg, i);
+                let ptxt = HpkeRustCrypto::aead_open(alg, key, nonce, aad, ct).unwrap();
+                assert_eq!(&ptxt, pt, "{:?} test case {}", alg, i);
+            }
+            [0] => assert!(
+                HpkeRustCrypto::aead_open(alg, key, nonce, aad, ct).is_err(),
+                "{:?} test case {}",
+                alg,
+                i
+            ),
+            _ => panic!("Invalid test result {:?}", result),
+        }
+    }
+}
+
+#[test]
+fn test_aes_gcm_128_wycheproof() {
+    wycheproof(
+        AeadAlgorithm::Aes128Gcm,
+        include_bytes!("../tests/wycheproof/aes_128_gcm.blb"),
+    );
+}
+
+#[test]
+fn test_aes_gcm_256_wycheproof() {
+    wycheproof(
+        AeadAlgorithm::Aes256Gcm,
+        include_bytes!("../tests/wycheproof/aes_256_gcm.blb"),
+    );
+}
+
+#[test]
+fn test_chacha20_poly1305_wycheproof() {
+    wycheproof(
+        AeadAlgorithm::ChaCha20Poly1305,
+        include_bytes!("../tests/wycheproof/chacha20_poly1305.blb"),
+    );
+}
+
+#[test]
+fn test_payload_limits() {
+    use crate::{check_payload, HpkeError};
+
+    for aead in [AeadAlgorithm::Aes128Gcm, AeadAlgorithm::Aes256Gcm] {
+        let max = usize::try_from(aead.max_plaintext_length()).unwrap();
+        assert_eq!(max, (1 << 36) - 32);
+        assert!(check_payload(aead, 0, max).is_ok());
+        assert_eq!(
+            check_payload(aead, 0, max + 1),
+            Err(HpkeError::PayloadTooLarge)
+        );
+        assert_eq!(
+            check_payload(aead, 1 << 61, 0),
+            Err(HpkeError::PayloadTooLarge)
+        );
+    }
+    let max = usize::try_from(AeadAlgorithm::ChaCha20Poly1305.max_plaintext_length()).unwrap();
+    assert!(check_payload(AeadAlgorithm::ChaCha20Poly1305, usize::MAX, max).is_ok());
+    assert_eq!(
+        check_payload(AeadAlgorithm::ChaCha20Poly1305, 0, max + 1),
+        Err(HpkeError::PayloadTooLarge)
+    );
+}
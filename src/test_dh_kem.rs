@@ -0,0 +1,71 @@
+use hpke_rs_crypto::{error::Error, types::KemAlgorithm, HpkeCrypto};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+use crate::{dh_kem::*, kem::ciphersuite, test_util::hex_to_bytes, HpkeVersion};
+
+// X25519 test vectors from RFC 7748 section 5.2.
+const X25519_VECTORS: [(&str, &str, &str); 2] = [
+    (
+        "a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4",
+        "e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c",
+        "c3da55379de9c6908e94ea4df28d084f32eccf03491c71f754b4075577a28552",
+    ),
+    (
+        "4b66e9d4d1b4673c5ad22691957d6af5c11b6421e0ea01d42ca4169e7918ba0d",
+        "e5210f12786811d3f4b7959d0538ae2c31dbe7106fc03c3efc4cd549c715a493",
+        "95cbde9476e8907d7aade45cb4b873f88b595a68799fa152e6f8f7647aac7957",
+    ),
+];
+
+// Public keys of low order and non-canonical encodings of them, from the
+// list at https://cr.yp.to/ecdh.html#validate.
+// The full Wycheproof X25519 test vectors are in `tests/test_wycheproof.rs`.
+const X25519_LOW_ORDER: [&str; 7] = [
+    "0000000000000000000000000000000000000000000000000000000000000000",
+    "0100000000000000000000000000000000000000000000000000000000000000",
+    "e0eb7a7c3b41b8ae1656e3faf19fc46ada098deb9c32b1fd866205165f49b800",
+    "5f9c95bca3508c24b1d0b1559c83ef5b04445cc4581c8e86d8224eddd09f1157",
+    "ecffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+    "edffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+    "eeffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+];
+
+#[test]
+fn test_x25519() {
+    for (sk, pk, expected) in X25519_VECTORS {
+        let (sk, mut pk, expected) = (hex_to_bytes(sk), hex_to_bytes(pk), hex_to_bytes(expected));
+        let dh = HpkeRustCrypto::kem_derive(KemAlgorithm::DhKem25519, &pk, &sk).unwrap();
+        assert_eq!(dh, expected);
+
+        // The most significant bit of the public key must be ignored.
+        pk[31] |= 0x80;
+        let dh = HpkeRustCrypto::kem_derive(KemAlgorithm::DhKem25519, &pk, &sk).unwrap();
+        assert_eq!(dh, expected);
+    }
+}
+
+#[test]
+fn test_x25519_low_order() {
+    let alg = KemAlgorithm::DhKem25519;
+    let version = HpkeVersion::Rfc9180;
+    let suite_id = ciphersuite(alg);
+    let sk = hex_to_bytes(X25519_VECTORS[0].0);
+    let pk = HpkeRustCrypto::kem_derive_base(alg, &sk).unwrap();
+    for low_order in X25519_LOW_ORDER {
+        let low_order = hex_to_bytes(low_order);
+        // Low order points result in an all-zero DH output, which the KEM
+        // must reject.
+        assert!(matches!(
+            encaps::<HpkeRustCrypto>(alg, version, &low_order, None, &suite_id, &[7; 32]),
+            Err(Error::KemInvalidPublicKey)
+        ));
+        assert!(matches!(
+            decaps::<HpkeRustCrypto>(alg, version, &low_order, None, &sk, &suite_id),
+            Err(Error::KemInvalidPublicKey)
+        ));
+        assert!(matches!(
+            auth_decaps::<HpkeRustCrypto>(alg, version, &pk, None, &sk, &low_order, &suite_id),
+            Err(Error::KemInvalidPublicKey)
+        ));
+    }
+}
@@ -23,3 +23,110 @@ fn test_hkdf_sha256() {
     assert_eq!(&expected_prk, &prk);
     assert_eq!(&expected_okm, &okm);
 }
+
+#[test]
+fn test_raw_hkdf_api() {
+    use hpke_rs_rust_crypto::hkdf;
+
+    // RFC 5869 test case 1
+    let ikm = hex_to_bytes("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b");
+    let salt = hex_to_bytes("000102030405060708090a0b0c");
+    let info = hex_to_bytes("f0f1f2f3f4f5f6f7f8f9");
+
+    let prk = hkdf::sha256_extract(&salt, &ikm);
+    assert_eq!(
+        prk,
+        hex_to_bytes("077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5")
+    );
+    assert_eq!(
+        hkdf::sha256_expand(&prk, &info, 42).unwrap(),
+        hex_to_bytes(
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+        )
+    );
+
+    // The per-hash functions are the provider's KDF.
+    let prk = hkdf::sha384_extract(&salt, &ikm);
+    assert_eq!(
+        prk,
+        HpkeRustCrypto::kdf_extract(KdfAlgorithm::HkdfSha384, &salt, &ikm)
+    );
+    assert_eq!(
+        hkdf::sha384_expand(&prk, &info, 100).unwrap(),
+        HpkeRustCrypto::kdf_expand(KdfAlgorithm::HkdfSha384, &prk, &info, 100).unwrap()
+    );
+    let prk = hkdf::sha512_extract(&salt, &ikm);
+    assert_eq!(
+        prk,
+        HpkeRustCrypto::kdf_extract(KdfAlgorithm::HkdfSha512, &salt, &ikm)
+    );
+    assert_eq!(
+        hkdf::sha512_expand(&prk, &info, 100).unwrap(),
+        HpkeRustCrypto::kdf_expand(KdfAlgorithm::HkdfSha512, &prk, &info, 100).unwrap()
+    );
+
+    // Too much output
+    assert!(hkdf::sha512_expand(&prk, &info, 255 * 64 + 1).is_err());
+
+    // The private-use HKDF-SHA3-256
+    let prk = hkdf::sha3_256_extract(&salt, &ikm);
+    assert_eq!(prk.len(), 32);
+    assert_ne!(prk, hkdf::sha256_extract(&salt, &ikm));
+    assert_eq!(
+        prk,
+        HpkeRustCrypto::kdf_extract(KdfAlgorithm::HkdfSha3_256, &salt, &ikm)
+    );
+    assert_eq!(
+        hkdf::sha3_256_expand(&prk, &info, 100).unwrap(),
+        HpkeRustCrypto::kdf_expand(KdfAlgorithm::HkdfSha3_256, &prk, &info, 100).unwrap()
+    );
+    assert!(hkdf::sha3_256_expand(&prk, &info, 255 * 32 + 1).is_err());
+}
+
+#[test]
+fn test_kmac256() {
+    use hpke_rs_rust_crypto::kmac;
+
+    // NIST SP 800-185 KMAC samples 4 and 6
+    let key = hex_to_bytes("404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f");
+    let customization = b"My Tagged Application";
+    assert_eq!(
+        kmac::kmac256(&key, &hex_to_bytes("00010203"), 64, customization),
+        hex_to_bytes(
+            "20c570c31346f703c9ac36c61c03cb64c3970d0cfc787e9b79599d273a68d2f7\
+             f69d4cc3de9d104a351689f27cf6f5951f0103f33f4f24871024d9c27773a8dd"
+        )
+    );
+    let data: Vec<u8> = (0..=0xc7).collect();
+    assert_eq!(
+        kmac::kmac256(&key, &data, 64, customization),
+        hex_to_bytes(
+            "b58618f71f92e1d56c1b8c55ddd7cd188b97b4ca4d99831eb2699a837da2e4d9\
+             70fbacfde50033aea585f1a2708510c32d07880801bd182898fe476876fc8965"
+        )
+    );
+
+    // The private-use KMAC256 KDF
+    let ikm = hex_to_bytes("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b");
+    let salt = hex_to_bytes("000102030405060708090a0b0c");
+    let info = hex_to_bytes("f0f1f2f3f4f5f6f7f8f9");
+    let prk = kmac::kmac256_extract(&salt, &ikm);
+    assert_eq!(prk, kmac::kmac256(&salt, &ikm, 64, b"HPKE-Extract"));
+    assert_eq!(
+        prk,
+        HpkeRustCrypto::kdf_extract(KdfAlgorithm::Kmac256, &salt, &ikm)
+    );
+    let okm = kmac::kmac256_expand(&prk, &info, 100).unwrap();
+    assert_eq!(okm, kmac::kmac256(&prk, &info, 100, b"HPKE-Expand"));
+    assert_eq!(
+        okm,
+        HpkeRustCrypto::kdf_expand(KdfAlgorithm::Kmac256, &prk, &info, 100).unwrap()
+    );
+    // Outputs of different lengths are unrelated.
+    assert_ne!(
+        okm[..32],
+        kmac::kmac256_expand(&prk, &info, 32).unwrap()[..]
+    );
+    assert!(kmac::kmac256_expand(&prk[..32], &info, 32).is_err());
+    assert!(kmac::kmac256_expand(&prk, &info, 255 * 64 + 1).is_err());
+}
@@ -0,0 +1,621 @@
+//! # Tink Hybrid Encryption
+//!
+//! With the `tink` feature, HPKE keysets of [Tink] can be read and written,
+//! and [`HybridEncrypt`] and [`HybridDecrypt`] produce and consume the
+//! ciphertexts of Tink's HPKE hybrid encryption.
+//! This allows to migrate away from Tink (or to interoperate with it) one
+//! party at a time.
+//!
+//! Tink uses HPKE in the base mode with the context info as HPKE `info` and an
+//! empty `aad`.
+//! A ciphertext is
+//!
+//! ```text
+//! output_prefix || enc || ciphertext
+//! ```
+//!
+//! where the output prefix is `0x01 || key_id` for `TINK` keys,
+//! `0x00 || key_id` for `LEGACY` and `CRUNCHY` keys, and empty for `RAW` keys.
+//!
+//! Only the binary protobuf keyset encoding is supported, not Tink's JSON
+//! encoding or encrypted keysets.
+//!
+//! [Tink]: https://developers.google.com/tink
+
+use hpke_rs_crypto::{
+    types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm},
+    HpkeCrypto,
+};
+
+use crate::{util::take, Hpke, HpkeError, HpkePrivateKey, HpkePublicKey, Mode};
+
+const PUBLIC_KEY_TYPE_URL: &str = "type.googleapis.com/google.crypto.tink.HpkePublicKey";
+const PRIVATE_KEY_TYPE_URL: &str = "type.googleapis.com/google.crypto.tink.HpkePrivateKey";
+
+// `KeyStatusType.ENABLED`
+const STATUS_ENABLED: u64 = 1;
+// `KeyData.KeyMaterialType`
+const ASYMMETRIC_PRIVATE: u64 = 2;
+const ASYMMETRIC_PUBLIC: u64 = 3;
+
+/// The output prefix type of a Tink key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputPrefixType {
+    /// `0x01 || key_id`
+    Tink,
+    /// `0x00 || key_id`
+    Legacy,
+    /// No prefix.
+    Raw,
+    /// `0x00 || key_id`
+    Crunchy,
+}
+
+impl OutputPrefixType {
+    fn from_proto(value: u64) -> Result<Self, HpkeError> {
+        match value {
+            1 => Ok(Self::Tink),
+            2 => Ok(Self::Legacy),
+            3 => Ok(Self::Raw),
+            4 => Ok(Self::Crunchy),
+            _ => Err(HpkeError::UnknownMode),
+        }
+    }
+
+    fn to_proto(self) -> u64 {
+        match self {
+            Self::Tink => 1,
+            Self::Legacy => 2,
+            Self::Raw => 3,
+            Self::Crunchy => 4,
+        }
+    }
+
+    fn prefix(self, key_id: u32) -> Vec<u8> {
+        let version = match self {
+            Self::Tink => 0x01,
+            Self::Legacy | Self::Crunchy => 0x00,
+            Self::Raw => return Vec::new(),
+        };
+        let mut prefix = vec![version];
+        prefix.extend_from_slice(&key_id.to_be_bytes());
+        prefix
+    }
+}
+
+/// An HPKE key of a Tink keyset.
+#[derive(Debug, PartialEq)]
+pub struct TinkKey {
+    /// The Tink key id.
+    pub key_id: u32,
+    /// Whether the key is enabled.
+    pub enabled: bool,
+    /// The output prefix type.
+    pub output_prefix_type: OutputPrefixType,
+    /// The KEM.
+    pub kem: KemAlgorithm,
+    /// The KDF.
+    pub kdf: KdfAlgorithm,
+    /// The AEAD.
+    pub aead: AeadAlgorithm,
+    /// The public key.
+    pub public_key: HpkePublicKey,
+    /// The private key, if this is a private keyset.
+    pub private_key: Option<HpkePrivateKey>,
+}
+
+impl TinkKey {
+    fn hpke<Crypto: HpkeCrypto>(&self) -> Hpke<Crypto> {
+        Hpke::new(Mode::Base, self.kem, self.kdf, self.aead)
+    }
+
+    fn output_prefix(&self) -> Vec<u8> {
+        self.output_prefix_type.prefix(self.key_id)
+    }
+
+    /// Decode the `KeyData` of an HPKE key.
+    ///
+    /// Returns `None` if it is not an HPKE key.
+    #[allow(clippy::type_complexity)]
+    fn decode_key_data(
+        bytes: &[u8],
+    ) -> Result<
+        Option<(
+            KemAlgorithm,
+            KdfAlgorithm,
+            AeadAlgorithm,
+            HpkePublicKey,
+            Option<HpkePrivateKey>,
+        )>,
+        HpkeError,
+    > {
+        let mut type_url: &[u8] = &[];
+        let mut value: &[u8] = &[];
+        for_each_field(bytes, |field, v| {
+            match (field, v) {
+                (1, Value::Bytes(b)) => type_url = b,
+                (2, Value::Bytes(b)) => value = b,
+                _ => (),
+            }
+            Ok(())
+        })?;
+        let (public_key, private_key) = if type_url == PRIVATE_KEY_TYPE_URL.as_bytes() {
+            let (public_key, private_key) = decode_hpke_private_key(value)?;
+            (public_key, Some(private_key))
+        } else if type_url == PUBLIC_KEY_TYPE_URL.as_bytes() {
+            (value, None)
+        } else {
+            return Ok(None);
+        };
+        let (kem, kdf, aead, public_key) = decode_hpke_public_key(public_key)?;
+        Ok(Some((kem, kdf, aead, public_key.into(), private_key)))
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        let mut params = Vec::new();
+        write_varint_field(&mut params, 1, kem_to_proto(self.kem));
+        write_varint_field(&mut params, 2, kdf_to_proto(self.kdf));
+        write_varint_field(&mut params, 3, aead_to_proto(self.aead));
+        let mut public_key = Vec::new();
+        write_bytes_field(&mut public_key, 2, &params);
+        write_bytes_field(&mut public_key, 3, self.public_key.as_slice());
+
+        let mut key_data = Vec::new();
+        match &self.private_key {
+            Some(private_key) => {
+                let mut private = Vec::new();
+                write_bytes_field(&mut private, 2, &public_key);
+                write_bytes_field(&mut private, 3, &private_key.value);
+                write_bytes_field(&mut key_data, 1, PRIVATE_KEY_TYPE_URL.as_bytes());
+                write_bytes_field(&mut key_data, 2, &private);
+                write_varint_field(&mut key_data, 3, ASYMMETRIC_PRIVATE);
+            }
+            None => {
+                write_bytes_field(&mut key_data, 1, PUBLIC_KEY_TYPE_URL.as_bytes());
+                write_bytes_field(&mut key_data, 2, &public_key);
+                write_varint_field(&mut key_data, 3, ASYMMETRIC_PUBLIC);
+            }
+        }
+
+        let mut key = Vec::new();
+        write_bytes_field(&mut key, 1, &key_data);
+        write_varint_field(&mut key, 2, if self.enabled { STATUS_ENABLED } else { 2 });
+        write_varint_field(&mut key, 3, self.key_id.into());
+        write_varint_field(&mut key, 4, self.output_prefix_type.to_proto());
+        write_bytes_field(out, 2, &key);
+    }
+}
+
+/// A Tink keyset of HPKE keys.
+#[derive(Debug, PartialEq)]
+pub struct TinkKeyset {
+    /// The key id of the primary key, which is used to encrypt.
+    pub primary_key_id: u32,
+    /// The HPKE keys.
+    pub keys: Vec<TinkKey>,
+}
+
+impl TinkKeyset {
+    /// Create a private keyset with a fresh key pair for `hpke` with the
+    /// Tink key id `key_id` as primary key.
+    ///
+    /// Returns [`HpkeError::InvalidConfig`] if `hpke` is not in the base mode
+    /// and [`HpkeError::UnknownMode`] for algorithms Tink doesn't support.
+    pub fn generate<Crypto: HpkeCrypto>(
+        hpke: &Hpke<Crypto>,
+        key_id: u32,
+        output_prefix_type: OutputPrefixType,
+    ) -> Result<Self, HpkeError> {
+        if hpke.mode != Mode::Base {
+            return Err(HpkeError::InvalidConfig);
+        }
+        if kem_to_proto(hpke.kem_id) == 0
+            || kdf_to_proto(hpke.kdf_id) == 0
+            || aead_to_proto(hpke.aead_id) == 0
+        {
+            return Err(HpkeError::UnknownMode);
+        }
+        let (private_key, public_key) = hpke.generate_key_pair()?.into_keys();
+        Ok(Self {
+            primary_key_id: key_id,
+            keys: vec![TinkKey {
+                key_id,
+                enabled: true,
+                output_prefix_type,
+                kem: hpke.kem_id,
+                kdf: hpke.kdf_id,
+                aead: hpke.aead_id,
+                public_key,
+                private_key: Some(private_key),
+            }],
+        })
+    }
+
+    /// The public keyset of this keyset.
+    pub fn public_keyset(&self) -> Self {
+        Self {
+            primary_key_id: self.primary_key_id,
+            keys: self
+                .keys
+                .iter()
+                .map(|key| TinkKey {
+                    private_key: None,
+                    public_key: key.public_key.clone(),
+                    ..*key
+                })
+                .collect(),
+        }
+    }
+
+    /// Decode a binary `Keyset` protobuf.
+    ///
+    /// Keys that are not HPKE keys are skipped.
+    /// Returns [`HpkeError::InvalidInput`] if the encoding is malformed and
+    /// [`HpkeError::UnknownMode`] for unsupported HPKE parameters.
+    pub fn decode(bytes: &[u8]) -> Result<Self, HpkeError> {
+        let mut primary_key_id = 0;
+        let mut keys = Vec::new();
+        for_each_field(bytes, |field, value| {
+            match (field, value) {
+                (1, Value::Varint(v)) => primary_key_id = varint_u32(v)?,
+                (2, Value::Bytes(b)) => {
+                    if let Some(key) = decode_key(b)? {
+                        keys.push(key);
+                    }
+                }
+                _ => (),
+            }
+            Ok(())
+        })?;
+        Ok(Self {
+            primary_key_id,
+            keys,
+        })
+    }
+
+    /// Encode the keyset as binary `Keyset` protobuf.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_varint_field(&mut out, 1, self.primary_key_id.into());
+        for key in &self.keys {
+            key.encode(&mut out);
+        }
+        out
+    }
+
+    fn primary(&self) -> Result<&TinkKey, HpkeError> {
+        self.keys
+            .iter()
+            .find(|key| key.key_id == self.primary_key_id && key.enabled)
+            .ok_or(HpkeError::InvalidConfig)
+    }
+}
+
+/// Tink's `HybridEncrypt` with the primary key of a keyset.
+pub struct HybridEncrypt<Crypto: 'static + HpkeCrypto> {
+    hpke: Hpke<Crypto>,
+    public_key: HpkePublicKey,
+    output_prefix: Vec<u8>,
+}
+
+impl<Crypto: HpkeCrypto> std::fmt::Debug for HybridEncrypt<Crypto> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HybridEncrypt")
+            .field("suite", &self.hpke.config())
+            .field("output_prefix", &self.output_prefix)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Crypto: HpkeCrypto> HybridEncrypt<Crypto> {
+    /// Encrypt with the primary key of the (public or private) `keyset`.
+    ///
+    /// Returns [`HpkeError::InvalidConfig`] if the primary key is missing or
+    /// disabled.
+    pub fn new(keyset: &TinkKeyset) -> Result<Self, HpkeError> {
+        let primary = keyset.primary()?;
+        Ok(Self {
+            hpke: primary.hpke(),
+            public_key: primary.public_key.clone(),
+            output_prefix: primary.output_prefix(),
+        })
+    }
+
+    /// Encrypt `plaintext` bound to `context_info`.
+    pub fn encrypt(&self, plaintext: &[u8], context_info: &[u8]) -> Result<Vec<u8>, HpkeError> {
+        let (enc, ct) = self.hpke.seal(
+            &self.public_key,
+            context_info,
+            &[],
+            plaintext,
+            None,
+            None,
+            None,
+        )?;
+        let mut out = Vec::with_capacity(self.output_prefix.len() + enc.len() + ct.len());
+        out.extend_from_slice(&self.output_prefix);
+        out.extend_from_slice(&enc);
+        out.extend_from_slice(&ct);
+        Ok(out)
+    }
+}
+
+/// Tink's `HybridDecrypt` with the keys of a private keyset.
+pub struct HybridDecrypt<Crypto: 'static + HpkeCrypto> {
+    keys: Vec<(Vec<u8>, Hpke<Crypto>, HpkePrivateKey)>,
+}
+
+impl<Crypto: HpkeCrypto> std::fmt::Debug for HybridDecrypt<Crypto> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HybridDecrypt")
+            .field("keys", &self.keys.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Crypto: HpkeCrypto> HybridDecrypt<Crypto> {
+    /// Decrypt with the enabled keys of the private `keyset`.
+    ///
+    /// Returns [`HpkeError::InvalidConfig`] if a key has no private key.
+    pub fn new(keyset: &TinkKeyset) -> Result<Self, HpkeError> {
+        let keys = keyset
+            .keys
+            .iter()
+            .filter(|key| key.enabled)
+            .map(|key| {
+                let private_key = key.private_key.as_ref().ok_or(HpkeError::InvalidConfig)?;
+                Ok((
+                    key.output_prefix(),
+                    key.hpke(),
+                    private_key.value.clone().into(),
+                ))
+            })
+            .collect::<Result<_, HpkeError>>()?;
+        Ok(Self { keys })
+    }
+
+    /// Decrypt `ciphertext` bound to `context_info`.
+    ///
+    /// The keys with a matching output prefix are tried first, then the `RAW`
+    /// keys.
+    /// Returns [`HpkeError::OpenError`] if no key decrypts the ciphertext.
+    pub fn decrypt(&self, ciphertext: &[u8], context_info: &[u8]) -> Result<Vec<u8>, HpkeError> {
+        let prefixed = self
+            .keys
+            .iter()
+            .filter(|(prefix, _, _)| !prefix.is_empty() && ciphertext.starts_with(prefix));
+        let raw = self.keys.iter().filter(|(prefix, _, _)| prefix.is_empty());
+        for (prefix, hpke, sk) in prefixed.chain(raw) {
+            let ciphertext = &ciphertext[prefix.len()..];
+            let nenc = hpke.nenc();
+            if ciphertext.len() < nenc {
+                continue;
+            }
+            let (enc, ct) = ciphertext.split_at(nenc);
+            if let Ok(pt) = hpke.open(enc, sk, context_info, &[], ct, None, None, None) {
+                return Ok(pt);
+            }
+        }
+        Err(HpkeError::OpenError)
+    }
+}
+
+/// Decode a `Keyset.Key`.
+///
+/// Returns `None` for keys that are not HPKE keys.
+fn decode_key(bytes: &[u8]) -> Result<Option<TinkKey>, HpkeError> {
+    let mut key_data = None;
+    let mut status = 0;
+    let mut key_id = 0;
+    let mut output_prefix_type = 0;
+    for_each_field(bytes, |field, value| {
+        match (field, value) {
+            (1, Value::Bytes(b)) => key_data = Some(b),
+            (2, Value::Varint(v)) => status = v,
+            (3, Value::Varint(v)) => key_id = varint_u32(v)?,
+            (4, Value::Varint(v)) => output_prefix_type = v,
+            _ => (),
+        }
+        Ok(())
+    })?;
+    let key_data = key_data.ok_or(HpkeError::InvalidInput)?;
+    let Some((kem, kdf, aead, public_key, private_key)) = TinkKey::decode_key_data(key_data)?
+    else {
+        return Ok(None);
+    };
+    Ok(Some(TinkKey {
+        key_id,
+        enabled: status == STATUS_ENABLED,
+        output_prefix_type: OutputPrefixType::from_proto(output_prefix_type)?,
+        kem,
+        kdf,
+        aead,
+        public_key,
+        private_key,
+    }))
+}
+
+/// Decode an `HpkePrivateKey` into the encoded `HpkePublicKey` and the
+/// private key.
+fn decode_hpke_private_key(bytes: &[u8]) -> Result<(&[u8], HpkePrivateKey), HpkeError> {
+    let mut public_key: &[u8] = &[];
+    let mut private_key: &[u8] = &[];
+    for_each_field(bytes, |field, value| {
+        match (field, value) {
+            (2, Value::Bytes(b)) => public_key = b,
+            (3, Value::Bytes(b)) => private_key = b,
+            _ => (),
+        }
+        Ok(())
+    })?;
+    Ok((public_key, private_key.into()))
+}
+
+/// Decode an `HpkePublicKey`.
+fn decode_hpke_public_key(
+    bytes: &[u8],
+) -> Result<(KemAlgorithm, KdfAlgorithm, AeadAlgorithm, Vec<u8>), HpkeError> {
+    let mut params: &[u8] = &[];
+    let mut public_key: &[u8] = &[];
+    for_each_field(bytes, |field, value| {
+        match (field, value) {
+            (2, Value::Bytes(b)) => params = b,
+            (3, Value::Bytes(b)) => public_key = b,
+            _ => (),
+        }
+        Ok(())
+    })?;
+    let (mut kem, mut kdf, mut aead) = (0, 0, 0);
+    for_each_field(params, |field, value| {
+        match (field, value) {
+            (1, Value::Varint(v)) => kem = v,
+            (2, Value::Varint(v)) => kdf = v,
+            (3, Value::Varint(v)) => aead = v,
+            _ => (),
+        }
+        Ok(())
+    })?;
+    Ok((
+        kem_from_proto(kem)?,
+        kdf_from_proto(kdf)?,
+        aead_from_proto(aead)?,
+        public_key.to_vec(),
+    ))
+}
+
+// Tink's `HpkeKem`, `HpkeKdf`, and `HpkeAead` enums.
+
+fn kem_from_proto(value: u64) -> Result<KemAlgorithm, HpkeError> {
+    match value {
+        1 => Ok(KemAlgorithm::DhKem25519),
+        2 => Ok(KemAlgorithm::DhKemP256),
+        3 => Ok(KemAlgorithm::DhKemP384),
+        4 => Ok(KemAlgorithm::DhKemP521),
+        _ => Err(HpkeError::UnknownMode),
+    }
+}
+
+fn kem_to_proto(kem: KemAlgorithm) -> u64 {
+    match kem {
+        KemAlgorithm::DhKem25519 => 1,
+        KemAlgorithm::DhKemP256 => 2,
+        KemAlgorithm::DhKemP384 => 3,
+        KemAlgorithm::DhKemP521 => 4,
+        // Not supported by Tink.
+        KemAlgorithm::DhKem448 => 0,
+    }
+}
+
+fn kdf_from_proto(value: u64) -> Result<KdfAlgorithm, HpkeError> {
+    match value {
+        1 => Ok(KdfAlgorithm::HkdfSha256),
+        2 => Ok(KdfAlgorithm::HkdfSha384),
+        3 => Ok(KdfAlgorithm::HkdfSha512),
+        _ => Err(HpkeError::UnknownMode),
+    }
+}
+
+fn kdf_to_proto(kdf: KdfAlgorithm) -> u64 {
+    match kdf {
+        KdfAlgorithm::HkdfSha256 => 1,
+        KdfAlgorithm::HkdfSha384 => 2,
+        KdfAlgorithm::HkdfSha512 => 3,
+        // Not supported by Tink.
+        KdfAlgorithm::HkdfSha3_256 | KdfAlgorithm::Kmac256 => 0,
+    }
+}
+
+fn aead_from_proto(value: u64) -> Result<AeadAlgorithm, HpkeError> {
+    match value {
+        1 => Ok(AeadAlgorithm::Aes128Gcm),
+        2 => Ok(AeadAlgorithm::Aes256Gcm),
+        3 => Ok(AeadAlgorithm::ChaCha20Poly1305),
+        _ => Err(HpkeError::UnknownMode),
+    }
+}
+
+fn aead_to_proto(aead: AeadAlgorithm) -> u64 {
+    match aead {
+        AeadAlgorithm::Aes128Gcm => 1,
+        AeadAlgorithm::Aes256Gcm => 2,
+        AeadAlgorithm::ChaCha20Poly1305 => 3,
+        // Not supported by Tink.
+        AeadAlgorithm::HpkeExport => 0,
+    }
+}
+
+// A minimal protobuf wire format codec.
+
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+fn varint_u32(value: u64) -> Result<u32, HpkeError> {
+    u32::try_from(value).map_err(|_| HpkeError::InvalidInput)
+}
+
+fn read_varint(reader: &mut &[u8]) -> Result<u64, HpkeError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = take(reader, 1)?[0];
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(HpkeError::InvalidInput)
+}
+
+/// Call `f` with every field of the message in `bytes`.
+/// Fixed-size fields are skipped.
+fn for_each_field<'a>(
+    bytes: &'a [u8],
+    mut f: impl FnMut(u64, Value<'a>) -> Result<(), HpkeError>,
+) -> Result<(), HpkeError> {
+    let mut reader = bytes;
+    while !reader.is_empty() {
+        let tag = read_varint(&mut reader)?;
+        let field = tag >> 3;
+        match tag & 0x7 {
+            0 => f(field, Value::Varint(read_varint(&mut reader)?))?,
+            1 => {
+                take(&mut reader, 8)?;
+            }
+            2 => {
+                let len = usize::try_from(read_varint(&mut reader)?)
+                    .map_err(|_| HpkeError::InvalidInput)?;
+                f(field, Value::Bytes(take(&mut reader, len)?))?
+            }
+            5 => {
+                take(&mut reader, 4)?;
+            }
+            _ => return Err(HpkeError::InvalidInput),
+        }
+    }
+    Ok(())
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_varint_field(out: &mut Vec<u8>, field: u64, value: u64) {
+    // Default values are not encoded.
+    if value != 0 {
+        write_varint(out, field << 3);
+        write_varint(out, value);
+    }
+}
+
+fn write_bytes_field(out: &mut Vec<u8>, field: u64, value: &[u8]) {
+    if !value.is_empty() {
+        write_varint(out, (field << 3) | 2);
+        write_varint(out, value.len() as u64);
+        out.extend_from_slice(value);
+    }
+}
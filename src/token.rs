@@ -0,0 +1,261 @@
+//! # Tokens
+//!
+//! Compact, URL-safe single-shot messages for encrypted cookies, magic links,
+//! and OAuth-style state blobs.
+//!
+//! A token is the unpadded base64url encoding of
+//!
+//! ```text
+//! struct {
+//!     uint16 kem_id;
+//!     uint16 kdf_id;
+//!     uint16 aead_id;
+//!     uint64 expires_at;     // seconds since the Unix epoch, 0: never
+//!     opaque enc[Nenc];
+//!     opaque ciphertext[];
+//! } Token;
+//! ```
+//!
+//! The ciphertext is sealed in the base mode with the header, i.e. the
+//! ciphersuite and the expiry, followed by the `aad` as additional data such
+//! that the expiry can't be changed.
+//!
+//! ## Compact Tokens
+//!
+//! A [`CompactToken`] is a dot-separated text encoding without an expiry for
+//! HTTP headers, query parameters, and JWT-like systems:
+//!
+//! ```text
+//! hpke.<suite>.<enc>.<ct>
+//! ```
+//!
+//! The segments after the `hpke` prefix are unpadded base64url, with
+//! `suite = I2OSP(kem_id, 2) || I2OSP(kdf_id, 2) || I2OSP(aead_id, 2)`.
+//! Receivers can [parse](CompactToken::parse) a token to pick the
+//! configuration and key for its suite before opening it with
+//! [`Hpke::open_compact`].
+//! The suite is bound by the key schedule of the base mode.
+
+use hpke_rs_crypto::HpkeCrypto;
+
+use crate::{
+    ciphersuite::Ciphersuite,
+    util::{self, base64_decode, base64_encode, take, take_u16, take_u64, BASE64_URL},
+    Hpke, HpkeError, HpkePrivateKey, HpkePublicKey, Mode, Plaintext,
+};
+
+/// The length of the token header.
+const HEADER_LEN: usize = 2 + 2 + 2 + 8;
+
+/// The first segment of a compact token.
+const COMPACT_PREFIX: &str = "hpke";
+
+/// A compact token `hpke.<suite>.<enc>.<ct>`, see
+/// [Compact Tokens](self#compact-tokens).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactToken {
+    /// The ciphersuite of the token.
+    pub suite: Ciphersuite,
+    /// The encapsulated secret.
+    pub enc: Vec<u8>,
+    /// The ciphertext.
+    pub ct: Vec<u8>,
+}
+
+impl CompactToken {
+    /// Parse a compact `token`.
+    ///
+    /// Returns [`HpkeError::InvalidInput`] if the token is malformed and
+    /// [`HpkeError::UnknownMode`] if an algorithm of the suite is unknown.
+    pub fn parse(token: &str) -> Result<Self, HpkeError> {
+        let mut segments = token.split('.');
+        if segments.next() != Some(COMPACT_PREFIX) {
+            return Err(HpkeError::InvalidInput);
+        }
+        let mut segment = || {
+            let segment = segments.next().ok_or(HpkeError::InvalidInput)?;
+            base64_decode(BASE64_URL, segment, false)
+        };
+        let (suite, enc, ct) = (segment()?, segment()?, segment()?);
+        if segments.next().is_some() {
+            return Err(HpkeError::InvalidInput);
+        }
+
+        let mut reader = suite.as_slice();
+        let (kem, kdf, aead) = (
+            take_u16(&mut reader)?,
+            take_u16(&mut reader)?,
+            take_u16(&mut reader)?,
+        );
+        if !reader.is_empty() {
+            return Err(HpkeError::InvalidInput);
+        }
+        Ok(Self {
+            suite: Ciphersuite::from_ids(kem, kdf, aead)?,
+            enc,
+            ct,
+        })
+    }
+
+    /// Encode this token.
+    pub fn encode(&self) -> String {
+        let (kem_id, kdf_id, aead_id) = self.suite.ids();
+        let suite = util::concat(&[
+            &kem_id.to_be_bytes(),
+            &kdf_id.to_be_bytes(),
+            &aead_id.to_be_bytes(),
+        ]);
+        [
+            COMPACT_PREFIX,
+            &base64_encode(BASE64_URL, &suite, false),
+            &base64_encode(BASE64_URL, &self.enc, false),
+            &base64_encode(BASE64_URL, &self.ct, false),
+        ]
+        .join(".")
+    }
+}
+
+impl std::fmt::Display for CompactToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.encode())
+    }
+}
+
+impl std::str::FromStr for CompactToken {
+    type Err = HpkeError;
+    fn from_str(token: &str) -> Result<Self, HpkeError> {
+        Self::parse(token)
+    }
+}
+
+impl<Crypto: HpkeCrypto> Hpke<Crypto> {
+    /// Seal `plain_txt` to `pk_r` as a URL-safe token.
+    ///
+    /// If `expires_at` is set, [`open_token`](Self::open_token) rejects the
+    /// token from that time on, in seconds since the Unix epoch.
+    /// This must be used with the Base mode and returns
+    /// [`HpkeError::InvalidConfig`] otherwise.
+    pub fn seal_token(
+        &self,
+        pk_r: &HpkePublicKey,
+        info: &[u8],
+        aad: &[u8],
+        plain_txt: &[u8],
+        expires_at: Option<u64>,
+    ) -> Result<String, HpkeError> {
+        if self.mode != Mode::Base || expires_at == Some(0) {
+            return Err(HpkeError::InvalidConfig);
+        }
+        let (kem_id, kdf_id, aead_id) = self.ciphersuite().ids();
+        let header = util::concat(&[
+            &kem_id.to_be_bytes(),
+            &kdf_id.to_be_bytes(),
+            &aead_id.to_be_bytes(),
+            &expires_at.unwrap_or(0).to_be_bytes(),
+        ]);
+        let (enc, ct) = self.seal(
+            pk_r,
+            info,
+            &util::concat(&[&header, aad]),
+            plain_txt,
+            None,
+            None,
+            None,
+        )?;
+        Ok(base64_encode(
+            BASE64_URL,
+            &util::concat(&[&header, &enc, &ct]),
+            false,
+        ))
+    }
+
+    /// Open a `token` sealed with [`seal_token`](Self::seal_token) with the
+    /// private key `sk_r`.
+    ///
+    /// `now` is the current time in seconds since the Unix epoch.
+    /// Returns [`HpkeError::Expired`] if the token expired at or before
+    /// `now`, [`HpkeError::InvalidConfig`] if the ciphersuite of the token is
+    /// not the one of this configuration or this is not the Base mode, and
+    /// [`HpkeError::InvalidInput`] if the token is malformed.
+    pub fn open_token(
+        &self,
+        token: &str,
+        sk_r: &HpkePrivateKey,
+        info: &[u8],
+        aad: &[u8],
+        now: u64,
+    ) -> Result<Plaintext, HpkeError> {
+        let bytes = base64_decode(BASE64_URL, token, false)?;
+        let mut reader = bytes.as_slice();
+        let suite = (
+            take_u16(&mut reader)?,
+            take_u16(&mut reader)?,
+            take_u16(&mut reader)?,
+        );
+        if self.mode != Mode::Base || suite != self.ciphersuite().ids() {
+            return Err(HpkeError::InvalidConfig);
+        }
+        let expires_at = take_u64(&mut reader)?;
+        let enc = take(&mut reader, self.nenc())?;
+
+        let header = &bytes[..HEADER_LEN];
+        let plain_txt = self.open(
+            enc,
+            sk_r,
+            info,
+            &util::concat(&[header, aad]),
+            reader,
+            None,
+            None,
+            None,
+        )?;
+        // Only authentic tokens are reported as expired.
+        if expires_at != 0 && expires_at <= now {
+            return Err(HpkeError::Expired);
+        }
+        Ok(plain_txt)
+    }
+
+    /// Seal `plain_txt` to `pk_r` as a [`CompactToken`].
+    ///
+    /// This must be used with the Base mode and returns
+    /// [`HpkeError::InvalidConfig`] otherwise.
+    pub fn seal_compact(
+        &self,
+        pk_r: &HpkePublicKey,
+        info: &[u8],
+        aad: &[u8],
+        plain_txt: &[u8],
+    ) -> Result<String, HpkeError> {
+        if self.mode != Mode::Base {
+            return Err(HpkeError::InvalidConfig);
+        }
+        let (enc, ct) = self.seal(pk_r, info, aad, plain_txt, None, None, None)?;
+        let token = CompactToken {
+            suite: self.ciphersuite(),
+            enc,
+            ct,
+        };
+        Ok(token.encode())
+    }
+
+    /// Open a compact `token` sealed with
+    /// [`seal_compact`](Self::seal_compact) with the private key `sk_r`.
+    ///
+    /// Returns [`HpkeError::InvalidConfig`] if the ciphersuite of the token is
+    /// not the one of this configuration or this is not the Base mode, and
+    /// the errors of [`CompactToken::parse`] if the token is malformed.
+    pub fn open_compact(
+        &self,
+        token: &str,
+        sk_r: &HpkePrivateKey,
+        info: &[u8],
+        aad: &[u8],
+    ) -> Result<Plaintext, HpkeError> {
+        let token = CompactToken::parse(token)?;
+        if self.mode != Mode::Base || token.suite != self.ciphersuite() {
+            return Err(HpkeError::InvalidConfig);
+        }
+        self.open(&token.enc, sk_r, info, aad, &token.ct, None, None, None)
+    }
+}
@@ -1,12 +1,177 @@
+use std::ops::{Deref, DerefMut};
+
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::HpkeError;
+
 #[inline]
 pub(crate) fn concat(values: &[&[u8]]) -> Vec<u8> {
     values.join(&[][..])
 }
 
+/// Compare `a` and `b` in constant time, e.g. MACs.
+pub(crate) fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// A secret of at most `N` bytes that is stored inline.
+///
+/// The context keeps its key, nonce, and exporter secret in these to avoid
+/// heap allocations. The bytes are zeroized on drop.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct FixedBytes<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> FixedBytes<N> {
+    /// Copy `value` into a new buffer.
+    /// Returns an [`HpkeError::InvalidConfig`] if `value` is longer than `N`.
+    pub(crate) fn from_slice(value: &[u8]) -> Result<Self, HpkeError> {
+        if value.len() > N {
+            return Err(HpkeError::InvalidConfig);
+        }
+        let mut bytes = [0u8; N];
+        bytes[..value.len()].copy_from_slice(value);
+        Ok(Self {
+            bytes,
+            len: value.len(),
+        })
+    }
+
+    /// Move `value` into a new buffer and zeroize it.
+    pub(crate) fn from_vec(mut value: Vec<u8>) -> Result<Self, HpkeError> {
+        let out = Self::from_slice(&value);
+        value.zeroize();
+        out
+    }
+}
+
+impl<const N: usize> Deref for FixedBytes<N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl<const N: usize> DerefMut for FixedBytes<N> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes[..self.len]
+    }
+}
+
+impl<const N: usize> std::fmt::Debug for FixedBytes<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
+/// Split `len` bytes off the front of `reader`.
+#[inline]
+pub(crate) fn take<'a>(reader: &mut &'a [u8], len: usize) -> Result<&'a [u8], HpkeError> {
+    if reader.len() < len {
+        return Err(HpkeError::InvalidInput);
+    }
+    let (out, rest) = reader.split_at(len);
+    *reader = rest;
+    Ok(out)
+}
+
+/// Read a big-endian `u16` from the front of `reader`.
 #[inline]
-pub(crate) fn xor_bytes(a: &[u8], b: &[u8]) -> Vec<u8> {
-    assert_eq!(a.len(), b.len());
-    a.iter().zip(b).map(|(x, y)| x ^ y).collect()
+pub(crate) fn take_u16(reader: &mut &[u8]) -> Result<u16, HpkeError> {
+    let bytes = take(reader, 2)?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// Read a big-endian `u32` from the front of `reader`.
+#[inline]
+pub(crate) fn take_u32(reader: &mut &[u8]) -> Result<u32, HpkeError> {
+    let bytes = take(reader, 4)?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Read a big-endian `u64` from the front of `reader`.
+#[inline]
+pub(crate) fn take_u64(reader: &mut &[u8]) -> Result<u64, HpkeError> {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(take(reader, 8)?);
+    Ok(u64::from_be_bytes(bytes))
+}
+
+/// The standard base64 alphabet (RFC 4648, Section 4).
+pub(crate) const BASE64: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The URL and filename safe base64 alphabet (RFC 4648, Section 5).
+pub(crate) const BASE64_URL: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Base64 encode `bytes` with the `alphabet`, with `=` padding if `pad`.
+pub(crate) fn base64_encode(alphabet: &[u8; 64], bytes: &[u8], pad: bool) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let mut block = [0u8; 3];
+        block[..chunk.len()].copy_from_slice(chunk);
+        let n = u32::from_be_bytes([0, block[0], block[1], block[2]]);
+        for i in 0..=chunk.len() {
+            out.push(alphabet[(n >> (18 - 6 * i) & 0x3f) as usize].into());
+        }
+        if pad {
+            for _ in chunk.len()..3 {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Base64 decode `encoded` with the `alphabet`.
+///
+/// Padding is required if `pad` and rejected otherwise.
+/// Returns [`HpkeError::InvalidInput`] for invalid characters and
+/// non-canonical encodings.
+pub(crate) fn base64_decode(
+    alphabet: &[u8; 64],
+    encoded: &str,
+    pad: bool,
+) -> Result<Vec<u8>, HpkeError> {
+    let mut encoded = encoded.as_bytes();
+    if pad {
+        if encoded.len() % 4 != 0 {
+            return Err(HpkeError::InvalidInput);
+        }
+        for _ in 0..2 {
+            if let Some(rest) = encoded.strip_suffix(b"=") {
+                encoded = rest;
+            }
+        }
+    }
+    if encoded.len() % 4 == 1 {
+        return Err(HpkeError::InvalidInput);
+    }
+    let mut out = Vec::with_capacity(encoded.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in encoded {
+        let value = alphabet
+            .iter()
+            .position(|a| a == c)
+            .ok_or(HpkeError::InvalidInput)?;
+        acc = (acc << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    // The unused bits of the last character must be zero.
+    if acc != 0 {
+        return Err(HpkeError::InvalidInput);
+    }
+    Ok(out)
 }
 
 #[test]
@@ -16,6 +181,53 @@ fn test_concat() {
     let expected = "blablaRFCXXXX ";
     assert_eq!(
         expected.as_bytes()[..],
-        concat(&[&a.as_bytes(), &b.as_bytes()])[..]
+        concat(&[a.as_bytes(), b.as_bytes()])[..]
     )
 }
+
+#[test]
+fn test_fixed_bytes() {
+    let bytes = FixedBytes::<4>::from_slice(&[1, 2, 3]).unwrap();
+    assert_eq!(&bytes[..], &[1, 2, 3]);
+    assert!(FixedBytes::<4>::from_vec(vec![0; 5]).is_err());
+}
+
+#[test]
+fn test_base64() {
+    // RFC 4648, Section 10
+    let vectors = [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
+    for (plain, encoded) in vectors {
+        assert_eq!(base64_encode(BASE64, plain.as_bytes(), true), encoded);
+        assert_eq!(
+            base64_decode(BASE64, encoded, true).unwrap(),
+            plain.as_bytes()
+        );
+        let unpadded = encoded.trim_end_matches('=');
+        assert_eq!(base64_encode(BASE64, plain.as_bytes(), false), unpadded);
+        assert_eq!(
+            base64_decode(BASE64, unpadded, false).unwrap(),
+            plain.as_bytes()
+        );
+    }
+    assert_eq!(base64_encode(BASE64, &[0xfb, 0xff], true), "+/8=");
+    assert_eq!(base64_encode(BASE64_URL, &[0xfb, 0xff], false), "-_8");
+    assert_eq!(
+        base64_decode(BASE64_URL, "-_8", false).unwrap(),
+        [0xfb, 0xff]
+    );
+    assert!(base64_decode(BASE64_URL, "+/8", false).is_err());
+
+    for invalid in ["Zg=", "Zh==", "Zg", "Z===", "Zm9v!", "Zm9=v"] {
+        assert!(base64_decode(BASE64, invalid, true).is_err(), "{invalid}");
+    }
+    assert!(base64_decode(BASE64, "Zg==", false).is_err());
+    assert!(base64_decode(BASE64, "Z", false).is_err());
+}
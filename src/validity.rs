@@ -0,0 +1,161 @@
+//! # Validity Periods
+//!
+//! Messages that are only valid between a not-before and a not-after time,
+//! e.g. encrypted commands that must not be replayed after they expired.
+//!
+//! The validity period is sent in front of the ciphertext and is part of
+//! the additional data such that it can't be changed.
+//!
+//! ```text
+//! struct {
+//!     uint64 not_before;     // 0: no lower bound
+//!     uint64 not_after;      // 2^64-1: no upper bound
+//!     opaque ciphertext[];   // sealed with aad = not_before || not_after || aad
+//! } TimedCiphertext;
+//! ```
+
+use hpke_rs_crypto::HpkeCrypto;
+
+use crate::{
+    util::{take, take_u64},
+    Ciphertext, Context, EncapsulatedSecret, Hpke, HpkeError, HpkePrivateKey, HpkePublicKey,
+    Plaintext,
+};
+
+pub(crate) const ENCODED_LEN: usize = 16;
+
+/// The validity period of a message or a key, in seconds since the Unix
+/// epoch.
+///
+/// Both bounds are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Validity {
+    /// Not valid before this time.
+    pub not_before: Option<u64>,
+    /// Not valid after this time.
+    pub not_after: Option<u64>,
+}
+
+impl Validity {
+    /// A validity period that ends `ttl` seconds after `now`.
+    pub fn expires_in(now: u64, ttl: u64) -> Self {
+        Self {
+            not_before: Some(now),
+            not_after: Some(now.saturating_add(ttl)),
+        }
+    }
+
+    /// Check that `now` is in this validity period.
+    ///
+    /// Returns [`HpkeError::NotYetValid`] before and [`HpkeError::Expired`]
+    /// after the validity period.
+    pub fn check(&self, now: u64) -> Result<(), HpkeError> {
+        if self.not_before.is_some_and(|not_before| now < not_before) {
+            return Err(HpkeError::NotYetValid);
+        }
+        if self.not_after.is_some_and(|not_after| now > not_after) {
+            return Err(HpkeError::Expired);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn encode(&self) -> [u8; ENCODED_LEN] {
+        let mut out = [0u8; ENCODED_LEN];
+        out[..8].copy_from_slice(&self.not_before.unwrap_or(0).to_be_bytes());
+        out[8..].copy_from_slice(&self.not_after.unwrap_or(u64::MAX).to_be_bytes());
+        out
+    }
+
+    pub(crate) fn decode(mut encoded: &[u8]) -> Result<Self, HpkeError> {
+        let not_before = take_u64(&mut encoded)?;
+        let not_after = take_u64(&mut encoded)?;
+        Ok(Self {
+            not_before: (not_before != 0).then_some(not_before),
+            not_after: (not_after != u64::MAX).then_some(not_after),
+        })
+    }
+}
+
+impl<Crypto: HpkeCrypto> Context<Crypto> {
+    /// [Seal](Self::seal) `plain_txt` such that it can only be opened with
+    /// [`open_with_validity`](Self::open_with_validity) within the
+    /// `validity` period.
+    ///
+    /// The validity period is prepended to the ciphertext and authenticated.
+    pub fn seal_with_validity(
+        &mut self,
+        aad: &[u8],
+        plain_txt: &[u8],
+        validity: &Validity,
+    ) -> Result<Ciphertext, HpkeError> {
+        let encoded = validity.encode();
+        let ctxt = self.seal_with_aad_parts(&[&encoded, aad], plain_txt)?;
+        let mut out = Vec::with_capacity(ENCODED_LEN + ctxt.len());
+        out.extend_from_slice(&encoded);
+        out.extend_from_slice(&ctxt);
+        Ok(out)
+    }
+
+    /// [Open](Self::open) a `cipher_txt` sealed with
+    /// [`seal_with_validity`](Self::seal_with_validity) and check its
+    /// validity period against `now`, in seconds since the Unix epoch.
+    ///
+    /// The validity period is only checked for authentic messages, which
+    /// advance the sequence number even if they are rejected as
+    /// [`HpkeError::NotYetValid`] or [`HpkeError::Expired`].
+    pub fn open_with_validity(
+        &mut self,
+        aad: &[u8],
+        cipher_txt: &[u8],
+        now: u64,
+    ) -> Result<Plaintext, HpkeError> {
+        let mut reader = cipher_txt;
+        let encoded = take(&mut reader, ENCODED_LEN)?;
+        let validity = Validity::decode(encoded)?;
+        let ptxt = self.open_with_aad_parts(&[encoded, aad], reader)?;
+        validity.check(now)?;
+        Ok(ptxt)
+    }
+}
+
+impl<Crypto: HpkeCrypto> Hpke<Crypto> {
+    /// Single-shot [`Context::seal_with_validity`].
+    ///
+    /// See [`Hpke::seal`] for the other arguments.
+    #[allow(clippy::too_many_arguments)]
+    pub fn seal_with_validity(
+        &self,
+        pk_r: &HpkePublicKey,
+        info: &[u8],
+        aad: &[u8],
+        plain_txt: &[u8],
+        psk: Option<&[u8]>,
+        psk_id: Option<&[u8]>,
+        sk_s: Option<&HpkePrivateKey>,
+        validity: &Validity,
+    ) -> Result<(EncapsulatedSecret, Ciphertext), HpkeError> {
+        let (enc, mut context) = self.setup_sender(pk_r, info, psk, psk_id, sk_s)?;
+        let ctxt = context.seal_with_validity(aad, plain_txt, validity)?;
+        Ok((enc, ctxt))
+    }
+
+    /// Single-shot [`Context::open_with_validity`].
+    ///
+    /// See [`Hpke::open`] for the other arguments.
+    #[allow(clippy::too_many_arguments)]
+    pub fn open_with_validity(
+        &self,
+        enc: &[u8],
+        sk_r: &HpkePrivateKey,
+        info: &[u8],
+        aad: &[u8],
+        cipher_txt: &[u8],
+        psk: Option<&[u8]>,
+        psk_id: Option<&[u8]>,
+        pk_s: Option<&HpkePublicKey>,
+        now: u64,
+    ) -> Result<Plaintext, HpkeError> {
+        let mut context = self.setup_receiver(enc, sk_r, info, psk, psk_id, pk_s)?;
+        context.open_with_validity(aad, cipher_txt, now)
+    }
+}
@@ -0,0 +1,82 @@
+extern crate hpke_rs as hpke;
+
+use hpke::armor::{self, armor, dearmor};
+use hpke::prelude::*;
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+const ARMORED_KEY: &str = "-----BEGIN HPKE PUBLIC KEY-----
+3kQWMrcwZJmBNaDJNK7nVbSP2ECzw0Jt5ZAgGdhLzUk=
+=W/1f
+-----END HPKE PUBLIC KEY-----
+";
+
+#[test]
+fn public_key() {
+    let pk = HpkePublicKey::from_armor(ARMORED_KEY).unwrap();
+    assert_eq!(pk.as_slice().len(), 32);
+    assert_eq!(pk.to_armor(), ARMORED_KEY);
+
+    // Surrounding text, CRLF line endings, and indentation are ignored.
+    let pasted = format!(
+        "Hi, here is my key:\r\n\r\n{}\r\nThanks",
+        ARMORED_KEY
+            .lines()
+            .map(|line| format!("  {line}"))
+            .collect::<Vec<_>>()
+            .join("\r\n")
+    );
+    assert_eq!(HpkePublicKey::from_armor(&pasted).unwrap(), pk);
+
+    // Corrupted body
+    let corrupted = ARMORED_KEY.replacen("3kQW", "3kQX", 1);
+    assert_eq!(
+        HpkePublicKey::from_armor(&corrupted).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    // Missing checksum
+    let missing = ARMORED_KEY.replacen("=W/1f\n", "", 1);
+    assert_eq!(
+        HpkePublicKey::from_armor(&missing).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    // Wrong label
+    assert_eq!(
+        dearmor(armor::CIPHERTEXT, ARMORED_KEY).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    // Truncated
+    let truncated = ARMORED_KEY.replacen("-----END HPKE PUBLIC KEY-----\n", "", 1);
+    assert_eq!(
+        HpkePublicKey::from_armor(&truncated).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+}
+
+#[test]
+fn ciphertext() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKemP256,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let (enc, ct) = hpke
+        .seal(&pk_r, b"info", b"", &[0x42; 200], None, None, None)
+        .unwrap();
+    let armored = armor(armor::CIPHERTEXT, &[enc.as_slice(), &ct].concat());
+    assert!(armored.lines().all(|line| line.len() <= 64));
+
+    let data = dearmor(armor::CIPHERTEXT, &armored).unwrap();
+    let (enc, ct) = data.split_at(hpke.nenc());
+    assert_eq!(
+        hpke.open(enc, &sk_r, b"info", b"", ct, None, None, None)
+            .unwrap(),
+        [0x42; 200]
+    );
+    assert_eq!(
+        dearmor(armor::ENVELOPE, &armor(armor::ENVELOPE, &[])).unwrap(),
+        [0u8; 0]
+    );
+}
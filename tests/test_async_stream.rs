@@ -0,0 +1,211 @@
+extern crate hpke_rs as hpke;
+
+use std::{
+    io::{self, Read, Write},
+    pin::Pin,
+    task::{Context as TaskContext, Poll},
+};
+
+use futures::{
+    executor::block_on,
+    io::{AsyncRead, AsyncWrite},
+};
+use hpke::{
+    async_stream::{AsyncDecryptingReader, AsyncEncryptingWriter},
+    prelude::*,
+    stream::{DecryptingReader, EncryptingWriter},
+};
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+fn contexts() -> (Context<HpkeRustCrypto>, Context<HpkeRustCrypto>) {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let (enc, sender) = hpke
+        .setup_sender(key_pair.public_key(), b"info", None, None, None)
+        .unwrap();
+    let receiver = hpke
+        .setup_receiver(&enc, key_pair.private_key(), b"info", None, None, None)
+        .unwrap();
+    (sender, receiver)
+}
+
+/// An IO object that is pending every other poll and then moves one byte.
+struct Trickle<T> {
+    inner: T,
+    pending: bool,
+}
+
+impl<T> Trickle<T> {
+    fn new(inner: T) -> Self {
+        Self {
+            inner,
+            pending: true,
+        }
+    }
+
+    fn poll(&mut self, cx: &mut TaskContext<'_>) -> Poll<()> {
+        self.pending = !self.pending;
+        if self.pending {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+}
+
+impl AsyncRead for Trickle<&[u8]> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        futures::ready!(this.poll(cx));
+        let n = buf.len().min(1);
+        Poll::Ready(Read::read(&mut this.inner, &mut buf[..n]))
+    }
+}
+
+impl AsyncWrite for Trickle<Vec<u8>> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        futures::ready!(this.poll(cx));
+        Poll::Ready(Write::write(&mut this.inner, &buf[..buf.len().min(1)]))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[test]
+fn futures_stream() {
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+
+    let data: Vec<u8> = (0..300u32).map(|i| i as u8).collect();
+    block_on(async {
+        let (sender, receiver) = contexts();
+        let mut writer = AsyncEncryptingWriter::new(sender, b"aad", Trickle::new(Vec::new()))
+            .with_chunk_size(64)
+            .unwrap();
+        writer.write_all(&data[..100]).await.unwrap();
+        writer.flush().await.unwrap();
+        writer.write_all(&data[100..]).await.unwrap();
+        writer.close().await.unwrap();
+        assert!(writer.write(b"more").await.is_err());
+        let ct = writer.into_inner().inner;
+
+        let mut reader = AsyncDecryptingReader::new(receiver, b"aad", Trickle::new(&ct[..]))
+            .with_chunk_size(64)
+            .unwrap();
+        let mut pt = Vec::new();
+        reader.read_to_end(&mut pt).await.unwrap();
+        assert!(reader.is_finished());
+        assert_eq!(pt, data);
+
+        // A truncated stream fails.
+        let (sender, receiver) = contexts();
+        let mut writer = AsyncEncryptingWriter::new(sender, b"aad", Vec::new());
+        writer.write_all(&data).await.unwrap();
+        writer.flush().await.unwrap();
+        let ct = writer.into_inner();
+        let mut reader = AsyncDecryptingReader::new(receiver, b"aad", &ct[..]);
+        assert_eq!(
+            reader
+                .read_to_end(&mut Vec::new())
+                .await
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+        assert!(reader.read(&mut [0u8; 8]).await.is_err());
+    });
+}
+
+#[test]
+fn tokio_stream() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let data: Vec<u8> = (0..300u32).map(|i| i as u8).collect();
+    block_on(async {
+        let (sender, receiver) = contexts();
+        let mut writer = AsyncEncryptingWriter::new(sender, b"aad", Vec::new())
+            .with_chunk_size(64)
+            .unwrap();
+        writer.write_all(&data).await.unwrap();
+        writer.shutdown().await.unwrap();
+        let ct = writer.into_inner();
+
+        let mut reader = AsyncDecryptingReader::new(receiver, b"aad", &ct[..])
+            .with_chunk_size(64)
+            .unwrap();
+        let mut pt = Vec::new();
+        reader.read_to_end(&mut pt).await.unwrap();
+        assert_eq!(pt, data);
+
+        // Tampering is detected.
+        let (sender, receiver) = contexts();
+        let mut writer = AsyncEncryptingWriter::new(sender, b"aad", Vec::new());
+        writer.write_all(&data).await.unwrap();
+        writer.shutdown().await.unwrap();
+        let mut ct = writer.into_inner();
+        ct[10] ^= 1;
+        let mut reader = AsyncDecryptingReader::new(receiver, b"aad", &ct[..]);
+        assert_eq!(
+            reader
+                .read_to_end(&mut Vec::new())
+                .await
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidData
+        );
+    });
+}
+
+#[test]
+fn async_and_sync_streams_interoperate() {
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+
+    let data = vec![0x5A; 200];
+
+    let (sender, receiver) = contexts();
+    let mut writer = EncryptingWriter::new(sender, b"aad", Vec::new())
+        .with_chunk_size(64)
+        .unwrap();
+    writer.write_all(&data).unwrap();
+    let ct = writer.finish().unwrap();
+    let mut reader = AsyncDecryptingReader::new(receiver, b"aad", &ct[..])
+        .with_chunk_size(64)
+        .unwrap();
+    let mut pt = Vec::new();
+    block_on(AsyncReadExt::read_to_end(&mut reader, &mut pt)).unwrap();
+    assert_eq!(pt, data);
+
+    let (sender, receiver) = contexts();
+    let mut writer = AsyncEncryptingWriter::new(sender, b"aad", Vec::new());
+    block_on(async {
+        AsyncWriteExt::write_all(&mut writer, &data).await.unwrap();
+        AsyncWriteExt::close(&mut writer).await.unwrap();
+    });
+    let ct = writer.into_inner();
+    let mut pt = Vec::new();
+    DecryptingReader::new(receiver, b"aad", &ct[..])
+        .read_to_end(&mut pt)
+        .unwrap();
+    assert_eq!(pt, data);
+}
@@ -0,0 +1,144 @@
+extern crate hpke_rs as hpke;
+
+use std::sync::{Arc, Mutex};
+
+use hpke::{
+    audit::{AuditEvent, AuditOperation},
+    prelude::*,
+};
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+fn hpke() -> (Hpke<HpkeRustCrypto>, Arc<Mutex<Vec<AuditEvent>>>) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let log = events.clone();
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    )
+    .with_audit_observer(move |event: &AuditEvent| log.lock().unwrap().push(event.clone()));
+    (hpke, events)
+}
+
+fn operations(events: &Mutex<Vec<AuditEvent>>) -> Vec<(AuditOperation, Option<u32>, bool)> {
+    events
+        .lock()
+        .unwrap()
+        .drain(..)
+        .map(|e| (e.operation, e.sequence_number, e.error.is_none()))
+        .collect()
+}
+
+#[test]
+fn audit_events() {
+    let (hpke, events) = hpke();
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let key_id = hpke.key_id(key_pair.public_key()).unwrap();
+
+    let (enc, mut sender) = hpke
+        .setup_sender(key_pair.public_key(), b"info", None, None, None)
+        .unwrap();
+    let ct0 = sender.seal(b"aad", b"message 0").unwrap();
+    let ct1 = sender.seal(b"aad", b"message 1").unwrap();
+    sender.export(b"context", 32).unwrap();
+
+    let mut receiver = hpke
+        .setup_receiver(&enc, key_pair.private_key(), b"info", None, None, None)
+        .unwrap();
+    receiver.open(b"aad", &ct0).unwrap();
+    assert_eq!(
+        receiver.open(b"aad", &ct0).unwrap_err(),
+        HpkeError::OpenError
+    );
+    receiver.open(b"aad", &ct1).unwrap();
+
+    {
+        let events = events.lock().unwrap();
+        assert!(events.iter().all(|e| e.config == hpke.config()));
+        // Only senders know the key id of the recipient.
+        assert!(events[..4].iter().all(|e| e.key_id == Some(key_id)));
+        assert!(events[4..].iter().all(|e| e.key_id.is_none()));
+        assert_eq!(events[5].error, None);
+        assert_eq!(events[6].error, Some(HpkeError::OpenError));
+    }
+    assert_eq!(
+        operations(&events),
+        vec![
+            (AuditOperation::SetupSender, None, true),
+            (AuditOperation::Seal, Some(0), true),
+            (AuditOperation::Seal, Some(1), true),
+            (AuditOperation::Export, None, true),
+            (AuditOperation::SetupReceiver, None, true),
+            (AuditOperation::Open, Some(0), true),
+            (AuditOperation::Open, Some(1), false),
+            (AuditOperation::Open, Some(1), true),
+        ]
+    );
+
+    // Failed setups are reported.
+    let other = hpke.generate_key_pair().unwrap();
+    hpke.setup_receiver(&[0; 3], other.private_key(), b"info", None, None, None)
+        .unwrap_err();
+    // Single-shot APIs report the setup and the operation.
+    let (enc, ct) = hpke
+        .seal(
+            key_pair.public_key(),
+            b"info",
+            b"aad",
+            b"message",
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    hpke.open(
+        &enc,
+        key_pair.private_key(),
+        b"info",
+        b"aad",
+        &ct,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        operations(&events),
+        vec![
+            (AuditOperation::SetupReceiver, None, false),
+            (AuditOperation::SetupSender, None, true),
+            (AuditOperation::Seal, Some(0), true),
+            (AuditOperation::SetupReceiver, None, true),
+            (AuditOperation::Open, Some(0), true),
+        ]
+    );
+}
+
+#[test]
+fn audit_sync_sender() {
+    let (hpke, events) = hpke();
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let (_, sender) = hpke
+        .setup_sender(key_pair.public_key(), b"info", None, None, None)
+        .unwrap();
+    let sender = sender.into_sync_sender().unwrap();
+    sender.seal(b"aad", b"message").unwrap();
+    sender.export(b"context", 32).unwrap();
+
+    let key_id = hpke.key_id(key_pair.public_key()).unwrap();
+    assert!(events
+        .lock()
+        .unwrap()
+        .iter()
+        .all(|e| e.key_id == Some(key_id)));
+    assert_eq!(
+        operations(&events),
+        vec![
+            (AuditOperation::SetupSender, None, true),
+            (AuditOperation::Seal, Some(0), true),
+            (AuditOperation::Export, None, true),
+        ]
+    );
+}
@@ -0,0 +1,124 @@
+extern crate hpke_rs as hpke;
+
+use hpke::backup::{self, BackupHeader, CHUNK_SIZE};
+use hpke::prelude::*;
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+fn hpke() -> Hpke<HpkeRustCrypto> {
+    Hpke::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    )
+}
+
+#[test]
+fn seal_open() {
+    let hpke = hpke();
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let chunk_size = CHUNK_SIZE as usize;
+    for len in [0, 1, chunk_size, 2 * chunk_size + 5] {
+        let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let sealed = backup::seal(&hpke, &pk_r, &data).unwrap();
+        assert_eq!(backup::open(&hpke, &sk_r, &sealed).unwrap(), data);
+    }
+
+    let sealed = backup::seal(&hpke, &pk_r, b"data").unwrap();
+    let header = BackupHeader::parse(&sealed).unwrap();
+    assert_eq!(header.version, 1);
+    assert_eq!(header.suite, hpke.ciphersuite());
+    assert_eq!(header.key_id, Some(hpke.key_id(&pk_r).unwrap()));
+    assert_eq!(header.chunk_size, CHUNK_SIZE);
+
+    let (other_sk, _) = hpke.generate_key_pair().unwrap().into_keys();
+    assert_eq!(
+        backup::open(&hpke, &other_sk, &sealed).unwrap_err(),
+        HpkeError::OpenError
+    );
+    let other = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    assert_eq!(
+        backup::open(&other, &sk_r, &sealed).unwrap_err(),
+        HpkeError::InvalidConfig
+    );
+}
+
+#[test]
+fn strict_parsing() {
+    let hpke = hpke();
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let data = vec![7u8; 2 * CHUNK_SIZE as usize + 5];
+    let sealed = backup::seal(&hpke, &pk_r, &data).unwrap();
+
+    // Dropping the last chunk (length, 5 bytes, and the tag)
+    let truncated = &sealed[..sealed.len() - (4 + 5 + 16)];
+    assert_eq!(
+        backup::open(&hpke, &sk_r, truncated).unwrap_err(),
+        HpkeError::OpenError
+    );
+
+    // Trailing bytes
+    let mut bytes = sealed.clone();
+    bytes.push(0);
+    assert_eq!(
+        backup::open(&hpke, &sk_r, &bytes).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+
+    // A newer format version
+    let mut bytes = sealed.clone();
+    bytes[8] = 2;
+    assert_eq!(
+        BackupHeader::parse(&bytes).unwrap_err(),
+        HpkeError::UnknownMode
+    );
+
+    // A tampered header
+    let mut bytes = sealed.clone();
+    bytes[b"HPKE-BAK".len() + 1 + 6 + 1 + 2] ^= 1;
+    assert_eq!(
+        backup::open(&hpke, &sk_r, &bytes).unwrap_err(),
+        HpkeError::OpenError
+    );
+
+    assert_eq!(
+        BackupHeader::parse(b"HPKE-SK").unwrap_err(),
+        HpkeError::InvalidInput
+    );
+}
+
+#[cfg(feature = "passphrase")]
+#[test]
+fn passphrase() {
+    use hpke::protected_key::PassphraseKdf;
+
+    // Cheap KDF parameters to keep the test fast.
+    let kdf = PassphraseKdf::Argon2id {
+        m_cost: 256,
+        t_cost: 1,
+        p_cost: 1,
+    };
+    let hpke = hpke();
+    let sealed = backup::seal_with_passphrase(&hpke, b"hunter2", &kdf, b"data").unwrap();
+    assert_eq!(BackupHeader::parse(&sealed).unwrap().key_id, None);
+    assert_eq!(
+        backup::open_with_passphrase(&hpke, b"hunter2", &sealed).unwrap(),
+        b"data"
+    );
+    assert_eq!(
+        backup::open_with_passphrase(&hpke, b"hunter3", &sealed).unwrap_err(),
+        HpkeError::OpenError
+    );
+
+    let (sk_r, _) = hpke.generate_key_pair().unwrap().into_keys();
+    assert_eq!(
+        backup::open(&hpke, &sk_r, &sealed).unwrap_err(),
+        HpkeError::InvalidConfig
+    );
+}
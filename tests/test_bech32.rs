@@ -0,0 +1,146 @@
+extern crate hpke_rs as hpke;
+
+use hpke::bech32::{decode, encode, PUBLIC_KEY_HRP};
+use hpke::keystore::*;
+use hpke::prelude::*;
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+/// Valid Bech32m strings from BIP 350.
+const VALID: &[&str] = &[
+    "A1LQFN3A",
+    "a1lqfn3a",
+    "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+    "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+    "?1v759aa",
+];
+
+/// Invalid Bech32m strings from BIP 350, and a valid Bech32 string.
+const INVALID: &[&str] = &[
+    "\x201xj0phk",
+    "\x7f1g6xzxy",
+    "qyrz8wqd2c9m",
+    "1qyrz8wqd2c9m",
+    "y1b0jsk6g",
+    "lt1igcx5c0",
+    "in1muywd",
+    "mm1crxm3i",
+    "au1s5cgom",
+    "M1VUXWEZ",
+    "16plkw9",
+    "1p2gdwpf",
+    "a12uel5l",
+    "A1lqfn3a",
+];
+
+#[test]
+fn bech32m_vectors() {
+    for s in VALID {
+        let (hrp, data) = decode(s).unwrap();
+        assert_eq!(hrp, s[..s.rfind('1').unwrap()].to_ascii_lowercase());
+        // Some vectors have 5 bit data that doesn't fill whole bytes.
+        if let Ok(encoded) = encode(&hrp, &data) {
+            if s.len() == encoded.len() {
+                assert_eq!(encoded, s.to_ascii_lowercase());
+            }
+        }
+    }
+    for s in INVALID {
+        assert_eq!(decode(s).unwrap_err(), HpkeError::InvalidInput, "{s:?}");
+    }
+
+    let encoded = encode("test", b"hello").unwrap();
+    assert_eq!(
+        decode(&encoded).unwrap(),
+        ("test".to_owned(), b"hello".to_vec())
+    );
+    assert_eq!(decode(&encoded.to_ascii_uppercase()).unwrap().1, b"hello");
+    assert_eq!(encode("", b"").unwrap_err(), HpkeError::InvalidInput);
+    assert_eq!(encode("Test", b"").unwrap_err(), HpkeError::InvalidInput);
+}
+
+#[test]
+fn public_key_strings() {
+    // P-256 keys are longer than the 90 characters of Bech32.
+    for kem in [KemAlgorithm::DhKem25519, KemAlgorithm::DhKemP256] {
+        let hpke = Hpke::<HpkeRustCrypto>::new(
+            HpkeMode::Base,
+            kem,
+            KdfAlgorithm::HkdfSha256,
+            AeadAlgorithm::ChaCha20Poly1305,
+        );
+        let pk = hpke.generate_key_pair().unwrap().public_key().clone();
+        let s = pk.to_bech32(kem);
+        assert!(s.starts_with("hpke1"));
+        assert_eq!(HpkePublicKey::from_bech32(&s).unwrap(), (kem, pk.clone()));
+
+        // A typo is detected.
+        let mut typo = s.clone().into_bytes();
+        let i = typo.len() / 2;
+        typo[i] = if typo[i] == b'q' { b'p' } else { b'q' };
+        let typo = String::from_utf8(typo).unwrap();
+        assert_eq!(
+            HpkePublicKey::from_bech32(&typo).unwrap_err(),
+            HpkeError::InvalidInput
+        );
+    }
+
+    let pk = HpkePublicKey::from(vec![7u8; 32]);
+    assert!(pk
+        .to_bech32(KemAlgorithm::DhKem25519)
+        .starts_with("hpke1qqsq"));
+
+    // Other HRPs, wrong lengths, and unknown KEMs.
+    let data = [&[0x00, 0x20][..], &[7; 32]].concat();
+    assert_eq!(
+        HpkePublicKey::from_bech32(&encode("age", &data).unwrap()).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    assert_eq!(
+        HpkePublicKey::from_bech32(&encode(PUBLIC_KEY_HRP, &data[..33]).unwrap()).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    let data = [&[0xff, 0xff][..], &[7; 32]].concat();
+    assert_eq!(
+        HpkePublicKey::from_bech32(&encode(PUBLIC_KEY_HRP, &data).unwrap()).unwrap_err(),
+        HpkeError::UnknownMode
+    );
+}
+
+#[test]
+fn key_store_lookup() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let mut store = MemoryKeyStore::new();
+    let key_id = store
+        .insert::<HpkeRustCrypto>(KemAlgorithm::DhKem25519, hpke.generate_key_pair().unwrap())
+        .unwrap();
+    let pk = store.get(&key_id).unwrap().public_key_string();
+
+    let (found, entry) = store
+        .get_by_public_key_string::<HpkeRustCrypto>(&pk)
+        .unwrap()
+        .unwrap();
+    assert_eq!(found, key_id);
+    assert_eq!(entry.public_key_string(), pk);
+
+    let other = hpke
+        .generate_key_pair()
+        .unwrap()
+        .public_key()
+        .to_bech32(KemAlgorithm::DhKem25519);
+    assert!(store
+        .get_by_public_key_string::<HpkeRustCrypto>(&other)
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        store
+            .get_by_public_key_string::<HpkeRustCrypto>("hpke1")
+            .unwrap_err(),
+        HpkeError::InvalidInput
+    );
+}
@@ -0,0 +1,128 @@
+extern crate hpke_rs as hpke;
+
+use hpke::cms::*;
+use hpke::kem::Kem;
+use hpke::prelude::*;
+use hpke::test_util::hex_to_bytes;
+use hpke_rs_crypto::types::{KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+fn params(kdf: KdfAlgorithm, wrap: KeyWrap, ukm: Option<Vec<u8>>) -> KemRecipientParams {
+    KemRecipientParams {
+        // An example KEM identifier
+        kem: AlgorithmIdentifier::from_arcs(&[1, 3, 6, 1, 4, 1, 99999, 1, 32]).unwrap(),
+        kdf,
+        wrap,
+        ukm,
+    }
+}
+
+#[test]
+fn kem_recipient_info() {
+    let cek = [0x42u8; 32];
+    for (alg, kdf, wrap, ukm) in [
+        (
+            KemAlgorithm::DhKem25519,
+            KdfAlgorithm::HkdfSha256,
+            KeyWrap::Aes128,
+            None,
+        ),
+        (
+            KemAlgorithm::DhKemP256,
+            KdfAlgorithm::HkdfSha384,
+            KeyWrap::Aes256,
+            Some(b"user keying material".to_vec()),
+        ),
+    ] {
+        let kem = Kem::<HpkeRustCrypto>::new(alg);
+        let kp = kem.generate_key_pair().unwrap();
+        let rid = RecipientIdentifier::SubjectKeyIdentifier(vec![1, 2, 3, 4]);
+        let params = params(kdf, wrap, ukm);
+        let ri = KemRecipientInfo::encapsulate(&kem, kp.public_key(), rid.clone(), &params, &cek)
+            .unwrap();
+        assert_eq!(ri.rid, rid);
+        assert_eq!(ri.kemct.len(), alg.enc_len());
+        assert_eq!(usize::from(ri.kek_length), wrap.kek_len());
+        assert_eq!(ri.encrypted_key.len(), cek.len() + 8);
+
+        let encoded = ri.encode();
+        let decoded = KemRecipientInfo::decode(&encoded).unwrap();
+        assert_eq!(decoded, ri);
+        assert_eq!(
+            KemRecipientInfo::decode_recipient_info(&ri.encode_recipient_info()).unwrap(),
+            ri
+        );
+        assert_eq!(decoded.decapsulate(&kem, kp.private_key()).unwrap(), cek);
+
+        // Tampering with the authenticated parameters breaks the unwrap.
+        let mut tampered = decoded.clone();
+        tampered.ukm = Some(b"other".to_vec());
+        assert_eq!(
+            tampered.decapsulate(&kem, kp.private_key()).unwrap_err(),
+            HpkeError::OpenError
+        );
+        let other = kem.generate_key_pair().unwrap();
+        assert_eq!(
+            decoded.decapsulate(&kem, other.private_key()).unwrap_err(),
+            HpkeError::OpenError
+        );
+
+        // Truncated and trailing bytes
+        assert_eq!(
+            KemRecipientInfo::decode(&encoded[..encoded.len() - 1]).unwrap_err(),
+            HpkeError::InvalidInput
+        );
+        assert_eq!(
+            KemRecipientInfo::decode(&[encoded.as_slice(), &[0]].concat()).unwrap_err(),
+            HpkeError::InvalidInput
+        );
+    }
+}
+
+#[test]
+fn kem_recipient_info_encoding() {
+    assert_eq!(
+        KeyWrap::Aes128.algorithm_identifier(),
+        AlgorithmIdentifier {
+            oid: hex_to_bytes("608648016503040105"),
+            parameters: None,
+        }
+    );
+    assert_eq!(
+        AlgorithmIdentifier::hkdf(KdfAlgorithm::HkdfSha256)
+            .unwrap()
+            .oid,
+        hex_to_bytes("2a864886f70d010910031c")
+    );
+
+    let ri = KemRecipientInfo {
+        rid: RecipientIdentifier::SubjectKeyIdentifier(vec![0xaa]),
+        kem: AlgorithmIdentifier::from_arcs(&[1, 2, 3]).unwrap(),
+        kemct: vec![0xbb],
+        kdf: AlgorithmIdentifier::hkdf(KdfAlgorithm::HkdfSha256).unwrap(),
+        kek_length: 16,
+        ukm: Some(vec![0xcc]),
+        wrap: KeyWrap::Aes128.algorithm_identifier(),
+        encrypted_key: vec![0xdd],
+    };
+    let expected = hex_to_bytes(concat!(
+        "3036",                           // SEQUENCE
+        "020100",                         // version
+        "8001aa",                         // rid [0] SubjectKeyIdentifier
+        "300406022a03",                   // kem
+        "0401bb",                         // kemct
+        "300d060b2a864886f70d010910031c", // kdf
+        "020110",                         // kekLength
+        "a0030401cc",                     // ukm
+        "300b0609608648016503040105",     // wrap
+        "0401dd",                         // encryptedKey
+    ));
+    assert_eq!(ri.encode(), expected);
+    assert_eq!(KemRecipientInfo::decode(&expected).unwrap(), ri);
+
+    // Other recipient infos
+    assert_eq!(
+        KemRecipientInfo::decode_recipient_info(&expected).unwrap_err(),
+        HpkeError::UnknownMode
+    );
+}
@@ -0,0 +1,87 @@
+extern crate hpke_rs as hpke;
+
+use bytes::BytesMut;
+use futures::{executor::block_on, SinkExt, StreamExt};
+use hpke::{codec::HpkeCodec, prelude::*};
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+use tokio_util::codec::{Decoder, Encoder, FramedRead, FramedWrite};
+
+fn contexts() -> (Context<HpkeRustCrypto>, Context<HpkeRustCrypto>) {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let (enc, sender) = hpke
+        .setup_sender(key_pair.public_key(), b"info", None, None, None)
+        .unwrap();
+    let receiver = hpke
+        .setup_receiver(&enc, key_pair.private_key(), b"info", None, None, None)
+        .unwrap();
+    (sender, receiver)
+}
+
+#[test]
+fn framed_channel() {
+    let (sender, receiver) = contexts();
+    let messages: [&[u8]; 3] = [b"hello", b"", b"world"];
+
+    let wire = block_on(async {
+        let mut sink = FramedWrite::new(Vec::new(), HpkeCodec::sender(sender).with_aad(b"aad"));
+        for message in messages {
+            sink.send(message).await.unwrap();
+        }
+        assert_eq!(
+            sink.encoder().sender_context().unwrap().sequence_number(),
+            3
+        );
+        sink.into_inner()
+    });
+    assert_eq!(wire.len(), 3 * (4 + 16) + 10);
+
+    let received: Vec<Vec<u8>> = block_on(
+        FramedRead::new(&wire[..], HpkeCodec::receiver(receiver).with_aad(b"aad"))
+            .map(Result::unwrap)
+            .collect(),
+    );
+    assert_eq!(received, messages);
+}
+
+#[test]
+fn codec_errors() {
+    let (sender, receiver) = contexts();
+    let mut sender = HpkeCodec::sender(sender).with_max_record_length(32);
+    let mut receiver = HpkeCodec::receiver(receiver).with_max_record_length(32);
+    let mut wire = BytesMut::new();
+
+    // A codec can only encode with a sender and decode with a receiver
+    // context.
+    assert!(receiver.encode(b"message", &mut wire).is_err());
+    assert!(sender.decode(&mut wire).is_err());
+
+    // Too long messages are rejected without using up a sequence number.
+    assert!(sender.encode([0u8; 17], &mut wire).is_err());
+    assert!(wire.is_empty());
+    sender.encode([1u8; 16], &mut wire).unwrap();
+
+    // Records are decoded once they are complete.
+    let mut partial = wire.split_to(10);
+    assert_eq!(receiver.decode(&mut partial).unwrap(), None);
+    partial.unsplit(wire);
+    assert_eq!(receiver.decode(&mut partial).unwrap().unwrap(), [1u8; 16]);
+
+    // Replayed and tampered records fail to open.
+    let mut wire = BytesMut::new();
+    sender.encode(b"message", &mut wire).unwrap();
+    let replay = wire.clone();
+    receiver.decode(&mut wire).unwrap().unwrap();
+    let err = receiver.decode(&mut replay.clone()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(
+        *err.into_inner().unwrap().downcast::<HpkeError>().unwrap(),
+        HpkeError::OpenError
+    );
+}
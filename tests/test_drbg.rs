@@ -0,0 +1,141 @@
+extern crate hpke_rs as hpke;
+
+use hpke::{
+    drbg::{HmacDrbg, MAX_REQUEST_LEN},
+    prelude::*,
+};
+use hpke_rs_crypto::{
+    types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm},
+    RngCore,
+};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn drbg(seed: u8) -> HmacDrbg<HpkeRustCrypto> {
+    HmacDrbg::new(
+        KdfAlgorithm::HkdfSha256,
+        &[seed; 32],
+        b"nonce",
+        b"hpke-rs test",
+    )
+    .unwrap()
+}
+
+#[test]
+fn cavp_sha256() {
+    // NIST CAVP HMAC_DRBG.rsp, [SHA-256], no prediction resistance, COUNT = 0
+    let mut drbg = HmacDrbg::<HpkeRustCrypto>::new(
+        KdfAlgorithm::HkdfSha256,
+        &hex("ca851911349384bffe89de1cbdc46e6831e44d34a4fb935ee285dd14b71a7488"),
+        &hex("659ba96c601dc69fc902940805ec0ca8"),
+        &[],
+    )
+    .unwrap();
+    let mut out = [0u8; 128];
+    drbg.generate(&mut out, &[]).unwrap();
+    drbg.generate(&mut out, &[]).unwrap();
+    assert_eq!(
+        out.to_vec(),
+        hex(concat!(
+            "e528e9abf2dece54d47c7e75e5fe302149f817ea9fb4bee6f4199697d04d5b89",
+            "d54fbb978a15b5c443c9ec21036d2460b6f73ebad0dc2aba6e624abf07745bc1",
+            "07694bb7547bb0995f70de25d6b29e2d3011bb19d27676c07162c8b5ccde0668",
+            "961df86803482cb37ed6d5c0bb8d50cf1f50d476aa0458bdaba806f48be9dcb8",
+        ))
+    );
+}
+
+#[test]
+fn inputs() {
+    let mut a = drbg(1);
+    let mut b = drbg(1);
+    assert_eq!(a.next_u64(), b.next_u64());
+
+    let mut other = drbg(2);
+    assert_ne!(drbg(1).next_u64(), other.next_u64());
+
+    let (mut out_a, mut out_b) = ([0u8; 32], [0u8; 32]);
+    a.generate(&mut out_a, b"additional").unwrap();
+    b.generate(&mut out_b, &[]).unwrap();
+    assert_ne!(out_a, out_b);
+
+    a.reseed(&[3; 32], &[]).unwrap();
+    b.reseed(&[4; 32], &[]).unwrap();
+    a.fill_bytes(&mut out_a);
+    b.fill_bytes(&mut out_b);
+    assert_ne!(out_a, out_b);
+
+    // Long fills are split into several requests.
+    let mut long = vec![0u8; MAX_REQUEST_LEN + 1];
+    a.fill_bytes(&mut long);
+    assert_eq!(
+        a.generate(&mut long, &[]).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+}
+
+#[test]
+fn invalid() {
+    assert_eq!(
+        HmacDrbg::<HpkeRustCrypto>::new(KdfAlgorithm::HkdfSha256, &[0; 31], &[], &[]).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    assert_eq!(
+        HmacDrbg::<HpkeRustCrypto>::new(KdfAlgorithm::Kmac256, &[0; 32], &[], &[]).unwrap_err(),
+        HpkeError::InvalidConfig
+    );
+    assert_eq!(
+        drbg(0).reseed(&[0; 16], &[]).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+}
+
+#[test]
+fn reproducible_hpke() {
+    let hpke = |seed| {
+        Hpke::<HpkeRustCrypto>::new(
+            HpkeMode::Base,
+            KemAlgorithm::DhKem25519,
+            KdfAlgorithm::HkdfSha256,
+            AeadAlgorithm::ChaCha20Poly1305,
+        )
+        .with_rng(drbg(seed))
+    };
+    let run = |seed| {
+        let hpke = hpke(seed);
+        let keys = hpke.generate_key_pair().unwrap();
+        let (enc, ct) = hpke
+            .seal(
+                keys.public_key(),
+                b"info",
+                b"aad",
+                b"message",
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let pt = hpke
+            .open(
+                &enc,
+                keys.private_key(),
+                b"info",
+                b"aad",
+                &ct,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(pt, b"message");
+        (keys.public_key().clone(), enc, ct)
+    };
+    assert_eq!(run(7), run(7));
+    assert_ne!(run(7), run(8));
+}
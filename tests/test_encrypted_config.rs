@@ -0,0 +1,80 @@
+#![cfg(feature = "serialization")]
+extern crate hpke_rs as hpke;
+
+use std::collections::BTreeMap;
+
+use hpke::{
+    encrypted_config::{decrypt_into, encrypt_from, INFO},
+    envelope::Envelope,
+    prelude::*,
+};
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct Config {
+    database_url: String,
+    pool_size: u32,
+    features: BTreeMap<String, bool>,
+}
+
+fn hpke() -> Hpke<HpkeRustCrypto> {
+    Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    )
+}
+
+#[test]
+fn round_trip() {
+    let hpke = hpke();
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let config = Config {
+        database_url: "postgres://admin:hunter2@db".into(),
+        pool_size: 8,
+        features: BTreeMap::from([("tracing".into(), true)]),
+    };
+
+    let envelope = encrypt_from(&hpke, &config, &pk_r).unwrap();
+    assert_eq!(envelope.key_id, Some(hpke.key_id(&pk_r).unwrap()));
+    let loaded: Config = decrypt_into(&hpke, &envelope, &sk_r).unwrap();
+    assert_eq!(loaded, config);
+
+    // The envelope holds plain JSON.
+    let json = envelope.open(&hpke, &sk_r, INFO, &[]).unwrap();
+    assert_eq!(json, serde_json::to_vec(&config).unwrap());
+}
+
+#[test]
+fn errors() {
+    let hpke = hpke();
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let (other_sk, _) = hpke.generate_key_pair().unwrap().into_keys();
+
+    let envelope = encrypt_from(&hpke, &42u32, &pk_r).unwrap();
+    assert_eq!(
+        decrypt_into::<_, u32>(&hpke, &envelope, &other_sk).unwrap_err(),
+        HpkeError::OpenError
+    );
+    assert_eq!(
+        decrypt_into::<_, Config>(&hpke, &envelope, &sk_r).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+
+    // Not JSON.
+    let envelope = Envelope::seal(&hpke, &pk_r, INFO, &[], b"pool_size = 8").unwrap();
+    assert_eq!(
+        decrypt_into::<_, Config>(&hpke, &envelope, &sk_r).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+
+    // Envelopes for other purposes don't open.
+    let envelope = Envelope::seal(&hpke, &pk_r, b"info", &[], b"42").unwrap();
+    assert_eq!(
+        decrypt_into::<_, u32>(&hpke, &envelope, &sk_r).unwrap_err(),
+        HpkeError::OpenError
+    );
+}
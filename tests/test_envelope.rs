@@ -0,0 +1,221 @@
+extern crate hpke_rs as hpke;
+
+use hpke::ciphersuite::Ciphersuite;
+use hpke::envelope::{Envelope, MultiEnvelope};
+use hpke::keystore::{KeyStore, MemoryKeyStore};
+use hpke::prelude::*;
+use hpke::proto::ProtoEnvelope;
+use hpke::test_util::hex_to_bytes;
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+fn hpke() -> Hpke<HpkeRustCrypto> {
+    Hpke::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    )
+}
+
+#[test]
+fn seal_open() {
+    let hpke = hpke();
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let envelope = Envelope::seal(&hpke, &pk_r, b"info", b"aad", b"message").unwrap();
+    assert_eq!(envelope.key_id, Some(hpke.key_id(&pk_r).unwrap()));
+    assert_eq!(
+        envelope.open(&hpke, &sk_r, b"info", b"aad").unwrap(),
+        b"message"
+    );
+
+    let other = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    assert_eq!(
+        envelope.open(&other, &sk_r, b"info", b"aad").unwrap_err(),
+        HpkeError::InvalidConfig
+    );
+}
+
+#[test]
+fn cbor() {
+    let envelope = Envelope {
+        suite: Ciphersuite::from_ids(0x0020, 0x0001, 0x0001).unwrap(),
+        key_id: None,
+        enc: vec![0xaa; 32],
+        ciphertext: vec![0xbb; 3],
+    };
+    let expected = [
+        "85",
+        "1820",
+        "01",
+        "01",
+        "5820",
+        &"aa".repeat(32),
+        "43bbbbbb",
+    ]
+    .concat();
+    let encoded = envelope.to_cbor();
+    assert_eq!(encoded, hex_to_bytes(&expected));
+    assert_eq!(Envelope::from_cbor(&encoded).unwrap(), envelope);
+
+    let envelope = Envelope {
+        key_id: Some([1, 2, 3, 4, 5, 6, 7, 8].into()),
+        ..envelope
+    };
+    let encoded = envelope.to_cbor();
+    assert_eq!(encoded[0], 0x86);
+    assert!(encoded.ends_with(&hex_to_bytes("480102030405060708")));
+    assert_eq!(Envelope::from_cbor(&encoded).unwrap(), envelope);
+
+    // Trailing bytes
+    let mut bytes = encoded.clone();
+    bytes.push(0);
+    assert_eq!(
+        Envelope::from_cbor(&bytes).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    // The AEAD id 1 not in its shortest form
+    let bytes = hex_to_bytes(&expected.replacen("18200101", "1820180101", 1));
+    assert_eq!(
+        Envelope::from_cbor(&bytes).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    // Indefinite length array
+    let bytes = hex_to_bytes(&expected.replacen("85", "9f", 1));
+    assert_eq!(
+        Envelope::from_cbor(&bytes).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    // Unknown KEM
+    let bytes = hex_to_bytes(&expected.replacen("1820", "1830", 1));
+    assert_eq!(
+        Envelope::from_cbor(&bytes).unwrap_err(),
+        HpkeError::UnknownMode
+    );
+
+    // Round trip through a real message
+    let hpke = hpke();
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let envelope = Envelope::seal(&hpke, &pk_r, b"info", b"", &[7; 300]).unwrap();
+    let decoded = Envelope::from_cbor(&envelope.to_cbor()).unwrap();
+    assert_eq!(decoded.open(&hpke, &sk_r, b"info", b"").unwrap(), [7; 300]);
+}
+
+#[test]
+fn multi_envelope() {
+    let hpke = hpke();
+    let key_pairs = hpke.generate_key_pairs(3).unwrap();
+    let pk_rs: Vec<_> = key_pairs.iter().map(|kp| kp.public_key().clone()).collect();
+    let envelope = MultiEnvelope::seal(&hpke, &pk_rs, b"info", b"aad", b"message").unwrap();
+    assert_eq!(envelope.recipients.len(), 3);
+    for kp in &key_pairs {
+        let key_id = hpke.key_id(kp.public_key()).unwrap();
+        let recipient = envelope.envelope_for(&key_id).unwrap();
+        assert_eq!(
+            recipient
+                .open(&hpke, kp.private_key(), b"info", b"aad")
+                .unwrap(),
+            b"message"
+        );
+    }
+    assert!(envelope.envelope_for(&KeyId::default()).is_none());
+}
+
+#[test]
+fn key_rotation() {
+    let hpke = hpke();
+    let previous = hpke.generate_key_pair().unwrap();
+    let current = hpke.generate_key_pair().unwrap();
+    let envelope = MultiEnvelope::seal(
+        &hpke,
+        &[current.public_key().clone(), previous.public_key().clone()],
+        b"info",
+        b"aad",
+        b"message",
+    )
+    .unwrap();
+
+    // A recipient that has rotated to the current key and one that hasn't.
+    for key_pair in [current, previous] {
+        let mut store = MemoryKeyStore::new();
+        store
+            .insert::<HpkeRustCrypto>(KemAlgorithm::DhKem25519, key_pair)
+            .unwrap();
+        assert_eq!(
+            envelope.open(&hpke, &store, b"info", b"aad", 0).unwrap(),
+            b"message"
+        );
+    }
+
+    let mut store = MemoryKeyStore::new();
+    assert_eq!(
+        envelope
+            .open(&hpke, &store, b"info", b"aad", 0)
+            .unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    store
+        .insert::<HpkeRustCrypto>(KemAlgorithm::DhKem25519, hpke.generate_key_pair().unwrap())
+        .unwrap();
+    assert_eq!(
+        envelope
+            .open(&hpke, &store, b"info", b"aad", 0)
+            .unwrap_err(),
+        HpkeError::InvalidInput
+    );
+}
+
+#[test]
+fn protobuf() {
+    let envelope = Envelope {
+        suite: Ciphersuite::from_ids(0x0020, 0x0001, 0x0003).unwrap(),
+        key_id: Some([1, 2, 3, 4, 5, 6, 7, 8].into()),
+        enc: vec![0xaa; 2],
+        ciphertext: vec![0xbb; 3],
+    };
+    let encoded = envelope.to_protobuf();
+    assert_eq!(
+        encoded,
+        hex_to_bytes("082010011803220801020304050607082a02aaaa3203bbbbbb")
+    );
+    assert_eq!(Envelope::from_protobuf(&encoded).unwrap(), envelope);
+
+    let proto = ProtoEnvelope {
+        key_id: vec![],
+        ..ProtoEnvelope::from(&envelope)
+    };
+    assert_eq!(Envelope::try_from(proto.clone()).unwrap().key_id, None);
+    let proto = ProtoEnvelope {
+        key_id: vec![1, 2, 3],
+        ..proto
+    };
+    assert_eq!(
+        Envelope::try_from(proto.clone()).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    let proto = ProtoEnvelope {
+        key_id: vec![],
+        kem_id: 0x1_0020,
+        ..proto
+    };
+    assert_eq!(
+        Envelope::try_from(proto).unwrap_err(),
+        HpkeError::UnknownMode
+    );
+    assert_eq!(
+        Envelope::from_protobuf(&[0x0a]).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+
+    let hpke = hpke();
+    let key_pairs = hpke.generate_key_pairs(2).unwrap();
+    let pk_rs: Vec<_> = key_pairs.iter().map(|kp| kp.public_key().clone()).collect();
+    let envelope = MultiEnvelope::seal(&hpke, &pk_rs, b"info", b"aad", b"message").unwrap();
+    let decoded = MultiEnvelope::from_protobuf(&envelope.to_protobuf()).unwrap();
+    assert_eq!(decoded, envelope);
+}
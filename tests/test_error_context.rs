@@ -0,0 +1,123 @@
+extern crate hpke_rs as hpke;
+
+use hpke::{
+    error_context::{ErrorContext, Stage},
+    prelude::*,
+};
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+fn hpke(mode: HpkeMode, aead: AeadAlgorithm) -> Hpke<HpkeRustCrypto> {
+    Hpke::<HpkeRustCrypto>::new(
+        mode,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        aead,
+    )
+    .with_error_context()
+}
+
+fn stage(context: Option<ErrorContext>) -> Stage {
+    context.expect("an error context").stage
+}
+
+#[test]
+fn setup() {
+    let hpke = hpke(HpkeMode::Base, AeadAlgorithm::ChaCha20Poly1305);
+    let keys = hpke.generate_key_pair().unwrap();
+    assert!(hpke.last_error_context().is_none());
+
+    let err = hpke
+        .setup_sender(&HpkePublicKey::from(vec![0u8; 3]), b"", None, None, None)
+        .unwrap_err();
+    assert_eq!(err, HpkeError::InvalidInput);
+    assert_eq!(stage(hpke.last_error_context()), Stage::Encapsulation);
+
+    let err = hpke
+        .setup_receiver(&[0u8; 3], keys.private_key(), b"", None, None, None)
+        .unwrap_err();
+    assert!(matches!(err, HpkeError::DecapError(_)));
+    assert_eq!(stage(hpke.last_error_context()), Stage::Decapsulation);
+
+    let err = hpke
+        .setup_sender(keys.public_key(), b"", Some(&[1; 32]), Some(b"id"), None)
+        .unwrap_err();
+    assert_eq!(err, HpkeError::UnnecessaryPsk);
+    assert_eq!(
+        hpke.last_error_context(),
+        Some(ErrorContext {
+            stage: Stage::KeySchedule,
+            config: hpke.config(),
+            error: HpkeError::UnnecessaryPsk,
+        })
+    );
+
+    // Clones share the record.
+    assert_eq!(hpke.clone().last_error_context(), hpke.last_error_context());
+}
+
+#[test]
+fn context() {
+    let hpke = hpke(HpkeMode::Base, AeadAlgorithm::ChaCha20Poly1305);
+    let keys = hpke.generate_key_pair().unwrap();
+    let (enc, mut sender) = hpke
+        .setup_sender(keys.public_key(), b"info", None, None, None)
+        .unwrap();
+    let mut receiver = hpke
+        .setup_receiver(&enc, keys.private_key(), b"info", None, None, None)
+        .unwrap();
+
+    let mut ct = sender.seal(b"aad", b"message").unwrap();
+    ct[0] ^= 1;
+    let err = receiver.open(b"aad", &ct).unwrap_err();
+    assert_eq!(err, HpkeError::OpenError);
+    assert_eq!(err.kind(), ErrorKind::AuthenticationFailure);
+    let context = receiver.last_error_context().unwrap();
+    assert_eq!(context.stage, Stage::Open);
+    assert_eq!(
+        context.to_string(),
+        "HPKE Error: OpenError in open with base_dhkem25519_hkdfsha256_chacha20poly1305"
+    );
+    assert_eq!(hpke.last_error_context(), Some(context));
+
+    // Single-shot open records the stage of the context.
+    let (enc, mut ct) = hpke
+        .seal(keys.public_key(), b"", b"", b"message", None, None, None)
+        .unwrap();
+    ct[0] ^= 1;
+    let err = hpke
+        .open(&enc, keys.private_key(), b"", b"", &ct, None, None, None)
+        .unwrap_err();
+    assert_eq!(err, HpkeError::OpenError);
+    assert_eq!(stage(hpke.last_error_context()), Stage::Open);
+
+    let exporter = hpke_export_only();
+    let keys = exporter.generate_key_pair().unwrap();
+    let (_, mut sender) = exporter
+        .setup_sender(keys.public_key(), b"", None, None, None)
+        .unwrap();
+    assert_eq!(sender.seal(b"", b""), Err(HpkeError::ExportOnly));
+    assert_eq!(stage(sender.last_error_context()), Stage::Seal);
+    let sender = sender.into_sync_sender().unwrap();
+    sender.export(b"", 0).unwrap();
+    assert_eq!(sender.seal(b"", b""), Err(HpkeError::ExportOnly));
+    assert_eq!(stage(sender.last_error_context()), Stage::Seal);
+}
+
+fn hpke_export_only() -> Hpke<HpkeRustCrypto> {
+    hpke(HpkeMode::Base, AeadAlgorithm::HpkeExport)
+}
+
+#[test]
+fn disabled_by_default() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let keys = hpke.generate_key_pair().unwrap();
+    hpke.setup_receiver(&[0u8; 3], keys.private_key(), b"", None, None, None)
+        .unwrap_err();
+    assert!(hpke.last_error_context().is_none());
+}
@@ -3,9 +3,10 @@
 extern crate hpke_rs as hpke;
 
 use hpke::prelude::*;
+use hpke::test_util::hex_to_bytes;
 use hpke_rs_crypto::{
     types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm},
-    HpkeCrypto, RngCore,
+    CryptoRng, HpkeCrypto, RngCore,
 };
 // use hpke_rs_evercrypt::HpkeEvercrypt;
 use hpke_rs_rust_crypto::HpkeRustCrypto;
@@ -129,6 +130,22 @@ generate_test_case!(
     AeadAlgorithm::Aes128Gcm,
     HpkeRustCrypto
 );
+generate_test_case!(
+    base_dhkemp384_hkdfsha384_Aes256Gcm,
+    HpkeMode::Base,
+    KemAlgorithm::DhKemP384,
+    KdfAlgorithm::HkdfSha384,
+    AeadAlgorithm::Aes256Gcm,
+    HpkeRustCrypto
+);
+generate_test_case!(
+    authpsk_dhkemp384_hkdfsha384_Aes256Gcm,
+    HpkeMode::AuthPsk,
+    KemAlgorithm::DhKemP384,
+    KdfAlgorithm::HkdfSha384,
+    AeadAlgorithm::Aes256Gcm,
+    HpkeRustCrypto
+);
 generate_test_case!(
     base_dhkemp256_hkdfsha384_Aes128Gcm,
     HpkeMode::Base,
@@ -705,3 +722,2485 @@ generate_test_case!(
     AeadAlgorithm::ChaCha20Poly1305,
     HpkeRustCrypto
 );
+
+#[test]
+fn key_id() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let other_key_pair = hpke.generate_key_pair().unwrap();
+
+    let key_id = hpke.key_id(key_pair.public_key()).unwrap();
+    assert_eq!(key_id.as_slice().len(), KEY_ID_LEN);
+    assert_eq!(
+        key_id,
+        key_pair
+            .key_id::<HpkeRustCrypto>(KemAlgorithm::DhKem25519)
+            .unwrap()
+    );
+    assert_ne!(key_id, hpke.key_id(other_key_pair.public_key()).unwrap());
+
+    // The KEM is bound into the key id.
+    assert_ne!(
+        key_id,
+        key_pair
+            .public_key()
+            .key_id::<HpkeRustCrypto>(KemAlgorithm::DhKem448)
+            .unwrap()
+    );
+
+    assert_eq!(KeyId::try_from(key_id.as_slice()).unwrap(), key_id);
+    assert_eq!(KeyId::try_from(&[0u8; 3][..]), Err(HpkeError::InvalidInput));
+    assert_eq!(key_id.to_string().len(), 2 * KEY_ID_LEN);
+
+    // Known answers for pkRm of the base mode test vectors in RFC 9180.
+    let pk = hex_to_bytes("3948cfe0ad1ddb695d780e59077195da6c56506b027329794ab02bca80815c4d");
+    assert_eq!(
+        KeyId::compute::<HpkeRustCrypto>(KemAlgorithm::DhKem25519, &pk)
+            .unwrap()
+            .as_slice(),
+        hex_to_bytes("51c9ae05f1bc41df")
+    );
+    let pk = hex_to_bytes(
+        "04fe8c19ce0905191ebc298a9245792531f26f0cece2460639e8bc39cb7f706a826a779b4cf969b8a0e539c7f62fb3d30ad6aa8f80e30f1d128aafd68a2ce72ea0",
+    );
+    assert_eq!(
+        KeyId::compute::<HpkeRustCrypto>(KemAlgorithm::DhKemP256, &pk)
+            .unwrap()
+            .as_slice(),
+        hex_to_bytes("fe5ab6422c850060")
+    );
+}
+
+#[test]
+fn ratchet() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKemP256,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let (enc, mut sender) = hpke.setup_sender(&pk_r, b"info", None, None, None).unwrap();
+    let mut receiver = hpke
+        .setup_receiver(&enc, &sk_r, b"info", None, None, None)
+        .unwrap();
+    let mut stale_receiver = hpke
+        .setup_receiver(&enc, &sk_r, b"info", None, None, None)
+        .unwrap();
+
+    let ctxt = sender.seal(b"aad", b"message").unwrap();
+    assert_eq!(receiver.open(b"aad", &ctxt).unwrap(), b"message");
+    stale_receiver.open(b"aad", &ctxt).unwrap();
+    let export = sender.export(b"context", 32).unwrap();
+
+    // Manual ratchet
+    sender.ratchet().unwrap();
+    receiver.ratchet().unwrap();
+    assert_ne!(sender.export(b"context", 32).unwrap(), export);
+    assert_eq!(
+        sender.export(b"context", 32).unwrap(),
+        receiver.export(b"context", 32).unwrap()
+    );
+    let ctxt = sender.seal(b"aad", b"message").unwrap();
+    assert_eq!(receiver.open(b"aad", &ctxt).unwrap(), b"message");
+    assert_eq!(
+        stale_receiver.open(b"aad", &ctxt).unwrap_err(),
+        HpkeError::OpenError
+    );
+
+    // Automatic ratchet
+    assert_eq!(
+        sender.set_ratchet_interval(Some(0)).unwrap_err(),
+        HpkeError::InvalidConfig
+    );
+    sender.set_ratchet_interval(Some(3)).unwrap();
+    receiver.set_ratchet_interval(Some(3)).unwrap();
+    for _ in 0..10 {
+        let ctxt = sender.seal(b"aad", b"message").unwrap();
+        assert_eq!(receiver.open(b"aad", &ctxt).unwrap(), b"message");
+    }
+    assert_eq!(
+        sender.export(b"context", 32).unwrap(),
+        receiver.export(b"context", 32).unwrap()
+    );
+}
+
+#[test]
+fn epochs() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let (enc, mut sender) = hpke.setup_sender(&pk_r, b"info", None, None, None).unwrap();
+    let mut receiver = hpke
+        .setup_receiver(&enc, &sk_r, b"info", None, None, None)
+        .unwrap();
+    assert_eq!(sender.epoch(), 0);
+
+    // An epoch differs from a plain ratchet only in the nonce.
+    let mut ratcheted = hpke
+        .setup_receiver(&enc, &sk_r, b"info", None, None, None)
+        .unwrap();
+    ratcheted.ratchet().unwrap();
+
+    sender.seal(b"aad", b"epoch 0").unwrap();
+    sender.advance_epoch().unwrap();
+    assert_eq!(sender.epoch(), 1);
+    assert_eq!(sender.sequence(), 0);
+    let ctxt = sender.seal(b"aad", b"epoch 1").unwrap();
+    assert_eq!(
+        ratcheted.open(b"aad", &ctxt).unwrap_err(),
+        HpkeError::OpenError
+    );
+    receiver.advance_epoch_to(1).unwrap();
+    assert_eq!(receiver.open(b"aad", &ctxt).unwrap(), b"epoch 1");
+
+    // Receivers catch up with senders that skipped epochs.
+    sender.advance_epoch().unwrap();
+    sender.advance_epoch().unwrap();
+    let ctxt = sender.seal(b"aad", b"epoch 3").unwrap();
+    receiver.advance_epoch_to(3).unwrap();
+    receiver.advance_epoch_to(3).unwrap();
+    assert_eq!(receiver.open(b"aad", &ctxt).unwrap(), b"epoch 3");
+    assert_eq!(
+        receiver.advance_epoch_to(2).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+
+    // Jumps are limited.
+    for _ in 0..20 {
+        sender.advance_epoch().unwrap();
+    }
+    let ctxt = sender.seal(b"aad", b"epoch 23").unwrap();
+    assert_eq!(
+        receiver.advance_epoch_to(23).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    assert_eq!(receiver.epoch(), 3);
+    receiver.set_max_epoch_jump(20);
+    receiver.advance_epoch_to(23).unwrap();
+    assert_eq!(receiver.open(b"aad", &ctxt).unwrap(), b"epoch 23");
+    assert_eq!(
+        sender.export(b"context", 32).unwrap(),
+        receiver.export(b"context", 32).unwrap()
+    );
+}
+
+#[test]
+fn key_schedule_template() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Psk,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    let psk = [7u8; 32];
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let template = hpke
+        .key_schedule_template(b"info", Some(&psk), Some(b"psk id"))
+        .unwrap();
+
+    for _ in 0..3 {
+        let (enc, mut sender) = template.setup_sender(&pk_r, None).unwrap();
+        let mut receiver = hpke
+            .setup_receiver(&enc, &sk_r, b"info", Some(&psk), Some(b"psk id"), None)
+            .unwrap();
+        let ctxt = sender.seal(b"aad", b"message").unwrap();
+        assert_eq!(receiver.open(b"aad", &ctxt).unwrap(), b"message");
+
+        let mut receiver = template.setup_receiver(&enc, &sk_r, None).unwrap();
+        receiver.skip_to(1).unwrap();
+        let ctxt = sender.seal(b"aad", b"message").unwrap();
+        assert_eq!(receiver.open(b"aad", &ctxt).unwrap(), b"message");
+    }
+
+    // The shared secret alone determines the context.
+    let shared_secret = [1u8; 32];
+    assert_eq!(
+        template
+            .key_schedule(&shared_secret)
+            .unwrap()
+            .export(b"context", 32)
+            .unwrap(),
+        hpke.key_schedule(&shared_secret, b"info", &psk, b"psk id")
+            .unwrap()
+            .export(b"context", 32)
+            .unwrap()
+    );
+
+    assert_eq!(
+        hpke.key_schedule_template(b"info", None, None).unwrap_err(),
+        HpkeError::MissingPsk
+    );
+    assert_eq!(
+        hpke.clone()
+            .with_version(HpkeVersion::Draft05)
+            .key_schedule_template(b"info", Some(&psk), Some(b"psk id"))
+            .unwrap_err(),
+        HpkeError::InvalidConfig
+    );
+}
+
+#[test]
+fn external_key_schedule() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKemP256,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+
+    // Encapsulate outside of the crate and use its key schedule.
+    let kem = hpke::kem::Kem::<HpkeRustCrypto>::new(KemAlgorithm::DhKemP256);
+    let (shared_secret, enc) = kem.encaps(&pk_r).unwrap();
+    let mut sender = hpke
+        .key_schedule(shared_secret.as_slice(), b"info", &[], &[])
+        .unwrap();
+
+    let mut receiver = hpke
+        .setup_receiver(&enc, &sk_r, b"info", None, None, None)
+        .unwrap();
+    let ctxt = sender.seal(b"aad", b"message").unwrap();
+    assert_eq!(receiver.open(b"aad", &ctxt).unwrap(), b"message");
+
+    assert_eq!(
+        hpke.key_schedule(shared_secret.as_slice(), b"info", &[7; 32], b"id")
+            .unwrap_err(),
+        HpkeError::UnnecessaryPsk
+    );
+}
+
+#[test]
+fn prehashed_info() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha384,
+        AeadAlgorithm::ChaCha20Poly1305,
+    )
+    // The receiver hashes the info in the key schedule.
+    .with_input_limits(hpke::limits::InputLimits::new().max_info(1 << 20));
+    let info = vec![0x42u8; 1 << 20];
+    let info_hash = hpke.info_hash(&info);
+    assert_eq!(info_hash.len(), hpke.nh());
+
+    let shared_secret = [3u8; 32];
+    let mut sender = hpke
+        .key_schedule_prehashed(&shared_secret, &info_hash, &[], &[])
+        .unwrap();
+    let mut receiver = hpke.key_schedule(&shared_secret, &info, &[], &[]).unwrap();
+    let ctxt = sender.seal(b"aad", b"message").unwrap();
+    assert_eq!(receiver.open(b"aad", &ctxt).unwrap(), b"message");
+
+    assert_eq!(
+        hpke.key_schedule_prehashed(&shared_secret, &info_hash[1..], &[], &[])
+            .unwrap_err(),
+        HpkeError::InvalidInput
+    );
+}
+
+#[test]
+fn generate_many_key_pairs() {
+    for kem in [KemAlgorithm::DhKem25519, KemAlgorithm::DhKemP256] {
+        let hpke = Hpke::<HpkeRustCrypto>::new(
+            HpkeMode::Base,
+            kem,
+            KdfAlgorithm::HkdfSha256,
+            AeadAlgorithm::Aes128Gcm,
+        );
+        let key_pairs = hpke.generate_key_pairs(8).unwrap();
+        assert_eq!(key_pairs.len(), 8);
+        for (i, kp) in key_pairs.iter().enumerate() {
+            assert!(key_pairs[..i]
+                .iter()
+                .all(|other| other.public_key() != kp.public_key()));
+            let (enc, ctxt) = hpke
+                .seal(
+                    kp.public_key(),
+                    b"info",
+                    b"aad",
+                    b"message",
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap();
+            assert_eq!(
+                hpke.open(
+                    &enc,
+                    kp.private_key(),
+                    b"info",
+                    b"aad",
+                    &ctxt,
+                    None,
+                    None,
+                    None
+                )
+                .unwrap(),
+                b"message"
+            );
+        }
+        assert!(hpke.generate_key_pairs(0).unwrap().is_empty());
+    }
+}
+
+#[test]
+fn aad_parts() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes256Gcm,
+    );
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let (enc, mut sender) = hpke.setup_sender(&pk_r, b"info", None, None, None).unwrap();
+    let mut receiver = hpke
+        .setup_receiver(&enc, &sk_r, b"info", None, None, None)
+        .unwrap();
+
+    let ctxt = sender
+        .seal_with_aad_parts(&[b"header", b"", b"metadata"], b"message")
+        .unwrap();
+    assert_eq!(receiver.open(b"headermetadata", &ctxt).unwrap(), b"message");
+
+    let ctxt = sender.seal(b"headermetadata", b"message").unwrap();
+    assert_eq!(
+        receiver
+            .open_with_aad_parts(&[b"head", b"ermeta", b"data"], &ctxt)
+            .unwrap(),
+        b"message"
+    );
+
+    let ctxt = sender.seal_with_aad_parts(&[], b"message").unwrap();
+    assert_eq!(
+        receiver
+            .open_with_aad_parts(&[b"header"], &ctxt)
+            .unwrap_err(),
+        HpkeError::OpenError
+    );
+    assert_eq!(
+        receiver.open_with_aad_parts(&[], &ctxt).unwrap(),
+        b"message"
+    );
+}
+
+#[test]
+fn sealed_sender() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let sender = hpke.generate_key_pair().unwrap();
+
+    let (enc, ct) = hpke
+        .seal_sealed_sender(&pk_r, &sender, b"info", b"aad", b"message")
+        .unwrap();
+    let (pk_s, pt) = hpke
+        .open_sealed_sender(&enc, &sk_r, b"info", b"aad", &ct)
+        .unwrap();
+    assert_eq!(&pk_s, sender.public_key());
+    assert_eq!(pt, b"message");
+
+    // The outer layer is a plain base mode message that hides the sender.
+    let payload = hpke
+        .open(&enc, &sk_r, b"info", b"aad", &ct, None, None, None)
+        .unwrap();
+    assert_eq!(&payload[2..34], sender.public_key().as_slice());
+
+    // Impersonation: claim another sender key for the inner message.
+    let other = hpke.generate_key_pair().unwrap();
+    let mut forged = payload.clone();
+    forged[2..34].copy_from_slice(other.public_key().as_slice());
+    let (enc, ct) = hpke
+        .seal(&pk_r, b"info", b"aad", &forged, None, None, None)
+        .unwrap();
+    assert_eq!(
+        hpke.open_sealed_sender(&enc, &sk_r, b"info", b"aad", &ct)
+            .unwrap_err(),
+        HpkeError::OpenError
+    );
+
+    let auth = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Auth,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    assert_eq!(
+        auth.seal_sealed_sender(&pk_r, &sender, b"info", b"aad", b"message")
+            .unwrap_err(),
+        HpkeError::InvalidConfig
+    );
+}
+
+#[test]
+fn tokens() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+
+    let token = hpke
+        .seal_token(&pk_r, b"info", b"session", b"state", None)
+        .unwrap();
+    assert!(token
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'));
+    assert_eq!(
+        hpke.open_token(&token, &sk_r, b"info", b"session", u64::MAX)
+            .unwrap(),
+        b"state"
+    );
+    assert_eq!(
+        hpke.open_token(&token, &sk_r, b"info", b"other", 0)
+            .unwrap_err(),
+        HpkeError::OpenError
+    );
+
+    let token = hpke
+        .seal_token(&pk_r, b"info", b"", b"state", Some(1_000))
+        .unwrap();
+    assert_eq!(
+        hpke.open_token(&token, &sk_r, b"info", b"", 999).unwrap(),
+        b"state"
+    );
+    assert_eq!(
+        hpke.open_token(&token, &sk_r, b"info", b"", 1_000)
+            .unwrap_err(),
+        HpkeError::Expired
+    );
+
+    // The expiry is authenticated.
+    // The first 16 characters encode the suite and the leading zero bytes
+    // of the expiry, the 18th the low bits of the expiry 0x03e8.
+    let mut bytes = token.into_bytes();
+    assert_eq!(&bytes[..16], b"ACAAAQADAAAAAAAA");
+    bytes[17] = b'A';
+    let tampered = String::from_utf8(bytes).unwrap();
+    assert_eq!(
+        hpke.open_token(&tampered, &sk_r, b"info", b"", 999)
+            .unwrap_err(),
+        HpkeError::OpenError
+    );
+
+    assert_eq!(
+        hpke.open_token("ACAAAQAD", &sk_r, b"info", b"", 0)
+            .unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    assert_eq!(
+        hpke.open_token("ACAAAQAB", &sk_r, b"info", b"", 0)
+            .unwrap_err(),
+        HpkeError::InvalidConfig
+    );
+    assert_eq!(
+        hpke.seal_token(&pk_r, b"info", b"", b"state", Some(0))
+            .unwrap_err(),
+        HpkeError::InvalidConfig
+    );
+}
+
+#[test]
+fn validity() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let validity = Validity::expires_in(1_000, 60);
+    let (enc, ct) = hpke
+        .seal_with_validity(
+            &pk_r, b"info", b"aad", b"command", None, None, None, &validity,
+        )
+        .unwrap();
+    let open = |ct: &[u8], now| {
+        hpke.open_with_validity(&enc, &sk_r, b"info", b"aad", ct, None, None, None, now)
+    };
+    assert_eq!(open(&ct, 1_000).unwrap(), b"command");
+    assert_eq!(open(&ct, 1_060).unwrap(), b"command");
+    assert_eq!(open(&ct, 999).unwrap_err(), HpkeError::NotYetValid);
+    assert_eq!(open(&ct, 1_061).unwrap_err(), HpkeError::Expired);
+
+    // The validity period is authenticated.
+    let mut tampered = ct.clone();
+    tampered[15] ^= 0xff;
+    assert_eq!(open(&tampered, 1_061).unwrap_err(), HpkeError::OpenError);
+    assert_eq!(open(&ct[..15], 1_000).unwrap_err(), HpkeError::InvalidInput);
+
+    // Unbounded validity periods
+    let (enc, mut sender) = hpke.setup_sender(&pk_r, b"info", None, None, None).unwrap();
+    let mut receiver = hpke
+        .setup_receiver(&enc, &sk_r, b"info", None, None, None)
+        .unwrap();
+    let ct = sender
+        .seal_with_validity(b"", b"forever", &Validity::default())
+        .unwrap();
+    assert_eq!(
+        &ct[..16],
+        &[0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+    );
+    assert_eq!(
+        receiver.open_with_validity(b"", &ct, u64::MAX).unwrap(),
+        b"forever"
+    );
+    assert_eq!(Validity::default().check(0), Ok(()));
+    assert_eq!(HpkeError::NotYetValid.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn injected_rng() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let hpke = |seed| {
+        Hpke::<HpkeRustCrypto>::new(
+            HpkeMode::Base,
+            KemAlgorithm::DhKem25519,
+            KdfAlgorithm::HkdfSha256,
+            AeadAlgorithm::ChaCha20Poly1305,
+        )
+        .with_rng(StdRng::seed_from_u64(seed))
+    };
+
+    // The same RNG gives the same keys and encapsulations.
+    let (a, b) = (hpke(1), hpke(1));
+    let key_pair = a.generate_key_pair().unwrap();
+    assert_eq!(
+        key_pair.public_key(),
+        b.generate_key_pair().unwrap().public_key()
+    );
+    let (enc, ct) = a
+        .seal(
+            key_pair.public_key(),
+            b"info",
+            b"aad",
+            b"message",
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    let (enc_b, _) = b
+        .seal(
+            key_pair.public_key(),
+            b"info",
+            b"aad",
+            b"message",
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(enc, enc_b);
+    let pt = a
+        .open(
+            &enc,
+            key_pair.private_key(),
+            b"info",
+            b"aad",
+            &ct,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(pt, b"message");
+
+    // Clones share the RNG.
+    let c = a.clone();
+    let from_c = c.generate_key_pair().unwrap();
+    let from_a = a.generate_key_pair().unwrap();
+    assert_eq!(
+        from_c.public_key(),
+        b.generate_key_pair().unwrap().public_key()
+    );
+    assert_eq!(
+        from_a.public_key(),
+        b.generate_key_pair().unwrap().public_key()
+    );
+    assert_ne!(
+        hpke(2).generate_key_pair().unwrap().public_key(),
+        key_pair.public_key()
+    );
+
+    // Failing RNGs are reported.
+    struct NoEntropy;
+    impl RngCore for NoEntropy {
+        fn next_u32(&mut self) -> u32 {
+            unimplemented!()
+        }
+        fn next_u64(&mut self) -> u64 {
+            unimplemented!()
+        }
+        fn fill_bytes(&mut self, _: &mut [u8]) {
+            unimplemented!()
+        }
+        fn try_fill_bytes(&mut self, _: &mut [u8]) -> Result<(), rand::Error> {
+            Err(rand::Error::new("no entropy"))
+        }
+    }
+    impl CryptoRng for NoEntropy {}
+    let hpke = a.with_rng(NoEntropy);
+    assert_eq!(
+        hpke.generate_key_pair().unwrap_err(),
+        HpkeError::InsufficientRandomness
+    );
+    assert_eq!(
+        hpke.seal(
+            key_pair.public_key(),
+            b"info",
+            b"aad",
+            b"message",
+            None,
+            None,
+            None
+        )
+        .unwrap_err(),
+        HpkeError::InsufficientRandomness
+    );
+
+    let kem = hpke::kem::Kem::<HpkeRustCrypto>::new(KemAlgorithm::DhKem25519)
+        .with_rng(StdRng::seed_from_u64(1));
+    assert_eq!(
+        kem.generate_key_pair().unwrap().public_key(),
+        key_pair.public_key()
+    );
+}
+
+#[test]
+fn combined_kem() {
+    use hpke::kem::{CombinedKem, Kem, KemScheme};
+
+    let hybrid = CombinedKem::new(
+        Kem::<HpkeRustCrypto>::new(KemAlgorithm::DhKemP256),
+        Kem::<HpkeRustCrypto>::new(KemAlgorithm::DhKem25519),
+        KdfAlgorithm::HkdfSha256,
+        b"P256-X25519",
+    );
+    assert_eq!(hybrid.public_key_len(), 65 + 32);
+    assert_eq!(hybrid.enc_len(), 65 + 32);
+    let key_pair = hybrid.generate_key_pair().unwrap();
+    assert_eq!(
+        key_pair.public_key().as_slice().len(),
+        hybrid.public_key_len()
+    );
+
+    let (shared_secret, enc) = hybrid.encaps(key_pair.public_key()).unwrap();
+    assert_eq!(enc.len(), hybrid.enc_len());
+    assert_eq!(shared_secret.as_slice().len(), 32);
+    assert_eq!(
+        hybrid.decaps(&enc, key_pair.private_key()).unwrap(),
+        shared_secret
+    );
+
+    // The shared secret depends on both encapsulations and on the label.
+    let mut tampered = enc.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    assert_ne!(
+        hybrid.decaps(&tampered, key_pair.private_key()).unwrap(),
+        shared_secret
+    );
+    let relabeled = CombinedKem::new(
+        Kem::<HpkeRustCrypto>::new(KemAlgorithm::DhKemP256),
+        Kem::<HpkeRustCrypto>::new(KemAlgorithm::DhKem25519),
+        KdfAlgorithm::HkdfSha256,
+        b"other",
+    );
+    assert_ne!(
+        relabeled.decaps(&enc, key_pair.private_key()).unwrap(),
+        shared_secret
+    );
+    assert_eq!(
+        hybrid
+            .decaps(&enc[1..], key_pair.private_key())
+            .unwrap_err(),
+        HpkeError::DecapError(DecapError::InvalidLength)
+    );
+    assert_eq!(
+        hybrid.encaps(&HpkePublicKey::new(vec![0; 32])).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+
+    // Combined KEMs compose and feed the key schedule.
+    let nested = CombinedKem::new(
+        hybrid,
+        Kem::<HpkeRustCrypto>::new(KemAlgorithm::DhKem25519),
+        KdfAlgorithm::HkdfSha256,
+        b"P256-X25519-X25519",
+    );
+    let key_pair = nested.generate_key_pair().unwrap();
+    let (shared_secret, enc) = nested.encaps(key_pair.public_key()).unwrap();
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKemP256,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    let mut sender = hpke
+        .key_schedule(shared_secret.as_slice(), b"info", &[], &[])
+        .unwrap();
+    let received = nested.decaps(&enc, key_pair.private_key()).unwrap();
+    let mut receiver = hpke
+        .key_schedule(received.as_slice(), b"info", &[], &[])
+        .unwrap();
+    let ctxt = sender.seal(b"aad", b"message").unwrap();
+    assert_eq!(receiver.open(b"aad", &ctxt).unwrap(), b"message");
+}
+
+#[test]
+fn hkdf_sha3_256() {
+    let hpke = Hpke::<HpkeRustCrypto>::try_from_ids(0x00, 0x0020, 0xFF01, 0x0003).unwrap();
+    assert_eq!(hpke.config().kdf_id, KdfAlgorithm::HkdfSha3_256);
+    assert_eq!(hpke.nh(), 32);
+    assert_eq!(
+        hpke.ciphersuite().to_string(),
+        "DHKEM(X25519, HKDF-SHA256), HKDF-SHA3-256, ChaCha20Poly1305"
+    );
+
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let (enc, ct) = hpke
+        .seal(
+            key_pair.public_key(),
+            b"info",
+            b"aad",
+            b"message",
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    let pt = hpke
+        .open(
+            &enc,
+            key_pair.private_key(),
+            b"info",
+            b"aad",
+            &ct,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(pt, b"message");
+
+    // The KDF is part of the key schedule.
+    let sha2 = Hpke::<HpkeRustCrypto>::try_from_ids(0x00, 0x0020, 0x0001, 0x0003).unwrap();
+    assert_eq!(
+        sha2.open(
+            &enc,
+            key_pair.private_key(),
+            b"info",
+            b"aad",
+            &ct,
+            None,
+            None,
+            None
+        )
+        .unwrap_err(),
+        HpkeError::OpenError
+    );
+}
+
+#[test]
+fn kmac256() {
+    let hpke = Hpke::<HpkeRustCrypto>::try_from_ids(0x00, 0x0020, 0xFF02, 0x0003).unwrap();
+    assert_eq!(hpke.config().kdf_id, KdfAlgorithm::Kmac256);
+    assert_eq!(hpke.nh(), 64);
+    assert_eq!(
+        hpke.ciphersuite().to_string(),
+        "DHKEM(X25519, HKDF-SHA256), KMAC256, ChaCha20Poly1305"
+    );
+
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let (enc, mut sender) = hpke
+        .setup_sender(key_pair.public_key(), b"info", None, None, None)
+        .unwrap();
+    let mut receiver = hpke
+        .setup_receiver(&enc, key_pair.private_key(), b"info", None, None, None)
+        .unwrap();
+    for i in 0..3u8 {
+        let ct = sender.seal(b"aad", &[i; 17]).unwrap();
+        assert_eq!(receiver.open(b"aad", &ct).unwrap(), [i; 17]);
+    }
+    assert_eq!(
+        sender.export(b"context", 100).unwrap(),
+        receiver.export(b"context", 100).unwrap()
+    );
+}
+
+#[test]
+fn size_calculators() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKemP256,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    assert_eq!(hpke.overhead(), 65 + 16);
+    assert_eq!(hpke.ct_len(100).unwrap(), 116);
+    assert_eq!(hpke.pt_len(116).unwrap(), 100);
+    assert_eq!(hpke.pt_len(16).unwrap(), 0);
+    assert_eq!(hpke.pt_len(15).unwrap_err(), HpkeError::InvalidInput);
+    assert_eq!(
+        hpke.ct_len(usize::MAX).unwrap_err(),
+        HpkeError::PayloadTooLarge
+    );
+
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let (enc, ct) = hpke
+        .seal(
+            key_pair.public_key(),
+            b"info",
+            b"aad",
+            &[0; 100],
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(enc.len() + ct.len(), 100 + hpke.overhead());
+
+    let (_, mut context) = hpke
+        .setup_sender(key_pair.public_key(), b"info", None, None, None)
+        .unwrap();
+    assert_eq!(context.overhead(), 16);
+    for len in [0, 1, 1000] {
+        let ct = context.seal(b"", &vec![0; len]).unwrap();
+        assert_eq!(ct.len(), context.ct_len(len).unwrap());
+        assert_eq!(context.pt_len(ct.len()).unwrap(), len);
+    }
+
+    let export_only = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::HpkeExport,
+    );
+    assert_eq!(export_only.overhead(), 32);
+    assert_eq!(export_only.ct_len(1).unwrap_err(), HpkeError::ExportOnly);
+    assert_eq!(export_only.pt_len(16).unwrap_err(), HpkeError::ExportOnly);
+}
+
+#[test]
+fn suite_binding() {
+    use hpke::ciphersuite::Ciphersuite;
+
+    let weak = Ciphersuite::new(
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    let strong = Ciphersuite::new(
+        KemAlgorithm::DhKemP256,
+        KdfAlgorithm::HkdfSha512,
+        AeadAlgorithm::Aes256Gcm,
+    );
+    let hpke = || Hpke::<HpkeRustCrypto>::new(HpkeMode::Base, weak.kem, weak.kdf, weak.aead);
+    let key_pair = hpke().generate_key_pair().unwrap();
+    let sender = hpke().with_suite_binding(&[strong, weak]).unwrap();
+    let (enc, ct) = sender
+        .seal(
+            key_pair.public_key(),
+            b"info",
+            b"aad",
+            b"message",
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    let open = |receiver: &Hpke<HpkeRustCrypto>| {
+        receiver.open(
+            &enc,
+            key_pair.private_key(),
+            b"info",
+            b"aad",
+            &ct,
+            None,
+            None,
+            None,
+        )
+    };
+
+    // Same offer.
+    let receiver = hpke().with_suite_binding(&[strong, weak]).unwrap();
+    assert_eq!(open(&receiver).unwrap(), b"message");
+    // The strong suite was stripped from the offer.
+    let receiver = hpke().with_suite_binding(&[weak]).unwrap();
+    assert_eq!(open(&receiver).unwrap_err(), HpkeError::OpenError);
+    // The offer was reordered.
+    let receiver = hpke().with_suite_binding(&[weak, strong]).unwrap();
+    assert_eq!(open(&receiver).unwrap_err(), HpkeError::OpenError);
+    // No binding.
+    assert_eq!(open(&hpke()).unwrap_err(), HpkeError::OpenError);
+
+    // The info hash includes the binding.
+    assert_ne!(receiver.info_hash(b"info"), hpke().info_hash(b"info"));
+
+    // The negotiated suite has to be offered.
+    assert_eq!(
+        hpke().with_suite_binding(&[strong]).unwrap_err(),
+        HpkeError::InvalidConfig
+    );
+}
+
+#[test]
+fn self_test() {
+    use hpke::{ciphersuite::Ciphersuite, self_test::SelfTestOutcome};
+
+    let report = Hpke::<HpkeRustCrypto>::self_test();
+    assert!(report.is_ok());
+    assert_eq!(report.failed().count(), 0);
+    let passed: Vec<Ciphersuite> = report.passed().collect();
+    assert_eq!(passed.len(), 5);
+    assert!(passed.contains(&Ciphersuite::new(
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::HpkeExport
+    )));
+    assert!(passed.contains(&Ciphersuite::new(
+        KemAlgorithm::DhKemP256,
+        KdfAlgorithm::HkdfSha512,
+        AeadAlgorithm::Aes128Gcm
+    )));
+
+    // The provider doesn't implement P-521 and X448.
+    for (suite, outcome) in &report.results {
+        if matches!(suite.kem, KemAlgorithm::DhKemP521 | KemAlgorithm::DhKem448) {
+            assert_eq!(*outcome, SelfTestOutcome::Unsupported);
+        }
+    }
+}
+
+#[test]
+fn fixed_size_outputs() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let keys = hpke.generate_key_pair().unwrap();
+
+    let pk: [u8; 32] = keys.public_key().try_into().unwrap();
+    assert_eq!(&HpkePublicKey::from(pk), keys.public_key());
+    assert_eq!(
+        <[u8; 65]>::try_from(keys.public_key()).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+
+    let (enc, sender) = hpke
+        .setup_sender(keys.public_key(), b"info", None, None, None)
+        .unwrap();
+    let receiver = hpke
+        .setup_receiver(&enc, keys.private_key(), b"info", None, None, None)
+        .unwrap();
+    let key: [u8; 32] = receiver.export_array(b"key").unwrap();
+    assert_eq!(key.to_vec(), receiver.export(b"key", 32).unwrap());
+    let sender = sender.into_sync_sender().unwrap();
+    assert_eq!(sender.export_array::<32>(b"key").unwrap(), key);
+    assert_eq!(
+        receiver.export_array::<16>(b"key").unwrap().to_vec(),
+        receiver.export(b"key", 16).unwrap()
+    );
+}
+
+#[test]
+fn short_authentication_string() {
+    use hpke::exporter::MAX_SAS_DIGITS;
+
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let keys = hpke.generate_key_pair().unwrap();
+    let (enc, sender) = hpke
+        .setup_sender(keys.public_key(), b"info", None, None, None)
+        .unwrap();
+    let receiver = hpke
+        .setup_receiver(&enc, keys.private_key(), b"info", None, None, None)
+        .unwrap();
+
+    let sas = sender.sas(6).unwrap();
+    assert_eq!(sas.len(), 6);
+    assert!(sas.bytes().all(|b| b.is_ascii_digit()));
+    assert_eq!(sas, receiver.sas(6).unwrap());
+    assert!(sender.sas(12).unwrap().ends_with(&sas));
+
+    // A man-in-the-middle can't make its two contexts agree.
+    let (_, mitm) = hpke
+        .setup_sender(keys.public_key(), b"info", None, None, None)
+        .unwrap();
+    assert_ne!(sender.sas(MAX_SAS_DIGITS), mitm.sas(MAX_SAS_DIGITS));
+
+    assert_eq!(sender.sas(0).unwrap_err(), HpkeError::InvalidInput);
+    assert_eq!(
+        sender.sas(MAX_SAS_DIGITS + 1).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+}
+
+#[test]
+fn application_label() {
+    let hpke = || {
+        Hpke::<HpkeRustCrypto>::new(
+            HpkeMode::Base,
+            KemAlgorithm::DhKem25519,
+            KdfAlgorithm::HkdfSha256,
+            AeadAlgorithm::ChaCha20Poly1305,
+        )
+    };
+    let (sk_r, pk_r) = hpke().generate_key_pair().unwrap().into_keys();
+    let backup = hpke().for_application("com.example.backup/v2").unwrap();
+    assert_eq!(backup.application(), Some("com.example.backup/v2"));
+    assert_eq!(hpke().application(), None);
+    assert_eq!(backup.clone().application(), backup.application());
+
+    let (enc, ct) = backup
+        .seal(&pk_r, b"info", b"aad", b"message", None, None, None)
+        .unwrap();
+    let open = |receiver: &Hpke<HpkeRustCrypto>| {
+        receiver.open(&enc, &sk_r, b"info", b"aad", &ct, None, None, None)
+    };
+    assert_eq!(open(&backup).unwrap(), b"message");
+    let chat = hpke().for_application("com.example.chat/v1").unwrap();
+    assert_eq!(open(&chat).unwrap_err(), HpkeError::OpenError);
+    assert_eq!(open(&hpke()).unwrap_err(), HpkeError::OpenError);
+
+    // The label is length-prefixed in front of the info.
+    let mut info = b"HPKE-app\x00\x15com.example.backup/v2".to_vec();
+    info.extend_from_slice(b"info");
+    assert_eq!(backup.info_hash(b"info"), hpke().info_hash(&info));
+    assert_ne!(
+        hpke().for_application("ab").unwrap().info_hash(b""),
+        hpke().for_application("a").unwrap().info_hash(b"b")
+    );
+
+    assert_eq!(
+        hpke().for_application("").unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    let long = "a".repeat(usize::from(u16::MAX) + 1);
+    assert_eq!(
+        hpke().for_application(&long).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+}
+
+#[test]
+fn exporter_session_id() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let keys = hpke.generate_key_pair().unwrap();
+    let (enc, sender) = hpke
+        .setup_sender(keys.public_key(), b"info", None, None, None)
+        .unwrap();
+    let receiver = hpke
+        .setup_receiver(&enc, keys.private_key(), b"info", None, None, None)
+        .unwrap();
+
+    let id = sender.session_id(16).unwrap();
+    assert_eq!(id.len(), 16);
+    assert_eq!(id, receiver.session_id(16).unwrap());
+    assert_eq!(id, sender.export(b"HPKE session id", 16).unwrap());
+    assert_ne!(id, sender.export(b"", 16).unwrap());
+
+    let (_, other) = hpke
+        .setup_sender(keys.public_key(), b"info", None, None, None)
+        .unwrap();
+    assert_ne!(id, other.session_id(16).unwrap());
+
+    assert_eq!(sender.session_id(0).unwrap_err(), HpkeError::InvalidInput);
+}
+
+#[test]
+fn compact_tokens() {
+    use hpke::token::CompactToken;
+
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+
+    let token = hpke
+        .seal_compact(&pk_r, b"info", b"request", b"payload")
+        .unwrap();
+    assert!(token.starts_with("hpke.ACAAAQAD."));
+    assert!(token
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b"-_.".contains(&b)));
+    assert_eq!(
+        hpke.open_compact(&token, &sk_r, b"info", b"request")
+            .unwrap(),
+        b"payload"
+    );
+    assert_eq!(
+        hpke.open_compact(&token, &sk_r, b"info", b"other")
+            .unwrap_err(),
+        HpkeError::OpenError
+    );
+
+    let parsed: CompactToken = token.parse().unwrap();
+    assert_eq!(parsed.suite, hpke.ciphersuite());
+    assert_eq!(parsed.enc.len(), 32);
+    assert_eq!(parsed.ct.len(), b"payload".len() + 16);
+    assert_eq!(parsed.to_string(), token);
+
+    // Tokens for other suites are rejected before opening.
+    let other = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    assert_eq!(
+        other
+            .open_compact(&token, &sk_r, b"info", b"request")
+            .unwrap_err(),
+        HpkeError::InvalidConfig
+    );
+
+    let segments: Vec<&str> = token.split('.').collect();
+    for malformed in [
+        format!("jwe.{}.{}.{}", segments[1], segments[2], segments[3]),
+        format!("hpke.{}.{}", segments[1], segments[2]),
+        format!("{token}.AA"),
+        format!("hpke.{}A.{}.{}", segments[1], segments[2], segments[3]),
+        format!("hpke.{}.{}=.{}", segments[1], segments[2], segments[3]),
+    ] {
+        assert_eq!(
+            CompactToken::parse(&malformed).unwrap_err(),
+            HpkeError::InvalidInput,
+            "{malformed}"
+        );
+    }
+    assert_eq!(
+        CompactToken::parse("hpke.AAAAAQAD.AA.AA").unwrap_err(),
+        HpkeError::UnknownMode
+    );
+}
+
+#[test]
+fn input_limits() {
+    use hpke::limits::{Input, InputLimits, DEFAULT_MAX_AAD, DEFAULT_MAX_LABELED_INPUT};
+
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Psk,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    assert_eq!(hpke.input_limits(), InputLimits::default());
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let psk = [7u8; 32];
+    let long = vec![0u8; DEFAULT_MAX_LABELED_INPUT + 1];
+
+    // The defaults.
+    let setup = |hpke: &Hpke<HpkeRustCrypto>, info: &[u8], psk_id: &[u8]| {
+        hpke.setup_sender(&pk_r, info, Some(&psk), Some(psk_id), None)
+    };
+    assert!(setup(&hpke, &long[1..], b"id").is_ok());
+    assert_eq!(
+        setup(&hpke, &long, b"id").unwrap_err(),
+        HpkeError::InputTooLong(Input::Info)
+    );
+    assert_eq!(
+        setup(&hpke, b"info", &long).unwrap_err(),
+        HpkeError::InputTooLong(Input::PskId)
+    );
+    let (enc, mut sender) = setup(&hpke, b"info", b"id").unwrap();
+    assert_eq!(
+        sender.export(&long, 32).unwrap_err(),
+        HpkeError::InputTooLong(Input::ExporterContext)
+    );
+    assert_eq!(
+        sender
+            .seal(&vec![0u8; DEFAULT_MAX_AAD + 1], b"")
+            .unwrap_err(),
+        HpkeError::InputTooLong(Input::Aad)
+    );
+    assert_eq!(
+        HpkeError::InputTooLong(Input::Aad).kind(),
+        ErrorKind::InvalidInput
+    );
+
+    // Custom limits, also for the receiver.
+    let limits = InputLimits::new()
+        .max_info(4)
+        .max_psk_id(2)
+        .max_aad(3)
+        .max_exporter_context(1);
+    assert_eq!(limits.max(Input::Info), 4);
+    let limited = hpke.clone().with_input_limits(limits);
+    assert_eq!(
+        limited
+            .setup_receiver(&enc, &sk_r, b"info!", Some(&psk), Some(b"id"), None)
+            .unwrap_err(),
+        HpkeError::InputTooLong(Input::Info)
+    );
+    assert_eq!(
+        limited
+            .setup_receiver(&enc, &sk_r, b"info", Some(&psk), Some(b"id!"), None)
+            .unwrap_err(),
+        HpkeError::InputTooLong(Input::PskId)
+    );
+    let mut receiver = limited
+        .setup_receiver(&enc, &sk_r, b"info", Some(&psk), Some(b"id"), None)
+        .unwrap();
+    let ct = sender.seal(b"aad!", b"message").unwrap();
+    assert_eq!(
+        receiver.open(b"aad!", &ct).unwrap_err(),
+        HpkeError::InputTooLong(Input::Aad)
+    );
+    assert_eq!(
+        receiver.export(b"ab", 32).unwrap_err(),
+        HpkeError::InputTooLong(Input::ExporterContext)
+    );
+    assert_eq!(receiver.export(b"a", 32), sender.export(b"a", 32));
+
+    let unlimited = hpke.with_input_limits(InputLimits::unlimited());
+    assert!(setup(&unlimited, &long, &long).is_ok());
+}
+
+#[test]
+fn fault_checks() {
+    for kem in [KemAlgorithm::DhKem25519, KemAlgorithm::DhKemP256] {
+        for mode in [
+            HpkeMode::Base,
+            HpkeMode::Psk,
+            HpkeMode::Auth,
+            HpkeMode::AuthPsk,
+        ] {
+            let hpke = Hpke::<HpkeRustCrypto>::new(
+                mode,
+                kem,
+                KdfAlgorithm::HkdfSha256,
+                AeadAlgorithm::Aes128Gcm,
+            );
+            let checked = hpke.clone().with_fault_checks();
+            let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+            let (sk_s, pk_s) = hpke.generate_key_pair().unwrap().into_keys();
+            let (psk, psk_id): (Option<&[u8]>, Option<&[u8]>) = match mode {
+                HpkeMode::Psk | HpkeMode::AuthPsk => (Some(&[7u8; 32]), Some(b"id")),
+                _ => (None, None),
+            };
+            let (sk_s, pk_s) = match mode {
+                HpkeMode::Auth | HpkeMode::AuthPsk => (Some(&sk_s), Some(&pk_s)),
+                _ => (None, None),
+            };
+
+            // Checked and unchecked contexts interoperate.
+            let (enc, mut sender) = checked
+                .setup_sender(&pk_r, b"info", psk, psk_id, sk_s)
+                .unwrap();
+            let mut receivers = [&hpke, &checked].map(|hpke| {
+                hpke.setup_receiver(&enc, &sk_r, b"info", psk, psk_id, pk_s)
+                    .unwrap()
+            });
+            let ct = sender.seal(b"aad", b"hello").unwrap();
+            let mut buffer = b"in place".to_vec();
+            sender.seal_in_place(b"aad", &mut buffer).unwrap();
+            for receiver in receivers.iter_mut() {
+                assert_eq!(receiver.open(b"aad", &ct).unwrap(), b"hello");
+                let mut buffer = buffer.clone();
+                receiver.open_in_place(b"aad", &mut buffer).unwrap();
+                assert_eq!(buffer, b"in place");
+                assert_eq!(
+                    sender.export(b"ctx", 32).unwrap(),
+                    receiver.export(b"ctx", 32).unwrap()
+                );
+            }
+
+            let sync_sender = sender.into_sync_sender().unwrap();
+            let (_, ct) = sync_sender.seal(b"aad", b"sync").unwrap();
+            for receiver in receivers.iter_mut() {
+                assert_eq!(receiver.open(b"aad", &ct).unwrap(), b"sync");
+            }
+        }
+    }
+    assert_eq!(HpkeError::FaultDetected.kind(), ErrorKind::Internal);
+}
+
+#[test]
+fn seal_to_many() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let recipients: Vec<HpkeKeyPair> = (0..8).map(|_| hpke.generate_key_pair().unwrap()).collect();
+    let pk_rs: Vec<HpkePublicKey> = recipients
+        .iter()
+        .map(|kp| kp.public_key().clone())
+        .collect();
+
+    let sealed = hpke
+        .seal_to_many(&pk_rs, b"info", b"aad", b"message", None, None, None)
+        .unwrap();
+    assert_eq!(sealed.len(), recipients.len());
+    for (kp, (enc, ct)) in recipients.iter().zip(sealed.iter()) {
+        let pt = hpke
+            .open(enc, kp.private_key(), b"info", b"aad", ct, None, None, None)
+            .unwrap();
+        assert_eq!(pt, b"message");
+    }
+
+    // One invalid recipient fails the whole operation.
+    let mut pk_rs = pk_rs;
+    pk_rs.push(HpkePublicKey::new(vec![0u8; 3]));
+    assert!(hpke
+        .seal_to_many(&pk_rs, b"info", b"aad", b"message", None, None, None)
+        .is_err());
+}
+
+#[test]
+fn public_key_cache() {
+    for kem in [KemAlgorithm::DhKem25519, KemAlgorithm::DhKemP256] {
+        for mode in [HpkeMode::Base, HpkeMode::Auth] {
+            let hpke = Hpke::<HpkeRustCrypto>::new(
+                mode,
+                kem,
+                KdfAlgorithm::HkdfSha256,
+                AeadAlgorithm::Aes128Gcm,
+            );
+            let cached = hpke.clone().with_public_key_cache(1);
+            let sender = hpke.generate_key_pair().unwrap();
+            let sk_s = (mode == HpkeMode::Auth).then(|| sender.private_key());
+            let pk_s = (mode == HpkeMode::Auth).then(|| sender.public_key());
+
+            // Two recipients with room for one key exercise the eviction.
+            let recipients = [
+                hpke.generate_key_pair().unwrap(),
+                hpke.generate_key_pair().unwrap(),
+            ];
+            for _ in 0..3 {
+                for kp in recipients.iter() {
+                    let (enc, ct) = cached
+                        .seal(kp.public_key(), b"info", b"aad", b"msg", None, None, sk_s)
+                        .unwrap();
+                    let pt = hpke
+                        .open(
+                            &enc,
+                            kp.private_key(),
+                            b"info",
+                            b"aad",
+                            &ct,
+                            None,
+                            None,
+                            pk_s,
+                        )
+                        .unwrap();
+                    assert_eq!(pt, b"msg");
+                }
+            }
+
+            let invalid = HpkePublicKey::new(vec![0xFF; 3]);
+            assert!(cached
+                .seal(&invalid, b"info", b"aad", b"msg", None, None, sk_s)
+                .is_err());
+        }
+    }
+}
+
+#[test]
+fn in_place_and_scratch_buffers() {
+    for aead in [
+        AeadAlgorithm::Aes128Gcm,
+        AeadAlgorithm::Aes256Gcm,
+        AeadAlgorithm::ChaCha20Poly1305,
+    ] {
+        let hpke = Hpke::<HpkeRustCrypto>::new(
+            HpkeMode::Base,
+            KemAlgorithm::DhKem25519,
+            KdfAlgorithm::HkdfSha256,
+            aead,
+        );
+        let kp = hpke.generate_key_pair().unwrap();
+        let (enc, mut sender) = hpke
+            .setup_sender(kp.public_key(), b"info", None, None, None)
+            .unwrap();
+        let mut receiver = hpke
+            .setup_receiver(&enc, kp.private_key(), b"info", None, None, None)
+            .unwrap();
+
+        let mut ct_buf = Vec::with_capacity(1024);
+        let mut pt_buf = Vec::with_capacity(1024);
+        for i in 0..5u8 {
+            let msg = vec![i; usize::from(i) * 10];
+
+            // Scratch buffers interoperate with the allocating API.
+            sender.seal_into(b"aad", &msg, &mut ct_buf).unwrap();
+            receiver.open_into(b"aad", &ct_buf, &mut pt_buf).unwrap();
+            assert_eq!(pt_buf, msg);
+
+            let mut buffer = msg.clone();
+            sender.seal_in_place(b"aad", &mut buffer).unwrap();
+            assert_eq!(receiver.open(b"aad", &buffer).unwrap(), msg);
+
+            let ct = sender.seal(b"aad", &msg).unwrap();
+            let mut buffer = ct.clone();
+            receiver.open_in_place(b"aad", &mut buffer).unwrap();
+            assert_eq!(buffer, msg);
+        }
+        assert!(ct_buf.capacity() >= 1024);
+
+        // A failed open clears the buffer and doesn't advance the receiver.
+        sender.seal_into(b"aad", b"message", &mut ct_buf).unwrap();
+        let mut tampered = ct_buf.clone();
+        tampered[0] ^= 1;
+        assert_eq!(
+            receiver.open_in_place(b"aad", &mut tampered),
+            Err(HpkeError::OpenError)
+        );
+        assert!(tampered.is_empty());
+        receiver.open_into(b"aad", &ct_buf, &mut pt_buf).unwrap();
+        assert_eq!(pt_buf, b"message");
+    }
+}
+
+#[test]
+fn poison_on_failure() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let kp = hpke.generate_key_pair().unwrap();
+    let (enc, mut sender) = hpke
+        .setup_sender(kp.public_key(), b"info", None, None, None)
+        .unwrap();
+    let mut receiver = hpke
+        .setup_receiver(&enc, kp.private_key(), b"info", None, None, None)
+        .unwrap();
+    let ct = sender.seal(b"aad", b"message").unwrap();
+    let mut forged = ct.clone();
+    forged[0] ^= 1;
+
+    // Poisoning is off by default.
+    assert_eq!(receiver.open(b"aad", &forged), Err(HpkeError::OpenError));
+    assert!(!receiver.is_poisoned());
+
+    receiver.set_poison_on_failure(true);
+    assert_eq!(receiver.open(b"aad", &forged), Err(HpkeError::OpenError));
+    assert!(receiver.is_poisoned());
+    assert_eq!(receiver.open(b"aad", &ct), Err(HpkeError::ContextPoisoned));
+    assert_eq!(
+        receiver.seal(b"aad", b"message"),
+        Err(HpkeError::ContextPoisoned)
+    );
+    assert_eq!(
+        receiver.export(b"context", 32),
+        Err(HpkeError::ContextPoisoned)
+    );
+
+    // The failed opens didn't advance the context.
+    receiver.reset_poison();
+    assert_eq!(receiver.open(b"aad", &ct).unwrap(), b"message");
+}
+
+#[test]
+fn secret_wrappers() {
+    use secrecy::ExposeSecret;
+
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Auth,
+        KemAlgorithm::DhKemP256,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    let kp_r = hpke.generate_key_pair().unwrap();
+    let kp_s = hpke.generate_key_pair().unwrap();
+
+    let (enc, shared_secret) = hpke
+        .encapsulate_secret(kp_r.public_key(), Some(kp_s.private_key()))
+        .unwrap();
+    let decapsulated = hpke
+        .decapsulate_secret(&enc, kp_r.private_key(), Some(kp_s.public_key()))
+        .unwrap();
+    assert_eq!(shared_secret.expose_secret(), decapsulated.expose_secret());
+    assert_eq!(shared_secret.expose_secret().len(), 32);
+
+    let (enc, exported) = hpke
+        .send_export_secret(
+            kp_r.public_key(),
+            b"info",
+            None,
+            None,
+            Some(kp_s.private_key()),
+            b"context",
+            42,
+        )
+        .unwrap();
+    let received = hpke
+        .receiver_export(
+            &enc,
+            kp_r.private_key(),
+            b"info",
+            None,
+            None,
+            Some(kp_s.public_key()),
+            b"context",
+            42,
+        )
+        .unwrap();
+    assert_eq!(exported.expose_secret(), &received);
+    let received = hpke
+        .receiver_export_secret(
+            &enc,
+            kp_r.private_key(),
+            b"info",
+            None,
+            None,
+            Some(kp_s.public_key()),
+            b"context",
+            42,
+        )
+        .unwrap();
+    assert_eq!(exported.expose_secret(), received.expose_secret());
+}
+
+#[test]
+fn error_kinds() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    let kp = hpke.generate_key_pair().unwrap();
+    let (enc, mut sender) = hpke
+        .setup_sender(kp.public_key(), b"info", None, None, None)
+        .unwrap();
+    let mut receiver = hpke
+        .setup_receiver(&enc, kp.private_key(), b"info", None, None, None)
+        .unwrap();
+
+    // Tampering
+    let mut ct = sender.seal(b"aad", b"message").unwrap();
+    ct[0] ^= 1;
+    let err = receiver.open(b"aad", &ct).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AuthenticationFailure);
+
+    // Wrong lengths
+    let err = receiver.open(b"aad", &[0u8; 3]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let err = hpke
+        .setup_receiver(&enc[1..], kp.private_key(), b"info", None, None, None)
+        .unwrap_err();
+    assert_eq!(err, HpkeError::DecapError(DecapError::InvalidLength));
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(HpkeError::PayloadTooLarge.kind(), ErrorKind::InvalidInput);
+    assert_eq!(HpkeError::Expired.kind(), ErrorKind::InvalidInput);
+
+    // Misuse of an export-only context
+    let export_only = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::HpkeExport,
+    );
+    let (_enc, mut sender) = export_only
+        .setup_sender(kp.public_key(), b"info", None, None, None)
+        .unwrap();
+    let err = sender.seal(b"aad", b"message").unwrap_err();
+    assert_eq!(err, HpkeError::ExportOnly);
+    assert_eq!(err.kind(), ErrorKind::InvalidState);
+
+    assert_eq!(
+        HpkeError::MessageLimitReached.kind(),
+        ErrorKind::InvalidState
+    );
+    assert_eq!(
+        HpkeError::InsufficientRandomness.kind(),
+        ErrorKind::Internal
+    );
+}
+
+#[test]
+fn fips_mode() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKemP256,
+        KdfAlgorithm::HkdfSha384,
+        AeadAlgorithm::Aes256Gcm,
+    )
+    .with_fips_mode()
+    .unwrap();
+    assert!(hpke.is_fips_mode());
+    let kp = hpke.generate_key_pair().unwrap();
+    let (enc, ct) = hpke
+        .seal(
+            kp.public_key(),
+            b"info",
+            b"aad",
+            b"message",
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    let pt = hpke
+        .open(
+            &enc,
+            kp.private_key(),
+            b"info",
+            b"aad",
+            &ct,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(pt, b"message");
+
+    for (kem, aead) in [
+        (KemAlgorithm::DhKem25519, AeadAlgorithm::Aes128Gcm),
+        (KemAlgorithm::DhKemP256, AeadAlgorithm::ChaCha20Poly1305),
+    ] {
+        let result =
+            Hpke::<HpkeRustCrypto>::new(HpkeMode::Base, kem, KdfAlgorithm::HkdfSha256, aead)
+                .with_fips_mode();
+        assert_eq!(result.err(), Some(HpkeError::NotAllowedInFipsMode));
+    }
+
+    // The private-use HKDF-SHA3-256 (0xFF01) and KMAC256 (0xFF02) are not
+    // approved.
+    for kdf in [KdfAlgorithm::HkdfSha3_256, KdfAlgorithm::Kmac256] {
+        let result = Hpke::<HpkeRustCrypto>::new(
+            HpkeMode::Base,
+            KemAlgorithm::DhKemP256,
+            kdf,
+            AeadAlgorithm::Aes128Gcm,
+        )
+        .with_fips_mode();
+        assert_eq!(result.err(), Some(HpkeError::NotAllowedInFipsMode));
+    }
+}
+
+#[test]
+fn sync_sender_context() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    let kp = hpke.generate_key_pair().unwrap();
+    let (enc, mut sender) = hpke
+        .setup_sender(kp.public_key(), b"info", None, None, None)
+        .unwrap();
+    let mut receiver = hpke
+        .setup_receiver(&enc, kp.private_key(), b"info", None, None, None)
+        .unwrap();
+    let first = sender.seal(b"aad", b"message 0").unwrap();
+
+    let sender = sender.into_sync_sender().unwrap();
+    assert_eq!(sender.sequence_number(), 1);
+    let mut ciphertexts = std::thread::scope(|s| {
+        let handles = (0..4)
+            .map(|_| {
+                s.spawn(|| {
+                    (0..25)
+                        .map(|_| sender.seal(b"aad", b"message").unwrap())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(sender.sequence_number(), 101);
+
+    // The receiver opens the ciphertexts in sequence order.
+    ciphertexts.sort_by_key(|(seq, _)| *seq);
+    assert_eq!(receiver.open(b"aad", &first).unwrap(), b"message 0");
+    for (i, (seq, ct)) in ciphertexts.iter().enumerate() {
+        assert_eq!(*seq as usize, i + 1);
+        assert_eq!(receiver.open(b"aad", ct).unwrap(), b"message");
+    }
+    assert_eq!(
+        sender.export(b"ctx", 32).unwrap(),
+        receiver.export(b"ctx", 32).unwrap()
+    );
+
+    // Ratcheting contexts can't be shared.
+    let (_, mut sender) = hpke
+        .setup_sender(kp.public_key(), b"info", None, None, None)
+        .unwrap();
+    sender.set_ratchet_interval(Some(10)).unwrap();
+    assert_eq!(
+        sender.into_sync_sender().err(),
+        Some(HpkeError::InvalidConfig)
+    );
+}
+
+#[test]
+fn legacy_draft_labels() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKemP256,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    let legacy = hpke.clone().with_legacy_draft_labels();
+    assert_eq!(hpke.version(), HpkeVersion::Rfc9180);
+    assert_eq!(legacy.version(), HpkeVersion::Draft05);
+    let kp = hpke.generate_key_pair().unwrap();
+
+    let (enc, ct) = legacy
+        .seal(
+            kp.public_key(),
+            b"info",
+            b"aad",
+            b"message",
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    let pt = legacy
+        .open(
+            &enc,
+            kp.private_key(),
+            b"info",
+            b"aad",
+            &ct,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(pt, b"message");
+    let (export_enc, exported) = legacy
+        .send_export(kp.public_key(), b"info", None, None, None, b"ctx", 32)
+        .unwrap();
+    assert_eq!(
+        legacy
+            .receiver_export(
+                &export_enc,
+                kp.private_key(),
+                b"info",
+                None,
+                None,
+                None,
+                b"ctx",
+                32
+            )
+            .unwrap(),
+        exported
+    );
+
+    // A single binary can talk to peers of both versions.
+    let draft_peer = hpke.clone().with_version(HpkeVersion::Draft05);
+    assert_eq!(
+        draft_peer
+            .open(
+                &enc,
+                kp.private_key(),
+                b"info",
+                b"aad",
+                &ct,
+                None,
+                None,
+                None
+            )
+            .unwrap(),
+        b"message"
+    );
+
+    // The draft and RFC 9180 labels are not compatible.
+    assert_eq!(
+        hpke.open(
+            &enc,
+            kp.private_key(),
+            b"info",
+            b"aad",
+            &ct,
+            None,
+            None,
+            None
+        ),
+        Err(HpkeError::OpenError)
+    );
+    assert_ne!(
+        hpke.receiver_export(
+            &export_enc,
+            kp.private_key(),
+            b"info",
+            None,
+            None,
+            None,
+            b"ctx",
+            32
+        )
+        .unwrap(),
+        exported
+    );
+}
+
+#[test]
+fn stateless_config() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let config = hpke.config();
+    let copy = config;
+    assert_eq!(copy, config);
+    assert_eq!(config.to_string(), hpke.to_string());
+    assert_eq!(config.version, HpkeVersion::Rfc9180);
+
+    // The configuration can be turned back into a (fresh) `Hpke`.
+    let hpke2 = Hpke::<HpkeRustCrypto>::from(config);
+    assert_eq!(hpke2.config(), config);
+
+    let kp = hpke.generate_key_pair().unwrap();
+    let (enc, mut sender) = hpke
+        .setup_sender(kp.public_key(), b"info", None, None, None)
+        .unwrap();
+    let mut receiver = hpke2
+        .setup_receiver(&enc, kp.private_key(), b"info", None, None, None)
+        .unwrap();
+    assert_eq!(sender.config(), config);
+    let ct = sender.seal(b"aad", b"message").unwrap();
+    assert_eq!(receiver.open(b"aad", &ct).unwrap(), b"message");
+}
+
+#[test]
+fn shared_config_many_recipients() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Hpke<HpkeRustCrypto>>();
+    assert_send_sync::<hpke::Context<HpkeRustCrypto>>();
+    assert_send_sync::<SyncSenderContext<HpkeRustCrypto>>();
+
+    let hpke = std::sync::Arc::new(Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes256Gcm,
+    ));
+    let recipients = (0..64)
+        .map(|_| hpke.generate_key_pair().unwrap())
+        .collect::<Vec<_>>();
+
+    // The test PRNG only has the seeded bytes, one ephemeral key per seal.
+    #[cfg(feature = "hpke-test-prng")]
+    {
+        let mut seed = vec![0u8; 32 * recipients.len()];
+        rand::thread_rng().fill_bytes(&mut seed);
+        hpke.seed(&seed).unwrap();
+    }
+
+    std::thread::scope(|s| {
+        for chunk in recipients.chunks(16) {
+            let hpke = hpke.clone();
+            s.spawn(move || {
+                for kp in chunk {
+                    let (enc, ct) = hpke
+                        .seal(
+                            kp.public_key(),
+                            b"info",
+                            b"aad",
+                            b"message",
+                            None,
+                            None,
+                            None,
+                        )
+                        .unwrap();
+                    let pt = hpke
+                        .open(
+                            &enc,
+                            kp.private_key(),
+                            b"info",
+                            b"aad",
+                            &ct,
+                            None,
+                            None,
+                            None,
+                        )
+                        .unwrap();
+                    assert_eq!(pt, b"message");
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn size_accessors() {
+    for (mode, kem, kdf, aead) in TEST_CASES.iter() {
+        let hpke = Hpke::<HpkeRustCrypto>::new(*mode, *kem, *kdf, *aead);
+        assert_eq!(hpke.nk(), HpkeRustCrypto::aead_key_length(*aead));
+        assert_eq!(hpke.nn(), HpkeRustCrypto::aead_nonce_length(*aead));
+        assert_eq!(hpke.nh(), HpkeRustCrypto::kdf_digest_length(*kdf));
+
+        let kp = hpke.generate_key_pair().unwrap();
+        assert_eq!(kp.public_key().as_slice().len(), hpke.npk());
+        assert_eq!(kp.private_key().as_slice().len(), hpke.nsk());
+
+        let psk = [1u8; 32];
+        let (psk, psk_id) = match mode {
+            HpkeMode::Psk | HpkeMode::AuthPsk => (Some(&psk[..]), Some(&b"psk id"[..])),
+            _ => (None, None),
+        };
+        let sender = hpke.generate_key_pair().unwrap();
+        let (enc, ct) = hpke
+            .seal(
+                kp.public_key(),
+                b"info",
+                b"aad",
+                b"message",
+                psk,
+                psk_id,
+                Some(sender.private_key()),
+            )
+            .unwrap();
+        assert_eq!(enc.len(), hpke.nenc());
+        assert_eq!(ct.len(), b"message".len() + hpke.nt());
+        assert_eq!(hpke.nsecret(), 32);
+    }
+}
+
+#[test]
+fn ciphersuite_registry() {
+    use hpke::ciphersuite::{Ciphersuite, AEAD_REGISTRY, KDF_REGISTRY, KEM_REGISTRY};
+
+    // The registries agree with the algorithm identifiers.
+    for entry in KEM_REGISTRY {
+        assert_eq!(entry.id, entry.algorithm as u16);
+        assert_eq!(KemAlgorithm::try_from(entry.id).unwrap(), entry.algorithm);
+    }
+    for entry in KDF_REGISTRY {
+        assert_eq!(entry.id, entry.algorithm as u16);
+        assert_eq!(KdfAlgorithm::try_from(entry.id).unwrap(), entry.algorithm);
+    }
+    for entry in AEAD_REGISTRY {
+        assert_eq!(entry.id, entry.algorithm as u16);
+        assert_eq!(AeadAlgorithm::try_from(entry.id).unwrap(), entry.algorithm);
+    }
+
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKemP256,
+        KdfAlgorithm::HkdfSha384,
+        AeadAlgorithm::Aes256Gcm,
+    );
+    let suite = hpke.ciphersuite();
+    assert_eq!(suite.ids(), (0x0010, 0x0002, 0x0002));
+    assert_eq!(
+        suite.suite_id(),
+        [b'H', b'P', b'K', b'E', 0x00, 0x10, 0x00, 0x02, 0x00, 0x02]
+    );
+    assert_eq!(suite.security_level(), 128);
+    assert_eq!(
+        suite.to_string(),
+        "DHKEM(P-256, HKDF-SHA256), HKDF-SHA384, AES-256-GCM"
+    );
+    assert_eq!(
+        Ciphersuite::from_names("DHKEM(P-256, HKDF-SHA256)", "HKDF-SHA384", "AES-256-GCM"),
+        Ok(suite)
+    );
+    assert_eq!(Ciphersuite::from_ids(0x0010, 0x0002, 0x0002), Ok(suite));
+    assert_eq!(
+        Ciphersuite::from_ids(0x0010, 0x0004, 0x0002),
+        Err(HpkeError::UnknownMode)
+    );
+}
+
+#[test]
+fn from_ids() {
+    let hpke = Hpke::<HpkeRustCrypto>::try_from_ids(0x02, 0x0020, 0x0001, 0x0003).unwrap();
+    assert_eq!(
+        hpke.config(),
+        Hpke::<HpkeRustCrypto>::new(
+            HpkeMode::Auth,
+            KemAlgorithm::DhKem25519,
+            KdfAlgorithm::HkdfSha256,
+            AeadAlgorithm::ChaCha20Poly1305,
+        )
+        .config()
+    );
+
+    // Unknown identifiers
+    for (mode, kem, kdf, aead) in [
+        (0x04, 0x0020, 0x0001, 0x0003),
+        (0x00, 0x0030, 0x0001, 0x0003),
+        (0x00, 0x0020, 0x0000, 0x0003),
+        (0x00, 0x0020, 0x0001, 0x0004),
+    ] {
+        assert_eq!(
+            Hpke::<HpkeRustCrypto>::try_from_ids(mode, kem, kdf, aead).err(),
+            Some(HpkeError::UnknownMode)
+        );
+    }
+
+    // X448 is registered but not supported by the provider.
+    assert_eq!(
+        Hpke::<HpkeRustCrypto>::try_from_ids(0x00, 0x0021, 0x0003, 0x0003).err(),
+        Some(HpkeError::UnknownMode)
+    );
+}
+
+#[test]
+fn strict_enc_validation() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKemP256,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    let kp = hpke.generate_key_pair().unwrap();
+    let (enc, _sender) = hpke
+        .setup_sender(kp.public_key(), b"info", None, None, None)
+        .unwrap();
+    assert_eq!(enc.len(), KemAlgorithm::DhKemP256.enc_len());
+
+    // Too short and too long
+    for enc in [&enc[..64], &[enc.as_slice(), &[0]].concat()[..]] {
+        assert_eq!(
+            hpke.setup_receiver(enc, kp.private_key(), b"info", None, None, None)
+                .err(),
+            Some(HpkeError::DecapError(DecapError::InvalidLength))
+        );
+    }
+
+    // The right length but not a point on the curve
+    let mut not_on_curve = vec![0xffu8; enc.len()];
+    not_on_curve[0] = 0x04;
+    assert_eq!(
+        hpke.setup_receiver(&not_on_curve, kp.private_key(), b"info", None, None, None)
+            .err(),
+        Some(HpkeError::DecapError(DecapError::InvalidPublicKey))
+    );
+}
+
+#[test]
+fn standalone_kem() {
+    use hpke::kem::Kem;
+
+    for alg in [KemAlgorithm::DhKem25519, KemAlgorithm::DhKemP256] {
+        let kem = Kem::<HpkeRustCrypto>::new(alg);
+        assert_eq!(kem.algorithm(), alg);
+        let kp_r = kem.generate_key_pair().unwrap();
+        let kp_s = kem.derive_key_pair(b"sender ikm").unwrap();
+
+        let (ss, enc) = kem.encaps(kp_r.public_key()).unwrap();
+        assert_eq!(enc.len(), alg.enc_len());
+        assert_eq!(ss.as_slice().len(), alg.shared_secret_len());
+        assert_eq!(kem.decaps(&enc, kp_r.private_key()).unwrap(), ss);
+
+        let (ss, enc) = kem
+            .auth_encaps(kp_r.public_key(), kp_s.private_key())
+            .unwrap();
+        assert_eq!(
+            kem.auth_decaps(&enc, kp_r.private_key(), kp_s.public_key())
+                .unwrap(),
+            ss
+        );
+        // The wrong sender gives a different secret.
+        assert_ne!(
+            kem.auth_decaps(&enc, kp_r.private_key(), kp_r.public_key())
+                .unwrap(),
+            ss
+        );
+
+        assert_eq!(
+            kem.decaps(&enc[1..], kp_r.private_key()).unwrap_err(),
+            HpkeError::DecapError(DecapError::InvalidLength)
+        );
+    }
+
+    // The KEM derives the same key pairs as HPKE.
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    let kem = Kem::<HpkeRustCrypto>::new(KemAlgorithm::DhKem25519);
+    assert_eq!(
+        hpke.derive_key_pair(b"ikm").unwrap().public_key(),
+        kem.derive_key_pair(b"ikm").unwrap().public_key()
+    );
+}
+
+#[test]
+fn exporter_helpers() {
+    use hpke::exporter::SrtpProfile;
+
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::HpkeExport,
+    );
+    let kp = hpke.generate_key_pair().unwrap();
+    let (enc, sender) = hpke
+        .setup_sender(kp.public_key(), b"info", None, None, None)
+        .unwrap();
+    let receiver = hpke
+        .setup_receiver(&enc, kp.private_key(), b"info", None, None, None)
+        .unwrap();
+
+    for profile in [SrtpProfile::Aes128CmHmacSha1_80, SrtpProfile::AeadAes256Gcm] {
+        let km = sender.export_srtp(profile).unwrap();
+        assert_eq!(km, receiver.export_srtp(profile).unwrap());
+        assert_eq!(km.client_key.len(), profile.key_len());
+        assert_eq!(km.server_salt.len(), profile.salt_len());
+        assert_ne!(km.client_key, km.server_key);
+
+        // The keying material is the plain export.
+        let raw = sender
+            .export(
+                b"EXTRACTOR-dtls_srtp",
+                2 * (profile.key_len() + profile.salt_len()),
+            )
+            .unwrap();
+        assert_eq!(raw[..profile.key_len()], km.client_key[..]);
+        assert_eq!(raw[raw.len() - profile.salt_len()..], km.server_salt[..]);
+    }
+
+    let client = sender
+        .export_record_keys(b"client", AeadAlgorithm::ChaCha20Poly1305)
+        .unwrap();
+    let server = sender
+        .export_record_keys(b"server", AeadAlgorithm::ChaCha20Poly1305)
+        .unwrap();
+    assert_eq!(
+        client,
+        receiver
+            .export_record_keys(b"client", AeadAlgorithm::ChaCha20Poly1305)
+            .unwrap()
+    );
+    assert_ne!(client, server);
+    assert_ne!(client.key, client.hp);
+    assert_eq!(client.key.len(), 32);
+    assert_eq!(client.iv.len(), 12);
+    assert_eq!(
+        sender
+            .export_record_keys(b"client", AeadAlgorithm::HpkeExport)
+            .unwrap_err(),
+        HpkeError::InvalidInput
+    );
+}
+
+#[test]
+fn failed_open_keeps_state() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    let kp = hpke.generate_key_pair().unwrap();
+    let (enc, mut sender) = hpke
+        .setup_sender(kp.public_key(), b"info", None, None, None)
+        .unwrap();
+    let mut receiver = hpke
+        .setup_receiver(&enc, kp.private_key(), b"info", None, None, None)
+        .unwrap();
+    let export = receiver.export(b"context", 32).unwrap();
+
+    let first = sender.seal(b"aad", b"first").unwrap();
+    let second = sender.seal(b"aad", b"second").unwrap();
+    let mut tampered = first.clone();
+    tampered[3] ^= 0x80;
+
+    // Bogus packets: tampered, wrong aad, truncated, empty, out of order, and
+    // garbage.
+    for bogus in [
+        (&b"aad"[..], &tampered[..]),
+        (b"other aad", &first[..]),
+        (b"aad", &first[..first.len() - 1]),
+        (b"aad", &[][..]),
+        (b"aad", &second[..]),
+        (b"aad", &[0xff; 64][..]),
+    ] {
+        for _ in 0..3 {
+            let (aad, ct) = bogus;
+            assert!(receiver.open(aad, ct).is_err());
+            let mut buffer = ct.to_vec();
+            assert!(receiver.open_in_place(aad, &mut buffer).is_err());
+            assert!(buffer.is_empty());
+            assert_eq!(receiver.sequence_number(), 0);
+            assert!(!receiver.is_poisoned());
+        }
+    }
+
+    // The context is untouched and processes the stream as if nothing
+    // happened.
+    assert_eq!(receiver.export(b"context", 32).unwrap(), export);
+    assert_eq!(receiver.open(b"aad", &first).unwrap(), b"first");
+    assert!(receiver.open(b"aad", &first).is_err());
+    assert_eq!(receiver.sequence_number(), 1);
+    assert_eq!(receiver.open(b"aad", &second).unwrap(), b"second");
+    assert_eq!(receiver.sequence_number(), 2);
+}
+
+#[test]
+fn sequence_skip_to() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let kp = hpke.generate_key_pair().unwrap();
+    let (enc, mut sender) = hpke
+        .setup_sender(kp.public_key(), b"info", None, None, None)
+        .unwrap();
+    let mut receiver = hpke
+        .setup_receiver(&enc, kp.private_key(), b"info", None, None, None)
+        .unwrap();
+    assert_eq!(sender.sequence(), 0);
+
+    // Packets 0 to 9 are lost.
+    for _ in 0..10 {
+        sender.seal(b"aad", b"lost").unwrap();
+    }
+    let ct = sender.seal(b"aad", b"message").unwrap();
+    assert_eq!(sender.sequence(), 11);
+    receiver.skip_to(10).unwrap();
+    assert_eq!(receiver.sequence(), 10);
+    assert_eq!(receiver.open(b"aad", &ct).unwrap(), b"message");
+
+    // A resuming sender skips ahead.
+    sender.skip_to(100).unwrap();
+    sender.skip_to(100).unwrap();
+    let ct = sender.seal(b"aad", b"resumed").unwrap();
+    receiver.skip_to(100).unwrap();
+    assert_eq!(receiver.open(b"aad", &ct).unwrap(), b"resumed");
+
+    // Forward only
+    assert_eq!(receiver.skip_to(5), Err(HpkeError::InvalidInput));
+    assert_eq!(receiver.sequence(), 101);
+
+    // Up to the message limit
+    receiver.skip_to(u32::MAX).unwrap();
+    assert_eq!(
+        receiver.open(b"aad", &ct),
+        Err(HpkeError::MessageLimitReached)
+    );
+
+    // Skipping to the ratchet interval ratchets, skipping past it is invalid.
+    let (enc, mut sender) = hpke
+        .setup_sender(kp.public_key(), b"info", None, None, None)
+        .unwrap();
+    let mut receiver = hpke
+        .setup_receiver(&enc, kp.private_key(), b"info", None, None, None)
+        .unwrap();
+    sender.set_ratchet_interval(Some(8)).unwrap();
+    receiver.set_ratchet_interval(Some(8)).unwrap();
+    assert_eq!(receiver.skip_to(9), Err(HpkeError::InvalidInput));
+    for _ in 0..8 {
+        sender.seal(b"aad", b"lost").unwrap();
+    }
+    receiver.skip_to(8).unwrap();
+    assert_eq!(receiver.sequence(), 0);
+    let ct = sender.seal(b"aad", b"ratcheted").unwrap();
+    assert_eq!(receiver.open(b"aad", &ct).unwrap(), b"ratcheted");
+}
+
+#[test]
+fn reorder_window() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let kp = hpke.generate_key_pair().unwrap();
+    let (enc, mut sender) = hpke
+        .setup_sender(kp.public_key(), b"info", None, None, None)
+        .unwrap();
+    let mut receiver = hpke
+        .setup_receiver(&enc, kp.private_key(), b"info", None, None, None)
+        .unwrap();
+    let cts: Vec<_> = (0..8u8)
+        .map(|i| sender.seal(b"aad", &[i]).unwrap())
+        .collect();
+
+    // Without a window only the expected ciphertext can be opened.
+    assert_eq!(
+        receiver.open_in_order(1, b"aad", &cts[1]),
+        Err(HpkeError::InvalidInput)
+    );
+    assert_eq!(
+        receiver.open_in_order(0, b"aad", &cts[0]).unwrap(),
+        vec![vec![0]]
+    );
+
+    receiver.set_reorder_window(Some(4)).unwrap();
+    assert!(receiver
+        .open_in_order(3, b"aad", &cts[3])
+        .unwrap()
+        .is_empty());
+    assert!(receiver
+        .open_in_order(2, b"aad", &cts[2])
+        .unwrap()
+        .is_empty());
+    assert_eq!(receiver.pending(), 2);
+    // Too far ahead
+    assert_eq!(
+        receiver.open_in_order(6, b"aad", &cts[6]),
+        Err(HpkeError::InvalidInput)
+    );
+    assert_eq!(
+        receiver.open_in_order(1, b"aad", &cts[1]).unwrap(),
+        vec![vec![1], vec![2], vec![3]]
+    );
+    assert_eq!(receiver.pending(), 0);
+    assert_eq!(receiver.sequence(), 4);
+
+    // Replays are rejected.
+    assert_eq!(
+        receiver.open_in_order(2, b"aad", &cts[2]),
+        Err(HpkeError::InvalidInput)
+    );
+
+    // A forged ciphertext ahead of the expected one fails right away and
+    // doesn't take the place of the genuine one.
+    let mut forged = cts[5].clone();
+    forged[0] ^= 1;
+    assert_eq!(
+        receiver.open_in_order(5, b"aad", &forged),
+        Err(HpkeError::OpenError)
+    );
+    assert_eq!(receiver.pending(), 0);
+    assert!(receiver
+        .open_in_order(5, b"aad", &cts[5])
+        .unwrap()
+        .is_empty());
+    assert!(receiver
+        .open_in_order(6, b"aad", &cts[6])
+        .unwrap()
+        .is_empty());
+    // Buffered sequence numbers can't be replaced.
+    assert_eq!(
+        receiver.open_in_order(5, b"aad", &cts[5]),
+        Err(HpkeError::InvalidInput)
+    );
+    assert_eq!(
+        receiver.open_in_order(4, b"aad", &cts[4]).unwrap(),
+        vec![vec![4], vec![5], vec![6]]
+    );
+    assert_eq!(receiver.sequence(), 7);
+
+    // Buffered plain texts are released up to the message limit.
+    sender.skip_to(u32::MAX - 2).unwrap();
+    let last: Vec<_> = (0..2u8)
+        .map(|i| sender.seal(b"aad", &[i]).unwrap())
+        .collect();
+    receiver.skip_to(u32::MAX - 2).unwrap();
+    assert!(receiver
+        .open_in_order(u32::MAX - 1, b"aad", &last[1])
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        receiver
+            .open_in_order(u32::MAX - 2, b"aad", &last[0])
+            .unwrap(),
+        vec![vec![0], vec![1]]
+    );
+    assert_eq!(receiver.pending(), 0);
+    assert_eq!(receiver.sequence(), u32::MAX);
+
+    // A forged ciphertext poisons the context (if enabled) and reports it,
+    // whether it is expected or ahead.
+    let (enc, mut sender) = hpke
+        .setup_sender(kp.public_key(), b"info", None, None, None)
+        .unwrap();
+    let mut receiver = hpke
+        .setup_receiver(&enc, kp.private_key(), b"info", None, None, None)
+        .unwrap();
+    receiver.set_reorder_window(Some(4)).unwrap();
+    receiver.set_poison_on_failure(true);
+    let cts: Vec<_> = (0..3u8)
+        .map(|i| sender.seal(b"aad", &[i]).unwrap())
+        .collect();
+    let mut forged = cts[2].clone();
+    forged[0] ^= 1;
+    assert_eq!(
+        receiver.open_in_order(2, b"aad", &forged),
+        Err(HpkeError::OpenError)
+    );
+    assert!(receiver.is_poisoned());
+    assert_eq!(
+        receiver.open_in_order(0, b"aad", &cts[0]),
+        Err(HpkeError::ContextPoisoned)
+    );
+    receiver.reset_poison();
+    assert!(receiver
+        .open_in_order(2, b"aad", &cts[2])
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        receiver.open_in_order(0, b"aad", &cts[0]).unwrap(),
+        vec![vec![0]]
+    );
+    assert_eq!(
+        receiver.open_in_order(1, b"aad", &cts[1]).unwrap(),
+        vec![vec![1], vec![2]]
+    );
+
+    // No reordering with ratcheting
+    assert_eq!(
+        receiver.set_ratchet_interval(Some(10)),
+        Err(HpkeError::InvalidConfig)
+    );
+    receiver.set_reorder_window(None).unwrap();
+    receiver.set_ratchet_interval(Some(10)).unwrap();
+    assert_eq!(
+        receiver.set_reorder_window(Some(4)),
+        Err(HpkeError::InvalidConfig)
+    );
+}
+
+#[test]
+fn derive_key_pair_p384() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        Mode::Base,
+        KemAlgorithm::DhKemP384,
+        KdfAlgorithm::HkdfSha384,
+        AeadAlgorithm::Aes256Gcm,
+    );
+    let kp = hpke.derive_key_pair(b"P-384 ikm").unwrap();
+    assert_eq!(kp.public_key().as_slice().len(), 97);
+    assert_eq!(kp.private_key().as_slice().len(), 48);
+    assert_eq!(
+        kp.public_key(),
+        hpke.derive_key_pair(b"P-384 ikm").unwrap().public_key()
+    );
+}
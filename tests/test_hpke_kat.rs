@@ -4,9 +4,11 @@ extern crate hpke_rs as hpke;
 use hpke_rs_rust_crypto::HpkeRustCrypto;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use serde::{self, Deserialize, Serialize};
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fs::File;
 use std::io::BufReader;
+use std::sync::Mutex;
 use std::time::Instant;
 
 use hpke::prelude::*;
@@ -60,8 +62,16 @@ struct ExportsKAT {
     exported_value: String,
 }
 
-fn kat<Crypto: HpkeCrypto + 'static>(tests: Vec<HpkeTestVector>) {
+/// The ciphersuite of a test vector as `(kem_id, kdf_id, aead_id)`.
+type Suite = (u16, u16, u16);
+
+/// Run all test vectors in `tests` that are supported by `Crypto`.
+///
+/// Returns the number of test vectors that were run per ciphersuite.
+fn kat<Crypto: HpkeCrypto + 'static>(tests: Vec<HpkeTestVector>) -> HashMap<Suite, usize> {
+    let run = Mutex::new(HashMap::new());
     tests.into_par_iter().for_each(|test| {
+        let suite = (test.kem_id, test.kdf_id, test.aead_id);
         let mode: HpkeMode = test.mode.try_into().unwrap();
         let kem_id: KemAlgorithm = test.kem_id.try_into().unwrap();
         let kdf_id: KdfAlgorithm = test.kdf_id.try_into().unwrap();
@@ -139,6 +149,19 @@ fn kat<Crypto: HpkeCrypto + 'static>(tests: Vec<HpkeTestVector>) {
         let ikm_e = hex_to_bytes(&test.ikmE);
         let ikm_s = hex_to_bytes_option(test.ikmS);
 
+        // Check the intermediate values of the key schedule.
+        let (key_schedule_context, secret) = hpke.key_schedule_intermediates(
+            &shared_secret,
+            &info,
+            psk.unwrap_or_default(),
+            psk_id.unwrap_or_default(),
+        );
+        assert_eq!(
+            key_schedule_context,
+            hex_to_bytes(&test.key_schedule_context)
+        );
+        assert_eq!(secret, hex_to_bytes(&test.secret));
+
         // Use internal `key_schedule` function for KAT.
         let mut direct_ctx = hpke
             .key_schedule(
@@ -171,6 +194,9 @@ fn kat<Crypto: HpkeCrypto + 'static>(tests: Vec<HpkeTestVector>) {
 
         // Setup KAT receiver.
         let kat_enc = hex_to_bytes(&test.enc);
+        let (my_shared_secret, my_enc) = hpke.encaps_with_ikm(&pk_rm, sk_sm, &ikm_e).unwrap();
+        assert_eq!(my_shared_secret, shared_secret);
+        assert_eq!(my_enc, kat_enc);
         let mut receiver_context_kat = hpke
             .setup_receiver(&kat_enc, &sk_rm, &info, psk, psk_id, pk_sm)
             .unwrap();
@@ -220,6 +246,15 @@ fn kat<Crypto: HpkeCrypto + 'static>(tests: Vec<HpkeTestVector>) {
             let aad = hex_to_bytes(&encryption.aad);
             let ptxt = hex_to_bytes(&encryption.pt);
             let ctxt_kat = hex_to_bytes(&encryption.ct);
+            let nonce_kat = hex_to_bytes(&encryption.nonce);
+
+            // Check the nonce for this sequence number.
+            let seq = direct_ctx.sequence_number().to_be_bytes();
+            let mut expected_nonce = nonce.clone();
+            for (n, s) in expected_nonce.iter_mut().rev().zip(seq.iter().rev()) {
+                *n ^= s;
+            }
+            assert_eq!(nonce_kat, expected_nonce);
 
             // Test context API self-test
             let ctxt_out = sender_context.seal(&aad, &ptxt).unwrap();
@@ -253,15 +288,32 @@ fn kat<Crypto: HpkeCrypto + 'static>(tests: Vec<HpkeTestVector>) {
 
             let exported_secret = direct_ctx.export(&export_context, length).unwrap();
             assert_eq!(export_value, exported_secret);
+            let exported_secret = receiver_context_kat
+                .export(&export_context, length)
+                .unwrap();
+            assert_eq!(export_value, exported_secret);
+
+            // Sender and receiver must agree.
+            assert_eq!(
+                sender_context.export(&export_context, length).unwrap(),
+                receiver_context.export(&export_context, length).unwrap()
+            );
         }
+        *run.lock().unwrap().entry(suite).or_insert(0) += 1;
     });
+    run.into_inner().unwrap()
 }
 
 #[test]
 fn test_kat() {
     let _ = pretty_env_logger::try_init();
-    let file = "tests/test_vectors.json";
-    let file = match File::open(file) {
+    // The test vectors can be replaced with the full set from the HPKE
+    // specification by pointing `HPKE_TEST_VECTORS` to a `test-vectors.json`.
+    let custom = std::env::var("HPKE_TEST_VECTORS").ok();
+    let file = custom
+        .clone()
+        .unwrap_or_else(|| "tests/test_vectors.json".to_string());
+    let file = match File::open(&file) {
         Ok(f) => f,
         Err(_) => panic!("Couldn't open file {}.", file),
     };
@@ -272,9 +324,28 @@ fn test_kat() {
     };
 
     let now = Instant::now();
-    kat::<HpkeRustCrypto>(tests.clone());
+    let run = kat::<HpkeRustCrypto>(tests.clone());
     let time = now.elapsed();
-    log::info!("Test vectors with Rust Crypto took: {}s", time.as_secs());
+    log::info!(
+        "Ran {} of {} test vectors with Rust Crypto in {}s",
+        run.values().sum::<usize>(),
+        tests.len(),
+        time.as_secs()
+    );
+
+    // The bundled test vectors have all four modes for every ciphersuite.
+    // The Rust Crypto provider supports the X25519 and P-256 KEMs.
+    if custom.is_none() {
+        let mut expected = HashMap::new();
+        for kem_id in [0x10, 0x20] {
+            for kdf_id in [0x1, 0x3] {
+                for aead_id in [0x1, 0x2, 0x3, 0xffff] {
+                    expected.insert((kem_id, kdf_id, aead_id), 4);
+                }
+            }
+        }
+        assert_eq!(run, expected);
+    }
 
     // let now = Instant::now();
     // kat::<HpkeEvercrypt>(tests);
@@ -334,3 +405,44 @@ fn test_serialization() {
     // let aead_id: aead::Mode = aead::Mode::AesGcm128;
     // let hpke = Hpke::new(mode, kem_id, kdf_id, aead_id);
 }
+
+#[test]
+fn intermediate_callback() {
+    use std::sync::{Arc, Mutex};
+
+    let values = Arc::new(Mutex::new(Vec::new()));
+    let sink = values.clone();
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Psk,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    )
+    .with_intermediate_callback(move |label, value| {
+        sink.lock().unwrap().push((label, value.to_vec()));
+    });
+    let kp = hpke.generate_key_pair().unwrap();
+    let psk = [7u8; 32];
+    let (_enc, context) = hpke
+        .setup_sender(kp.public_key(), b"info", Some(&psk), Some(b"psk id"), None)
+        .unwrap();
+
+    let values = values.lock().unwrap();
+    let labels: Vec<&str> = values.iter().map(|(label, _)| *label).collect();
+    assert_eq!(
+        labels,
+        [
+            "shared_secret",
+            "psk_id_hash",
+            "info_hash",
+            "key_schedule_context",
+            "secret",
+            "key",
+            "base_nonce",
+            "exporter_secret"
+        ]
+    );
+    assert_eq!(values[5].1, context.key());
+    assert_eq!(values[6].1, context.nonce());
+    assert_eq!(values[7].1, context.exporter_secret());
+}
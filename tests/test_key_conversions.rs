@@ -0,0 +1,78 @@
+extern crate hpke_rs as hpke;
+
+use hpke::prelude::*;
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+use rand::{rngs::StdRng, SeedableRng};
+
+fn hpke(kem: KemAlgorithm) -> Hpke<HpkeRustCrypto> {
+    Hpke::new(
+        HpkeMode::Base,
+        kem,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    )
+}
+
+fn round_trip(hpke: &Hpke<HpkeRustCrypto>, pk: &HpkePublicKey, sk: &HpkePrivateKey) {
+    let (enc, ct) = hpke
+        .seal(pk, b"info", b"aad", b"message", None, None, None)
+        .unwrap();
+    let pt = hpke
+        .open(&enc, sk, b"info", b"aad", &ct, None, None, None)
+        .unwrap();
+    assert_eq!(pt, b"message");
+}
+
+#[test]
+fn x25519_dalek_keys() {
+    let hpke = hpke(KemAlgorithm::DhKem25519);
+    let secret = x25519_dalek::StaticSecret::random_from_rng(StdRng::seed_from_u64(0));
+    let public = x25519_dalek::PublicKey::from(&secret);
+
+    let key_pair = HpkeKeyPair::from(&secret);
+    assert_eq!(key_pair.public_key(), &HpkePublicKey::from(public));
+    round_trip(&hpke, &public.into(), &secret.clone().into());
+
+    // Keys generated by hpke-rs convert the other way.
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let secret = x25519_dalek::StaticSecret::try_from(key_pair.private_key()).unwrap();
+    let public = x25519_dalek::PublicKey::try_from(key_pair.public_key()).unwrap();
+    assert_eq!(public, x25519_dalek::PublicKey::from(&secret));
+    assert_eq!(key_pair.public_key().as_slice(), public.as_bytes());
+
+    assert_eq!(
+        x25519_dalek::PublicKey::try_from(&HpkePublicKey::new(vec![0; 31])).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    assert!(x25519_dalek::StaticSecret::try_from(&HpkePrivateKey::new(vec![0; 33])).is_err());
+}
+
+#[test]
+fn p256_keys() {
+    let hpke = hpke(KemAlgorithm::DhKemP256);
+    let secret = p256::SecretKey::random(&mut StdRng::seed_from_u64(0));
+    let public = secret.public_key();
+
+    let key_pair = HpkeKeyPair::from(&secret);
+    assert_eq!(key_pair.public_key(), &HpkePublicKey::from(public));
+    // Public keys are uncompressed.
+    assert_eq!(key_pair.public_key().as_slice().len(), 65);
+    round_trip(&hpke, &public.into(), &secret.clone().into());
+
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let secret = p256::SecretKey::try_from(key_pair.private_key()).unwrap();
+    let public = p256::PublicKey::try_from(key_pair.public_key()).unwrap();
+    assert_eq!(public, secret.public_key());
+
+    assert_eq!(
+        p256::PublicKey::try_from(&HpkePublicKey::new(vec![4; 65])).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    // The zero scalar is not a valid secret key.
+    assert_eq!(
+        p256::SecretKey::try_from(&HpkePrivateKey::new(vec![0; 32])).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    assert!(p256::SecretKey::try_from(&HpkePrivateKey::new(vec![1; 31])).is_err());
+}
@@ -0,0 +1,173 @@
+extern crate hpke_rs as hpke;
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    sync::{Arc, Mutex, Once},
+};
+
+use hpke::keystore::*;
+use hpke::prelude::*;
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+use keyring::credential::{Credential, CredentialApi, CredentialBuilderApi};
+
+/// An in-memory keyring shared by all entries, in place of the platform
+/// keyring.
+type Secrets = Arc<Mutex<HashMap<(String, String), Vec<u8>>>>;
+
+#[derive(Debug)]
+struct MemoryCredential {
+    secrets: Secrets,
+    key: (String, String),
+}
+
+impl CredentialApi for MemoryCredential {
+    fn set_secret(&self, secret: &[u8]) -> keyring::Result<()> {
+        let mut secrets = self.secrets.lock().unwrap();
+        secrets.insert(self.key.clone(), secret.to_vec());
+        Ok(())
+    }
+
+    fn get_secret(&self) -> keyring::Result<Vec<u8>> {
+        let secrets = self.secrets.lock().unwrap();
+        secrets
+            .get(&self.key)
+            .cloned()
+            .ok_or(keyring::Error::NoEntry)
+    }
+
+    fn delete_credential(&self) -> keyring::Result<()> {
+        let mut secrets = self.secrets.lock().unwrap();
+        secrets
+            .remove(&self.key)
+            .map(|_| ())
+            .ok_or(keyring::Error::NoEntry)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+struct MemoryCredentialBuilder(Secrets);
+
+impl CredentialBuilderApi for MemoryCredentialBuilder {
+    fn build(
+        &self,
+        _target: Option<&str>,
+        service: &str,
+        user: &str,
+    ) -> keyring::Result<Box<Credential>> {
+        Ok(Box::new(MemoryCredential {
+            secrets: self.0.clone(),
+            key: (service.to_owned(), user.to_owned()),
+        }))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+fn use_memory_keyring() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        keyring::set_default_credential_builder(Box::<MemoryCredentialBuilder>::default())
+    });
+}
+
+fn hpke() -> Hpke<HpkeRustCrypto> {
+    Hpke::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    )
+}
+
+#[test]
+fn keyring_key_store() {
+    use_memory_keyring();
+    let hpke = hpke();
+    let mut store = KeyringKeyStore::open::<HpkeRustCrypto>("hpke-rs keyring_key_store").unwrap();
+    assert!(store.key_ids().is_empty());
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let pk = key_pair.public_key().clone();
+    let key_id = store
+        .insert::<HpkeRustCrypto>(KemAlgorithm::DhKem25519, key_pair)
+        .unwrap();
+    let other = store
+        .insert::<HpkeRustCrypto>(KemAlgorithm::DhKem25519, hpke.generate_key_pair().unwrap())
+        .unwrap();
+    drop(store);
+
+    // Re-open the store and use the stored key.
+    let mut store = KeyringKeyStore::open::<HpkeRustCrypto>("hpke-rs keyring_key_store").unwrap();
+    assert_eq!(store.service(), "hpke-rs keyring_key_store");
+    let mut key_ids = vec![key_id, other];
+    key_ids.sort();
+    assert_eq!(store.key_ids(), key_ids);
+    let entry = store.get(&key_id).unwrap();
+    assert_eq!(entry.kem_id(), KemAlgorithm::DhKem25519);
+    assert_eq!(entry.public_key(), &pk);
+    let (enc, ct) = hpke
+        .seal(&pk, b"info", b"aad", b"message", None, None, None)
+        .unwrap();
+    let pt = hpke
+        .open(
+            &enc,
+            entry.private_key(),
+            b"info",
+            b"aad",
+            &ct,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(pt, b"message");
+
+    store.remove(&key_id).unwrap();
+    let store = KeyringKeyStore::open::<HpkeRustCrypto>("hpke-rs keyring_key_store").unwrap();
+    assert_eq!(store.key_ids(), vec![other]);
+    assert!(
+        KeyringKeyStore::open::<HpkeRustCrypto>("hpke-rs other service")
+            .unwrap()
+            .key_ids()
+            .is_empty()
+    );
+}
+
+#[test]
+fn keyring_key_store_persists_status_and_usage() {
+    use_memory_keyring();
+    let hpke = hpke();
+    let service = "hpke-rs keyring_key_store_persists_status_and_usage";
+    let mut store = KeyringKeyStore::open::<HpkeRustCrypto>(service).unwrap();
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let key_id = hpke.key_id(key_pair.public_key()).unwrap();
+    store
+        .insert_entry(
+            key_id,
+            KeyEntry::new(KemAlgorithm::DhKem25519, key_pair).with_usage_limit(2),
+        )
+        .unwrap();
+    assert_eq!(store.record_use(&key_id).unwrap(), 1);
+
+    let mut store = KeyringKeyStore::open::<HpkeRustCrypto>(service).unwrap();
+    assert_eq!(store.get(&key_id).unwrap().usage(), 1);
+    assert_eq!(store.record_use(&key_id).unwrap(), 2);
+    assert_eq!(
+        store.record_use(&key_id).unwrap_err(),
+        HpkeError::KeyUsageLimitReached
+    );
+    store.set_status(&key_id, KeyStatus::Revoked).unwrap();
+
+    let store = KeyringKeyStore::open::<HpkeRustCrypto>(service).unwrap();
+    let entry = store.get(&key_id).unwrap();
+    assert_eq!(entry.usage(), 2);
+    assert_eq!(entry.status(), KeyStatus::Revoked);
+    assert!(store.decryption_key(&key_id, 0).is_none());
+}
@@ -0,0 +1,326 @@
+extern crate hpke_rs as hpke;
+
+use hpke::keystore::*;
+use hpke::prelude::*;
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+fn hpke() -> Hpke<HpkeRustCrypto> {
+    Hpke::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    )
+}
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("hpke-rs-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn memory_key_store() {
+    let hpke = hpke();
+    let mut store = MemoryKeyStore::new();
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let pk = key_pair.public_key().clone();
+    let key_id = store
+        .insert::<HpkeRustCrypto>(KemAlgorithm::DhKem25519, key_pair)
+        .unwrap();
+    assert_eq!(key_id, hpke.key_id(&pk).unwrap());
+    assert_eq!(store.len(), 1);
+    assert_eq!(store.key_ids(), vec![key_id]);
+
+    // Look up the key for a message addressed to `key_id`.
+    let (enc, ct) = hpke
+        .seal(&pk, b"info", b"aad", b"message", None, None, None)
+        .unwrap();
+    let sk_r = store.private_key(&key_id).unwrap();
+    let pt = hpke
+        .open(&enc, sk_r, b"info", b"aad", &ct, None, None, None)
+        .unwrap();
+    assert_eq!(pt, b"message");
+
+    assert!(store.remove(&key_id).unwrap().is_some());
+    assert!(store.get(&key_id).is_none());
+    assert!(store.is_empty());
+}
+
+#[test]
+fn file_key_store() {
+    let hpke = hpke();
+    let dir = temp_dir("file-key-store");
+    let mut store = FileKeyStore::open::<HpkeRustCrypto>(&dir).unwrap();
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let pk = key_pair.public_key().clone();
+    let key_id = store
+        .insert::<HpkeRustCrypto>(KemAlgorithm::DhKem25519, key_pair)
+        .unwrap();
+    drop(store);
+
+    // Re-open the store and use the stored key.
+    let mut store = FileKeyStore::open::<HpkeRustCrypto>(&dir).unwrap();
+    assert_eq!(store.key_ids(), vec![key_id]);
+    let entry = store.get(&key_id).unwrap();
+    assert_eq!(entry.kem_id(), KemAlgorithm::DhKem25519);
+    assert_eq!(entry.public_key(), &pk);
+    let (enc, ct) = hpke
+        .seal(&pk, b"info", b"aad", b"message", None, None, None)
+        .unwrap();
+    let pt = hpke
+        .open(
+            &enc,
+            entry.private_key(),
+            b"info",
+            b"aad",
+            &ct,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(pt, b"message");
+
+    store.remove(&key_id).unwrap();
+    let store = FileKeyStore::open::<HpkeRustCrypto>(&dir).unwrap();
+    assert!(store.key_ids().is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn file_key_store_key_id_mismatch() {
+    let hpke = hpke();
+    let dir = temp_dir("file-key-store-key-id-mismatch");
+    let mut store = FileKeyStore::open::<HpkeRustCrypto>(&dir).unwrap();
+    let key_id = store
+        .insert::<HpkeRustCrypto>(KemAlgorithm::DhKem25519, hpke.generate_key_pair().unwrap())
+        .unwrap();
+    let valid_id = store
+        .insert::<HpkeRustCrypto>(KemAlgorithm::DhKem25519, hpke.generate_key_pair().unwrap())
+        .unwrap();
+    drop(store);
+
+    // Swap in another key pair under the same key id.
+    let key_file = dir.join(format!("{key_id}.key"));
+    let other = hpke.generate_key_pair().unwrap();
+    let mut bytes = std::fs::read(&key_file).unwrap();
+    let sk = other.private_key().as_slice();
+    let pk = other.public_key().as_slice();
+    let offset = 1 + KEY_ID_LEN + 2;
+    bytes[offset + 2..offset + 2 + sk.len()].copy_from_slice(sk);
+    bytes[offset + 2 + sk.len() + 2..offset + 2 + sk.len() + 2 + pk.len()].copy_from_slice(pk);
+    std::fs::write(&key_file, bytes).unwrap();
+
+    // The mismatching key file is skipped, the other keys are still loaded.
+    let mut store = FileKeyStore::open::<HpkeRustCrypto>(&dir).unwrap();
+    assert_eq!(store.key_ids(), [valid_id]);
+    assert!(store.get(&key_id).is_none());
+    assert!(store.get(&valid_id).is_some());
+    assert_eq!(store.rejected_files(), [key_file]);
+
+    // Keys can't be inserted under another key id.
+    let wrong_id = KeyId::from([0; KEY_ID_LEN]);
+    let entry = KeyEntry::new(KemAlgorithm::DhKem25519, hpke.generate_key_pair().unwrap());
+    assert_eq!(
+        store.insert_entry(wrong_id, entry).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    assert!(!dir.join(format!("{wrong_id}.key")).exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn file_key_store_key_too_long() {
+    let dir = temp_dir("file-key-store-key-too-long");
+    let mut store = FileKeyStore::open::<HpkeRustCrypto>(&dir).unwrap();
+    let pk = vec![1u8; usize::from(u16::MAX) + 1];
+    let key_id = KeyId::compute::<HpkeRustCrypto>(KemAlgorithm::DhKem25519, &pk).unwrap();
+    let entry = KeyEntry::new(
+        KemAlgorithm::DhKem25519,
+        HpkeKeyPair::new(vec![1u8; 32], pk),
+    );
+    assert_eq!(
+        store.insert_entry(key_id, entry).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    assert!(store.key_ids().is_empty());
+    assert!(!dir.join(format!("{key_id}.key")).exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn key_lifecycle() {
+    let hpke = hpke();
+    let mut store = MemoryKeyStore::new();
+    let old = store
+        .insert::<HpkeRustCrypto>(KemAlgorithm::DhKem25519, hpke.generate_key_pair().unwrap())
+        .unwrap();
+    let new_pair = hpke.generate_key_pair().unwrap();
+    let new = hpke.key_id(new_pair.public_key()).unwrap();
+    let validity = Validity {
+        not_before: Some(100),
+        not_after: Some(200),
+    };
+    store
+        .insert_entry(
+            new,
+            KeyEntry::new(KemAlgorithm::DhKem25519, new_pair).with_validity(validity),
+        )
+        .unwrap();
+
+    // The new key is preferred while it is valid.
+    assert_eq!(store.encryption_key(50).unwrap().0, old);
+    assert_eq!(store.encryption_key(150).unwrap().0, new);
+    assert_eq!(store.encryption_key(250).unwrap().0, old);
+    assert!(store.decryption_key(&new, 250).is_none());
+
+    // Retiring keys only decrypt, revoked keys are never used.
+    store.set_status(&old, KeyStatus::Retiring).unwrap();
+    assert_eq!(store.get(&old).unwrap().status(), KeyStatus::Retiring);
+    assert!(store.encryption_key(50).is_none());
+    assert!(store.decryption_key(&old, 50).is_some());
+    store.set_status(&old, KeyStatus::Revoked).unwrap();
+    assert!(store.decryption_key(&old, 50).is_none());
+    assert!(store.private_key(&old).is_some());
+    assert_eq!(
+        store
+            .set_status(&KeyId::default(), KeyStatus::Active)
+            .unwrap_err(),
+        HpkeError::InvalidInput
+    );
+}
+
+#[test]
+fn file_key_lifecycle() {
+    let hpke = hpke();
+    let dir = temp_dir("file-key-lifecycle");
+    let mut store = FileKeyStore::open::<HpkeRustCrypto>(&dir).unwrap();
+    let key_id = store
+        .insert::<HpkeRustCrypto>(KemAlgorithm::DhKem25519, hpke.generate_key_pair().unwrap())
+        .unwrap();
+
+    // A new key is active without a validity period.
+    let mut reopened = FileKeyStore::open::<HpkeRustCrypto>(&dir).unwrap();
+    let entry = reopened.get(&key_id).unwrap();
+    assert_eq!(entry.status(), KeyStatus::Active);
+    assert_eq!(entry.validity(), Validity::default());
+
+    // The status is persisted.
+    reopened.set_status(&key_id, KeyStatus::Retiring).unwrap();
+    let reopened = FileKeyStore::open::<HpkeRustCrypto>(&dir).unwrap();
+    assert_eq!(reopened.get(&key_id).unwrap().status(), KeyStatus::Retiring);
+    assert!(reopened.encryption_key(0).is_none());
+    assert!(reopened.decryption_key(&key_id, 0).is_some());
+
+    // The validity period is persisted.
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let validity = Validity::expires_in(10, 10);
+    let key_id = hpke.key_id(key_pair.public_key()).unwrap();
+    store
+        .insert_entry(
+            key_id,
+            KeyEntry::new(KemAlgorithm::DhKem25519, key_pair).with_validity(validity),
+        )
+        .unwrap();
+    let store = FileKeyStore::open::<HpkeRustCrypto>(&dir).unwrap();
+    assert_eq!(store.get(&key_id).unwrap().validity(), validity);
+    assert_eq!(store.encryption_key(15).unwrap().0, key_id);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn key_usage_limits() {
+    use std::sync::{Arc, Mutex};
+
+    let hpke = hpke();
+    let rotated = Arc::new(Mutex::new(Vec::new()));
+    let log = rotated.clone();
+    let mut store =
+        MemoryKeyStore::new().on_usage_limit(move |key_id| log.lock().unwrap().push(*key_id));
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let key_id = hpke.key_id(key_pair.public_key()).unwrap();
+    store
+        .insert_entry(
+            key_id,
+            KeyEntry::new(KemAlgorithm::DhKem25519, key_pair).with_usage_limit(2),
+        )
+        .unwrap();
+    let unlimited = store
+        .insert::<HpkeRustCrypto>(KemAlgorithm::DhKem25519, hpke.generate_key_pair().unwrap())
+        .unwrap();
+
+    assert_eq!(store.record_use(&key_id).unwrap(), 1);
+    assert_eq!(store.get(&key_id).unwrap().remaining_uses(), Some(1));
+    assert!(rotated.lock().unwrap().is_empty());
+
+    // The use that reaches the limit retires the key and calls the callback.
+    assert_eq!(store.record_use(&key_id).unwrap(), 2);
+    assert_eq!(*rotated.lock().unwrap(), vec![key_id]);
+    let entry = store.get(&key_id).unwrap();
+    assert_eq!(entry.status(), KeyStatus::Retiring);
+    assert_eq!(entry.remaining_uses(), Some(0));
+    assert_eq!(store.encryption_key(0).unwrap().0, unlimited);
+    assert!(store.decryption_key(&key_id, 0).is_some());
+
+    // Further uses are rejected.
+    assert_eq!(
+        store.record_use(&key_id).unwrap_err(),
+        HpkeError::KeyUsageLimitReached
+    );
+    assert_eq!(store.get(&key_id).unwrap().usage(), 2);
+    assert_eq!(
+        store.record_use(&KeyId::default()).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    assert_eq!(store.record_use(&unlimited).unwrap(), 1);
+    assert_eq!(store.get(&unlimited).unwrap().remaining_uses(), None);
+    assert_eq!(
+        HpkeError::KeyUsageLimitReached.kind(),
+        ErrorKind::InvalidState
+    );
+}
+
+#[test]
+fn file_key_usage_limits() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    let hpke = hpke();
+    let dir = temp_dir("file-key-usage");
+    let mut store = FileKeyStore::open::<HpkeRustCrypto>(&dir).unwrap();
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let key_id = hpke.key_id(key_pair.public_key()).unwrap();
+    store
+        .insert_entry(
+            key_id,
+            KeyEntry::new(KemAlgorithm::DhKem25519, key_pair).with_usage_limit(2),
+        )
+        .unwrap();
+    store.record_use(&key_id).unwrap();
+
+    // The usage is persisted.
+    let rotations = Arc::new(AtomicUsize::new(0));
+    let counter = rotations.clone();
+    let mut store = FileKeyStore::open::<HpkeRustCrypto>(&dir)
+        .unwrap()
+        .on_usage_limit(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+    let entry = store.get(&key_id).unwrap();
+    assert_eq!(entry.usage(), 1);
+    assert_eq!(entry.usage_limit(), Some(2));
+    assert_eq!(store.record_use(&key_id).unwrap(), 2);
+    assert_eq!(rotations.load(Ordering::Relaxed), 1);
+
+    let mut store = FileKeyStore::open::<HpkeRustCrypto>(&dir).unwrap();
+    assert_eq!(store.get(&key_id).unwrap().status(), KeyStatus::Retiring);
+    assert_eq!(
+        store.record_use(&key_id).unwrap_err(),
+        HpkeError::KeyUsageLimitReached
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
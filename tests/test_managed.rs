@@ -0,0 +1,119 @@
+extern crate hpke_rs as hpke;
+
+use hpke::managed::{ManagedReceiver, ManagedSender};
+use hpke::prelude::*;
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+fn hpke(mode: HpkeMode) -> Hpke<HpkeRustCrypto> {
+    Hpke::new(
+        mode,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    )
+}
+
+#[test]
+fn reencapsulate() {
+    let hpke = hpke(HpkeMode::Base);
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let mut sender = ManagedSender::new(&hpke, &pk_r, b"info", None, None, None)
+        .unwrap()
+        .with_messages_per_context(3);
+    assert_eq!(sender.messages_per_context(), 3);
+    let mut receiver = ManagedReceiver::new(&hpke, &sk_r, b"info", None, None, None);
+    assert!(!receiver.has_context());
+
+    let overhead = 1 + AeadAlgorithm::ChaCha20Poly1305.tag_length();
+    for i in 0..10u8 {
+        let frame = sender.seal(b"aad", &[i; 5]).unwrap();
+        if i % 3 == 0 {
+            assert_eq!(frame[0], 1);
+            assert_eq!(frame.len(), overhead + hpke.nenc() + 5);
+        } else {
+            assert_eq!(frame[0], 0);
+            assert_eq!(frame.len(), overhead + 5);
+        }
+        assert_eq!(receiver.open(b"aad", &frame).unwrap(), [i; 5]);
+    }
+    assert_eq!(sender.encapsulations(), 4);
+    assert!(receiver.has_context());
+}
+
+#[test]
+fn auth_psk() {
+    let hpke = hpke(HpkeMode::AuthPsk);
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let (sk_s, pk_s) = hpke.generate_key_pair().unwrap().into_keys();
+    let psk = [7u8; 32];
+    let mut sender =
+        ManagedSender::new(&hpke, &pk_r, b"info", Some(&psk), Some(b"id"), Some(&sk_s))
+            .unwrap()
+            .with_messages_per_context(2);
+    let mut receiver =
+        ManagedReceiver::new(&hpke, &sk_r, b"info", Some(&psk), Some(b"id"), Some(&pk_s));
+    for i in 0..5u8 {
+        let frame = sender.seal(b"", &[i]).unwrap();
+        assert_eq!(receiver.open(b"", &frame).unwrap(), [i]);
+    }
+    assert_eq!(sender.encapsulations(), 3);
+
+    // A receiver with the wrong PSK doesn't open the first message.
+    let mut sender =
+        ManagedSender::new(&hpke, &pk_r, b"info", Some(&psk), Some(b"id"), Some(&sk_s)).unwrap();
+    let mut receiver = ManagedReceiver::new(
+        &hpke,
+        &sk_r,
+        b"info",
+        Some(&[8u8; 32]),
+        Some(b"id"),
+        Some(&pk_s),
+    );
+    let frame = sender.seal(b"", b"hello").unwrap();
+    assert_eq!(
+        receiver.open(b"", &frame).unwrap_err(),
+        HpkeError::OpenError
+    );
+    assert!(!receiver.has_context());
+}
+
+#[test]
+fn forged_frames() {
+    let hpke = hpke(HpkeMode::Base);
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let mut sender = ManagedSender::new(&hpke, &pk_r, b"info", None, None, None).unwrap();
+    let mut receiver = ManagedReceiver::new(&hpke, &sk_r, b"info", None, None, None);
+
+    // Malformed frames.
+    let first = sender.seal(b"", b"first").unwrap();
+    assert_eq!(
+        receiver.open(b"", &[]).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    assert_eq!(
+        receiver.open(b"", &[2, 0, 0]).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    assert_eq!(
+        receiver.open(b"", &first[..hpke.nenc()]).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    let mut continued = first.clone();
+    continued[0] = 0;
+    assert_eq!(
+        receiver.open(b"", &continued).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    assert_eq!(receiver.open(b"", &first).unwrap(), b"first");
+
+    // A new context that doesn't open doesn't replace the current one.
+    let mut forged = first.clone();
+    *forged.last_mut().unwrap() ^= 1;
+    assert_eq!(
+        receiver.open(b"", &forged).unwrap_err(),
+        HpkeError::OpenError
+    );
+    let second = sender.seal(b"", b"second").unwrap();
+    assert_eq!(receiver.open(b"", &second).unwrap(), b"second");
+}
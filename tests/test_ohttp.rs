@@ -0,0 +1,111 @@
+extern crate hpke_rs as hpke;
+
+use hpke::ciphersuite::Ciphersuite;
+use hpke::ohttp::KeyConfig;
+use hpke::prelude::*;
+use hpke::test_util::hex_to_bytes;
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+// RFC 9458, Appendix A
+const KEY_CONFIG: &str =
+    "01002031e1f05a740102115220e9af918f738674aec95f54db6e04eb705aae8e79815500080001000100010003";
+
+#[test]
+fn rfc9458_key_config() {
+    let bytes = hex_to_bytes(KEY_CONFIG);
+    let config = KeyConfig::decode(&bytes).unwrap();
+    assert_eq!(config.key_id, 1);
+    assert_eq!(config.kem, KemAlgorithm::DhKem25519);
+    assert_eq!(
+        config.public_key.as_slice(),
+        hex_to_bytes("31e1f05a740102115220e9af918f738674aec95f54db6e04eb705aae8e798155")
+    );
+    assert_eq!(
+        config.symmetric,
+        vec![
+            (KdfAlgorithm::HkdfSha256, AeadAlgorithm::Aes128Gcm),
+            (KdfAlgorithm::HkdfSha256, AeadAlgorithm::ChaCha20Poly1305),
+        ]
+    );
+    assert_eq!(config.encode(), bytes);
+    assert_eq!(
+        config.select_suite::<HpkeRustCrypto>().unwrap(),
+        Ciphersuite::new(
+            KemAlgorithm::DhKem25519,
+            KdfAlgorithm::HkdfSha256,
+            AeadAlgorithm::Aes128Gcm
+        )
+    );
+
+    // Trailing bytes
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert_eq!(KeyConfig::decode(&trailing), Err(HpkeError::InvalidInput));
+    // Truncated
+    assert_eq!(
+        KeyConfig::decode(&bytes[..bytes.len() - 1]),
+        Err(HpkeError::InvalidInput)
+    );
+}
+
+#[test]
+fn ohttp_keys() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKemP256,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    let kp = hpke.generate_key_pair().unwrap();
+    let p256 = KeyConfig::new(
+        7,
+        KemAlgorithm::DhKemP256,
+        kp.public_key().clone(),
+        vec![(KdfAlgorithm::HkdfSha384, AeadAlgorithm::Aes256Gcm)],
+    )
+    .unwrap();
+    let x25519 = KeyConfig::decode(&hex_to_bytes(KEY_CONFIG)).unwrap();
+
+    // An X448 key config, which isn't supported by the provider, and one with
+    // an unregistered KEM.
+    let x448 = KeyConfig::new(
+        2,
+        KemAlgorithm::DhKem448,
+        vec![0u8; 56].into(),
+        vec![(KdfAlgorithm::HkdfSha512, AeadAlgorithm::Aes256Gcm)],
+    )
+    .unwrap();
+    let mut unknown = x25519.encode();
+    unknown[1..3].copy_from_slice(&0x0030u16.to_be_bytes());
+
+    let mut list = KeyConfig::encode_list(&[p256.clone(), x448.clone()]);
+    list.extend_from_slice(&(unknown.len() as u16).to_be_bytes());
+    list.extend_from_slice(&unknown);
+    list.extend_from_slice(&KeyConfig::encode_list(std::slice::from_ref(&x25519)));
+
+    let configs = KeyConfig::decode_list(&list).unwrap();
+    assert_eq!(configs, vec![p256.clone(), x448.clone(), x25519]);
+    assert_eq!(
+        x448.select_suite::<HpkeRustCrypto>(),
+        Err(HpkeError::UnknownMode)
+    );
+    assert_eq!(KeyConfig::decode(&unknown), Err(HpkeError::UnknownMode));
+
+    // Unknown symmetric algorithms are ignored.
+    let mut encoded = p256.encode();
+    let len = encoded.len();
+    encoded[len - 6..len - 4].copy_from_slice(&8u16.to_be_bytes());
+    encoded.extend_from_slice(&[0x00, 0x01, 0x00, 0x04]);
+    assert_eq!(KeyConfig::decode(&encoded).unwrap(), p256);
+
+    // Invalid key configs
+    assert_eq!(
+        KeyConfig::new(1, KemAlgorithm::DhKemP256, vec![0u8; 32].into(), vec![]),
+        Err(HpkeError::InvalidInput)
+    );
+    assert_eq!(
+        KeyConfig::decode_list(&list[..list.len() - 1]),
+        Err(HpkeError::InvalidInput)
+    );
+}
@@ -0,0 +1,111 @@
+extern crate hpke_rs as hpke;
+
+use hpke::{policy::Policy, prelude::*};
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+fn hpke(kem: KemAlgorithm, kdf: KdfAlgorithm, aead: AeadAlgorithm) -> Hpke<HpkeRustCrypto> {
+    Hpke::new(HpkeMode::Base, kem, kdf, aead)
+}
+
+#[test]
+fn policy_check() {
+    let policy = Policy::new()
+        .allow_kems(&[KemAlgorithm::DhKemP256, KemAlgorithm::DhKemP384])
+        .deny_aead(AeadAlgorithm::ChaCha20Poly1305);
+    assert!(policy
+        .check(
+            KemAlgorithm::DhKemP256,
+            KdfAlgorithm::HkdfSha256,
+            AeadAlgorithm::Aes128Gcm
+        )
+        .is_ok());
+    assert_eq!(
+        policy.check(
+            KemAlgorithm::DhKem25519,
+            KdfAlgorithm::HkdfSha256,
+            AeadAlgorithm::Aes128Gcm
+        ),
+        Err(HpkeError::NotAllowedByPolicy)
+    );
+    assert_eq!(
+        policy.check(
+            KemAlgorithm::DhKemP256,
+            KdfAlgorithm::HkdfSha256,
+            AeadAlgorithm::ChaCha20Poly1305
+        ),
+        Err(HpkeError::NotAllowedByPolicy)
+    );
+
+    let policy = Policy::new().min_security_level(192);
+    assert!(policy
+        .check(
+            KemAlgorithm::DhKemP384,
+            KdfAlgorithm::HkdfSha384,
+            AeadAlgorithm::Aes256Gcm
+        )
+        .is_ok());
+    assert!(policy
+        .check(
+            KemAlgorithm::DhKemP384,
+            KdfAlgorithm::HkdfSha384,
+            AeadAlgorithm::HpkeExport
+        )
+        .is_ok());
+    assert_eq!(
+        policy.check(
+            KemAlgorithm::DhKemP384,
+            KdfAlgorithm::HkdfSha256,
+            AeadAlgorithm::Aes256Gcm
+        ),
+        Err(HpkeError::NotAllowedByPolicy)
+    );
+}
+
+#[test]
+fn per_configuration_and_installed_policy() {
+    // This is the only test in this binary that installs a policy.
+    let policy = Policy::new().deny_kdf(KdfAlgorithm::HkdfSha512);
+    let result = hpke(
+        KemAlgorithm::DhKemP256,
+        KdfAlgorithm::HkdfSha512,
+        AeadAlgorithm::Aes128Gcm,
+    )
+    .with_policy(policy.clone());
+    assert_eq!(result.err(), Some(HpkeError::NotAllowedByPolicy));
+    let allowed = hpke(
+        KemAlgorithm::DhKemP256,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    )
+    .with_policy(policy)
+    .unwrap();
+    let kp = allowed.generate_key_pair().unwrap();
+    assert!(allowed
+        .seal(kp.public_key(), b"info", b"aad", b"msg", None, None, None)
+        .is_ok());
+
+    // The installed policy applies to all configurations.
+    let x25519 = hpke(
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    let kp = x25519.generate_key_pair().unwrap();
+    Policy::new().deny_kem(KemAlgorithm::DhKem25519).install();
+    assert!(Policy::installed().is_some());
+    assert_eq!(
+        x25519
+            .seal(kp.public_key(), b"info", b"aad", b"msg", None, None, None)
+            .err(),
+        Some(HpkeError::NotAllowedByPolicy)
+    );
+    assert_eq!(
+        x25519.generate_key_pair().err(),
+        Some(HpkeError::NotAllowedByPolicy)
+    );
+    Policy::uninstall();
+    assert!(x25519
+        .seal(kp.public_key(), b"info", b"aad", b"msg", None, None, None)
+        .is_ok());
+}
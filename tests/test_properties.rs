@@ -0,0 +1,145 @@
+extern crate hpke_rs as hpke;
+
+use hpke::prelude::*;
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+use proptest::prelude::*;
+
+fn mode() -> impl Strategy<Value = HpkeMode> {
+    prop_oneof![
+        Just(HpkeMode::Base),
+        Just(HpkeMode::Psk),
+        Just(HpkeMode::Auth),
+        Just(HpkeMode::AuthPsk),
+    ]
+}
+
+fn kem() -> impl Strategy<Value = KemAlgorithm> {
+    prop_oneof![
+        Just(KemAlgorithm::DhKemP256),
+        Just(KemAlgorithm::DhKem25519)
+    ]
+}
+
+fn kdf() -> impl Strategy<Value = KdfAlgorithm> {
+    prop_oneof![
+        Just(KdfAlgorithm::HkdfSha256),
+        Just(KdfAlgorithm::HkdfSha384),
+        Just(KdfAlgorithm::HkdfSha512),
+    ]
+}
+
+fn aead() -> impl Strategy<Value = AeadAlgorithm> {
+    prop_oneof![
+        Just(AeadAlgorithm::Aes128Gcm),
+        Just(AeadAlgorithm::Aes256Gcm),
+        Just(AeadAlgorithm::ChaCha20Poly1305),
+    ]
+}
+
+/// An HPKE mode and ciphersuite.
+fn suite() -> impl Strategy<Value = (HpkeMode, KemAlgorithm, KdfAlgorithm, AeadAlgorithm)> {
+    (mode(), kem(), kdf(), aead())
+}
+
+fn bytes(max: usize) -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(any::<u8>(), 0..max)
+}
+
+/// A receiver and (optional) sender key pair, and the PSK for `mode`.
+struct Setup {
+    receiver: HpkeKeyPair,
+    sender: Option<HpkeKeyPair>,
+    psk: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl Setup {
+    fn new(hpke: &Hpke<HpkeRustCrypto>, mode: HpkeMode, psk: Vec<u8>) -> Self {
+        let auth = matches!(mode, HpkeMode::Auth | HpkeMode::AuthPsk);
+        let with_psk = matches!(mode, HpkeMode::Psk | HpkeMode::AuthPsk);
+        Self {
+            receiver: hpke.generate_key_pair().unwrap(),
+            sender: auth.then(|| hpke.generate_key_pair().unwrap()),
+            psk: with_psk.then(|| (psk, b"psk id".to_vec())),
+        }
+    }
+
+    fn psk(&self) -> Option<&[u8]> {
+        self.psk.as_ref().map(|(psk, _)| psk.as_slice())
+    }
+
+    fn psk_id(&self) -> Option<&[u8]> {
+        self.psk.as_ref().map(|(_, psk_id)| psk_id.as_slice())
+    }
+
+    fn sk_s(&self) -> Option<&HpkePrivateKey> {
+        self.sender.as_ref().map(|kp| kp.private_key())
+    }
+
+    fn pk_s(&self) -> Option<&HpkePublicKey> {
+        self.sender.as_ref().map(|kp| kp.public_key())
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn seal_open_round_trip(
+        (mode, kem, kdf, aead) in suite(),
+        info in bytes(64),
+        aad in bytes(64),
+        pt in bytes(512),
+        psk in prop::collection::vec(any::<u8>(), 32..64),
+    ) {
+        let hpke = Hpke::<HpkeRustCrypto>::new(mode, kem, kdf, aead);
+        let setup = Setup::new(&hpke, mode, psk);
+        let (enc, ct) = hpke
+            .seal(setup.receiver.public_key(), &info, &aad, &pt, setup.psk(), setup.psk_id(), setup.sk_s())
+            .unwrap();
+        let opened = hpke
+            .open(&enc, setup.receiver.private_key(), &info, &aad, &ct, setup.psk(), setup.psk_id(), setup.pk_s())
+            .unwrap();
+        prop_assert_eq!(opened, pt);
+    }
+
+    #[test]
+    fn tampered_ciphertext_never_opens(
+        (mode, kem, kdf, aead) in suite(),
+        aad in bytes(64),
+        pt in bytes(512),
+        bit in any::<prop::sample::Index>(),
+    ) {
+        let hpke = Hpke::<HpkeRustCrypto>::new(mode, kem, kdf, aead);
+        let setup = Setup::new(&hpke, mode, vec![0x42; 32]);
+        let (enc, mut sender) = hpke
+            .setup_sender(setup.receiver.public_key(), b"info", setup.psk(), setup.psk_id(), setup.sk_s())
+            .unwrap();
+        let mut receiver = hpke
+            .setup_receiver(&enc, setup.receiver.private_key(), b"info", setup.psk(), setup.psk_id(), setup.pk_s())
+            .unwrap();
+        let mut ct = sender.seal(&aad, &pt).unwrap();
+        let bit = bit.index(ct.len() * 8);
+        ct[bit / 8] ^= 1 << (bit % 8);
+        prop_assert_eq!(receiver.open(&aad, &ct), Err(HpkeError::OpenError));
+    }
+
+    #[test]
+    fn sequence_numbers_never_reuse_nonces(
+        (mode, kem, kdf, aead) in suite(),
+        shared_secret in bytes(64),
+        messages in 2usize..64,
+    ) {
+        let hpke = Hpke::<HpkeRustCrypto>::new(mode, kem, kdf, aead);
+        let psk = matches!(mode, HpkeMode::Psk | HpkeMode::AuthPsk);
+        let (psk, psk_id): (&[u8], &[u8]) = if psk { (&[0x42; 32], b"psk id") } else { (&[], &[]) };
+        let mut context = hpke.key_schedule(&shared_secret, b"info", psk, psk_id).unwrap();
+
+        // Sealing the same message under distinct nonces gives distinct
+        // ciphertexts.
+        let mut ciphertexts = std::collections::HashSet::new();
+        for _ in 0..messages {
+            prop_assert!(ciphertexts.insert(context.seal(b"aad", b"message").unwrap()));
+        }
+    }
+}
@@ -0,0 +1,203 @@
+#![cfg(feature = "passphrase")]
+
+extern crate hpke_rs as hpke;
+
+use hpke::prelude::*;
+use hpke::protected_key::*;
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+// Cheap KDF parameters to keep the tests fast.
+const KDFS: [PassphraseKdf; 2] = [
+    PassphraseKdf::Argon2id {
+        m_cost: 256,
+        t_cost: 1,
+        p_cost: 1,
+    },
+    PassphraseKdf::Scrypt {
+        log_n: 4,
+        r: 8,
+        p: 1,
+    },
+];
+
+#[test]
+fn protected_private_key() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKemP256,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    let (sk, _pk) = hpke.generate_key_pair().unwrap().into_keys();
+
+    for kdf in KDFS {
+        for aead in [AeadAlgorithm::Aes256Gcm, AeadAlgorithm::ChaCha20Poly1305] {
+            let params = ProtectionParams { kdf, aead };
+            let protected = sk
+                .to_protected::<HpkeRustCrypto>(b"correct horse", &params)
+                .unwrap();
+            let decrypted =
+                HpkePrivateKey::from_protected::<HpkeRustCrypto>(&protected, b"correct horse")
+                    .unwrap();
+            assert_eq!(sk, decrypted);
+
+            // Wrong passphrase
+            assert_eq!(
+                HpkePrivateKey::from_protected::<HpkeRustCrypto>(&protected, b"battery staple")
+                    .unwrap_err(),
+                HpkeError::OpenError
+            );
+
+            // The header is authenticated.
+            let mut tampered = protected.clone();
+            *tampered.last_mut().unwrap() ^= 1;
+            assert!(
+                HpkePrivateKey::from_protected::<HpkeRustCrypto>(&tampered, b"correct horse")
+                    .is_err()
+            );
+
+            // Unknown version
+            let mut tampered = protected.clone();
+            tampered[7] = 0xFF;
+            assert_eq!(
+                HpkePrivateKey::from_protected::<HpkeRustCrypto>(&tampered, b"correct horse")
+                    .unwrap_err(),
+                HpkeError::InvalidInput
+            );
+        }
+    }
+
+    assert_eq!(
+        sk.to_protected::<HpkeRustCrypto>(
+            b"correct horse",
+            &ProtectionParams {
+                kdf: KDFS[0],
+                aead: AeadAlgorithm::HpkeExport
+            }
+        )
+        .unwrap_err(),
+        HpkeError::InvalidConfig
+    );
+}
+
+#[test]
+fn kdf_limits() {
+    let sk = HpkePrivateKey::new(vec![7u8; 32]);
+    for kdf in KDFS {
+        let params = ProtectionParams {
+            kdf,
+            aead: AeadAlgorithm::ChaCha20Poly1305,
+        };
+        let protected = sk
+            .to_protected::<HpkeRustCrypto>(b"correct horse", &params)
+            .unwrap();
+
+        // An oversized header is rejected before the KDF runs.
+        let mut oversized = protected.clone();
+        match kdf {
+            PassphraseKdf::Argon2id { .. } => oversized[9..13].copy_from_slice(&[0xFF; 4]),
+            PassphraseKdf::Scrypt { .. } => oversized[9] = 63,
+        }
+        assert_eq!(
+            HpkePrivateKey::from_protected::<HpkeRustCrypto>(&oversized, b"correct horse")
+                .unwrap_err(),
+            HpkeError::InvalidInput
+        );
+
+        // Callers can lower the limits.
+        let limits = KdfLimits {
+            max_m_cost: 128,
+            max_log_n: 3,
+            ..KdfLimits::default()
+        };
+        assert_eq!(
+            HpkePrivateKey::from_protected_with_limits::<HpkeRustCrypto>(
+                &protected,
+                b"correct horse",
+                &limits
+            )
+            .unwrap_err(),
+            HpkeError::InvalidInput
+        );
+        assert_eq!(
+            HpkePrivateKey::from_protected_with_limits::<HpkeRustCrypto>(
+                &protected,
+                b"correct horse",
+                &KdfLimits::default()
+            )
+            .unwrap(),
+            sk
+        );
+    }
+}
+
+#[test]
+fn password_psk() {
+    use hpke::password_psk::PasswordPsk;
+
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Psk,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+
+    for kdf in KDFS {
+        let sender = PasswordPsk::new::<HpkeRustCrypto>(b"correct horse", &kdf).unwrap();
+        assert_eq!(sender.psk().len(), 32);
+        let (enc, ct) = hpke
+            .seal(
+                &pk_r,
+                b"info",
+                b"aad",
+                b"message",
+                Some(sender.psk()),
+                Some(sender.psk_id()),
+                None,
+            )
+            .unwrap();
+
+        let receiver = PasswordPsk::from_psk_id(b"correct horse", sender.psk_id()).unwrap();
+        assert_eq!(receiver.psk(), sender.psk());
+        let pt = hpke
+            .open(
+                &enc,
+                &sk_r,
+                b"info",
+                b"aad",
+                &ct,
+                Some(receiver.psk()),
+                Some(receiver.psk_id()),
+                None,
+            )
+            .unwrap();
+        assert_eq!(pt, b"message");
+
+        // Wrong password
+        let wrong = PasswordPsk::from_psk_id(b"battery staple", sender.psk_id()).unwrap();
+        assert_eq!(
+            hpke.open(
+                &enc,
+                &sk_r,
+                b"info",
+                b"aad",
+                &ct,
+                Some(wrong.psk()),
+                Some(wrong.psk_id()),
+                None,
+            )
+            .unwrap_err(),
+            HpkeError::OpenError
+        );
+
+        // Trailing bytes
+        let mut psk_id = sender.psk_id().to_vec();
+        psk_id.push(0);
+        assert_eq!(
+            PasswordPsk::from_psk_id(b"correct horse", &psk_id).unwrap_err(),
+            HpkeError::InvalidInput
+        );
+    }
+}
@@ -0,0 +1,52 @@
+extern crate hpke_rs as hpke;
+
+use hpke::rustls_provider::RustlsHpke;
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+use rustls::crypto::hpke::Hpke;
+use rustls::internal::msgs::enums::{HpkeAead, HpkeKdf, HpkeKem};
+
+#[test]
+fn rustls_hpke_provider() {
+    let suite: &'static dyn Hpke = Box::leak(Box::new(RustlsHpke::<HpkeRustCrypto>::new(
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    )));
+    assert_eq!(suite.suite().kem, HpkeKem::DHKEM_X25519_HKDF_SHA256);
+    assert_eq!(suite.suite().sym.kdf_id, HpkeKdf::HKDF_SHA256);
+    assert_eq!(suite.suite().sym.aead_id, HpkeAead::CHACHA20_POLY_1305);
+    assert!(!suite.fips());
+
+    let (pk, sk) = suite.generate_key_pair().unwrap();
+
+    // Single-shot
+    let (enc, ct) = suite.seal(b"info", b"aad", b"client hello", &pk).unwrap();
+    assert_eq!(
+        suite.open(&enc, b"info", b"aad", &ct, &sk).unwrap(),
+        b"client hello"
+    );
+    assert!(suite.open(&enc, b"other", b"aad", &ct, &sk).is_err());
+
+    // Contexts
+    let (enc, mut sealer) = suite.setup_sealer(b"info", &pk).unwrap();
+    let mut opener = suite.setup_opener(&enc, b"info", &sk).unwrap();
+    for msg in [&b"first"[..], b"second"] {
+        let ct = sealer.seal(b"aad", msg).unwrap();
+        assert_eq!(opener.open(b"aad", &ct).unwrap(), msg);
+    }
+}
+
+#[test]
+fn rustls_hpke_base_mode_only() {
+    let hpke = hpke::Hpke::<HpkeRustCrypto>::new(
+        hpke::Mode::Psk,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    assert_eq!(
+        RustlsHpke::from_hpke(hpke).unwrap_err(),
+        hpke::HpkeError::InvalidConfig
+    );
+}
@@ -0,0 +1,165 @@
+extern crate hpke_rs as hpke;
+
+use hpke::{
+    prelude::*,
+    secrets_file::{decrypt, encrypt, METADATA_KEY},
+};
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+use serde_json::{json, Value};
+
+fn hpke() -> Hpke<HpkeRustCrypto> {
+    Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    )
+}
+
+fn document() -> Value {
+    json!({
+        "db": {
+            "user_unencrypted": "admin",
+            "password": "hunter2",
+            "port": 5432,
+            "tls": true,
+            "replica": null,
+        },
+        "api/keys": ["k1", "k2"],
+        "ratio": 0.5,
+    })
+}
+
+#[test]
+fn round_trip() {
+    let hpke = hpke();
+    let alice = hpke.generate_key_pair().unwrap();
+    let bob = hpke.generate_key_pair().unwrap();
+    let eve = hpke.generate_key_pair().unwrap();
+
+    let encrypted = encrypt(
+        &hpke,
+        &[alice.public_key().clone(), bob.public_key().clone()],
+        &document(),
+    )
+    .unwrap();
+
+    // The structure, the keys, and the unencrypted values stay readable.
+    assert_eq!(encrypted["db"]["user_unencrypted"], "admin");
+    assert_eq!(encrypted["db"]["replica"], Value::Null);
+    let password = encrypted["db"]["password"].as_str().unwrap();
+    assert!(password.starts_with("ENC[HPKE,data:"));
+    assert!(encrypted["db"]["port"].is_string());
+    assert_eq!(encrypted["api/keys"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        encrypted[METADATA_KEY]["recipients"]
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+
+    for keys in [&alice, &bob] {
+        assert_eq!(
+            decrypt(&hpke, keys.private_key(), &encrypted).unwrap(),
+            document()
+        );
+    }
+    assert_eq!(
+        decrypt(&hpke, eve.private_key(), &encrypted).unwrap_err(),
+        HpkeError::OpenError
+    );
+
+    // Files survive a round trip through text.
+    let text = serde_json::to_string_pretty(&encrypted).unwrap();
+    let parsed: Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(
+        decrypt(&hpke, alice.private_key(), &parsed).unwrap(),
+        document()
+    );
+}
+
+#[test]
+fn tampering() {
+    let hpke = hpke();
+    let keys = hpke.generate_key_pair().unwrap();
+    let encrypted = encrypt(&hpke, &[keys.public_key().clone()], &document()).unwrap();
+
+    // Swapped values.
+    let mut swapped = encrypted.clone();
+    swapped["db"]["password"] = encrypted["api/keys"][0].clone();
+    swapped["api/keys"][0] = encrypted["db"]["password"].clone();
+    assert_eq!(
+        decrypt(&hpke, keys.private_key(), &swapped).unwrap_err(),
+        HpkeError::OpenError
+    );
+
+    // Removed value.
+    let mut removed = encrypted.clone();
+    removed["db"].as_object_mut().unwrap().remove("tls");
+    assert_eq!(
+        decrypt(&hpke, keys.private_key(), &removed).unwrap_err(),
+        HpkeError::OpenError
+    );
+
+    // Injected plain text value.
+    let mut injected = encrypted.clone();
+    injected["db"]["password"] = json!(1234);
+    assert_eq!(
+        decrypt(&hpke, keys.private_key(), &injected).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+
+    // Unencrypted values can change.
+    let mut renamed = encrypted.clone();
+    renamed["db"]["user_unencrypted"] = json!("root");
+    let mut expected = document();
+    expected["db"]["user_unencrypted"] = json!("root");
+    assert_eq!(
+        decrypt(&hpke, keys.private_key(), &renamed).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn invalid() {
+    let hpke = hpke();
+    let keys = hpke.generate_key_pair().unwrap();
+    let pk_rs = [keys.public_key().clone()];
+
+    assert_eq!(
+        encrypt(&hpke, &pk_rs, &json!(["not", "an", "object"])).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    assert_eq!(
+        encrypt(&hpke, &pk_rs, &json!({ METADATA_KEY: {} })).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    let export_only = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::HpkeExport,
+    );
+    assert_eq!(
+        encrypt(&export_only, &pk_rs, &document()).unwrap_err(),
+        HpkeError::InvalidConfig
+    );
+
+    let encrypted = encrypt(&hpke, &pk_rs, &document()).unwrap();
+    let other_suite = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::Aes128Gcm,
+    );
+    assert_eq!(
+        decrypt(&other_suite, keys.private_key(), &encrypted).unwrap_err(),
+        HpkeError::InvalidConfig
+    );
+    assert_eq!(
+        decrypt(&hpke, keys.private_key(), &document()).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+}
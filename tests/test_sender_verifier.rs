@@ -0,0 +1,305 @@
+extern crate hpke_rs as hpke;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use hpke::{prelude::*, sender_verifier::PinnedSenders};
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+fn hpke(mode: HpkeMode) -> Hpke<HpkeRustCrypto> {
+    Hpke::<HpkeRustCrypto>::new(
+        mode,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    )
+}
+
+/// A configuration that only accepts `trusted` as the sender and counts the
+/// verified keys.
+fn verifying(mode: HpkeMode, trusted: &HpkePublicKey) -> (Hpke<HpkeRustCrypto>, Arc<AtomicUsize>) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let trusted = trusted.clone();
+    let hpke = hpke(mode).with_sender_verifier(move |pk_s: &HpkePublicKey, config: &HpkeConfig| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        assert_eq!(config.kem_id, KemAlgorithm::DhKem25519);
+        if pk_s == &trusted {
+            Ok(())
+        } else {
+            Err(HpkeError::UntrustedSender)
+        }
+    });
+    (hpke, calls)
+}
+
+#[test]
+fn auth() {
+    let sender = hpke(HpkeMode::Auth);
+    let receiver_keys = sender.generate_key_pair().unwrap();
+    let trusted_keys = sender.generate_key_pair().unwrap();
+    let other_keys = sender.generate_key_pair().unwrap();
+    let (receiver, calls) = verifying(HpkeMode::Auth, trusted_keys.public_key());
+
+    let (enc, ct) = sender
+        .seal(
+            receiver_keys.public_key(),
+            b"info",
+            b"aad",
+            b"message",
+            None,
+            None,
+            Some(trusted_keys.private_key()),
+        )
+        .unwrap();
+    let pt = receiver
+        .open(
+            &enc,
+            receiver_keys.private_key(),
+            b"info",
+            b"aad",
+            &ct,
+            None,
+            None,
+            Some(trusted_keys.public_key()),
+        )
+        .unwrap();
+    assert_eq!(pt, b"message");
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    let (enc, _) = sender
+        .setup_sender(
+            receiver_keys.public_key(),
+            b"info",
+            None,
+            None,
+            Some(other_keys.private_key()),
+        )
+        .unwrap();
+    let err = receiver
+        .setup_receiver(
+            &enc,
+            receiver_keys.private_key(),
+            b"info",
+            None,
+            None,
+            Some(other_keys.public_key()),
+        )
+        .unwrap_err();
+    assert_eq!(err, HpkeError::UntrustedSender);
+    assert_eq!(err.kind(), ErrorKind::AuthenticationFailure);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    // Clones share the verifier.
+    let clone = receiver.clone();
+    assert_eq!(
+        clone
+            .setup_receiver(
+                &enc,
+                receiver_keys.private_key(),
+                b"info",
+                None,
+                None,
+                Some(other_keys.public_key()),
+            )
+            .unwrap_err(),
+        HpkeError::UntrustedSender
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn auth_psk() {
+    let psk = [7u8; 32];
+    let sender = hpke(HpkeMode::AuthPsk);
+    let receiver_keys = sender.generate_key_pair().unwrap();
+    let sender_keys = sender.generate_key_pair().unwrap();
+    let trusted_keys = sender.generate_key_pair().unwrap();
+    let (receiver, calls) = verifying(HpkeMode::AuthPsk, trusted_keys.public_key());
+
+    let (enc, _) = sender
+        .setup_sender(
+            receiver_keys.public_key(),
+            b"info",
+            Some(&psk),
+            Some(b"psk id"),
+            Some(sender_keys.private_key()),
+        )
+        .unwrap();
+    assert_eq!(
+        receiver
+            .setup_receiver(
+                &enc,
+                receiver_keys.private_key(),
+                b"info",
+                Some(&psk),
+                Some(b"psk id"),
+                Some(sender_keys.public_key()),
+            )
+            .unwrap_err(),
+        HpkeError::UntrustedSender
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn unauthenticated_modes() {
+    let sender = hpke(HpkeMode::Base);
+    let receiver_keys = sender.generate_key_pair().unwrap();
+    let (receiver, calls) = verifying(HpkeMode::Base, receiver_keys.public_key());
+
+    let (enc, ct) = sender
+        .seal(
+            receiver_keys.public_key(),
+            b"",
+            b"",
+            b"message",
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    receiver
+        .open(
+            &enc,
+            receiver_keys.private_key(),
+            b"",
+            b"",
+            &ct,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn sealed_sender() {
+    let sender = hpke(HpkeMode::Base);
+    let receiver_keys = sender.generate_key_pair().unwrap();
+    let trusted_keys = sender.generate_key_pair().unwrap();
+    let other_keys = sender.generate_key_pair().unwrap();
+    let (receiver, calls) = verifying(HpkeMode::Base, trusted_keys.public_key());
+
+    let (enc, ct) = sender
+        .seal_sealed_sender(
+            receiver_keys.public_key(),
+            &trusted_keys,
+            b"info",
+            b"aad",
+            b"message",
+        )
+        .unwrap();
+    let (pk_s, pt) = receiver
+        .open_sealed_sender(&enc, receiver_keys.private_key(), b"info", b"aad", &ct)
+        .unwrap();
+    assert_eq!(&pk_s, trusted_keys.public_key());
+    assert_eq!(pt, b"message");
+
+    let (enc, ct) = sender
+        .seal_sealed_sender(
+            receiver_keys.public_key(),
+            &other_keys,
+            b"info",
+            b"aad",
+            b"message",
+        )
+        .unwrap();
+    assert_eq!(
+        receiver
+            .open_sealed_sender(&enc, receiver_keys.private_key(), b"info", b"aad", &ct)
+            .unwrap_err(),
+        HpkeError::UntrustedSender
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn pinned_senders() {
+    let sender = hpke(HpkeMode::Auth);
+    let receiver_keys = sender.generate_key_pair().unwrap();
+    let pinned_keys = sender.generate_key_pair().unwrap();
+    let pinned_id_keys = sender.generate_key_pair().unwrap();
+    let other_keys = sender.generate_key_pair().unwrap();
+    let pinned = PinnedSenders::<HpkeRustCrypto>::new()
+        .with_key(pinned_keys.public_key())
+        .with_key_id(
+            pinned_id_keys
+                .key_id::<HpkeRustCrypto>(KemAlgorithm::DhKem25519)
+                .unwrap(),
+        );
+    let receiver = hpke(HpkeMode::Auth).with_pinned_senders(pinned.clone());
+
+    for (keys, accepted) in [
+        (&pinned_keys, true),
+        (&pinned_id_keys, true),
+        (&other_keys, false),
+    ] {
+        let (enc, _) = sender
+            .setup_sender(
+                receiver_keys.public_key(),
+                b"info",
+                None,
+                None,
+                Some(keys.private_key()),
+            )
+            .unwrap();
+        let result = receiver.setup_receiver(
+            &enc,
+            receiver_keys.private_key(),
+            b"info",
+            None,
+            None,
+            Some(keys.public_key()),
+        );
+        if accepted {
+            assert!(result.is_ok());
+        } else {
+            assert_eq!(result.unwrap_err(), HpkeError::UnknownSender);
+        }
+    }
+
+    // A key id only matches for the KEM it was computed for.
+    let p256 = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Auth,
+        KemAlgorithm::DhKemP256,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    )
+    .config();
+    assert!(pinned
+        .is_pinned(pinned_id_keys.public_key(), &receiver.config())
+        .unwrap());
+    assert!(!pinned
+        .is_pinned(pinned_id_keys.public_key(), &p256)
+        .unwrap());
+
+    // Nobody is pinned by default.
+    let receiver = hpke(HpkeMode::Auth).with_pinned_senders(PinnedSenders::default());
+    let (enc, _) = sender
+        .setup_sender(
+            receiver_keys.public_key(),
+            b"info",
+            None,
+            None,
+            Some(pinned_keys.private_key()),
+        )
+        .unwrap();
+    assert_eq!(
+        receiver
+            .setup_receiver(
+                &enc,
+                receiver_keys.private_key(),
+                b"info",
+                None,
+                None,
+                Some(pinned_keys.public_key()),
+            )
+            .unwrap_err(),
+        HpkeError::UnknownSender
+    );
+}
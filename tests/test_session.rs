@@ -0,0 +1,174 @@
+extern crate hpke_rs as hpke;
+
+use std::sync::Arc;
+
+use hpke::prelude::*;
+use hpke::session::{SessionId, SessionManager};
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+const NOW: u64 = 1_700_000_000;
+
+fn hpke() -> Hpke<HpkeRustCrypto> {
+    Hpke::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    )
+}
+
+#[test]
+fn accept_and_open() {
+    let hpke = hpke();
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let sessions = SessionManager::new(60);
+    assert!(sessions.is_empty());
+
+    let (enc, mut client) = hpke.setup_sender(&pk_r, b"info", None, None, None).unwrap();
+    let id = sessions
+        .accept(&hpke, &enc, &sk_r, b"info", None, None, None, NOW)
+        .unwrap();
+    assert_eq!(
+        id,
+        SessionId::from_enc::<HpkeRustCrypto>(KemAlgorithm::DhKem25519, &enc).unwrap()
+    );
+    assert_eq!(sessions.len(), 1);
+
+    for (i, msg) in [&b"one"[..], b"two", b"three"].into_iter().enumerate() {
+        let ct = client.seal(b"aad", msg).unwrap();
+        let pt = sessions.open(&id, b"aad", &ct, NOW + i as u64).unwrap();
+        assert_eq!(pt, msg);
+    }
+    let exported = sessions
+        .with_session(&id, NOW, |context| context.export(b"ctx", 32))
+        .unwrap()
+        .unwrap();
+    assert_eq!(exported, client.export(b"ctx", 32).unwrap());
+
+    // A replayed `enc` doesn't reset the session.
+    assert_eq!(
+        sessions
+            .accept(&hpke, &enc, &sk_r, b"info", None, None, None, NOW)
+            .unwrap_err(),
+        HpkeError::InvalidInput
+    );
+
+    let context = sessions.remove(&id).unwrap();
+    assert!(context.is_some());
+    assert!(sessions.remove(&id).unwrap().is_none());
+    let ct = client.seal(b"aad", b"four").unwrap();
+    assert_eq!(
+        sessions.open(&id, b"aad", &ct, NOW).unwrap_err(),
+        HpkeError::UnknownSession
+    );
+}
+
+#[test]
+fn expiry() {
+    let hpke = hpke();
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let sessions = SessionManager::new(60);
+    let mut ids = Vec::new();
+    for i in 0..3 {
+        let (enc, _) = hpke.setup_sender(&pk_r, b"info", None, None, None).unwrap();
+        let id = sessions
+            .accept(&hpke, &enc, &sk_r, b"info", None, None, None, NOW + i * 30)
+            .unwrap();
+        ids.push(id);
+    }
+
+    // Using a session extends it.
+    sessions.with_session(&ids[0], NOW + 60, |_| ()).unwrap();
+    assert_eq!(
+        sessions
+            .with_session(&ids[0], NOW + 121, |_| ())
+            .unwrap_err(),
+        HpkeError::UnknownSession
+    );
+    assert_eq!(sessions.len(), 2);
+
+    assert_eq!(sessions.evict_expired(NOW + 90).unwrap(), 0);
+    assert_eq!(sessions.evict_expired(NOW + 91).unwrap(), 1);
+    assert_eq!(sessions.evict_expired(NOW + 200).unwrap(), 1);
+    assert!(sessions.is_empty());
+}
+
+#[test]
+fn capacity() {
+    let hpke = hpke();
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let sessions = SessionManager::new(60).with_shards(1).with_capacity(2);
+    let mut contexts = Vec::new();
+    let mut ids = Vec::new();
+    for i in 0..2 {
+        let (enc, client) = hpke.setup_sender(&pk_r, b"info", None, None, None).unwrap();
+        ids.push(
+            sessions
+                .accept(&hpke, &enc, &sk_r, b"info", None, None, None, NOW + i)
+                .unwrap(),
+        );
+        contexts.push(client);
+    }
+    // The first session was used last.
+    sessions.with_session(&ids[0], NOW + 2, |_| ()).unwrap();
+
+    let id = SessionId::from([7; 16]);
+    let context = hpke
+        .setup_receiver(
+            &hpke
+                .setup_sender(&pk_r, b"info", None, None, None)
+                .unwrap()
+                .0,
+            &sk_r,
+            b"info",
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    sessions.insert(id, context, NOW + 3).unwrap();
+    assert_eq!(sessions.len(), 2);
+    assert!(sessions.with_session(&ids[0], NOW + 3, |_| ()).is_ok());
+    assert_eq!(
+        sessions.with_session(&ids[1], NOW + 3, |_| ()).unwrap_err(),
+        HpkeError::UnknownSession
+    );
+    assert!(sessions.with_session(&id, NOW + 3, |_| ()).is_ok());
+    assert_eq!(id.to_string(), "07".repeat(16));
+}
+
+#[test]
+fn concurrent_sessions() {
+    let hpke = hpke();
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let sessions = Arc::new(SessionManager::new(60));
+
+    // The test PRNG only has the seeded bytes, one ephemeral key per setup.
+    #[cfg(feature = "hpke-test-prng")]
+    {
+        use rand::RngCore;
+        let mut seed = vec![0u8; 32 * 8 * 16];
+        rand::thread_rng().fill_bytes(&mut seed);
+        hpke.seed(&seed).unwrap();
+    }
+
+    std::thread::scope(|scope| {
+        for _ in 0..8 {
+            let (hpke, sessions) = (&hpke, sessions.clone());
+            let (sk_r, pk_r) = (&sk_r, &pk_r);
+            scope.spawn(move || {
+                for _ in 0..16 {
+                    let (enc, mut client) =
+                        hpke.setup_sender(pk_r, b"info", None, None, None).unwrap();
+                    let id = sessions
+                        .accept(hpke, &enc, sk_r, b"info", None, None, None, NOW)
+                        .unwrap();
+                    let ct = client.seal(b"", b"message").unwrap();
+                    assert_eq!(sessions.open(&id, b"", &ct, NOW).unwrap(), b"message");
+                }
+            });
+        }
+    });
+    assert_eq!(sessions.len(), 128);
+}
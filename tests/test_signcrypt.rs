@@ -0,0 +1,62 @@
+#![cfg(feature = "signcryption")]
+
+extern crate hpke_rs as hpke;
+
+use ed25519_dalek::SigningKey;
+use hpke::prelude::*;
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+#[test]
+fn signcrypt() {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let (sk_r, pk_r) = hpke.generate_key_pair().unwrap().into_keys();
+    let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+    let verifying_key = signing_key.verifying_key();
+
+    let (enc, ct) = hpke
+        .signcrypt(&pk_r, b"info", b"aad", b"message", None, None, &signing_key)
+        .unwrap();
+    let pt = hpke
+        .verify_open(
+            &enc,
+            &sk_r,
+            b"info",
+            b"aad",
+            &ct,
+            None,
+            None,
+            &verifying_key,
+        )
+        .unwrap();
+    assert_eq!(pt, b"message");
+
+    // A different sender key doesn't verify.
+    let other_key = SigningKey::from_bytes(&[8u8; 32]).verifying_key();
+    assert_eq!(
+        hpke.verify_open(&enc, &sk_r, b"info", b"aad", &ct, None, None, &other_key),
+        Err(HpkeError::InvalidSignature)
+    );
+
+    // A tampered ciphertext doesn't decrypt.
+    let mut tampered = ct.clone();
+    tampered[0] ^= 1;
+    assert_eq!(
+        hpke.verify_open(
+            &enc,
+            &sk_r,
+            b"info",
+            b"aad",
+            &tampered,
+            None,
+            None,
+            &verifying_key
+        ),
+        Err(HpkeError::OpenError)
+    );
+}
@@ -0,0 +1,182 @@
+extern crate hpke_rs as hpke;
+
+use std::io::{self, Read, Write};
+
+use hpke::{
+    prelude::*,
+    stream::{DecryptingReader, EncryptingWriter},
+};
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+type Reader<'a> = DecryptingReader<HpkeRustCrypto, &'a [u8]>;
+
+fn contexts() -> (Context<HpkeRustCrypto>, Context<HpkeRustCrypto>) {
+    let hpke = Hpke::<HpkeRustCrypto>::new(
+        HpkeMode::Base,
+        KemAlgorithm::DhKem25519,
+        KdfAlgorithm::HkdfSha256,
+        AeadAlgorithm::ChaCha20Poly1305,
+    );
+    let key_pair = hpke.generate_key_pair().unwrap();
+    let (enc, sender) = hpke
+        .setup_sender(key_pair.public_key(), b"info", None, None, None)
+        .unwrap();
+    let receiver = hpke
+        .setup_receiver(&enc, key_pair.private_key(), b"info", None, None, None)
+        .unwrap();
+    (sender, receiver)
+}
+
+fn encrypt(sender: Context<HpkeRustCrypto>, chunk_size: usize, data: &[u8]) -> Vec<u8> {
+    let mut writer = EncryptingWriter::new(sender, b"aad", Vec::new())
+        .with_chunk_size(chunk_size)
+        .unwrap();
+    // Write in pieces that don't line up with the chunks.
+    for piece in data.chunks(7) {
+        writer.write_all(piece).unwrap();
+    }
+    let ct_len = writer.ct_len(data.len());
+    let ct = writer.finish().unwrap();
+    assert_eq!(ct.len(), ct_len);
+    ct
+}
+
+fn decrypt(receiver: Context<HpkeRustCrypto>, chunk_size: usize, ct: &[u8]) -> io::Result<Vec<u8>> {
+    let mut reader = Reader::new(receiver, b"aad", ct)
+        .with_chunk_size(chunk_size)
+        .unwrap();
+    let mut pt = Vec::new();
+    reader.read_to_end(&mut pt)?;
+    assert!(reader.is_finished());
+    Ok(pt)
+}
+
+#[test]
+fn stream_round_trip() {
+    let data: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+    for (len, chunk_size) in [
+        (0, 16),
+        (1, 16),
+        (16, 16),
+        (17, 16),
+        (1000, 16),
+        (1000, 4096),
+    ] {
+        let (sender, receiver) = contexts();
+        let ct = encrypt(sender, chunk_size, &data[..len]);
+        let records = len.div_ceil(chunk_size).max(1);
+        assert_eq!(ct.len(), len + records * (4 + 16));
+        assert_eq!(decrypt(receiver, chunk_size, &ct).unwrap(), &data[..len]);
+    }
+
+    // io::copy works in both directions.
+    let (sender, receiver) = contexts();
+    let mut writer = EncryptingWriter::new(sender, b"aad", Vec::new());
+    io::copy(&mut &data[..], &mut writer).unwrap();
+    let ct = writer.finish().unwrap();
+    let mut pt = Vec::new();
+    io::copy(&mut Reader::new(receiver, b"aad", &ct), &mut pt).unwrap();
+    assert_eq!(pt, data);
+}
+
+#[test]
+fn stream_flush() {
+    let (sender, receiver) = contexts();
+    let mut writer = EncryptingWriter::new(sender, b"aad", Vec::new());
+    writer.write_all(b"hello").unwrap();
+    assert!(writer.get_ref().is_empty());
+    // Flushing seals the buffered data such that it can be read right away.
+    writer.flush().unwrap();
+    let flushed = writer.get_ref().len();
+    assert_eq!(flushed, 4 + 5 + 16);
+    writer.write_all(b" world").unwrap();
+    let ct = writer.finish().unwrap();
+
+    let mut reader = Reader::new(receiver, b"aad", &ct[..flushed]);
+    let mut pt = [0u8; 5];
+    reader.read_exact(&mut pt).unwrap();
+    assert_eq!(&pt, b"hello");
+    assert!(!reader.is_finished());
+
+    let (sender, receiver) = contexts();
+    let mut writer = EncryptingWriter::new(sender, b"aad", Vec::new());
+    writer.write_all(b"hello").unwrap();
+    writer.flush().unwrap();
+    writer.write_all(b" world").unwrap();
+    let ct = writer.finish().unwrap();
+    assert_eq!(decrypt(receiver, 1024, &ct).unwrap(), b"hello world");
+}
+
+#[test]
+fn stream_tampering() {
+    let data = vec![0xAB; 100];
+    let record = 4 + 32 + 16;
+
+    // Truncated at a record boundary.
+    let (sender, receiver) = contexts();
+    let ct = encrypt(sender, 32, &data);
+    assert_eq!(
+        decrypt(receiver, 32, &ct[..2 * record]).unwrap_err().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+
+    // Truncated within a record.
+    let (sender, receiver) = contexts();
+    let ct = encrypt(sender, 32, &data);
+    assert_eq!(
+        decrypt(receiver, 32, &ct[..2 * record + 10])
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+
+    // A non-final record marked as the last one.
+    let (sender, receiver) = contexts();
+    let mut ct = encrypt(sender, 32, &data);
+    ct[record] |= 0x80;
+    assert_eq!(
+        decrypt(receiver, 32, &ct).unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+
+    // Swapped records.
+    let (sender, receiver) = contexts();
+    let ct = encrypt(sender, 32, &data);
+    let swapped = [&ct[record..2 * record], &ct[..record], &ct[2 * record..]].concat();
+    assert_eq!(
+        decrypt(receiver, 32, &swapped).unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+
+    // A different aad.
+    let (sender, receiver) = contexts();
+    let ct = encrypt(sender, 32, &data);
+    let mut reader = Reader::new(receiver, b"other", &ct)
+        .with_chunk_size(32)
+        .unwrap();
+    let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    // The reader stays failed.
+    assert!(reader.read(&mut [0u8; 8]).is_err());
+
+    // Records larger than the reader's chunk size are rejected before they
+    // are read.
+    let (sender, receiver) = contexts();
+    let ct = encrypt(sender, 64, &data);
+    assert_eq!(
+        decrypt(receiver, 32, &ct).unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+}
+
+#[test]
+fn stream_chunk_size() {
+    let (sender, receiver) = contexts();
+    assert!(EncryptingWriter::new(sender, b"", Vec::new())
+        .with_chunk_size(0)
+        .is_err());
+    assert!(Reader::new(receiver, b"", &[])
+        .with_chunk_size(hpke::stream::MAX_CHUNK_SIZE + 1)
+        .is_err());
+}
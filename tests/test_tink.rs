@@ -0,0 +1,141 @@
+extern crate hpke_rs as hpke;
+
+use hpke::prelude::*;
+use hpke::tink::*;
+use hpke_rs_crypto::types::{AeadAlgorithm, KdfAlgorithm, KemAlgorithm};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+
+fn hpke(kem: KemAlgorithm, aead: AeadAlgorithm) -> Hpke<HpkeRustCrypto> {
+    Hpke::new(HpkeMode::Base, kem, KdfAlgorithm::HkdfSha256, aead)
+}
+
+#[test]
+fn tink_hybrid_encryption() {
+    for (kem, aead, output_prefix_type, prefix) in [
+        (
+            KemAlgorithm::DhKem25519,
+            AeadAlgorithm::Aes128Gcm,
+            OutputPrefixType::Tink,
+            vec![0x01, 0x12, 0x34, 0x56, 0x78],
+        ),
+        (
+            KemAlgorithm::DhKemP256,
+            AeadAlgorithm::ChaCha20Poly1305,
+            OutputPrefixType::Legacy,
+            vec![0x00, 0x12, 0x34, 0x56, 0x78],
+        ),
+        (
+            KemAlgorithm::DhKem25519,
+            AeadAlgorithm::Aes256Gcm,
+            OutputPrefixType::Raw,
+            vec![],
+        ),
+    ] {
+        let private =
+            TinkKeyset::generate(&hpke(kem, aead), 0x12345678, output_prefix_type).unwrap();
+        let public = TinkKeyset::decode(&private.public_keyset().encode()).unwrap();
+        assert_eq!(public, private.public_keyset());
+        assert_eq!(TinkKeyset::decode(&private.encode()).unwrap(), private);
+
+        let encrypt = HybridEncrypt::<HpkeRustCrypto>::new(&public).unwrap();
+        let decrypt = HybridDecrypt::<HpkeRustCrypto>::new(&private).unwrap();
+        let ct = encrypt.encrypt(b"message", b"context info").unwrap();
+        assert!(ct.starts_with(&prefix));
+        assert_eq!(
+            ct.len(),
+            prefix.len() + kem.enc_len() + b"message".len() + aead.tag_length()
+        );
+        assert_eq!(decrypt.decrypt(&ct, b"context info").unwrap(), b"message");
+        assert_eq!(
+            decrypt.decrypt(&ct, b"other info").unwrap_err(),
+            HpkeError::OpenError
+        );
+
+        // The ciphertext is plain HPKE behind the prefix.
+        let key = &private.keys[0];
+        let (enc, ct) = ct[prefix.len()..].split_at(kem.enc_len());
+        assert_eq!(
+            hpke(kem, aead)
+                .open(
+                    enc,
+                    key.private_key.as_ref().unwrap(),
+                    b"context info",
+                    &[],
+                    ct,
+                    None,
+                    None,
+                    None
+                )
+                .unwrap(),
+            b"message"
+        );
+
+        // A public keyset can't decrypt.
+        assert_eq!(
+            HybridDecrypt::<HpkeRustCrypto>::new(&public).unwrap_err(),
+            HpkeError::InvalidConfig
+        );
+    }
+}
+
+#[test]
+fn tink_key_rotation() {
+    let old = TinkKeyset::generate(
+        &hpke(KemAlgorithm::DhKem25519, AeadAlgorithm::Aes128Gcm),
+        1,
+        OutputPrefixType::Tink,
+    )
+    .unwrap();
+    let new = TinkKeyset::generate(
+        &hpke(KemAlgorithm::DhKemP256, AeadAlgorithm::Aes128Gcm),
+        2,
+        OutputPrefixType::Tink,
+    )
+    .unwrap();
+    let old_ct = HybridEncrypt::<HpkeRustCrypto>::new(&old)
+        .unwrap()
+        .encrypt(b"old", b"")
+        .unwrap();
+
+    let mut keyset =
+        TinkKeyset::decode(&[new.encode(), old.encode()[2..].to_vec()].concat()).unwrap();
+    assert_eq!(keyset.primary_key_id, 2);
+    assert_eq!(keyset.keys.len(), 2);
+    let new_ct = HybridEncrypt::<HpkeRustCrypto>::new(&keyset)
+        .unwrap()
+        .encrypt(b"new", b"")
+        .unwrap();
+    assert_eq!(new_ct[..5], [0x01, 0, 0, 0, 2]);
+
+    let decrypt = HybridDecrypt::<HpkeRustCrypto>::new(&keyset).unwrap();
+    assert_eq!(decrypt.decrypt(&old_ct, b"").unwrap(), b"old");
+    assert_eq!(decrypt.decrypt(&new_ct, b"").unwrap(), b"new");
+
+    // Disabled keys are not used.
+    keyset.keys[1].enabled = false;
+    let decrypt = HybridDecrypt::<HpkeRustCrypto>::new(&keyset).unwrap();
+    assert_eq!(
+        decrypt.decrypt(&old_ct, b"").unwrap_err(),
+        HpkeError::OpenError
+    );
+    keyset.primary_key_id = 1;
+    assert_eq!(
+        HybridEncrypt::<HpkeRustCrypto>::new(&keyset).unwrap_err(),
+        HpkeError::InvalidConfig
+    );
+
+    // Malformed and unsupported keysets
+    assert_eq!(
+        TinkKeyset::decode(&new.encode()[..20]).unwrap_err(),
+        HpkeError::InvalidInput
+    );
+    assert_eq!(
+        TinkKeyset::generate(
+            &hpke(KemAlgorithm::DhKem25519, AeadAlgorithm::HpkeExport),
+            1,
+            OutputPrefixType::Raw
+        )
+        .unwrap_err(),
+        HpkeError::UnknownMode
+    );
+}
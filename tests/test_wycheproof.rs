@@ -0,0 +1,164 @@
+//! Wycheproof test vectors in the upstream JSON format.
+//!
+//! The tests run the test vectors in a checkout of
+//! <https://github.com/C2SP/wycheproof> that `WYCHEPROOF_DIR` points to, and
+//! are skipped if it isn't set.
+//! See `tests/wycheproof/README.md`.
+
+extern crate hpke_rs as hpke;
+
+use std::{fs::File, io::BufReader, path::PathBuf};
+
+use hpke::test_util::hex_to_bytes;
+use hpke_rs_crypto::{
+    types::{AeadAlgorithm, KemAlgorithm},
+    HpkeCrypto,
+};
+use hpke_rs_rust_crypto::HpkeRustCrypto;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TestFile<Group> {
+    test_groups: Vec<Group>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AeadGroup {
+    iv_size: usize,
+    key_size: usize,
+    tag_size: usize,
+    tests: Vec<AeadTest>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AeadTest {
+    tc_id: u64,
+    key: String,
+    iv: String,
+    aad: String,
+    msg: String,
+    ct: String,
+    tag: String,
+    result: String,
+}
+
+#[derive(Deserialize)]
+struct XdhGroup {
+    curve: String,
+    tests: Vec<XdhTest>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct XdhTest {
+    tc_id: u64,
+    public: String,
+    private: String,
+    shared: String,
+    result: String,
+}
+
+/// Load the test vector file `name` from `WYCHEPROOF_DIR`, if it is set.
+fn load<Group: for<'de> Deserialize<'de>>(name: &str) -> Option<TestFile<Group>> {
+    let dir = PathBuf::from(std::env::var_os("WYCHEPROOF_DIR")?);
+    let file = ["testvectors_v1", "testvectors"]
+        .iter()
+        .map(|sub| dir.join(sub).join(name))
+        .find(|file| file.exists())
+        .unwrap_or_else(|| panic!("Couldn't find {} in {}.", name, dir.display()));
+    let reader = BufReader::new(File::open(&file).unwrap());
+    Some(serde_json::from_reader(reader).unwrap())
+}
+
+fn aead(alg: AeadAlgorithm, name: &str) {
+    let Some(file) = load::<AeadGroup>(name) else {
+        return;
+    };
+    let mut run = 0;
+    for group in file.test_groups {
+        if group.iv_size != 8 * alg.nonce_length()
+            || group.key_size != 8 * alg.key_length()
+            || group.tag_size != 8 * alg.tag_length()
+        {
+            continue;
+        }
+        for test in group.tests {
+            let key = hex_to_bytes(&test.key);
+            let nonce = hex_to_bytes(&test.iv);
+            let aad = hex_to_bytes(&test.aad);
+            let msg = hex_to_bytes(&test.msg);
+            let ct = [hex_to_bytes(&test.ct), hex_to_bytes(&test.tag)].concat();
+            let opened = HpkeRustCrypto::aead_open(alg, &key, &nonce, &aad, &ct);
+            match test.result.as_str() {
+                "valid" => {
+                    let sealed = HpkeRustCrypto::aead_seal(alg, &key, &nonce, &aad, &msg);
+                    assert_eq!(sealed.unwrap(), ct, "{:?} test case {}", alg, test.tc_id);
+                    assert_eq!(opened.unwrap(), msg, "{:?} test case {}", alg, test.tc_id);
+                }
+                "invalid" => assert!(opened.is_err(), "{:?} test case {}", alg, test.tc_id),
+                _ => {
+                    if let Ok(opened) = opened {
+                        assert_eq!(opened, msg, "{:?} test case {}", alg, test.tc_id);
+                    }
+                }
+            }
+            run += 1;
+        }
+    }
+    assert!(run > 0, "No test vectors for {:?} in {}", alg, name);
+}
+
+#[test]
+fn aes_128_gcm() {
+    aead(AeadAlgorithm::Aes128Gcm, "aes_gcm_test.json");
+}
+
+#[test]
+fn aes_256_gcm() {
+    aead(AeadAlgorithm::Aes256Gcm, "aes_gcm_test.json");
+}
+
+#[test]
+fn chacha20_poly1305() {
+    aead(
+        AeadAlgorithm::ChaCha20Poly1305,
+        "chacha20_poly1305_test.json",
+    );
+}
+
+#[test]
+fn x25519() {
+    let Some(file) = load::<XdhGroup>("x25519_test.json") else {
+        return;
+    };
+    let mut run = 0;
+    for group in file.test_groups {
+        assert_eq!(group.curve, "curve25519");
+        for test in group.tests {
+            let pk = hex_to_bytes(&test.public);
+            let sk = hex_to_bytes(&test.private);
+            let shared = hex_to_bytes(&test.shared);
+            let derived = HpkeRustCrypto::kem_derive(KemAlgorithm::DhKem25519, &pk, &sk);
+            match test.result.as_str() {
+                "valid" => assert_eq!(derived.unwrap(), shared, "test case {}", test.tc_id),
+                "invalid" => assert!(
+                    derived.map_or(true, |derived| derived != shared),
+                    "test case {}",
+                    test.tc_id
+                ),
+                // Acceptable test cases are mostly low-order public keys, which
+                // HPKE rejects because the shared secret is zero.
+                _ => {
+                    if let Ok(derived) = derived {
+                        assert_eq!(derived, shared, "test case {}", test.tc_id);
+                    }
+                }
+            }
+            run += 1;
+        }
+    }
+    assert!(run > 0);
+}
@@ -0,0 +1,28 @@
+#!/bin/sh
+# Re-extract the vendored Wycheproof AEAD vectors from the published
+# RustCrypto crates they were copied from, and check them against
+# SHA256SUMS.
+#
+# The RustCrypto crates generate these files from the Wycheproof JSON test
+# vectors with the `wycheproof2blb` tool of https://github.com/RustCrypto/utils.
+set -eu
+
+cd "$(dirname "$0")"
+tmp=$(mktemp -d)
+trap 'rm -rf "$tmp"' EXIT
+
+fetch() {
+    crate=$1
+    version=$2
+    curl -sSfL "https://static.crates.io/crates/$crate/$crate-$version.crate" |
+        tar -xz -C "$tmp"
+}
+
+fetch aes-gcm 0.10.3
+fetch chacha20poly1305 0.10.1
+
+cp "$tmp/aes-gcm-0.10.3/tests/data/wycheproof-128.blb" aes_128_gcm.blb
+cp "$tmp/aes-gcm-0.10.3/tests/data/wycheproof-256.blb" aes_256_gcm.blb
+cp "$tmp/chacha20poly1305-0.10.1/tests/data/wycheproof_chacha20poly1305.blb" chacha20_poly1305.blb
+
+sha256sum -c SHA256SUMS
@@ -25,6 +25,9 @@ pub enum Error {
     /// Unknown or unsupported AEAD algorithm.
     UnknownAeadAlgorithm,
 
+    /// Invalid key for the AEAD algorithm.
+    AeadInvalidKey,
+
     /// Invalid nonce for the AEAD algorithm.
     AeadInvalidNonce,
 
@@ -38,6 +38,8 @@ pub trait HpkeCrypto: core::fmt::Debug + Send + Sync {
             types::KdfAlgorithm::HkdfSha256 => 32,
             types::KdfAlgorithm::HkdfSha384 => 48,
             types::KdfAlgorithm::HkdfSha512 => 64,
+            types::KdfAlgorithm::HkdfSha3_256 => 32,
+            types::KdfAlgorithm::Kmac256 => 64,
         }
     }
 
@@ -64,6 +66,23 @@ pub trait HpkeCrypto: core::fmt::Debug + Send + Sync {
     /// Validate a secret key for its correctness.
     fn kem_validate_sk(alg: KemAlgorithm, sk: &[u8]) -> Result<Vec<u8>, Error>;
 
+    /// A KEM public key that is decoded and validated.
+    ///
+    /// Senders that encrypt to the same recipient many times can prepare the
+    /// public key once and skip the decoding and validation for every
+    /// encapsulation.
+    type KemPublicKey: Send + Sync;
+
+    /// Decode and validate the public key `pk`.
+    fn kem_prepare_pk(alg: KemAlgorithm, pk: &[u8]) -> Result<Self::KemPublicKey, Error>;
+
+    /// KEM Derive with a prepared public key.
+    fn kem_derive_prepared(
+        alg: KemAlgorithm,
+        pk: &Self::KemPublicKey,
+        sk: &[u8],
+    ) -> Result<Vec<u8>, Error>;
+
     /// AEAD encrypt.
     fn aead_seal(
         alg: AeadAlgorithm,
@@ -82,6 +101,69 @@ pub trait HpkeCrypto: core::fmt::Debug + Send + Sync {
         msg: &[u8],
     ) -> Result<Vec<u8>, Error>;
 
+    /// An AEAD cipher that is initialized with a key.
+    ///
+    /// Contexts initialize the cipher once in the key schedule and use it for
+    /// all messages such that the key setup (e.g. the AES key expansion) isn't
+    /// repeated for every message.
+    type AeadCipher: Send + Sync;
+
+    /// Initialize an AEAD cipher with the `key`.
+    fn aead_init(alg: AeadAlgorithm, key: &[u8]) -> Result<Self::AeadCipher, Error>;
+
+    /// AEAD encrypt with an initialized cipher.
+    fn aead_cipher_seal(
+        cipher: &Self::AeadCipher,
+        nonce: &[u8],
+        aad: &[u8],
+        msg: &[u8],
+    ) -> Result<Vec<u8>, Error>;
+
+    /// AEAD decrypt with an initialized cipher.
+    fn aead_cipher_open(
+        cipher: &Self::AeadCipher,
+        nonce: &[u8],
+        aad: &[u8],
+        msg: &[u8],
+    ) -> Result<Vec<u8>, Error>;
+
+    /// AEAD encrypt the plain text in `buffer` in place with an initialized
+    /// cipher.
+    ///
+    /// On success `buffer` holds the ciphertext including the tag.
+    /// The default implementation falls back to [`HpkeCrypto::aead_cipher_seal`]
+    /// and allocates.
+    fn aead_cipher_seal_in_place(
+        cipher: &Self::AeadCipher,
+        nonce: &[u8],
+        aad: &[u8],
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let ctxt = Self::aead_cipher_seal(cipher, nonce, aad, buffer)?;
+        buffer.clear();
+        buffer.extend_from_slice(&ctxt);
+        Ok(())
+    }
+
+    /// AEAD decrypt the ciphertext in `buffer` in place with an initialized
+    /// cipher.
+    ///
+    /// On success `buffer` holds the plain text.
+    /// On error the content of `buffer` is unspecified and must not be used.
+    /// The default implementation falls back to [`HpkeCrypto::aead_cipher_open`]
+    /// and allocates.
+    fn aead_cipher_open_in_place(
+        cipher: &Self::AeadCipher,
+        nonce: &[u8],
+        aad: &[u8],
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let ptxt = Self::aead_cipher_open(cipher, nonce, aad, buffer)?;
+        buffer.clear();
+        buffer.extend_from_slice(&ptxt);
+        Ok(())
+    }
+
     /// Get key length for AEAD.
     ///
     /// Note that this function returns `0` for export only keys of unknown size.
@@ -48,7 +48,7 @@ impl std::convert::TryFrom<u16> for KemAlgorithm {
 
 impl KemAlgorithm {
     /// Get the length of the private key for the KEM in bytes.
-    pub fn private_key_len(&self) -> usize {
+    pub const fn private_key_len(&self) -> usize {
         match self {
             KemAlgorithm::DhKemP256 => 32,
             KemAlgorithm::DhKemP384 => 48,
@@ -59,7 +59,7 @@ impl KemAlgorithm {
     }
 
     /// Get the length of the shared secret for the KEM in bytes.
-    pub fn shared_secret_len(&self) -> usize {
+    pub const fn shared_secret_len(&self) -> usize {
         match self {
             KemAlgorithm::DhKemP256 => 32,
             KemAlgorithm::DhKemP384 => 48,
@@ -68,6 +68,26 @@ impl KemAlgorithm {
             KemAlgorithm::DhKem448 => 64,
         }
     }
+
+    /// Get the length of an encoded public key for the KEM in bytes.
+    ///
+    /// The NIST curve keys are uncompressed points.
+    pub const fn public_key_len(&self) -> usize {
+        match self {
+            KemAlgorithm::DhKemP256 => 65,
+            KemAlgorithm::DhKemP384 => 97,
+            KemAlgorithm::DhKemP521 => 133,
+            KemAlgorithm::DhKem25519 => 32,
+            KemAlgorithm::DhKem448 => 56,
+        }
+    }
+
+    /// Get the length of the encapsulated secret for the KEM in bytes.
+    ///
+    /// For the DH KEMs this is an encoded public key.
+    pub const fn enc_len(&self) -> usize {
+        self.public_key_len()
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
@@ -148,6 +168,33 @@ impl AeadAlgorithm {
             AeadAlgorithm::HpkeExport => 0,
         }
     }
+
+    /// Get the maximum plaintext length of the [`AeadAlgorithm`] in bytes.
+    ///
+    /// This is `2^36 - 32` for AES-GCM (NIST SP 800-38D) and `2^38 - 64` for
+    /// ChaCha20Poly1305 (RFC 8439).
+    /// The function returns `0` for the [`AeadAlgorithm::HpkeExport`] type.
+    pub const fn max_plaintext_length(&self) -> u64 {
+        match self {
+            AeadAlgorithm::Aes128Gcm | AeadAlgorithm::Aes256Gcm => (1 << 36) - 32,
+            AeadAlgorithm::ChaCha20Poly1305 => (1 << 38) - 64,
+            AeadAlgorithm::HpkeExport => 0,
+        }
+    }
+
+    /// Get the maximum length of the additional data of the [`AeadAlgorithm`]
+    /// in bytes.
+    ///
+    /// This is `2^61 - 1` for AES-GCM (NIST SP 800-38D) and `2^64 - 1` for
+    /// ChaCha20Poly1305 (RFC 8439).
+    /// The function returns `0` for the [`AeadAlgorithm::HpkeExport`] type.
+    pub const fn max_aad_length(&self) -> u64 {
+        match self {
+            AeadAlgorithm::Aes128Gcm | AeadAlgorithm::Aes256Gcm => (1 << 61) - 1,
+            AeadAlgorithm::ChaCha20Poly1305 => u64::MAX,
+            AeadAlgorithm::HpkeExport => 0,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
@@ -165,6 +212,23 @@ pub enum KdfAlgorithm {
 
     /// HKDF SHA 512
     HkdfSha512 = 0x0003,
+
+    /// HKDF SHA3-256
+    ///
+    /// This uses the private-use identifier `0xFF01`, which is not
+    /// registered with IANA and only interoperates with peers that use the
+    /// same identifier.
+    HkdfSha3_256 = 0xFF01,
+
+    /// KMAC256 (NIST SP 800-185)
+    ///
+    /// This uses the private-use identifier `0xFF02`, which is not
+    /// registered with IANA and only interoperates with peers that use the
+    /// same identifier.
+    /// Extract and expand are KMAC256 with the customization strings
+    /// `"HPKE-Extract"` and `"HPKE-Expand"`, keyed with the salt and the
+    /// pseudorandom key respectively.
+    Kmac256 = 0xFF02,
 }
 
 impl std::fmt::Display for KdfAlgorithm {
@@ -180,11 +244,27 @@ impl std::convert::TryFrom<u16> for KdfAlgorithm {
             0x0001 => Ok(KdfAlgorithm::HkdfSha256),
             0x0002 => Ok(KdfAlgorithm::HkdfSha384),
             0x0003 => Ok(KdfAlgorithm::HkdfSha512),
+            0xFF01 => Ok(KdfAlgorithm::HkdfSha3_256),
+            0xFF02 => Ok(KdfAlgorithm::Kmac256),
             _ => Err(Self::Error::UnknownKdfAlgorithm),
         }
     }
 }
 
+impl KdfAlgorithm {
+    /// Get the output size of the hash function of the [`KdfAlgorithm`] in
+    /// bytes.
+    pub const fn digest_length(&self) -> usize {
+        match self {
+            KdfAlgorithm::HkdfSha256 => 32,
+            KdfAlgorithm::HkdfSha384 => 48,
+            KdfAlgorithm::HkdfSha512 => 64,
+            KdfAlgorithm::HkdfSha3_256 => 32,
+            KdfAlgorithm::Kmac256 => 64,
+        }
+    }
+}
+
 impl From<KemAlgorithm> for KdfAlgorithm {
     fn from(kem: KemAlgorithm) -> Self {
         match kem {